@@ -0,0 +1,569 @@
+//! Animated exports for prototype previews and spinner assets: GIF with a
+//! shared optimized palette, APNG and animated WebP, all from a sequence of
+//! RGBA frames with per-frame delays.
+
+use crate::binary_ipc;
+use crate::png_optimize;
+use base64::{engine::general_purpose, Engine as _};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const FORMAT_GIF: &str = "gif";
+pub const FORMAT_APNG: &str = "apng";
+pub const FORMAT_WEBP: &str = "webp";
+
+/// GIF stores 1-bit transparency; alpha below this is treated as clear.
+const GIF_ALPHA_THRESHOLD: u8 = 128;
+/// Browsers bump GIF delays under 2cs up to 10cs, so never write less.
+const GIF_MIN_DELAY_CS: u16 = 2;
+/// ANMF durations are 24-bit milliseconds.
+const WEBP_MAX_DURATION_MS: u32 = 0xFF_FFFF;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeAnimationArgs {
+    pub frames: Vec<AnimationFrame>,
+    #[serde(flatten)]
+    pub options: AnimationOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationFrame {
+    /// Raw RGBA pixel data as base64
+    pub rgba_base64: String,
+    pub delay_ms: u32,
+}
+
+/// Header arguments for `encode_animation_raw`; the body is every frame's
+/// RGBA pixels back to back.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeAnimationRawArgs {
+    pub delays_ms: Vec<u32>,
+    #[serde(flatten)]
+    pub options: AnimationOptions,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationOptions {
+    pub width: u32,
+    pub height: u32,
+    /// "gif", "apng" or "webp"
+    pub format: String,
+    /// Total number of plays; 0 or omitted loops forever
+    pub loop_count: Option<u16>,
+    /// GIF palette size, 2-256 including the transparent entry
+    pub max_colors: Option<u16>,
+    /// GIF error diffusion strength, 0 to 1 (default)
+    pub dithering: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeAnimationResult {
+    pub data_base64: String,
+    pub mime: String,
+    pub extension: String,
+    pub byte_size: usize,
+    pub frame_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnimationFormat {
+    Gif,
+    Apng,
+    WebP,
+}
+
+impl AnimationFormat {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            FORMAT_GIF => Ok(AnimationFormat::Gif),
+            FORMAT_APNG | "png" => Ok(AnimationFormat::Apng),
+            FORMAT_WEBP => Ok(AnimationFormat::WebP),
+            other => Err(format!("unsupported_format: {other}")),
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "image/gif",
+            AnimationFormat::Apng => "image/apng",
+            AnimationFormat::WebP => "image/webp",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+            AnimationFormat::WebP => "webp",
+        }
+    }
+}
+
+#[tauri::command(async)]
+pub fn encode_animation(args: EncodeAnimationArgs) -> Result<EncodeAnimationResult, String> {
+    let format = AnimationFormat::parse(&args.options.format)?;
+    let mut frames = Vec::with_capacity(args.frames.len());
+    let mut delays = Vec::with_capacity(args.frames.len());
+    for frame in args.frames {
+        let rgba = general_purpose::STANDARD
+            .decode(&frame.rgba_base64)
+            .map_err(|e| format!("Failed to decode base64: {}", e))?;
+        frames.push(crate::rgba_image(
+            rgba,
+            args.options.width,
+            args.options.height,
+        )?);
+        delays.push(frame.delay_ms);
+    }
+    let bytes = encode_frames(&frames, &delays, &args.options)?;
+
+    Ok(EncodeAnimationResult {
+        byte_size: bytes.len(),
+        frame_count: frames.len(),
+        data_base64: general_purpose::STANDARD.encode(bytes),
+        mime: format.mime().to_string(),
+        extension: format.extension().to_string(),
+    })
+}
+
+/// `encode_animation` over raw bytes: concatenated frames in, file out.
+#[tauri::command(async)]
+pub fn encode_animation_raw(
+    request: tauri::ipc::Request<'_>,
+) -> Result<tauri::ipc::Response, String> {
+    let args: EncodeAnimationRawArgs = binary_ipc::header_args(&request)?;
+    let body = binary_ipc::raw_body(&request)?;
+    let frame_len = args.options.width as usize * args.options.height as usize * 4;
+    if frame_len == 0 || body.len() != frame_len * args.delays_ms.len() {
+        return Err(format!(
+            "Invalid RGBA data length: expected {}, got {}",
+            frame_len * args.delays_ms.len(),
+            body.len()
+        ));
+    }
+    let frames = body
+        .chunks_exact(frame_len)
+        .map(|rgba| crate::rgba_image(rgba.to_vec(), args.options.width, args.options.height))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tauri::ipc::Response::new(encode_frames(
+        &frames,
+        &args.delays_ms,
+        &args.options,
+    )?))
+}
+
+pub fn encode_frames(
+    frames: &[RgbaImage],
+    delays_ms: &[u32],
+    options: &AnimationOptions,
+) -> Result<Vec<u8>, String> {
+    if frames.is_empty() {
+        return Err("no_frames".to_string());
+    }
+    if frames.len() != delays_ms.len() {
+        return Err(format!(
+            "frame_count_mismatch: {} frames, {} delays",
+            frames.len(),
+            delays_ms.len()
+        ));
+    }
+    let plays = options.loop_count.unwrap_or(0);
+    match AnimationFormat::parse(&options.format)? {
+        AnimationFormat::Gif => encode_gif(frames, delays_ms, options, plays),
+        AnimationFormat::Apng => encode_apng(frames, delays_ms, plays),
+        AnimationFormat::WebP => encode_webp(frames, delays_ms, plays),
+    }
+}
+
+fn encode_gif(
+    frames: &[RgbaImage],
+    delays_ms: &[u32],
+    options: &AnimationOptions,
+    plays: u16,
+) -> Result<Vec<u8>, String> {
+    let (width, height) = frames[0].dimensions();
+    let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(format!(
+            "image_too_large: GIF supports up to 65535px, got {width}x{height}"
+        ));
+    };
+
+    let palette = gif_palette(frames, options.max_colors.unwrap_or(256) as usize);
+    let transparent = palette.len() as u8;
+    let mut global_palette: Vec<u8> = palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+    global_palette.extend_from_slice(&[0, 0, 0]);
+    let dithering = options.dithering.unwrap_or(1.0).clamp(0.0, 1.0);
+
+    // With no transparency anywhere, later frames only need the pixels that
+    // changed; everything else is left transparent over the previous frame.
+    let opaque = frames
+        .iter()
+        .all(|frame| frame.pixels().all(|pixel| pixel[3] >= GIF_ALPHA_THRESHOLD));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut bytes, gif_width, gif_height, &global_palette)
+            .map_err(|e| format!("Failed to encode GIF: {e}"))?;
+        // A single play means no NETSCAPE loop extension at all.
+        let repeat = match plays {
+            0 => Some(gif::Repeat::Infinite),
+            1 => None,
+            n => Some(gif::Repeat::Finite(n - 1)),
+        };
+        if let Some(repeat) = repeat {
+            encoder
+                .set_repeat(repeat)
+                .map_err(|e| format!("Failed to encode GIF: {e}"))?;
+        }
+
+        let mut previous: Option<Vec<u8>> = None;
+        for (frame, &delay_ms) in frames.iter().zip(delays_ms) {
+            let indices = map_to_palette(frame, &palette, transparent, dithering);
+            let delay =
+                (delay_ms.saturating_add(5) / 10).clamp(GIF_MIN_DELAY_CS as u32, u16::MAX as u32);
+
+            let mut gif_frame = match previous.as_deref().filter(|_| opaque) {
+                Some(previous) => changed_region(&indices, previous, width, transparent),
+                None => gif::Frame::from_indexed_pixels(
+                    gif_width,
+                    gif_height,
+                    indices.clone(),
+                    Some(transparent),
+                ),
+            };
+            gif_frame.delay = delay as u16;
+            gif_frame.dispose = if opaque {
+                gif::DisposalMethod::Keep
+            } else {
+                gif::DisposalMethod::Background
+            };
+            encoder
+                .write_frame(&gif_frame)
+                .map_err(|e| format!("Failed to encode GIF: {e}"))?;
+            previous = Some(indices);
+        }
+    }
+    Ok(bytes)
+}
+
+/// One palette for every frame, leaving room for the transparent index.
+fn gif_palette(frames: &[RgbaImage], max_colors: usize) -> Vec<[u8; 4]> {
+    let mut histogram: HashMap<[u8; 4], u32> = HashMap::new();
+    for frame in frames {
+        for pixel in frame.pixels() {
+            if pixel[3] >= GIF_ALPHA_THRESHOLD {
+                *histogram
+                    .entry([pixel[0], pixel[1], pixel[2], 255])
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+    if histogram.is_empty() {
+        return vec![[0, 0, 0, 255]];
+    }
+    png_optimize::median_cut(
+        histogram.into_iter().collect(),
+        max_colors.clamp(2, 256) - 1,
+    )
+}
+
+fn map_to_palette(
+    frame: &RgbaImage,
+    palette: &[[u8; 4]],
+    transparent: u8,
+    dithering: f32,
+) -> Vec<u8> {
+    // Opaque copies so the alpha channel doesn't feed the error diffusion.
+    let opaque: Vec<u8> = frame
+        .pixels()
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect();
+    let mut indices = if dithering > 0.0 {
+        png_optimize::dither(&opaque, frame.width() as usize, palette, dithering)
+    } else {
+        let mut cache: HashMap<[u8; 4], u8> = HashMap::new();
+        opaque
+            .chunks_exact(4)
+            .map(|p| {
+                let color = [p[0], p[1], p[2], p[3]];
+                *cache
+                    .entry(color)
+                    .or_insert_with(|| png_optimize::nearest(palette, color.map(f32::from)))
+            })
+            .collect()
+    };
+    for (index, pixel) in indices.iter_mut().zip(frame.pixels()) {
+        if pixel[3] < GIF_ALPHA_THRESHOLD {
+            *index = transparent;
+        }
+    }
+    indices
+}
+
+/// The bounding box of pixels that differ from `previous`, with unchanged
+/// pixels inside it made transparent.
+fn changed_region(
+    indices: &[u8],
+    previous: &[u8],
+    width: u32,
+    transparent: u8,
+) -> gif::Frame<'static> {
+    let width = width as usize;
+    let height = indices.len() / width;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+    for (i, (current, before)) in indices.iter().zip(previous).enumerate() {
+        if current != before {
+            let (x, y) = (i % width, i / width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    // Identical frames still need a pixel to carry the delay.
+    if min_x > max_x {
+        (min_x, min_y, max_x, max_y) = (0, 0, 0, 0);
+    }
+
+    let mut buffer = Vec::with_capacity((max_x - min_x + 1) * (max_y - min_y + 1));
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let i = y * width + x;
+            buffer.push(if indices[i] == previous[i] {
+                transparent
+            } else {
+                indices[i]
+            });
+        }
+    }
+    let mut frame = gif::Frame::from_indexed_pixels(
+        (max_x - min_x + 1) as u16,
+        (max_y - min_y + 1) as u16,
+        buffer,
+        Some(transparent),
+    );
+    frame.left = min_x as u16;
+    frame.top = min_y as u16;
+    frame
+}
+
+fn encode_apng(frames: &[RgbaImage], delays_ms: &[u32], plays: u16) -> Result<Vec<u8>, String> {
+    let (width, height) = frames[0].dimensions();
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::High);
+        encoder
+            .set_animated(frames.len() as u32, plays as u32)
+            .map_err(|e| format!("Failed to encode APNG: {e}"))?;
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("Failed to encode APNG: {e}"))?;
+        for (frame, &delay_ms) in frames.iter().zip(delays_ms) {
+            // fcTL delays are a u16 fraction; fall back to centiseconds for
+            // delays over a minute.
+            let (numerator, denominator) = match u16::try_from(delay_ms) {
+                Ok(ms) => (ms, 1000),
+                Err(_) => (
+                    (delay_ms.saturating_add(5) / 10).min(u16::MAX as u32) as u16,
+                    100,
+                ),
+            };
+            writer
+                .set_frame_delay(numerator, denominator)
+                .map_err(|e| format!("Failed to encode APNG: {e}"))?;
+            writer
+                .write_image_data(frame.as_raw())
+                .map_err(|e| format!("Failed to encode APNG: {e}"))?;
+        }
+        writer
+            .finish()
+            .map_err(|e| format!("Failed to encode APNG: {e}"))?;
+    }
+    Ok(bytes)
+}
+
+/// Lossless frames from the still encoder, rewrapped as ANMF chunks in an
+/// extended-format container.
+fn encode_webp(frames: &[RgbaImage], delays_ms: &[u32], plays: u16) -> Result<Vec<u8>, String> {
+    let (width, height) = frames[0].dimensions();
+    if width > 1 << 24 || height > 1 << 24 {
+        return Err(format!(
+            "image_too_large: WebP supports up to 16777216px, got {width}x{height}"
+        ));
+    }
+
+    let mut body = Vec::new();
+    body.extend_from_slice(b"WEBP");
+    let mut vp8x = vec![0x10 | 0x02, 0, 0, 0];
+    vp8x.extend_from_slice(&u24(width - 1));
+    vp8x.extend_from_slice(&u24(height - 1));
+    push_chunk(&mut body, b"VP8X", &vp8x);
+    let mut anim = vec![0, 0, 0, 0];
+    anim.extend_from_slice(&plays.to_le_bytes());
+    push_chunk(&mut body, b"ANIM", &anim);
+
+    for (frame, &delay_ms) in frames.iter().zip(delays_ms) {
+        let mut still = Vec::new();
+        image::codecs::webp::WebPEncoder::new_lossless(&mut still)
+            .encode(
+                frame.as_raw(),
+                width,
+                height,
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| format!("Failed to encode WebP: {e}"))?;
+
+        let mut anmf = Vec::new();
+        anmf.extend_from_slice(&u24(0));
+        anmf.extend_from_slice(&u24(0));
+        anmf.extend_from_slice(&u24(width - 1));
+        anmf.extend_from_slice(&u24(height - 1));
+        anmf.extend_from_slice(&u24(delay_ms.min(WEBP_MAX_DURATION_MS)));
+        // Full frames: replace rather than alpha-blend, no disposal.
+        anmf.push(0x02);
+        for (fourcc, data) in riff_chunks(&still)? {
+            if matches!(fourcc, b"ALPH" | b"VP8 " | b"VP8L") {
+                push_chunk(&mut anmf, fourcc, data);
+            }
+        }
+        push_chunk(&mut body, b"ANMF", &anmf);
+    }
+
+    let mut bytes = Vec::with_capacity(body.len() + 8);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+fn push_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// A RIFF chunk's FourCC and payload.
+type Chunk<'a> = (&'a [u8; 4], &'a [u8]);
+
+/// The chunks of a RIFF WEBP file, after the file header.
+fn riff_chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+    let invalid = || "Failed to encode WebP: malformed still frame".to_string();
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return Err(invalid());
+    }
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let fourcc: &[u8; 4] = bytes[offset..offset + 4]
+            .try_into()
+            .map_err(|_| invalid())?;
+        let len = u32::from_le_bytes(
+            bytes[offset + 4..offset + 8]
+                .try_into()
+                .map_err(|_| invalid())?,
+        ) as usize;
+        let data = bytes
+            .get(offset + 8..offset + 8 + len)
+            .ok_or_else(invalid)?;
+        chunks.push((fourcc, data));
+        offset += 8 + len + len % 2;
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_frames, riff_chunks, AnimationOptions};
+    use image::{Rgba, RgbaImage};
+
+    fn frames() -> Vec<RgbaImage> {
+        (0..3)
+            .map(|i| {
+                let mut frame = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+                frame.put_pixel(i, i, Rgba([200, 0, 0, 255]));
+                frame
+            })
+            .collect()
+    }
+
+    fn options(format: &str) -> AnimationOptions {
+        AnimationOptions {
+            width: 8,
+            height: 8,
+            format: format.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gif_frames_decode_with_delays() {
+        let bytes = encode_frames(&frames(), &[100, 40, 5], &options("gif")).unwrap();
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(bytes.as_slice()).unwrap();
+        let mut delays = Vec::new();
+        let mut sizes = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+            sizes.push((frame.width, frame.height));
+        }
+        assert_eq!(delays, vec![10, 4, 2]);
+        let bytes = encode_frames(&frames(), &[u32::MAX; 3], &options("gif")).unwrap();
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(bytes.as_slice())
+            .unwrap();
+        assert_eq!(decoder.read_next_frame().unwrap().unwrap().delay, u16::MAX);
+        // Later frames only carry the changed region.
+        assert_eq!(sizes[0], (8, 8));
+        assert!(sizes[1].0 < 8 && sizes[1].1 < 8);
+    }
+
+    #[test]
+    fn apng_declares_every_frame() {
+        let bytes = encode_frames(&frames(), &[100, 100, 100], &options("apng")).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let reader = decoder.read_info().unwrap();
+        let control = reader.info().animation_control().unwrap();
+        assert_eq!(control.num_frames, 3);
+        assert_eq!(control.num_plays, 0);
+    }
+
+    #[test]
+    fn webp_wraps_each_frame() {
+        let mut animation = options("webp");
+        animation.loop_count = Some(2);
+        let bytes = encode_frames(&frames(), &[100, 100, 100], &animation).unwrap();
+        let chunks = riff_chunks(&bytes).unwrap();
+        let names: Vec<&[u8; 4]> = chunks.iter().map(|(fourcc, _)| *fourcc).collect();
+        assert_eq!(names, vec![b"VP8X", b"ANIM", b"ANMF", b"ANMF", b"ANMF"]);
+        assert_eq!(chunks[1].1[4..6], 2u16.to_le_bytes());
+    }
+
+    #[test]
+    fn mismatched_delays_are_rejected() {
+        assert!(encode_frames(&frames(), &[100], &options("gif")).is_err());
+        assert!(encode_frames(&[], &[], &options("gif")).is_err());
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected_without_overflowing() {
+        assert!(crate::rgba_image(Vec::new(), u32::MAX, u32::MAX).is_err());
+    }
+}
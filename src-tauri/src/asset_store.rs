@@ -0,0 +1,513 @@
+//! Content-addressed blob store for document assets.
+//!
+//! Blobs are keyed by the hex SHA-256 of their bytes and live in
+//! `.galileo-assets/` beside the project file, or in the app data dir for
+//! documents that haven't been saved yet. A saved `.galileo` file keeps its
+//! assets' `mime`, size and `hash` but not their `dataBase64`, so saving
+//! doesn't rewrite image data that hasn't changed and documents in the same
+//! folder share identical images.
+
+use crate::binary_ipc;
+use crate::bundle;
+use crate::document_backup;
+use crate::draft_store::write_atomic;
+use crate::image_variants::content_hash;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+
+const PROJECT_STORE_DIR: &str = ".galileo-assets";
+const APP_STORE_DIR: &str = "assets";
+const DOCUMENT_EXT: &str = "galileo";
+/// Blobs younger than this survive GC even when unreferenced, since the
+/// document that uses them may not have been saved yet.
+const GC_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+/// Asset fields that hold a nested image of their own
+const VARIANT_FIELDS: [&str; 2] = ["proxy", "thumbnail"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetPutArgs {
+    /// The `.galileo` file the asset belongs to; the app store when omitted
+    pub project_path: Option<String>,
+    pub data_base64: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetPutRawArgs {
+    pub project_path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetPutResult {
+    pub hash: String,
+    pub byte_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetGetArgs {
+    pub project_path: Option<String>,
+    pub hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetGcArgs {
+    pub project_path: Option<String>,
+    /// Documents whose assets must be kept. Every `.galileo` file beside
+    /// `project_path` is always included, and so are their backups.
+    #[serde(default)]
+    pub documents: Vec<String>,
+    /// Extra hashes to keep, e.g. assets of unsaved documents
+    #[serde(default)]
+    pub keep: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetGcReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub kept: usize,
+}
+
+pub struct AssetStore {
+    root: PathBuf,
+}
+
+impl AssetStore {
+    /// The store shared by documents in `document`'s folder.
+    pub fn beside(document: &Path) -> Self {
+        let dir = document.parent().unwrap_or_else(|| Path::new("."));
+        Self {
+            root: dir.join(PROJECT_STORE_DIR),
+        }
+    }
+
+    fn open(app: &tauri::AppHandle, project_path: Option<&str>) -> Result<Self, String> {
+        match project_path {
+            Some(path) => Ok(Self::beside(Path::new(path))),
+            None => {
+                let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+                Ok(Self {
+                    root: app_data.join(APP_STORE_DIR),
+                })
+            }
+        }
+    }
+
+    fn blob_path(&self, hash: &str) -> Result<PathBuf, String> {
+        let valid = hash.len() == 64
+            && hash
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+        if !valid {
+            return Err(format!("invalid_hash: {hash}"));
+        }
+        Ok(self.root.join(&hash[..2]).join(hash))
+    }
+
+    /// Stores `bytes` unless an identical blob exists; returns its hash.
+    pub fn put(&self, bytes: &[u8]) -> Result<String, String> {
+        let hash = content_hash(bytes);
+        let path = self.blob_path(&hash)?;
+        if !path.exists() {
+            write_atomic(&path, bytes)?;
+        }
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Vec<u8>, String> {
+        let path = self.blob_path(hash)?;
+        fs::read(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("asset_not_found: {hash}"),
+            _ => e.to_string(),
+        })
+    }
+
+    pub fn open_blob(&self, hash: &str) -> Result<fs::File, String> {
+        let path = self.blob_path(hash)?;
+        fs::File::open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("asset_not_found: {hash}"),
+            _ => e.to_string(),
+        })
+    }
+
+    /// Removes blobs not in `keep` that are older than `grace`.
+    pub fn gc(&self, keep: &HashSet<String>, grace: Duration) -> Result<AssetGcReport, String> {
+        let mut report = AssetGcReport::default();
+        let Ok(shards) = fs::read_dir(&self.root) else {
+            return Ok(report);
+        };
+        let now = SystemTime::now();
+        for shard in shards.flatten() {
+            let Ok(blobs) = fs::read_dir(shard.path()) else {
+                continue;
+            };
+            for blob in blobs.flatten() {
+                let name = blob.file_name().to_string_lossy().to_string();
+                if keep.contains(&name) {
+                    report.kept += 1;
+                    continue;
+                }
+                let Ok(metadata) = blob.metadata() else {
+                    continue;
+                };
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .unwrap_or_default();
+                if age < grace {
+                    report.kept += 1;
+                    continue;
+                }
+                fs::remove_file(blob.path()).map_err(|e| e.to_string())?;
+                report.removed += 1;
+                report.freed_bytes += metadata.len();
+            }
+            // Only succeeds once the shard is empty.
+            let _ = fs::remove_dir(shard.path());
+        }
+        Ok(report)
+    }
+}
+
+#[tauri::command]
+pub fn asset_put(app: tauri::AppHandle, args: AssetPutArgs) -> Result<AssetPutResult, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(&args.data_base64)
+        .map_err(|e| format!("Invalid base64: {e}"))?;
+    let store = AssetStore::open(&app, args.project_path.as_deref())?;
+    Ok(AssetPutResult {
+        hash: store.put(&bytes)?,
+        byte_size: bytes.len(),
+    })
+}
+
+/// `asset_put` with the blob as the raw invoke body.
+#[tauri::command]
+pub fn asset_put_raw(
+    app: tauri::AppHandle,
+    request: tauri::ipc::Request<'_>,
+) -> Result<AssetPutResult, String> {
+    let args: AssetPutRawArgs = binary_ipc::header_args(&request)?;
+    let bytes = binary_ipc::raw_body(&request)?;
+    let store = AssetStore::open(&app, args.project_path.as_deref())?;
+    Ok(AssetPutResult {
+        hash: store.put(bytes)?,
+        byte_size: bytes.len(),
+    })
+}
+
+#[tauri::command]
+pub fn asset_get(app: tauri::AppHandle, args: AssetGetArgs) -> Result<String, String> {
+    let store = AssetStore::open(&app, args.project_path.as_deref())?;
+    Ok(general_purpose::STANDARD.encode(store.get(&args.hash)?))
+}
+
+#[tauri::command]
+pub fn asset_get_raw(
+    app: tauri::AppHandle,
+    args: AssetGetArgs,
+) -> Result<tauri::ipc::Response, String> {
+    let store = AssetStore::open(&app, args.project_path.as_deref())?;
+    Ok(tauri::ipc::Response::new(store.get(&args.hash)?))
+}
+
+#[tauri::command]
+pub fn asset_gc(app: tauri::AppHandle, args: AssetGcArgs) -> Result<AssetGcReport, String> {
+    let store = AssetStore::open(&app, args.project_path.as_deref())?;
+    let documents = live_documents(args.project_path.as_deref(), &args.documents)?;
+    let mut keep: HashSet<String> = args.keep.into_iter().collect();
+    keep.extend(referenced_hashes(&documents)?);
+    store.gc(&keep, GC_GRACE_PERIOD)
+}
+
+/// `documents`, every document beside `project_path`, and the backups of
+/// all of them.
+fn live_documents(
+    project_path: Option<&str>,
+    documents: &[String],
+) -> Result<Vec<PathBuf>, String> {
+    let mut documents: Vec<PathBuf> = documents.iter().map(PathBuf::from).collect();
+    if let Some(dir) = project_path.and_then(|path| Path::new(path).parent()) {
+        let siblings = fs::read_dir(dir).map_err(|e| e.to_string())?;
+        documents.extend(
+            siblings
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == DOCUMENT_EXT)),
+        );
+    }
+
+    // Backups are restorable, so their assets are live too.
+    let backups: Vec<PathBuf> = documents
+        .iter()
+        .flat_map(|document| {
+            (0..document_backup::BACKUP_COUNT)
+                .map(|index| document_backup::backup_path(document, index))
+        })
+        .filter(|backup| backup.exists())
+        .collect();
+    documents.extend(backups);
+    Ok(documents)
+}
+
+/// Blob hashes the plain JSON documents among `documents` refer to. Bundles
+/// carry their own copy of every asset, so they keep no blobs alive.
+fn referenced_hashes(documents: &[PathBuf]) -> Result<HashSet<String>, String> {
+    let mut hashes = HashSet::new();
+    for path in documents {
+        // A document that can't be read keeps nothing alive, so refuse to
+        // collect rather than delete assets it may still use.
+        let unreadable = |e: String| format!("unreadable_document: {}: {e}", path.display());
+        if bundle::is_bundle(path).map_err(unreadable)? {
+            continue;
+        }
+        let content = fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+        let document: Value = serde_json::from_str(&content)
+            .map_err(|e| format!("invalid_document: {}: {e}", path.display()))?;
+        for_each_image(&document, |image| {
+            if let Some(hash) = image.get("hash").and_then(Value::as_str) {
+                hashes.insert(hash.to_string());
+            }
+        });
+    }
+    Ok(hashes)
+}
+
+/// Moves inline `dataBase64` of every asset (and its proxy and thumbnail)
+/// into `store`, leaving a `hash` reference. The rest of the document is
+/// passed through as JSON values so nothing the native types don't model
+/// is lost.
+pub fn externalize(store: &AssetStore, content: &str) -> Result<String, String> {
+    let mut document: Value =
+        serde_json::from_str(content).map_err(|e| format!("invalid_document: {e}"))?;
+    let mut result = Ok(());
+    for_each_image_mut(&mut document, |image| {
+        let Some(Value::String(data)) = image.get("dataBase64") else {
+            return;
+        };
+        let stored = general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("invalid_asset: {e}"))
+            .and_then(|bytes| store.put(&bytes));
+        match stored {
+            Ok(hash) => {
+                image.remove("dataBase64");
+                image.insert("hash".to_string(), Value::String(hash));
+            }
+            Err(error) => {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+    });
+    result?;
+    serde_json::to_string_pretty(&document).map_err(|e| e.to_string())
+}
+
+/// Restores `dataBase64` for asset references found in `store`. A missing
+/// blob leaves its reference in place, so the document still opens with
+/// that image unresolved rather than failing to load.
+pub fn rehydrate(store: &AssetStore, content: String) -> Result<String, String> {
+    let Ok(mut document) = serde_json::from_str::<Value>(&content) else {
+        return Ok(content);
+    };
+    let mut changed = false;
+    for_each_image_mut(&mut document, |image| {
+        if image.contains_key("dataBase64") {
+            return;
+        }
+        let Some(hash) = image.get("hash").and_then(Value::as_str) else {
+            return;
+        };
+        if let Ok(bytes) = store.get(hash) {
+            let data = general_purpose::STANDARD.encode(bytes);
+            image.insert("dataBase64".to_string(), Value::String(data));
+            changed = true;
+        }
+    });
+    if !changed {
+        return Ok(content);
+    }
+    serde_json::to_string(&document).map_err(|e| e.to_string())
+}
+
+fn for_each_image(document: &Value, mut visit: impl FnMut(&Map<String, Value>)) {
+    let Some(assets) = document.get("assets").and_then(Value::as_object) else {
+        return;
+    };
+    for asset in assets.values().filter_map(Value::as_object) {
+        visit(asset);
+        for field in VARIANT_FIELDS {
+            if let Some(variant) = asset.get(field).and_then(Value::as_object) {
+                visit(variant);
+            }
+        }
+    }
+}
+
+pub(crate) fn for_each_image_mut(
+    document: &mut Value,
+    mut visit: impl FnMut(&mut Map<String, Value>),
+) {
+    let Some(assets) = document.get_mut("assets").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for asset in assets.values_mut().filter_map(Value::as_object_mut) {
+        visit(asset);
+        for field in VARIANT_FIELDS {
+            if let Some(variant) = asset.get_mut(field).and_then(Value::as_object_mut) {
+                visit(variant);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store(name: &str) -> AssetStore {
+        let root =
+            std::env::temp_dir().join(format!("galileo-assets-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        AssetStore { root }
+    }
+
+    #[test]
+    fn blobs_are_keyed_by_content() {
+        let store = store("put");
+        let hash = store.put(b"pixels").unwrap();
+        assert_eq!(store.put(b"pixels").unwrap(), hash);
+        assert_eq!(store.get(&hash).unwrap(), b"pixels");
+        assert!(store
+            .get(&content_hash(b"other"))
+            .unwrap_err()
+            .starts_with("asset_not_found"));
+        assert!(store
+            .get("../../etc/passwd")
+            .unwrap_err()
+            .starts_with("invalid_hash"));
+        let _ = fs::remove_dir_all(&store.root);
+    }
+
+    #[test]
+    fn saving_swaps_inline_data_for_references() {
+        let store = store("roundtrip");
+        let data = general_purpose::STANDARD.encode(b"original");
+        let thumb = general_purpose::STANDARD.encode(b"thumb");
+        let document = json!({
+            "version": 9,
+            "assets": {
+                "a1": {
+                    "type": "image", "mime": "image/png", "width": 4, "height": 4,
+                    "dataBase64": data,
+                    "thumbnail": { "mime": "image/png", "width": 1, "height": 1, "dataBase64": thumb },
+                },
+            },
+            "nodes": {},
+        })
+        .to_string();
+
+        let saved: Value = serde_json::from_str(&externalize(&store, &document).unwrap()).unwrap();
+        let asset = &saved["assets"]["a1"];
+        assert!(asset.get("dataBase64").is_none());
+        assert_eq!(asset["hash"], content_hash(b"original"));
+        assert_eq!(asset["thumbnail"]["hash"], content_hash(b"thumb"));
+        assert_eq!(asset["mime"], "image/png");
+
+        let loaded: Value =
+            serde_json::from_str(&rehydrate(&store, saved.to_string()).unwrap()).unwrap();
+        assert_eq!(loaded["assets"]["a1"]["dataBase64"], data);
+        assert_eq!(loaded["assets"]["a1"]["thumbnail"]["dataBase64"], thumb);
+        let _ = fs::remove_dir_all(&store.root);
+    }
+
+    #[test]
+    fn gc_removes_only_unreferenced_blobs() {
+        let store = store("gc");
+        let kept = store.put(b"kept").unwrap();
+        let dropped = store.put(b"dropped").unwrap();
+
+        let report = store.gc(&HashSet::new(), GC_GRACE_PERIOD).unwrap();
+        assert_eq!(report.removed, 0, "fresh blobs are in their grace period");
+
+        let report = store
+            .gc(&HashSet::from([kept.clone()]), Duration::ZERO)
+            .unwrap();
+        assert_eq!((report.removed, report.kept), (1, 1));
+        assert_eq!(report.freed_bytes, 7);
+        assert!(store.get(&kept).is_ok());
+        assert!(store.get(&dropped).is_err());
+        let _ = fs::remove_dir_all(&store.root);
+    }
+
+    #[test]
+    fn bundles_keep_their_own_assets() {
+        let dir = std::env::temp_dir().join(format!("galileo-gc-bundle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("plain.galileo");
+        let packaged = dir.join("packaged.galileo");
+        let hash = content_hash(b"pixels");
+        let document = json!({ "assets": { "a1": { "mime": "image/png", "hash": hash } } });
+        fs::write(&plain, document.to_string()).unwrap();
+        fs::write(&packaged, b"PK\x03\x04not json").unwrap();
+
+        let hashes = referenced_hashes(&[plain, packaged]).unwrap();
+        assert_eq!(hashes, HashSet::from([hash]));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn gc_keeps_assets_that_only_a_backup_uses() {
+        let dir = std::env::temp_dir().join(format!("galileo-gc-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("design.galileo");
+        let store = AssetStore::beside(&path);
+        let save = |pixels: &[u8]| {
+            let data = general_purpose::STANDARD.encode(pixels);
+            let document =
+                json!({ "assets": { "a1": { "mime": "image/png", "dataBase64": data } } });
+            let content = externalize(&store, &document.to_string()).unwrap();
+            document_backup::save(&path, &Default::default(), |file| {
+                std::io::Write::write_all(file, content.as_bytes()).map_err(|e| e.to_string())
+            })
+            .unwrap();
+        };
+        save(b"before");
+        save(b"after");
+
+        let documents = live_documents(path.to_str(), &[]).unwrap();
+        let report = store
+            .gc(&referenced_hashes(&documents).unwrap(), Duration::ZERO)
+            .unwrap();
+        assert_eq!(report.removed, 0);
+
+        document_backup::restore_document_backup(document_backup::RestoreBackupArgs {
+            path: path.to_string_lossy().to_string(),
+            index: 0,
+        })
+        .unwrap();
+        let restored: Value = serde_json::from_str(&bundle::read_text(&path).unwrap()).unwrap();
+        assert_eq!(
+            restored["assets"]["a1"]["dataBase64"],
+            general_purpose::STANDARD.encode(b"before")
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,46 +1,658 @@
 #![allow(unexpected_cfgs)]
 
-use base64::engine::general_purpose;
+use crate::binary_ipc::{self, BlobRef, FramedResponse};
+use crate::matting::{self, MaskBounds};
+use base64::{engine::general_purpose, Engine as _};
+use image::{ImageBuffer, ImageFormat, Luma, RgbaImage};
 use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+pub const PROVIDER_APPLE_VISION: &str = "apple-vision";
+pub const PROVIDER_ONNX: &str = "onnx";
+
+#[cfg(target_os = "macos")]
+const DEFAULT_PROVIDER: &str = PROVIDER_APPLE_VISION;
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_PROVIDER: &str = PROVIDER_ONNX;
+
+/// Alpha below this doesn't count towards a subject's bounding box, so
+/// faint guided-filter residue doesn't inflate it.
+const BOUNDS_THRESHOLD: u8 = 8;
+
+const OUTPUT_MASK: &str = "mask";
+const OUTPUT_CUTOUT: &str = "cutout";
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoveBackgroundArgs {
     pub image_base64: String,
+    #[serde(flatten)]
+    pub options: RemoveBackgroundOptions,
+}
+
+/// Everything but the image, shared by the base64 and raw commands.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveBackgroundOptions {
+    /// "apple-vision" or "onnx"; defaults to the best provider for this platform
+    pub provider: Option<String>,
+    /// Extra edge softening radius in pixels, applied after refinement;
+    /// capped at the image's larger side
+    pub feather: Option<f32>,
+    /// Grows (positive) or shrinks (negative) the mask edge by this many
+    /// pixels, up to the image's larger side
+    pub edge_shift: Option<f32>,
+    /// Also return the source with edge colors re-estimated so the old
+    /// background doesn't bleed into semi-transparent pixels
+    pub decontaminate_colors: Option<bool>,
+    /// Instance ids to keep; all detected instances when omitted
+    pub instances: Option<Vec<u32>>,
+    /// "mask" (default) or "cutout" to also return the cropped subject
+    pub output: Option<String>,
+    /// "png" (default) or "webp"; only used for cut-outs
+    pub format: Option<String>,
+    /// Transparent margin around the cut-out's subject bounds, in pixels;
+    /// capped at the image's larger side
+    pub padding: Option<u32>,
+}
+
+/// The subject with premultiplied colors.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CutoutImage {
+    pub data_base64: String,
+    pub mime: String,
+    /// Top-left of the cut-out in source image coordinates; negative when the
+    /// padding reaches past the image edge
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectInstance {
+    /// Stable within one result; pass back via `RemoveBackgroundArgs::instances`
+    pub id: u32,
+    pub bounds: MaskBounds,
+    pub mask_png_base64: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoveBackgroundResult {
+    /// Combined mask of the selected instances
     pub mask_png_base64: String,
     pub width: u32,
     pub height: u32,
     pub revision: Option<i32>,
+    pub provider: String,
+    pub model: String,
+    /// Source image with decontaminated edge colors, when requested
+    pub decontaminated_png_base64: Option<String>,
+    /// Every detected subject, selected or not
+    pub instances: Vec<SubjectInstance>,
+    pub selected_instances: Vec<u32>,
+    pub bounds: Option<MaskBounds>,
+    /// Subject with the mask applied, when `output` is "cutout"
+    pub cutout: Option<CutoutImage>,
+}
+
+/// Framed-response metadata for `remove_background_raw`; mirrors
+/// [`RemoveBackgroundResult`] with blobs in place of base64 strings.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveBackgroundRawResult {
+    pub mask_png: BlobRef,
+    pub width: u32,
+    pub height: u32,
+    pub revision: Option<i32>,
+    pub provider: String,
+    pub model: String,
+    pub decontaminated_png: Option<BlobRef>,
+    pub instances: Vec<SubjectInstanceRaw>,
+    pub selected_instances: Vec<u32>,
+    pub bounds: Option<MaskBounds>,
+    pub cutout: Option<CutoutImageRaw>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectInstanceRaw {
+    pub id: u32,
+    pub bounds: MaskBounds,
+    pub mask_png: BlobRef,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CutoutImageRaw {
+    pub data: BlobRef,
+    pub mime: String,
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Encoded outputs of one removal, before they're packed for transport.
+struct Removal {
+    mask_png: Vec<u8>,
+    width: u32,
+    height: u32,
+    revision: Option<i32>,
+    provider: &'static str,
+    model: String,
+    decontaminated_png: Option<Vec<u8>>,
+    instances: Vec<(u32, MaskBounds, Vec<u8>)>,
+    selected_instances: Vec<u32>,
+    bounds: Option<MaskBounds>,
+    cutout: Option<Cutout>,
+}
+
+struct Cutout {
+    data: Vec<u8>,
+    mime: &'static str,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+}
+
+/// Soft foreground confidence for one detected subject, before refinement.
+struct ProviderInstance {
+    id: u32,
+    mask: Vec<u8>,
+}
+
+struct ProviderMask {
+    instances: Vec<ProviderInstance>,
+    mask_width: u32,
+    mask_height: u32,
+    image_width: u32,
+    image_height: u32,
+    revision: Option<i32>,
+    provider: &'static str,
+    model: String,
+}
+
+#[tauri::command(async)]
+pub fn remove_background(
+    app: tauri::AppHandle,
+    args: RemoveBackgroundArgs,
+) -> Result<RemoveBackgroundResult, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(&args.image_base64)
+        .map_err(|e| format!("Failed to decode image bytes: {e}"))?;
+    let removal = run_removal(&app, &bytes, &args.options)?;
+
+    let encode = |bytes: Vec<u8>| general_purpose::STANDARD.encode(bytes);
+    Ok(RemoveBackgroundResult {
+        mask_png_base64: encode(removal.mask_png),
+        width: removal.width,
+        height: removal.height,
+        revision: removal.revision,
+        provider: removal.provider.to_string(),
+        model: removal.model,
+        decontaminated_png_base64: removal.decontaminated_png.map(encode),
+        instances: removal
+            .instances
+            .into_iter()
+            .map(|(id, bounds, mask_png)| SubjectInstance {
+                id,
+                bounds,
+                mask_png_base64: encode(mask_png),
+            })
+            .collect(),
+        selected_instances: removal.selected_instances,
+        bounds: removal.bounds,
+        cutout: removal.cutout.map(|cutout| CutoutImage {
+            data_base64: encode(cutout.data),
+            mime: cutout.mime.to_string(),
+            x: cutout.x,
+            y: cutout.y,
+            width: cutout.width,
+            height: cutout.height,
+        }),
+    })
+}
+
+/// `remove_background` with the image as the raw invoke body and the
+/// options in the args header. Returns a framed [`RemoveBackgroundRawResult`].
+#[tauri::command(async)]
+pub fn remove_background_raw(
+    app: tauri::AppHandle,
+    request: tauri::ipc::Request<'_>,
+) -> Result<tauri::ipc::Response, String> {
+    let options: RemoveBackgroundOptions = binary_ipc::header_args(&request)?;
+    let removal = run_removal(&app, binary_ipc::raw_body(&request)?, &options)?;
+
+    let mut framed = FramedResponse::default();
+    let metadata = RemoveBackgroundRawResult {
+        mask_png: framed.push(&removal.mask_png),
+        width: removal.width,
+        height: removal.height,
+        revision: removal.revision,
+        provider: removal.provider.to_string(),
+        model: removal.model,
+        decontaminated_png: removal.decontaminated_png.map(|bytes| framed.push(&bytes)),
+        instances: removal
+            .instances
+            .into_iter()
+            .map(|(id, bounds, mask_png)| SubjectInstanceRaw {
+                id,
+                bounds,
+                mask_png: framed.push(&mask_png),
+            })
+            .collect(),
+        selected_instances: removal.selected_instances,
+        bounds: removal.bounds,
+        cutout: removal.cutout.map(|cutout| CutoutImageRaw {
+            data: framed.push(&cutout.data),
+            mime: cutout.mime.to_string(),
+            x: cutout.x,
+            y: cutout.y,
+            width: cutout.width,
+            height: cutout.height,
+        }),
+    };
+    framed.finish(&metadata)
 }
 
-#[tauri::command]
-pub fn remove_background(args: RemoveBackgroundArgs) -> Result<RemoveBackgroundResult, String> {
-    #[cfg(target_os = "macos")]
-    {
-        remove_background_macos(args)
+fn run_removal(
+    app: &tauri::AppHandle,
+    bytes: &[u8],
+    args: &RemoveBackgroundOptions,
+) -> Result<Removal, String> {
+    // Vision can read formats the image crate can't (HEIC); in that case the
+    // mask is still produced, just without image-guided refinement.
+    let source = image::load_from_memory(bytes)
+        .ok()
+        .map(|img| img.to_rgba8());
+
+    let cutout_format = match args.output.as_deref().unwrap_or(OUTPUT_MASK) {
+        OUTPUT_MASK => None,
+        OUTPUT_CUTOUT => Some(match args.format.as_deref().unwrap_or("png") {
+            "png" => ImageFormat::Png,
+            "webp" => ImageFormat::WebP,
+            other => return Err(format!("unsupported_format: {other}")),
+        }),
+        other => return Err(format!("unsupported_output: {other}")),
+    };
+
+    let provider = args.provider.as_deref().unwrap_or(DEFAULT_PROVIDER);
+    let raw = match provider {
+        PROVIDER_APPLE_VISION => {
+            #[cfg(target_os = "macos")]
+            {
+                generate_mask_macos(bytes)?
+            }
+            #[cfg(not(target_os = "macos"))]
+            {
+                return Err("unsupported_platform".to_string());
+            }
+        }
+        PROVIDER_ONNX => {
+            let source = source
+                .as_ref()
+                .ok_or_else(|| "Failed to decode image".to_string())?;
+            onnx::generate_mask(app, source)?
+        }
+        other => return Err(format!("unsupported_provider: {other}")),
+    };
+    if raw.instances.is_empty() {
+        return Err("no_subject_detected".to_string());
     }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = args;
-        Err("unsupported_platform".to_string())
+
+    let mut selected_instances: Vec<u32> = match &args.instances {
+        Some(ids) => {
+            if let Some(unknown) = ids
+                .iter()
+                .find(|id| !raw.instances.iter().any(|instance| instance.id == **id))
+            {
+                return Err(format!("unknown_instance: {unknown}"));
+            }
+            raw.instances
+                .iter()
+                .map(|instance| instance.id)
+                .filter(|id| ids.contains(id))
+                .collect()
+        }
+        None => raw.instances.iter().map(|instance| instance.id).collect(),
+    };
+    if selected_instances.is_empty() {
+        return Err("no_instances_selected".to_string());
+    }
+
+    let width = raw.image_width;
+    let height = raw.image_height;
+    let guide = source
+        .as_ref()
+        .filter(|source| source.dimensions() == (width, height));
+
+    let mut mask = vec![0u8; (width * height) as usize];
+    let mut instances = Vec::with_capacity(raw.instances.len());
+    for instance in &raw.instances {
+        let refined = refine_mask(&raw, &instance.mask, guide, args)?;
+        // Refinement can erode a faint instance away entirely; it's then
+        // gone from the result, selection included.
+        let Some(bounds) =
+            matting::mask_bounds(&refined, width as usize, height as usize, BOUNDS_THRESHOLD)
+        else {
+            continue;
+        };
+        if selected_instances.contains(&instance.id) {
+            for (combined, value) in mask.iter_mut().zip(&refined) {
+                *combined = (*combined).max(*value);
+            }
+        }
+        let mask_png = encode_rgba(&mask_to_rgba(refined, width, height)?, ImageFormat::Png)?;
+        instances.push((instance.id, bounds, mask_png));
+    }
+    selected_instances.retain(|id| instances.iter().any(|(instance, ..)| instance == id));
+    if selected_instances.is_empty() {
+        return Err("no_subject_detected".to_string());
+    }
+
+    let foreground = match guide {
+        Some(guide) if args.decontaminate_colors.unwrap_or(false) => {
+            Some(matting::estimate_foreground(guide, &mask))
+        }
+        _ => None,
+    };
+    let bounds = matting::mask_bounds(&mask, width as usize, height as usize, BOUNDS_THRESHOLD);
+
+    let cutout = match cutout_format {
+        Some(format) => {
+            // Prefer decontaminated colors so the old background doesn't
+            // fringe the cut-out's soft edge.
+            let colors = foreground
+                .as_ref()
+                .or(guide)
+                .ok_or_else(|| "Failed to decode image".to_string())?;
+            let bounds = bounds
+                .as_ref()
+                .ok_or_else(|| "no_subject_detected".to_string())?;
+            let (image, x, y) = matting::cut_out(colors, &mask, bounds, args.padding.unwrap_or(0));
+            Some(Cutout {
+                data: encode_rgba(&image, format)?,
+                mime: format.to_mime_type(),
+                x,
+                y,
+                width: image.width(),
+                height: image.height(),
+            })
+        }
+        None => None,
+    };
+    let decontaminated_png = match foreground {
+        Some(foreground) => Some(encode_rgba(&foreground, ImageFormat::Png)?),
+        None => None,
+    };
+
+    Ok(Removal {
+        mask_png: encode_rgba(&mask_to_rgba(mask, width, height)?, ImageFormat::Png)?,
+        width,
+        height,
+        revision: raw.revision,
+        provider: raw.provider,
+        model: raw.model,
+        decontaminated_png,
+        instances,
+        selected_instances,
+        bounds,
+        cutout,
+    })
+}
+
+/// Scales a provider mask to the image size, snaps its edge to the image
+/// with a guided filter, then applies the user's edge shift and feather.
+fn refine_mask(
+    raw: &ProviderMask,
+    instance_mask: &[u8],
+    guide: Option<&RgbaImage>,
+    args: &RemoveBackgroundOptions,
+) -> Result<Vec<u8>, String> {
+    let width = raw.image_width;
+    let height = raw.image_height;
+    let mut mask = if raw.mask_width != width || raw.mask_height != height {
+        let mask_img: ImageBuffer<Luma<u8>, Vec<u8>> =
+            ImageBuffer::from_raw(raw.mask_width, raw.mask_height, instance_mask.to_vec())
+                .ok_or_else(|| "Failed to create mask image".to_string())?;
+        image::imageops::resize(
+            &mask_img,
+            width,
+            height,
+            image::imageops::FilterType::Triangle,
+        )
+        .into_raw()
+    } else {
+        instance_mask.to_vec()
+    };
+
+    if let Some(guide) = guide {
+        // Cover at least one source pixel of the provider mask so upsampling
+        // blockiness is smoothed away.
+        let upscale = (width as f32 / raw.mask_width.max(1) as f32)
+            .max(height as f32 / raw.mask_height.max(1) as f32);
+        let radius = (upscale * 2.0)
+            .max(width.max(height) as f32 / 512.0)
+            .ceil()
+            .max(2.0) as u32;
+        mask = matting::guided_refine(guide, &mask, radius);
+    }
+
+    // Past the image's larger side both settle on the same result, so
+    // bigger values only cost time.
+    let (w, h) = (width as usize, height as usize);
+    let limit = width.max(height) as f32;
+    if let Some(shift) = args.edge_shift {
+        matting::shift_edge(&mut mask, w, h, shift.clamp(-limit, limit));
+    }
+    if let Some(radius) = args.feather {
+        matting::feather(&mut mask, w, h, radius.min(limit));
+    }
+    Ok(mask)
+}
+
+/// White pixels carrying the mask in alpha, the format the canvas composites.
+fn mask_to_rgba(mask: Vec<u8>, width: u32, height: u32) -> Result<RgbaImage, String> {
+    let mut rgba: Vec<u8> = Vec::with_capacity(mask.len() * 4);
+    for value in mask {
+        rgba.extend_from_slice(&[255, 255, 255, value]);
+    }
+    ImageBuffer::from_raw(width, height, rgba).ok_or_else(|| "Failed to build mask PNG".to_string())
+}
+
+fn encode_rgba(image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut cursor = Cursor::new(&mut bytes);
+    image.write_to(&mut cursor, format).map_err(|e| {
+        let name = format.extensions_str().first().copied().unwrap_or("image");
+        format!("Failed to encode {}: {e}", name.to_uppercase())
+    })?;
+    Ok(bytes)
+}
+
+/// CPU segmentation with a U²-Net style ONNX model. Works on every platform
+/// as long as a model is available in the app's resource dir.
+mod onnx {
+    use super::{ProviderInstance, ProviderMask, PROVIDER_ONNX};
+    use crate::matting;
+    use image::imageops::FilterType;
+    use image::RgbaImage;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use tauri::{path::BaseDirectory, Manager};
+    use tract_onnx::prelude::*;
+
+    /// Overrides the bundled model, e.g. to try u2net or isnet locally.
+    const MODEL_PATH_ENV: &str = "GALILEO_BG_MODEL_PATH";
+    const MODEL_RESOURCES: &[&str] = &["models/u2netp.onnx", "models/u2net.onnx"];
+    const INPUT_SIZE: u32 = 320;
+    const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+    const STD: [f32; 3] = [0.229, 0.224, 0.225];
+    /// Subjects smaller than this share of the frame are treated as noise.
+    const MIN_INSTANCE_AREA: f32 = 0.005;
+    /// Saliency the model must reach somewhere for the image to have a
+    /// subject; below it, stretching would only amplify noise.
+    const SUBJECT_THRESHOLD: f32 = 0.5;
+
+    type Model = TypedRunnableModel<TypedModel>;
+    type CachedModel = Option<(PathBuf, Arc<Model>)>;
+
+    fn model_cache() -> &'static Mutex<CachedModel> {
+        static CACHE: OnceLock<Mutex<CachedModel>> = OnceLock::new();
+        CACHE.get_or_init(|| Mutex::new(None))
+    }
+
+    fn resolve_model_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+        if let Ok(value) = std::env::var(MODEL_PATH_ENV) {
+            let trimmed = value.trim();
+            if !trimmed.is_empty() {
+                let path = PathBuf::from(trimmed);
+                if path.is_file() {
+                    return Ok(path);
+                }
+                return Err(format!(
+                    "model_not_found: {MODEL_PATH_ENV} points to {}",
+                    path.display()
+                ));
+            }
+        }
+
+        for resource in MODEL_RESOURCES {
+            if let Ok(path) = app.path().resolve(resource, BaseDirectory::Resource) {
+                if path.is_file() {
+                    return Ok(path);
+                }
+            }
+        }
+        Err(format!(
+            "model_not_found: expected one of {} in the resource dir",
+            MODEL_RESOURCES.join(", ")
+        ))
+    }
+
+    fn load_model(path: &Path) -> Result<Arc<Model>, String> {
+        let mut cache = model_cache()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((cached_path, model)) = cache.as_ref() {
+            if cached_path == path {
+                return Ok(model.clone());
+            }
+        }
+
+        let size = INPUT_SIZE as usize;
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, 3, size, size]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| format!("Failed to load segmentation model: {e}"))?;
+        let model = Arc::new(model);
+        *cache = Some((path.to_path_buf(), model.clone()));
+        Ok(model)
+    }
+
+    fn model_name(path: &Path) -> String {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "u2net".to_string())
+    }
+
+    /// Runs the model and returns a min-max normalized INPUT_SIZE² mask, or
+    /// an empty one when nothing in the image is salient.
+    fn predict_mask(model: &Model, image: &RgbaImage) -> Result<Vec<u8>, String> {
+        let resized = image::imageops::resize(image, INPUT_SIZE, INPUT_SIZE, FilterType::Lanczos3);
+        // Same preprocessing as the reference U²-Net pipeline: scale by the
+        // brightest channel value, then apply ImageNet mean/std.
+        let max_value = resized
+            .as_raw()
+            .chunks_exact(4)
+            .flat_map(|pixel| &pixel[..3])
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+        let size = INPUT_SIZE as usize;
+        let input: Tensor =
+            tract_ndarray::Array4::from_shape_fn((1, 3, size, size), |(_, channel, y, x)| {
+                let value = resized.get_pixel(x as u32, y as u32)[channel] as f32 / max_value;
+                (value - MEAN[channel]) / STD[channel]
+            })
+            .into();
+
+        let outputs = model
+            .run(tvec!(input.into()))
+            .map_err(|e| format!("Segmentation failed: {e}"))?;
+        let prediction = outputs
+            .first()
+            .ok_or_else(|| "Segmentation produced no output".to_string())?
+            .to_array_view::<f32>()
+            .map_err(|e| format!("Unexpected segmentation output: {e}"))?;
+        let values: Vec<f32> = prediction.iter().copied().collect();
+        if values.len() != size * size {
+            return Err(format!(
+                "Unexpected segmentation output size: {}",
+                values.len()
+            ));
+        }
+
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        if max < SUBJECT_THRESHOLD {
+            return Ok(vec![0; size * size]);
+        }
+        let range = (max - min).max(f32::EPSILON);
+        Ok(values
+            .into_iter()
+            .map(|value| (((value - min) / range) * 255.0).round().clamp(0.0, 255.0) as u8)
+            .collect())
+    }
+
+    pub fn generate_mask(
+        app: &tauri::AppHandle,
+        image: &RgbaImage,
+    ) -> Result<ProviderMask, String> {
+        let model_path = resolve_model_path(app)?;
+        let model = load_model(&model_path)?;
+        let mask = predict_mask(&model, image)?;
+        // U²-Net predicts a single saliency map; separate subjects are its
+        // disconnected regions.
+        let size = INPUT_SIZE as usize;
+        let min_area = (MIN_INSTANCE_AREA * (size * size) as f32) as usize;
+        let instances = matting::split_instances(&mask, size, size, min_area)
+            .into_iter()
+            .enumerate()
+            .map(|(index, mask)| ProviderInstance {
+                id: index as u32 + 1,
+                mask,
+            })
+            .collect();
+
+        Ok(ProviderMask {
+            instances,
+            mask_width: INPUT_SIZE,
+            mask_height: INPUT_SIZE,
+            image_width: image.width(),
+            image_height: image.height(),
+            revision: None,
+            provider: PROVIDER_ONNX,
+            model: model_name(&model_path),
+        })
     }
 }
 
 #[cfg(target_os = "macos")]
 mod macos {
-    use super::{RemoveBackgroundArgs, RemoveBackgroundResult};
-    use base64::Engine;
-    use image::{ImageBuffer, ImageFormat, Luma, Rgba};
+    use super::{ProviderInstance, ProviderMask, PROVIDER_APPLE_VISION};
     use objc::rc::autoreleasepool;
     use objc::runtime::{Object, BOOL, NO};
     use objc::{class, msg_send, sel, sel_impl};
     use std::ffi::CStr;
-    use std::io::Cursor;
     use std::os::raw::{c_char, c_void};
     use std::ptr;
 
@@ -92,61 +704,37 @@ mod macos {
     }
 
     const PIXEL_BUFFER_LOCK_READONLY: u64 = 0;
+    /// The scaled mask is kCVPixelFormatType_OneComponent32Float.
+    const MASK_BYTES_PER_PIXEL: usize = 4;
 
-    pub fn remove_background_macos(
-        args: RemoveBackgroundArgs,
-    ) -> Result<RemoveBackgroundResult, String> {
-        let bytes = super::general_purpose::STANDARD
-            .decode(args.image_base64)
-            .map_err(|e| format!("Failed to decode image bytes: {e}"))?;
-
-        let (target_width, target_height) =
-            decode_image_dimensions(&bytes).map_err(|e| format!("Failed to decode image: {e}"))?;
-
-        let (mask, mask_width, mask_height, revision) =
-            unsafe { generate_mask_from_vision(&bytes)? };
-
-        let scaled_mask = if mask_width != target_width || mask_height != target_height {
-            let mask_img: ImageBuffer<Luma<u8>, Vec<u8>> =
-                ImageBuffer::from_raw(mask_width, mask_height, mask)
-                    .ok_or_else(|| "Failed to create mask image".to_string())?;
-            image::imageops::resize(
-                &mask_img,
-                target_width,
-                target_height,
-                image::imageops::FilterType::Triangle,
-            )
-            .into_raw()
-        } else {
-            mask
-        };
-
-        let mut rgba: Vec<u8> = Vec::with_capacity((target_width * target_height * 4) as usize);
-        for value in scaled_mask {
-            rgba.extend_from_slice(&[255, 255, 255, value]);
-        }
+    /// NSIndexSet's sentinel for "no further index".
+    const NS_NOT_FOUND: usize = isize::MAX as usize;
 
-        let image: ImageBuffer<Rgba<u8>, Vec<u8>> =
-            ImageBuffer::from_raw(target_width, target_height, rgba)
-                .ok_or_else(|| "Failed to build mask PNG".to_string())?;
+    pub fn generate_mask_macos(image_bytes: &[u8]) -> Result<ProviderMask, String> {
+        let (image_width, image_height) = decode_image_dimensions(image_bytes)
+            .map_err(|e| format!("Failed to decode image: {e}"))?;
 
-        let mut png_bytes: Vec<u8> = Vec::new();
-        let mut cursor = Cursor::new(&mut png_bytes);
-        image
-            .write_to(&mut cursor, ImageFormat::Png)
-            .map_err(|e| format!("Failed to encode mask PNG: {e}"))?;
+        let (instances, mask_width, mask_height, revision) =
+            unsafe { generate_masks_from_vision(image_bytes)? };
 
-        Ok(RemoveBackgroundResult {
-            mask_png_base64: super::general_purpose::STANDARD.encode(&png_bytes),
-            width: target_width,
-            height: target_height,
+        Ok(ProviderMask {
+            instances,
+            mask_width,
+            mask_height,
+            image_width,
+            image_height,
             revision,
+            provider: PROVIDER_APPLE_VISION,
+            model: "foreground-instance-mask".to_string(),
         })
     }
 
-    unsafe fn generate_mask_from_vision(
+    /// Reads Vision's soft mask for each detected instance. Unlike the raw
+    /// `instanceMask` label buffer, the scaled mask is Float32 confidence at
+    /// the source resolution, so hair and fur edges keep partial alpha.
+    unsafe fn generate_masks_from_vision(
         image_bytes: &[u8],
-    ) -> Result<(Vec<u8>, u32, u32, Option<i32>), String> {
+    ) -> Result<(Vec<ProviderInstance>, u32, u32, Option<i32>), String> {
         autoreleasepool(|| {
             let nsdata: *mut Object = msg_send![class!(NSData), dataWithBytes: image_bytes.as_ptr() length: image_bytes.len()];
             if nsdata.is_null() {
@@ -191,45 +779,73 @@ mod macos {
                 return Err("no_subject_detected".to_string());
             }
 
-            let pixel_buffer: *mut c_void = msg_send![observation, instanceMask];
-            if pixel_buffer.is_null() {
-                return Err("Failed to access mask pixels".to_string());
+            let all_instances: *mut Object = msg_send![observation, allInstances];
+            let mut instances = Vec::new();
+            let (mut mask_width, mut mask_height) = (0, 0);
+            let mut id: usize = msg_send![all_instances, firstIndex];
+            while id != NS_NOT_FOUND {
+                let index_set: *mut Object = msg_send![class!(NSIndexSet), indexSetWithIndex: id];
+                let (mask, width, height) = read_scaled_mask(observation, index_set, handler)?;
+                mask_width = width;
+                mask_height = height;
+                instances.push(ProviderInstance {
+                    id: id as u32,
+                    mask,
+                });
+                id = msg_send![all_instances, indexGreaterThanIndex: id];
             }
 
-            let lock_status =
-                CVPixelBufferLockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
-            if lock_status != 0 {
-                return Err("Failed to lock mask buffer".to_string());
-            }
+            Ok((instances, mask_width, mask_height, Some(revision)))
+        })
+    }
 
-            let width = CVPixelBufferGetWidth(pixel_buffer) as usize;
-            let height = CVPixelBufferGetHeight(pixel_buffer) as usize;
-            let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
-            let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
-            if base.is_null() {
-                CVPixelBufferUnlockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
-                return Err("Failed to read mask buffer".to_string());
-            }
+    unsafe fn read_scaled_mask(
+        observation: *mut Object,
+        instances: *mut Object,
+        handler: *mut Object,
+    ) -> Result<(Vec<u8>, u32, u32), String> {
+        let mut error: *mut Object = std::ptr::null_mut();
+        let pixel_buffer: *mut c_void = msg_send![observation, generateScaledMaskForImageForInstances: instances fromRequestHandler: handler error: &mut error];
+        if pixel_buffer.is_null() {
+            let message = if error.is_null() {
+                "Failed to access mask pixels".to_string()
+            } else {
+                nsstring_to_string(msg_send![error, localizedDescription])
+            };
+            return Err(message);
+        }
 
-            let bytes_per_pixel = if width > 0 { bytes_per_row / width } else { 0 };
-            if bytes_per_pixel == 0 {
-                CVPixelBufferUnlockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
-                return Err("Invalid mask buffer stride".to_string());
-            }
+        let lock_status = CVPixelBufferLockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
+        if lock_status != 0 {
+            return Err("Failed to lock mask buffer".to_string());
+        }
 
-            let mut mask: Vec<u8> = vec![0; width * height];
-            for y in 0..height {
-                let row = base.add(y * bytes_per_row);
-                for x in 0..width {
-                    let value = *row.add(x * bytes_per_pixel);
-                    mask[y * width + x] = if value > 0 { 255 } else { 0 };
-                }
-            }
+        let width = CVPixelBufferGetWidth(pixel_buffer);
+        let height = CVPixelBufferGetHeight(pixel_buffer);
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
+        if base.is_null() {
+            CVPixelBufferUnlockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
+            return Err("Failed to read mask buffer".to_string());
+        }
 
+        if bytes_per_row < width * MASK_BYTES_PER_PIXEL {
             CVPixelBufferUnlockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
+            return Err("Invalid mask buffer stride".to_string());
+        }
 
-            Ok((mask, width as u32, height as u32, Some(revision)))
-        })
+        let mut mask: Vec<u8> = vec![0; width * height];
+        for y in 0..height {
+            let row = base.add(y * bytes_per_row);
+            for x in 0..width {
+                let value = (row.add(x * MASK_BYTES_PER_PIXEL) as *const f32).read_unaligned();
+                mask[y * width + x] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, PIXEL_BUFFER_LOCK_READONLY);
+
+        Ok((mask, width as u32, height as u32))
     }
 
     unsafe fn nsstring_to_string(ns_string: *mut Object) -> String {
@@ -268,4 +884,4 @@ mod macos {
 }
 
 #[cfg(target_os = "macos")]
-use macos::remove_background_macos;
+use macos::generate_mask_macos;
@@ -0,0 +1,348 @@
+//! One render in, many files out: `export_batch` downsamples a single
+//! high-resolution RGBA render to each target scale and encodes the targets
+//! in parallel, so the frontend makes one IPC round trip instead of one per
+//! scale and format.
+
+use crate::binary_ipc;
+use crate::export::{self, EncodeOptions, EncodeSettings, ExportFormat};
+use base64::{engine::general_purpose, Engine as _};
+use image::{imageops::FilterType, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const DEFAULT_NAME_TEMPLATE: &str = "{name}{suffix}.{ext}";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportBatchArgs {
+    /// Raw RGBA pixel data as base64
+    pub rgba_base64: String,
+    #[serde(flatten)]
+    pub options: ExportBatchOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportBatchOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Scale the render was made at; target scales are relative to 1x
+    pub render_scale: f32,
+    pub directory: String,
+    /// Base file name without extension
+    pub name: String,
+    /// Placeholders: {name}, {suffix}, {scale}, {format}, {ext}, {width},
+    /// {height}. Defaults to "{name}{suffix}.{ext}".
+    pub name_template: Option<String>,
+    pub targets: Vec<ExportTarget>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTarget {
+    pub scale: f32,
+    /// e.g. "@2x"; empty when omitted
+    pub suffix: Option<String>,
+    /// Format and encoder settings, as for `encode_image`; the size is
+    /// derived from `scale`
+    #[serde(flatten)]
+    pub encode: EncodeSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedFile {
+    pub path: String,
+    pub scale: f32,
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub byte_size: usize,
+}
+
+#[tauri::command(async)]
+pub fn export_batch(args: ExportBatchArgs) -> Result<Vec<ExportedFile>, String> {
+    let rgba = general_purpose::STANDARD
+        .decode(&args.rgba_base64)
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    run_batch(rgba, &args.options)
+}
+
+/// `export_batch` with the render as the raw invoke body.
+#[tauri::command(async)]
+pub fn export_batch_raw(request: tauri::ipc::Request<'_>) -> Result<Vec<ExportedFile>, String> {
+    let options: ExportBatchOptions = binary_ipc::header_args(&request)?;
+    run_batch(binary_ipc::raw_body(&request)?.to_vec(), &options)
+}
+
+fn run_batch(rgba: Vec<u8>, options: &ExportBatchOptions) -> Result<Vec<ExportedFile>, String> {
+    let render = crate::rgba_image(rgba, options.width, options.height)?;
+    if options.targets.is_empty() {
+        return Err("no_targets".to_string());
+    }
+    if !options.render_scale.is_finite() || options.render_scale <= 0.0 {
+        return Err(format!("invalid_scale: {}", options.render_scale));
+    }
+
+    // Resolve every name up front so a bad template fails before any work.
+    let directory = PathBuf::from(&options.directory);
+    let mut planned = Vec::with_capacity(options.targets.len());
+    for target in &options.targets {
+        let (width, height) = target_size(&render, options.render_scale, target.scale)?;
+        let format = ExportFormat::parse(&target.encode.format)?;
+        let file_name = render_name(options, target, format, width, height)?;
+        if planned
+            .iter()
+            .any(|(_, _, _, path): &(_, _, _, PathBuf)| path.ends_with(&file_name))
+        {
+            return Err(format!("duplicate_file_name: {file_name}"));
+        }
+        planned.push((target, width, height, directory.join(file_name)));
+    }
+    fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+    let premultiplied = premultiply(&render);
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<ExportedFile, String>>>> =
+        Mutex::new((0..planned.len()).map(|_| None).collect());
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(planned.len());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((target, width, height, path)) = planned.get(index) else {
+                    break;
+                };
+                let result = export_target(&render, &premultiplied, target, *width, *height, path);
+                results.lock().unwrap_or_else(|p| p.into_inner())[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|p| p.into_inner())
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("Export worker failed".to_string())))
+        .collect()
+}
+
+fn target_size(render: &RgbaImage, render_scale: f32, scale: f32) -> Result<(u32, u32), String> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("invalid_scale: {scale}"));
+    }
+    let ratio = scale / render_scale;
+    if ratio > 1.0 + f32::EPSILON {
+        return Err(format!(
+            "target_exceeds_render: {scale}x needs a render of at least {scale}x, got {render_scale}x"
+        ));
+    }
+    let width = ((render.width() as f32 * ratio).round() as u32).max(1);
+    let height = ((render.height() as f32 * ratio).round() as u32).max(1);
+    Ok((width, height))
+}
+
+fn render_name(
+    options: &ExportBatchOptions,
+    target: &ExportTarget,
+    format: ExportFormat,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
+    let template = options
+        .name_template
+        .as_deref()
+        .unwrap_or(DEFAULT_NAME_TEMPLATE);
+    let name = template
+        .replace("{name}", &options.name)
+        .replace("{suffix}", target.suffix.as_deref().unwrap_or(""))
+        .replace("{scale}", &format_scale(target.scale))
+        .replace("{format}", &target.encode.format)
+        .replace("{ext}", format.extensions()[0])
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string());
+
+    let is_plain_file_name = Path::new(&name).file_name().and_then(|n| n.to_str()) == Some(&name);
+    if name.is_empty() || name.contains(['/', '\\']) || !is_plain_file_name {
+        return Err(format!("invalid_file_name: {name}"));
+    }
+    Ok(name)
+}
+
+/// "2" rather than "2.0", "1.5" stays "1.5".
+fn format_scale(scale: f32) -> String {
+    let rounded = (scale * 100.0).round() / 100.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{rounded}")
+    }
+}
+
+fn export_target(
+    render: &RgbaImage,
+    premultiplied: &Rgba32FImage,
+    target: &ExportTarget,
+    width: u32,
+    height: u32,
+    path: &Path,
+) -> Result<ExportedFile, String> {
+    let pixels = if (width, height) == render.dimensions() {
+        render.as_raw().clone()
+    } else {
+        downsample(premultiplied, width, height)
+    };
+    let options = EncodeOptions {
+        width,
+        height,
+        settings: target.encode.clone(),
+    };
+    let bytes = export::encode_rgba(&pixels, &options)?;
+    fs::write(path, &bytes).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    Ok(ExportedFile {
+        path: path.to_string_lossy().to_string(),
+        scale: target.scale,
+        format: options.settings.format,
+        width,
+        height,
+        byte_size: bytes.len(),
+    })
+}
+
+/// Resampling straight alpha lets transparent pixels' colors bleed into
+/// edges, so filter in premultiplied float space.
+pub(crate) fn premultiply(image: &RgbaImage) -> Rgba32FImage {
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0.map(|v| v as f32 / 255.0);
+        Rgba([r * a, g * a, b * a, a])
+    })
+}
+
+pub(crate) fn downsample(premultiplied: &Rgba32FImage, width: u32, height: u32) -> Vec<u8> {
+    unpremultiply(&image::imageops::resize(
+        premultiplied,
+        width,
+        height,
+        FilterType::Lanczos3,
+    ))
+}
+
+pub(crate) fn unpremultiply(premultiplied: &Rgba32FImage) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(premultiplied.as_raw().len());
+    for pixel in premultiplied.pixels() {
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        let unpremultiply = |value: f32| {
+            if alpha > 0.0 {
+                (value / alpha).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        pixels.extend_from_slice(&[
+            (unpremultiply(pixel[0]) * 255.0).round() as u8,
+            (unpremultiply(pixel[1]) * 255.0).round() as u8,
+            (unpremultiply(pixel[2]) * 255.0).round() as u8,
+            (alpha * 255.0).round() as u8,
+        ]);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        downsample, format_scale, premultiply, render_name, ExportBatchOptions, ExportTarget,
+    };
+    use crate::export::{EncodeSettings, ExportFormat};
+    use image::{Rgba, RgbaImage};
+
+    fn options(template: Option<&str>) -> ExportBatchOptions {
+        ExportBatchOptions {
+            width: 0,
+            height: 0,
+            render_scale: 3.0,
+            directory: String::new(),
+            name: "icon".to_string(),
+            name_template: template.map(str::to_string),
+            targets: Vec::new(),
+        }
+    }
+
+    fn target(scale: f32, suffix: &str, format: &str) -> ExportTarget {
+        ExportTarget {
+            scale,
+            suffix: Some(suffix.to_string()),
+            encode: EncodeSettings {
+                format: format.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn names_follow_template() {
+        let name = render_name(
+            &options(None),
+            &target(2.0, "@2x", "png"),
+            ExportFormat::Png,
+            64,
+            64,
+        );
+        assert_eq!(name.unwrap(), "icon@2x.png");
+
+        let name = render_name(
+            &options(Some("{name}-{width}x{height}-{scale}x.{ext}")),
+            &target(1.5, "", "jpeg"),
+            ExportFormat::Jpeg,
+            48,
+            48,
+        );
+        assert_eq!(name.unwrap(), "icon-48x48-1.5x.jpg");
+    }
+
+    #[test]
+    fn names_cannot_escape_the_directory() {
+        for template in ["../{name}.{ext}", "sub/{name}.{ext}", ".."] {
+            let name = render_name(
+                &options(Some(template)),
+                &target(1.0, "", "png"),
+                ExportFormat::Png,
+                1,
+                1,
+            );
+            assert!(name.is_err(), "{template}");
+        }
+        assert_eq!(format_scale(2.0), "2");
+    }
+
+    #[test]
+    fn targets_take_encoder_options_without_a_size() {
+        let target: ExportTarget =
+            serde_json::from_str(r#"{"scale":2,"suffix":"@2x","format":"jpeg","quality":80}"#)
+                .unwrap();
+        assert_eq!(target.encode.format, "jpeg");
+        assert_eq!(target.encode.quality, Some(80));
+    }
+
+    #[test]
+    fn downsampling_does_not_darken_edges() {
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+        for y in 0..4 {
+            image.put_pixel(0, y, Rgba([255, 255, 255, 255]));
+            image.put_pixel(1, y, Rgba([255, 255, 255, 255]));
+        }
+        let pixels = downsample(&premultiply(&image), 2, 2);
+        for pixel in pixels.chunks_exact(4) {
+            if pixel[3] > 0 {
+                assert!(pixel[0] > 250, "{pixel:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Raw byte transport for commands that move image or file data.
+//!
+//! The `*_raw` commands take their payload as the invoke body (a `Uint8Array`
+//! on the JS side) and return `tauri::ipc::Response` bytes, avoiding the
+//! one-third base64 inflation and the encode/decode on both ends. Scalar
+//! arguments travel as percent-encoded JSON in the [`ARGS_HEADER`] header,
+//! since header values must be ASCII.
+//!
+//! Results that carry metadata next to one or more blobs use a framed body:
+//! a little-endian `u32` metadata length, the UTF-8 JSON metadata, then the
+//! blob section. Metadata points into the blob section with [`BlobRef`]s.
+
+use percent_encoding::percent_decode_str;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{InvokeBody, Request, Response};
+
+pub const ARGS_HEADER: &str = "x-galileo-args";
+
+/// Returns the raw invoke body. JSON bodies are rejected rather than
+/// reinterpreted so a caller passing a plain array fails loudly.
+pub fn raw_body<'a>(request: &'a Request<'_>) -> Result<&'a [u8], String> {
+    match request.body() {
+        InvokeBody::Raw(bytes) => Ok(bytes),
+        InvokeBody::Json(_) => Err("expected_raw_body".to_string()),
+    }
+}
+
+/// Parses the JSON arguments sent in [`ARGS_HEADER`].
+pub fn header_args<T: DeserializeOwned>(request: &Request<'_>) -> Result<T, String> {
+    let value = request
+        .headers()
+        .get(ARGS_HEADER)
+        .ok_or_else(|| format!("missing_header: {ARGS_HEADER}"))?
+        .to_str()
+        .map_err(|e| format!("invalid_args: {e}"))?;
+    parse_args(value)
+}
+
+fn parse_args<T: DeserializeOwned>(encoded: &str) -> Result<T, String> {
+    let json = percent_decode_str(encoded)
+        .decode_utf8()
+        .map_err(|e| format!("invalid_args: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("invalid_args: {e}"))
+}
+
+/// Location of a blob inside a framed response's blob section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobRef {
+    pub offset: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct FramedResponse {
+    blobs: Vec<u8>,
+}
+
+impl FramedResponse {
+    pub fn push(&mut self, bytes: &[u8]) -> BlobRef {
+        let offset = self.blobs.len();
+        self.blobs.extend_from_slice(bytes);
+        BlobRef {
+            offset,
+            length: bytes.len(),
+        }
+    }
+
+    pub fn finish<T: Serialize>(self, metadata: &T) -> Result<Response, String> {
+        Ok(Response::new(self.into_bytes(metadata)?))
+    }
+
+    fn into_bytes<T: Serialize>(self, metadata: &T) -> Result<Vec<u8>, String> {
+        let json =
+            serde_json::to_vec(metadata).map_err(|e| format!("Failed to encode metadata: {e}"))?;
+        let length = u32::try_from(json.len()).map_err(|_| "Metadata too large".to_string())?;
+        let mut body = Vec::with_capacity(4 + json.len() + self.blobs.len());
+        body.extend_from_slice(&length.to_le_bytes());
+        body.extend_from_slice(&json);
+        body.extend_from_slice(&self.blobs);
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, BlobRef, FramedResponse};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct PathArgs {
+        path: String,
+    }
+
+    #[test]
+    fn args_are_percent_decoded_json() {
+        let args: PathArgs =
+            parse_args("%7B%22path%22%3A%22%2Ftmp%2Fd%C3%A9j%C3%A0%20vu.png%22%7D").unwrap();
+        assert_eq!(args.path, "/tmp/déjà vu.png");
+        assert!(parse_args::<PathArgs>("%7Bnot json").is_err());
+    }
+
+    #[test]
+    fn framed_response_prefixes_metadata_length() {
+        let mut framed = FramedResponse::default();
+        let first = framed.push(&[1, 2, 3]);
+        let second = framed.push(&[4]);
+        assert_eq!(
+            first,
+            BlobRef {
+                offset: 0,
+                length: 3
+            }
+        );
+        assert_eq!(
+            second,
+            BlobRef {
+                offset: 3,
+                length: 1
+            }
+        );
+
+        let body = framed.into_bytes(&json!({ "blob": second })).unwrap();
+        let length = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+        let metadata: serde_json::Value = serde_json::from_slice(&body[4..4 + length]).unwrap();
+        assert_eq!(metadata["blob"]["offset"], 3);
+        assert_eq!(&body[4 + length..], &[1, 2, 3, 4]);
+    }
+}
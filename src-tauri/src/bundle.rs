@@ -0,0 +1,588 @@
+//! Packaged `.galileo` documents. A bundle is a zip holding:
+//!
+//! - `manifest.json`: the bundle format version and an index of the files
+//! - `document.json`: the document, its assets reduced to `hash` references
+//! - `assets/<hash>.<ext>`: each distinct image once, stored uncompressed
+//! - `fonts/`: the project fonts the document's text renders with
+//! - `thumbnail.png`: a preview of the active page, when the app sent one
+//!
+//! Bundles are written when a design is packaged for sharing; regular saves
+//! stay plain JSON with assets in the project's asset store, so a save only
+//! writes what changed. Both load; they are told apart by the zip signature. Asset bytes are streamed between the archive and base64
+//! rather than buffered whole.
+
+use crate::asset_store::{self, AssetStore};
+use crate::document::Document;
+use crate::fonts::{self, DirectoryFontSource, FontFaceInfo};
+use crate::image_variants::content_hash;
+use crate::scene;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DOCUMENT_ENTRY: &str = "document.json";
+const THUMBNAIL_ENTRY: &str = "thumbnail.png";
+const ASSETS_DIR: &str = "assets";
+const FONTS_DIR: &str = "fonts";
+const ZIP_SIGNATURE: &[u8; 4] = b"PK\x03\x04";
+/// Larger project fonts are left out and listed in the manifest's
+/// `missingFonts` instead.
+const MAX_EMBEDDED_FONT_BYTES: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format_version: u32,
+    pub document_version: u32,
+    /// Keyed by content hash
+    #[serde(default)]
+    pub assets: BTreeMap<String, ManifestAsset>,
+    #[serde(default)]
+    pub fonts: Vec<ManifestFont>,
+    /// Font families the document uses that weren't embedded, including
+    /// every system font
+    #[serde(default)]
+    pub missing_fonts: Vec<String>,
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestAsset {
+    pub path: String,
+    pub mime: String,
+    pub byte_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFont {
+    pub path: String,
+    pub family: String,
+    pub style_name: String,
+    pub weight: u16,
+    pub style: String,
+    pub face_index: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadBundleFontsArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFont {
+    pub family: String,
+    pub style_name: String,
+    pub weight: u16,
+    pub style: String,
+    pub data_base64: String,
+}
+
+/// Fonts embedded in a bundle, for registering with the webview. Empty for
+/// plain JSON documents.
+#[tauri::command]
+pub fn load_bundle_fonts(args: LoadBundleFontsArgs) -> Result<Vec<BundleFont>, String> {
+    let path = Path::new(&args.path);
+    if !is_bundle(path)? {
+        return Ok(Vec::new());
+    }
+    let mut archive = open(path)?;
+    let manifest: Manifest = read_json(&mut archive, MANIFEST_ENTRY)?;
+    manifest
+        .fonts
+        .into_iter()
+        .map(|font| {
+            Ok(BundleFont {
+                data_base64: read_base64(&mut archive, &font.path)?,
+                family: font.family,
+                style_name: font.style_name,
+                weight: font.weight,
+                style: font.style,
+            })
+        })
+        .collect()
+}
+
+pub fn is_bundle(path: &Path) -> Result<bool, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    has_signature(&mut file)
+}
+
+/// Checks the zip signature and rewinds `file`.
+fn has_signature(file: &mut File) -> Result<bool, String> {
+    let mut signature = [0u8; 4];
+    let bundle = file.read_exact(&mut signature).is_ok() && &signature == ZIP_SIGNATURE;
+    file.rewind().map_err(|e| e.to_string())?;
+    Ok(bundle)
+}
+
+/// The document JSON at `path` with every asset's `dataBase64` inlined,
+/// whether it's a bundle or a plain JSON file with asset-store references.
+pub fn read_text(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    read_text_from(path, file)
+}
+
+/// [`read_text`] from an already open handle to `path`, so the text comes
+/// from the same bytes the caller versioned even if the file is replaced.
+pub fn read_text_from(path: &Path, mut file: File) -> Result<String, String> {
+    if !has_signature(&mut file)? {
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| e.to_string())?;
+        return asset_store::rehydrate(&AssetStore::beside(path), content);
+    }
+
+    let mut archive =
+        ZipArchive::new(BufReader::new(file)).map_err(|e| format!("invalid_bundle: {e}"))?;
+    let manifest: Manifest = read_json(&mut archive, MANIFEST_ENTRY)?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "unsupported_bundle_version: {}",
+            manifest.format_version
+        ));
+    }
+    let mut document: Value = read_json(&mut archive, DOCUMENT_ENTRY)?;
+    let mut result = Ok(());
+    asset_store::for_each_image_mut(&mut document, |image| {
+        if image.contains_key("dataBase64") || result.is_err() {
+            return;
+        }
+        let Some(asset) = image
+            .get("hash")
+            .and_then(Value::as_str)
+            .and_then(|hash| manifest.assets.get(hash))
+        else {
+            return;
+        };
+        match read_base64(&mut archive, &asset.path) {
+            Ok(data) => {
+                image.insert("dataBase64".to_string(), Value::String(data));
+            }
+            Err(error) => result = Err(error),
+        }
+    });
+    result?;
+    serde_json::to_string(&document).map_err(|e| e.to_string())
+}
+
+/// Writes `content` (document JSON) as a bundle into `file`. Asset-store
+/// references and project fonts are resolved beside `project`, the document
+/// the bundle is packaged from.
+pub fn write_to(
+    file: &mut File,
+    project: &Path,
+    content: &str,
+    thumbnail_png: Option<&[u8]>,
+) -> Result<(), String> {
+    let mut document: Value =
+        serde_json::from_str(content).map_err(|e| format!("invalid_document: {e}"))?;
+    let fonts = match Document::parse(content) {
+        Ok(doc) => scene::used_fonts(&doc),
+        Err(_) => BTreeSet::new(),
+    };
+    let store = AssetStore::beside(project);
+
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let mut manifest = Manifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        document_version: document
+            .get("version")
+            .and_then(Value::as_u64)
+            .unwrap_or_default() as u32,
+        ..Manifest::default()
+    };
+
+    write_assets(&mut zip, &mut document, &store, &mut manifest)?;
+    let project_fonts = project
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(FONTS_DIR);
+    write_fonts(&mut zip, &fonts, &project_fonts, &mut manifest)?;
+
+    let json = serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?;
+    write_entry(&mut zip, DOCUMENT_ENTRY, CompressionMethod::Deflated)?;
+    zip.write_all(&json).map_err(|e| e.to_string())?;
+    if let Some(png) = thumbnail_png {
+        write_entry(&mut zip, THUMBNAIL_ENTRY, CompressionMethod::Stored)?;
+        zip.write_all(png).map_err(|e| e.to_string())?;
+        manifest.thumbnail = Some(THUMBNAIL_ENTRY.to_string());
+    }
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    write_entry(&mut zip, MANIFEST_ENTRY, CompressionMethod::Deflated)?;
+    zip.write_all(&json).map_err(|e| e.to_string())?;
+
+    zip.finish()
+        .map_err(|e| e.to_string())?
+        .flush()
+        .map_err(|e| e.to_string())
+}
+
+type BundleWriter<'a> = ZipWriter<BufWriter<&'a mut File>>;
+
+/// Moves inline image data into `assets/`, leaving `hash` references.
+/// References without data (a document saved to the asset store) are
+/// copied over from the project's store when the blob is there.
+fn write_assets(
+    zip: &mut BundleWriter,
+    document: &mut Value,
+    store: &AssetStore,
+    manifest: &mut Manifest,
+) -> Result<(), String> {
+    let mut result = Ok(());
+    asset_store::for_each_image_mut(document, |image| {
+        if result.is_err() {
+            return;
+        }
+        let mime = image
+            .get("mime")
+            .and_then(Value::as_str)
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let written = match image.remove("dataBase64") {
+            Some(Value::String(data)) => general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| format!("invalid_asset: {e}"))
+                .and_then(|bytes| {
+                    let hash = content_hash(&bytes);
+                    add_asset(zip, manifest, &hash, &mime, &mut bytes.as_slice())?;
+                    Ok(Some(hash))
+                }),
+            _ => match image.get("hash").and_then(Value::as_str) {
+                Some(hash) => match store.open_blob(hash) {
+                    Ok(mut blob) => add_asset(zip, manifest, hash, &mime, &mut blob)
+                        .map(|_| Some(hash.to_string())),
+                    Err(_) => Ok(None),
+                },
+                None => Ok(None),
+            },
+        };
+        match written {
+            Ok(Some(hash)) => {
+                image.insert("hash".to_string(), Value::String(hash));
+            }
+            Ok(None) => {}
+            Err(error) => result = Err(error),
+        }
+    });
+    result
+}
+
+fn add_asset(
+    zip: &mut BundleWriter,
+    manifest: &mut Manifest,
+    hash: &str,
+    mime: &str,
+    source: &mut impl Read,
+) -> Result<(), String> {
+    if manifest.assets.contains_key(hash) {
+        return Ok(());
+    }
+    let path = format!("{ASSETS_DIR}/{hash}.{}", mime_extension(mime));
+    write_entry(zip, &path, CompressionMethod::Stored)?;
+    let byte_size = io::copy(source, zip).map_err(|e| e.to_string())?;
+    manifest.assets.insert(
+        hash.to_string(),
+        ManifestAsset {
+            path,
+            mime: mime.to_string(),
+            byte_size,
+        },
+    );
+    Ok(())
+}
+
+/// Embeds the face each used font-family list resolves to in the project's
+/// `fonts/` folder. System fonts are only named in `missingFonts`: their
+/// licenses rarely allow redistributing them. Generic families resolve
+/// differently per machine, so they aren't embedded either.
+fn write_fonts(
+    zip: &mut BundleWriter,
+    used: &BTreeSet<(String, u16)>,
+    project_fonts: &Path,
+    manifest: &mut Manifest,
+) -> Result<(), String> {
+    let named: Vec<(Vec<&str>, u16)> = used
+        .iter()
+        .map(|(list, weight)| {
+            let families = list
+                .split(',')
+                .map(|family| family.trim().trim_matches(['"', '\'']).trim())
+                .filter(|family| !is_generic_family(family))
+                .collect();
+            (families, *weight)
+        })
+        .filter(|(families, _): &(Vec<&str>, u16)| !families.is_empty())
+        .collect();
+    if named.is_empty() {
+        return Ok(());
+    }
+
+    let faces = DirectoryFontSource::new(vec![project_fonts.to_path_buf()]).faces();
+    let mut embedded: BTreeMap<String, String> = BTreeMap::new();
+    let mut missing = BTreeSet::new();
+    for (families, weight) in named {
+        let Some(face) = families
+            .iter()
+            .find_map(|family| fonts::select_face(&faces, family, weight, "normal"))
+        else {
+            missing.insert(families[0].to_string());
+            continue;
+        };
+        if !embedded.contains_key(&face.path) {
+            match embed_font(zip, face, embedded.len())? {
+                Some(path) => {
+                    embedded.insert(face.path.clone(), path);
+                }
+                None => {
+                    missing.insert(face.family.clone());
+                    continue;
+                }
+            }
+        }
+        manifest.fonts.push(ManifestFont {
+            path: embedded[&face.path].clone(),
+            family: face.family.clone(),
+            style_name: face.style_name.clone(),
+            weight: face.weight,
+            style: face.style.clone(),
+            face_index: face.face_index,
+        });
+    }
+    manifest.missing_fonts = missing.into_iter().collect();
+    Ok(())
+}
+
+/// Copies a font file into `fonts/`; `None` when it's too large or its
+/// license doesn't allow embedding.
+fn embed_font(
+    zip: &mut BundleWriter,
+    face: &FontFaceInfo,
+    index: usize,
+) -> Result<Option<String>, String> {
+    let size = fs::metadata(&face.path).map_err(|e| e.to_string())?.len();
+    if size > MAX_EMBEDDED_FONT_BYTES {
+        return Ok(None);
+    }
+    let data = fs::read(&face.path).map_err(|e| e.to_string())?;
+    if !allows_editable_embedding(&data, face.face_index) {
+        return Ok(None);
+    }
+    let name = Path::new(&face.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "font".to_string());
+    // Prefixed so faces from different folders can share a file name.
+    let path = format!("{FONTS_DIR}/{index}-{name}");
+    write_entry(zip, &path, CompressionMethod::Deflated)?;
+    zip.write_all(&data).map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+/// Reads the OS/2 `fsType` bits. A bundle stays editable, so only
+/// installable and editable faces qualify; preview-and-print, restricted
+/// and bitmap-only ones don't.
+fn allows_editable_embedding(data: &[u8], face_index: u32) -> bool {
+    let Ok(face) = ttf_parser::Face::parse(data, face_index) else {
+        return false;
+    };
+    face.tables().os2.is_none_or(|os2| {
+        matches!(
+            os2.permissions(),
+            Some(ttf_parser::Permissions::Installable | ttf_parser::Permissions::Editable)
+        ) && os2.is_outline_embedding_allowed()
+    })
+}
+
+fn is_generic_family(family: &str) -> bool {
+    matches!(
+        family,
+        "" | "sans-serif" | "serif" | "monospace" | "system-ui" | "cursive" | "fantasy"
+    )
+}
+
+fn write_entry(
+    zip: &mut BundleWriter,
+    name: &str,
+    method: CompressionMethod,
+) -> Result<(), String> {
+    zip.start_file(
+        name,
+        SimpleFileOptions::default().compression_method(method),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn mime_extension(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        _ => "bin",
+    }
+}
+
+fn open(path: &Path) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| format!("invalid_bundle: {e}"))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(
+    archive: &mut ZipArchive<BufReader<File>>,
+    name: &str,
+) -> Result<T, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("invalid_bundle: missing {name}"))?;
+    serde_json::from_reader(BufReader::new(entry))
+        .map_err(|e| format!("invalid_bundle: {name}: {e}"))
+}
+
+fn read_base64(archive: &mut ZipArchive<BufReader<File>>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("invalid_bundle: missing {name}"))?;
+    let mut encoder = base64::write::EncoderStringWriter::new(&general_purpose::STANDARD);
+    io::copy(&mut entry, &mut encoder).map_err(|e| format!("invalid_bundle: {name}: {e}"))?;
+    Ok(encoder.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draft_store::replace_atomic;
+    use serde_json::json;
+
+    fn write(path: &Path, content: &str, thumbnail_png: Option<&[u8]>) -> Result<(), String> {
+        replace_atomic(path, |file| write_to(file, path, content, thumbnail_png))
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("galileo-bundle-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("design.galileo")
+    }
+
+    fn document(data: &str) -> String {
+        json!({
+            "version": 9,
+            "rootId": "root",
+            "activePageId": "page_1",
+            "pages": [{ "id": "page_1", "name": "Page 1", "rootId": "root" }],
+            "nodes": {
+                "root": { "id": "root", "type": "frame", "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 }, "children": [] },
+            },
+            "assets": {
+                "a1": { "type": "image", "mime": "image/png", "width": 1, "height": 1, "dataBase64": data },
+                "a2": { "type": "image", "mime": "image/png", "width": 1, "height": 1, "dataBase64": data },
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn bundles_round_trip_with_assets_stored_once() {
+        let path = temp_path("roundtrip");
+        let data = general_purpose::STANDARD.encode(b"png bytes");
+        write(&path, &document(&data), Some(b"thumb")).unwrap();
+        assert!(is_bundle(&path).unwrap());
+
+        let mut archive = open(&path).unwrap();
+        let manifest: Manifest = read_json(&mut archive, MANIFEST_ENTRY).unwrap();
+        assert_eq!(manifest.format_version, BUNDLE_FORMAT_VERSION);
+        assert_eq!(manifest.document_version, 9);
+        assert_eq!(manifest.assets.len(), 1);
+        let asset = &manifest.assets[&content_hash(b"png bytes")];
+        assert_eq!(asset.byte_size, 9);
+        assert!(asset.path.starts_with("assets/") && asset.path.ends_with(".png"));
+        assert_eq!(manifest.thumbnail.as_deref(), Some(THUMBNAIL_ENTRY));
+        let stored: Value = read_json(&mut archive, DOCUMENT_ENTRY).unwrap();
+        assert!(stored["assets"]["a1"].get("dataBase64").is_none());
+
+        let loaded: Value = serde_json::from_str(&read_text(&path).unwrap()).unwrap();
+        assert_eq!(loaded["assets"]["a1"]["dataBase64"], data);
+        assert_eq!(loaded["assets"]["a2"]["dataBase64"], data);
+        let doc = Document::read(&path).unwrap();
+        assert_eq!(doc.assets["a2"].data_base64.as_deref(), Some(data.as_str()));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn plain_json_documents_still_load() {
+        let path = temp_path("json");
+        fs::write(&path, document("AAAA")).unwrap();
+        assert!(!is_bundle(&path).unwrap());
+        let loaded: Value = serde_json::from_str(&read_text(&path).unwrap()).unwrap();
+        assert_eq!(loaded["assets"]["a1"]["dataBase64"], "AAAA");
+        assert!(load_bundle_fonts(LoadBundleFontsArgs {
+            path: path.to_string_lossy().to_string(),
+        })
+        .unwrap()
+        .is_empty());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn system_fonts_are_listed_but_not_embedded() {
+        let path = temp_path("fonts");
+        let mut doc: Value = serde_json::from_str(&document("AAAA")).unwrap();
+        doc["nodes"]["text"] = json!({
+            "id": "text", "type": "text", "text": "Hi", "fontFamily": "Arial, sans-serif",
+            "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 },
+        });
+        doc["nodes"]["root"]["children"] = json!(["text"]);
+        write(&path, &doc.to_string(), None).unwrap();
+
+        let mut archive = open(&path).unwrap();
+        let manifest: Manifest = read_json(&mut archive, MANIFEST_ENTRY).unwrap();
+        assert!(manifest.fonts.is_empty());
+        assert_eq!(manifest.missing_fonts, vec!["Arial".to_string()]);
+        assert!(!allows_editable_embedding(b"not a font", 0));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn newer_bundles_and_failed_writes_leave_files_alone() {
+        let path = temp_path("version");
+        write(&path, &document("AAAA"), None).unwrap();
+        let before = fs::read(&path).unwrap();
+        assert!(write(&path, "not json", None)
+            .unwrap_err()
+            .starts_with("invalid_document"));
+        assert!(write(&path, &document("%%%"), None)
+            .unwrap_err()
+            .starts_with("invalid_asset"));
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        replace_atomic(&path, |file| {
+            let mut zip = ZipWriter::new(BufWriter::new(file));
+            write_entry(&mut zip, MANIFEST_ENTRY, CompressionMethod::Stored)?;
+            zip.write_all(br#"{"formatVersion":99,"documentVersion":9,"thumbnail":null}"#)
+                .map_err(|e| e.to_string())?;
+            zip.finish().map_err(|e| e.to_string())?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            read_text(&path).unwrap_err(),
+            "unsupported_bundle_version: 99"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,589 @@
+//! Serde mirror of the frontend's `documentSchema` (src/core/doc/types.ts),
+//! for native code that reads `.galileo` files without the webview.
+//!
+//! Only the fields native features use are typed; everything else is kept
+//! in `extra` so a document survives a parse/serialize round trip.
+
+use crate::migration;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// `CURRENT_DOCUMENT_VERSION` in src/core/doc/serialization.ts
+pub const CURRENT_DOCUMENT_VERSION: u32 = 9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub version: u32,
+    pub root_id: String,
+    pub pages: Vec<Page>,
+    pub active_page_id: String,
+    pub nodes: BTreeMap<String, Node>,
+    #[serde(default)]
+    pub assets: BTreeMap<String, Asset>,
+    #[serde(default)]
+    pub styles: StyleLibrary,
+    #[serde(default)]
+    pub variables: VariableLibrary,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    pub id: String,
+    pub name: String,
+    pub root_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeType {
+    Frame,
+    Group,
+    Rectangle,
+    Text,
+    Image,
+    ComponentInstance,
+    Ellipse,
+    Path,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: NodeType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<String>>,
+    pub position: Position,
+    pub size: Size,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_style_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<Stroke>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corner_radius: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_style_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_weight: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_height_px: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub letter_spacing_px: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_resize_mode: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<NodeImage>,
+
+    /// Either an SVG path string or `{ d, fillRule }`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector: Option<VectorData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boolean_data: Option<BooleanData>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clip_content: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_overflow: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effects: Option<Vec<ShadowEffect>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect_style_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect_variables: Option<BTreeMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout_sizing: Option<LayoutSizing>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Node {
+    /// A node with only the fields every node type requires.
+    pub fn new(id: impl Into<String>, kind: NodeType, position: Position, size: Size) -> Self {
+        Node {
+            id: id.into(),
+            kind,
+            name: None,
+            children: None,
+            position,
+            size,
+            rotation: None,
+            layout: None,
+            fill: None,
+            fill_style_id: None,
+            stroke: None,
+            opacity: None,
+            corner_radius: None,
+            text: None,
+            text_style_id: None,
+            font_size: None,
+            font_family: None,
+            font_weight: None,
+            text_align: None,
+            line_height_px: None,
+            letter_spacing_px: None,
+            text_resize_mode: None,
+            image: None,
+            path: None,
+            vector: None,
+            path_data: None,
+            d: None,
+            boolean_data: None,
+            visible: None,
+            clip_content: None,
+            shadow_overflow: None,
+            effects: None,
+            effect_style_id: None,
+            effect_variables: None,
+            layout_sizing: None,
+            extra: Map::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible != Some(false)
+    }
+}
+
+/// Solid colors carry a CSS color string; gradients are passthrough objects
+/// whose stop and handle fields vary by source (see draw-list/builder.ts).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Color {
+    Solid { value: String },
+    Gradient(Map<String, Value>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f64,
+    pub style: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub direction: String,
+    pub gap: f64,
+    pub padding: Padding,
+    pub alignment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cross_alignment: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Padding {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutSizing {
+    pub horizontal: String,
+    pub vertical: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeImage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub src: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask_asset_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Handle {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorPoint {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_handle: Option<Handle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_handle: Option<Handle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corner_mode: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorSegment {
+    pub id: String,
+    pub from_id: String,
+    pub to_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorData {
+    pub points: Vec<VectorPoint>,
+    pub segments: Vec<VectorSegment>,
+    pub closed: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BooleanData {
+    pub op: String,
+    pub operand_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isolation_operand_id: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_code: Option<String>,
+    pub tolerance: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ShadowEffect {
+    Drop(Shadow),
+    Inner(Shadow),
+    Auto(AutoShadow),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shadow {
+    pub x: f64,
+    pub y: f64,
+    pub blur: f64,
+    pub spread: f64,
+    pub color: String,
+    pub opacity: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blend_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoShadow {
+    pub elevation: f64,
+    pub angle: f64,
+    pub distance: f64,
+    pub softness: f64,
+    pub color: String,
+    pub opacity: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blend_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub mime: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_base64: Option<String>,
+    pub width: f64,
+    pub height: f64,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StyleLibrary {
+    #[serde(default)]
+    pub paint: BTreeMap<String, PaintStyle>,
+    #[serde(default)]
+    pub text: BTreeMap<String, TextStyle>,
+    #[serde(default)]
+    pub effect: BTreeMap<String, EffectStyle>,
+    #[serde(default)]
+    pub grid: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaintStyle {
+    pub id: String,
+    pub name: String,
+    pub paint: Color,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStyle {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_weight: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_height_px: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub letter_spacing_px: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_resize_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectStyle {
+    pub id: String,
+    pub name: String,
+    pub effects: Vec<ShadowEffect>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableLibrary {
+    #[serde(default)]
+    pub collections: BTreeMap<String, VariableCollection>,
+    #[serde(default)]
+    pub tokens: BTreeMap<String, VariableToken>,
+    #[serde(default)]
+    pub active_mode_by_collection: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableCollection {
+    pub id: String,
+    pub name: String,
+    pub modes: Vec<VariableMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_mode_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariableMode {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableToken {
+    pub id: String,
+    pub name: String,
+    pub collection_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub values_by_mode: BTreeMap<String, Value>,
+}
+
+/// Where a native command reads its document from: the in-memory JSON when
+/// the frontend has unsaved edits, otherwise the file on disk.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSource {
+    pub path: Option<String>,
+    pub content: Option<String>,
+}
+
+impl DocumentSource {
+    pub fn load(&self) -> Result<Document, String> {
+        match (&self.content, &self.path) {
+            (Some(content), _) => Document::parse(content),
+            (None, Some(path)) => Document::read(Path::new(path)),
+            (None, None) => Err("missing_document: pass path or content".to_string()),
+        }
+    }
+
+    /// The document JSON, with a bundle's assets inlined.
+    pub fn text(&self) -> Result<Cow<'_, str>, String> {
+        match (&self.content, &self.path) {
+            (Some(content), _) => Ok(Cow::Borrowed(content)),
+            (None, Some(path)) => crate::bundle::read_text(Path::new(path)).map(Cow::Owned),
+            (None, None) => Err("missing_document: pass path or content".to_string()),
+        }
+    }
+}
+
+impl Document {
+    /// Parses a document of any supported version, migrating and
+    /// normalizing its pages the way the frontend does on load.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let raw: Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid_document: {e}"))?;
+        let mut migrated = migration::migrate(raw)?;
+        migration::normalize_pages(&mut migrated.document, &mut migrated.warnings);
+        Self::from_migrated(migrated.document)
+    }
+
+    /// Types an already migrated document.
+    pub fn from_migrated(document: Map<String, Value>) -> Result<Self, String> {
+        serde_json::from_value(Value::Object(document))
+            .map_err(|e| format!("invalid_document: {e}"))
+    }
+
+    /// Reads a plain JSON or bundled `.galileo` file with its assets inlined.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        Self::parse(&crate::bundle::read_text(path)?)
+    }
+
+    pub fn page(&self, page_id: &str) -> Option<&Page> {
+        self.pages.iter().find(|page| page.id == page_id)
+    }
+
+    /// A token's value in its collection's active mode, as
+    /// `resolveVariableTokenValue` does.
+    pub fn token_value(&self, token_id: &str) -> Option<&Value> {
+        let token = self.variables.tokens.get(token_id)?;
+        let collection = self.variables.collections.get(&token.collection_id)?;
+        let has_mode = |id: &String| collection.modes.iter().any(|mode| &mode.id == id);
+        let mode_id = self
+            .variables
+            .active_mode_by_collection
+            .get(&token.collection_id)
+            .filter(|id| has_mode(id))
+            .or(collection
+                .default_mode_id
+                .as_ref()
+                .filter(|id| has_mode(id)))
+            .or(collection.modes.first().map(|mode| &mode.id))?;
+        token.values_by_mode.get(mode_id)
+    }
+
+    pub fn token_string(&self, token_id: &str) -> Option<String> {
+        match self.token_value(token_id)? {
+            Value::String(value) => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn token_number(&self, token_id: &str) -> Option<f64> {
+        match self.token_value(token_id)? {
+            Value::Number(value) => value.as_f64(),
+            Value::String(value) => value.trim().parse().ok(),
+            _ => None,
+        }
+        .filter(|value: &f64| value.is_finite())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, Document, NodeType, ShadowEffect};
+
+    const DOC: &str = r##"{
+        "version": 9,
+        "rootId": "root",
+        "pages": [{ "id": "page_1", "name": "Page 1", "rootId": "root" }],
+        "activePageId": "page_1",
+        "nodes": {
+            "root": {
+                "id": "root", "type": "frame", "position": { "x": 0, "y": 0 },
+                "size": { "width": 100, "height": 80 }, "children": ["a"],
+                "fill": { "type": "gradient", "stops": ["#fff", "#000"], "angle": 90 },
+                "devicePresetId": "iphone"
+            },
+            "a": {
+                "id": "a", "type": "rectangle", "position": { "x": 1, "y": 2 },
+                "size": { "width": 3, "height": 4 },
+                "effects": [{ "type": "auto", "elevation": 4, "angle": 90, "distance": 8,
+                    "softness": 50, "color": "#000", "opacity": 0.3 }]
+            }
+        },
+        "assets": {},
+        "components": { "definitions": {}, "sets": {} },
+        "styles": { "paint": {}, "text": {}, "effect": {}, "grid": {} },
+        "variables": { "collections": {}, "tokens": {}, "activeModeByCollection": {} }
+    }"##;
+
+    #[test]
+    fn parses_and_round_trips_unknown_fields() {
+        let doc = Document::parse(DOC).unwrap();
+        let root = &doc.nodes["root"];
+        assert_eq!(root.kind, NodeType::Frame);
+        assert!(matches!(root.fill, Some(Color::Gradient(_))));
+        assert!(matches!(
+            doc.nodes["a"].effects.as_deref(),
+            Some([ShadowEffect::Auto(_)])
+        ));
+
+        let json = serde_json::to_value(&doc).unwrap();
+        assert_eq!(json["nodes"]["root"]["devicePresetId"], "iphone");
+        assert_eq!(json["nodes"]["root"]["fill"]["type"], "gradient");
+        assert_eq!(json["components"]["sets"], serde_json::json!({}));
+    }
+}
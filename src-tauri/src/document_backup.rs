@@ -0,0 +1,337 @@
+//! Crash-safe document saves with a rotating set of backups.
+//!
+//! A save writes and fsyncs a temp file beside the document, shifts the
+//! backups along (`design.galileo.bak` is the newest, then `.bak.1`, up to
+//! [`BACKUP_COUNT`] in total), hard-links the current file in as the new
+//! `.bak` and only then renames the temp file over the document. A crash at
+//! any point leaves the previous document intact.
+//!
+//! Saves can also carry the mtime or hash the caller last saw; if the file
+//! has changed on disk since (a sync client, a teammate) the save fails with
+//! `conflict: {"mtimeMs":..,"hash":..}` describing what is there now.
+
+use crate::draft_store::{commit_temp, write_temp};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Previous saves kept per document
+pub const BACKUP_COUNT: usize = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentBackupsArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreBackupArgs {
+    pub path: String,
+    /// 0 is the most recent backup
+    pub index: usize,
+}
+
+/// What the caller believes is on disk; unset fields are not checked.
+#[derive(Debug, Default)]
+pub struct ExpectedVersion {
+    pub mtime_ms: Option<u64>,
+    pub hash: Option<String>,
+}
+
+/// Both fields are `None` when the file does not exist.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentVersion {
+    pub mtime_ms: Option<u64>,
+    /// Hex SHA-256 of the file
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentBackup {
+    pub index: usize,
+    pub path: String,
+    pub saved_at_ms: Option<u64>,
+    pub byte_size: u64,
+}
+
+/// Newest first.
+#[tauri::command]
+pub fn list_document_backups(args: DocumentBackupsArgs) -> Result<Vec<DocumentBackup>, String> {
+    let path = Path::new(&args.path);
+    let mut backups = Vec::new();
+    for index in 0..BACKUP_COUNT {
+        let backup = backup_path(path, index);
+        let Ok(metadata) = fs::metadata(&backup) else {
+            continue;
+        };
+        let saved_at_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64);
+        backups.push(DocumentBackup {
+            index,
+            path: backup.to_string_lossy().to_string(),
+            saved_at_ms,
+            byte_size: metadata.len(),
+        });
+    }
+    Ok(backups)
+}
+
+/// Replaces the document with backup `index`. The current document is
+/// backed up first, so a restore can itself be undone.
+#[tauri::command]
+pub fn restore_document_backup(args: RestoreBackupArgs) -> Result<DocumentVersion, String> {
+    if args.index >= BACKUP_COUNT {
+        return Err(format!("backup_not_found: {}", args.index));
+    }
+    let path = Path::new(&args.path);
+    let mut backup = File::open(backup_path(path, args.index)).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => format!("backup_not_found: {}", args.index),
+        _ => e.to_string(),
+    })?;
+    save(path, &ExpectedVersion::default(), |file| {
+        io::copy(&mut backup, file)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Atomically replaces `path` with what `write` produces, keeping the
+/// previous version as the newest backup. The precondition is checked after
+/// the new contents are on disk, right before they replace the document.
+pub fn save(
+    path: &Path,
+    expected: &ExpectedVersion,
+    write: impl FnOnce(&mut File) -> Result<(), String>,
+) -> Result<DocumentVersion, String> {
+    let tmp_path = write_temp(path, write)?;
+    let prepared = check_unchanged(path, expected)
+        .and_then(|()| rotate_backups(path).map_err(|e| format!("backup_failed: {e}")));
+    if let Err(error) = prepared {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(error);
+    }
+    commit_temp(&tmp_path, path)?;
+    current_version(path)
+}
+
+pub fn current_version(path: &Path) -> Result<DocumentVersion, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(DocumentVersion {
+                mtime_ms: None,
+                hash: None,
+            })
+        }
+        Err(error) => return Err(error.to_string()),
+    };
+    file_version(&file)
+}
+
+/// Version of the file behind `file`, read through the handle itself so it
+/// matches what the handle reads even if the path is replaced meanwhile.
+/// Leaves the handle where it was.
+pub fn file_version(mut file: &File) -> Result<DocumentVersion, String> {
+    let position = file.stream_position().map_err(|e| e.to_string())?;
+    let mtime_ms = file
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| e.to_string())?
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_millis() as u64;
+    let mut hasher = Sha256::new();
+    file.rewind().map_err(|e| e.to_string())?;
+    io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(position))
+        .map_err(|e| e.to_string())?;
+    Ok(DocumentVersion {
+        mtime_ms: Some(mtime_ms),
+        hash: Some(format!("{:x}", hasher.finalize())),
+    })
+}
+
+fn check_unchanged(path: &Path, expected: &ExpectedVersion) -> Result<(), String> {
+    if expected.mtime_ms.is_none() && expected.hash.is_none() {
+        return Ok(());
+    }
+    let current = current_version(path)?;
+    let mtime_matches = expected.mtime_ms.is_none() || expected.mtime_ms == current.mtime_ms;
+    let hash_matches = expected.hash.is_none() || expected.hash == current.hash;
+    if mtime_matches && hash_matches {
+        return Ok(());
+    }
+    let detail = serde_json::to_string(&current).map_err(|e| e.to_string())?;
+    Err(format!("conflict: {detail}"))
+}
+
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    if index > 0 {
+        name.push(format!(".{index}"));
+    }
+    path.with_file_name(name)
+}
+
+fn rotate_backups(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    match fs::remove_file(backup_path(path, BACKUP_COUNT - 1)) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    for index in (0..BACKUP_COUNT - 1).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    // The document is about to be replaced by a rename, so a hard link
+    // keeps its current contents without copying them.
+    let newest = backup_path(path, 0);
+    if fs::hard_link(path, &newest).is_err() {
+        fs::copy(path, &newest)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_document(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("galileo-backup-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("design.galileo")
+    }
+
+    fn save_text(path: &Path, text: &str) -> Result<(), String> {
+        save(path, &ExpectedVersion::default(), |file| {
+            file.write_all(text.as_bytes()).map_err(|e| e.to_string())
+        })
+        .map(|_| ())
+    }
+
+    #[test]
+    fn saves_keep_the_last_few_versions() {
+        let path = temp_document("rotate");
+        for version in 0..=BACKUP_COUNT + 1 {
+            save_text(&path, &format!("v{version}")).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("v{}", BACKUP_COUNT + 1)
+        );
+        let backups = list_document_backups(DocumentBackupsArgs {
+            path: path.to_string_lossy().to_string(),
+        })
+        .unwrap();
+        assert_eq!(backups.len(), BACKUP_COUNT);
+        assert_eq!(
+            fs::read_to_string(&backups[0].path).unwrap(),
+            format!("v{BACKUP_COUNT}")
+        );
+        assert_eq!(
+            fs::read_to_string(&backups[BACKUP_COUNT - 1].path).unwrap(),
+            "v1"
+        );
+        assert!(!path.with_file_name("design.galileo.tmp").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn failed_writes_leave_the_document_and_backups_alone() {
+        let path = temp_document("failed");
+        save_text(&path, "first").unwrap();
+        let result = save(&path, &ExpectedVersion::default(), |file| {
+            file.write_all(b"partial").unwrap();
+            Err("disk full".to_string())
+        });
+        assert_eq!(result.unwrap_err(), "disk full");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(!backup_path(&path, 0).exists());
+        assert!(!path.with_file_name("design.galileo.tmp").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn restoring_a_backup_backs_up_the_current_version() {
+        let path = temp_document("restore");
+        save_text(&path, "good").unwrap();
+        save_text(&path, "broken").unwrap();
+        let args = |index| RestoreBackupArgs {
+            path: path.to_string_lossy().to_string(),
+            index,
+        };
+        restore_document_backup(args(0)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "good");
+        assert_eq!(fs::read_to_string(backup_path(&path, 0)).unwrap(), "broken");
+        assert_eq!(
+            restore_document_backup(args(3)).unwrap_err(),
+            "backup_not_found: 3"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn saves_fail_with_a_conflict_when_the_file_changed() {
+        let path = temp_document("conflict");
+        let write = |text: &'static str| {
+            move |file: &mut File| file.write_all(text.as_bytes()).map_err(|e| e.to_string())
+        };
+        let saved = save(&path, &ExpectedVersion::default(), write("ours")).unwrap();
+        assert_eq!(saved, current_version(&path).unwrap());
+
+        let chained = ExpectedVersion {
+            mtime_ms: saved.mtime_ms,
+            hash: saved.hash.clone(),
+        };
+        let saved = save(&path, &chained, write("ours again")).unwrap();
+
+        fs::write(&path, "theirs").unwrap();
+        let stale = ExpectedVersion {
+            mtime_ms: None,
+            hash: saved.hash,
+        };
+        let error = save(&path, &stale, write("overwrite")).unwrap_err();
+        let detail = error.strip_prefix("conflict: ").unwrap();
+        let on_disk = current_version(&path).unwrap();
+        assert_eq!(detail, serde_json::to_string(&on_disk).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs");
+        assert!(!path.with_file_name("design.galileo.tmp").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn open_handles_version_the_bytes_they_read() {
+        let path = temp_document("handle");
+        save_text(&path, r#"{"v":1}"#).unwrap();
+        let file = File::open(&path).unwrap();
+        let read = current_version(&path).unwrap();
+
+        // A save landing between opening and reading replaces the path, not
+        // the file behind the handle.
+        save_text(&path, r#"{"v":2}"#).unwrap();
+        assert_eq!(file_version(&file).unwrap(), read);
+        assert_eq!(
+            crate::bundle::read_text_from(&path, file).unwrap(),
+            r#"{"v":1}"#
+        );
+        assert_ne!(current_version(&path).unwrap(), read);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
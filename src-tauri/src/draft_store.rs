@@ -2,6 +2,7 @@ use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::Manager;
@@ -89,28 +90,70 @@ fn ensure_drafts_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(dir)
 }
 
-fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    replace_atomic(path, |file| file.write_all(data).map_err(|e| e.to_string()))
+}
+
+/// Fills a temp file beside `path` with `write`, then moves it over `path`,
+/// so readers see either the old file or the complete new one.
+pub(crate) fn replace_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> Result<(), String>,
+) -> Result<(), String> {
+    let tmp_path = write_temp(path, write)?;
+    commit_temp(&tmp_path, path)
+}
+
+/// Writes `path`'s temp sibling and flushes it to disk. The temp file is
+/// removed again if `write` fails.
+pub(crate) fn write_temp(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> Result<(), String>,
+) -> Result<PathBuf, String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
 
-    let mut tmp_path = path.to_path_buf();
-    tmp_path.set_extension("tmp");
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
 
-    fs::write(&tmp_path, data).map_err(|e| e.to_string())?;
+    let written = fs::File::create(&tmp_path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all().map_err(|e| e.to_string())
+        });
+    match written {
+        Ok(()) => Ok(tmp_path),
+        Err(error) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(error)
+        }
+    }
+}
 
-    match fs::rename(&tmp_path, path) {
-        Ok(()) => Ok(()),
+/// Moves a file from [`write_temp`] into place and syncs the directory
+/// entry, so the rename itself survives a crash.
+pub(crate) fn commit_temp(tmp_path: &Path, path: &Path) -> Result<(), String> {
+    match fs::rename(tmp_path, path) {
+        Ok(()) => {}
         Err(rename_err) => {
             if path.exists() {
                 fs::remove_file(path).map_err(|e| e.to_string())?;
-                fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+                fs::rename(tmp_path, path).map_err(|e| e.to_string())?;
             } else {
-                let _ = fs::remove_file(&tmp_path);
-                Err(rename_err.to_string())
+                let _ = fs::remove_file(tmp_path);
+                return Err(rename_err.to_string());
             }
         }
     }
+
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().and_then(|parent| fs::File::open(parent).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
 }
 
 fn read_draft(path: &Path) -> Result<Option<(StoredDraft, usize, usize)>, String> {
@@ -218,7 +261,7 @@ pub fn list_drafts(app: tauri::AppHandle) -> Result<Vec<DraftSummary>, String> {
         }
     }
 
-    summaries.sort_by(|a, b| b.saved_at_ms.cmp(&a.saved_at_ms));
+    summaries.sort_by_key(|b| std::cmp::Reverse(b.saved_at_ms));
     Ok(summaries)
 }
 
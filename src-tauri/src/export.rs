@@ -0,0 +1,579 @@
+//! Native image export. `encode_image` covers every format the save dialog
+//! offers and writes color profile and resolution metadata, which the
+//! canvas encoders can't.
+
+use crate::binary_ipc;
+use crate::png_optimize::{self, PngMetadata, PngSettings, Quantize};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+pub const FORMAT_PNG: &str = "png";
+pub const FORMAT_JPEG: &str = "jpeg";
+pub const FORMAT_WEBP: &str = "webp";
+pub const FORMAT_AVIF: &str = "avif";
+pub const FORMAT_TIFF: &str = "tiff";
+
+pub const PROFILE_SRGB: &str = "srgb";
+pub const PROFILE_DISPLAY_P3: &str = "display-p3";
+
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+const DEFAULT_AVIF_QUALITY: u8 = 80;
+const DEFAULT_AVIF_SPEED: u8 = 6;
+const METERS_PER_INCH: f64 = 0.0254;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeImageArgs {
+    /// Raw RGBA pixel data as base64
+    pub rgba_base64: String,
+    #[serde(flatten)]
+    pub options: EncodeOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeOptions {
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub settings: EncodeSettings,
+}
+
+/// Format and encoder settings, shared with `export_batch` targets, which
+/// derive their size from their scale.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeSettings {
+    /// "png", "jpeg", "webp", "avif" or "tiff"
+    pub format: String,
+    /// 1-100; JPEG and AVIF only
+    pub quality: Option<u8>,
+    /// JPEG only
+    pub progressive: Option<bool>,
+    /// 8 (default) or 16; TIFF only. At 16 the pixel data is RGBA16 with
+    /// little-endian samples.
+    pub bit_depth: Option<u8>,
+    /// "srgb" (default) or "display-p3". Tags the pixels, it doesn't convert them.
+    pub color_profile: Option<String>,
+    /// Resolution metadata in pixels per inch; PNG, JPEG and TIFF only
+    pub dpi: Option<u32>,
+    /// AVIF encoder speed, 1 (slowest, smallest) to 10
+    pub speed: Option<u8>,
+    /// PNG only
+    #[serde(flatten)]
+    pub png: PngOptions,
+}
+
+/// PNG size options, shared with `encode_png`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PngOptions {
+    /// Deflate level, 0 (store) to 9 (smallest)
+    pub compression_level: Option<u8>,
+    /// Row filter: "none", "sub", "up", "average", "paeth", "adaptive" or
+    /// "min-entropy"
+    pub filter: Option<String>,
+    /// Try lossless color type, bit depth and filter reductions
+    pub optimize: Option<bool>,
+    /// Quantize to an 8-bit (or smaller) palette of at most this many
+    /// colors. Lossy.
+    pub max_colors: Option<u16>,
+    /// Error diffusion strength for `max_colors`, 0 to 1 (default)
+    pub dithering: Option<f32>,
+}
+
+impl PngOptions {
+    /// Whether any option moves the output away from a default encode.
+    pub fn is_tuned(&self) -> bool {
+        self.optimize.unwrap_or(false)
+            || self.max_colors.is_some()
+            || self.compression_level.is_some()
+            || self.filter.is_some()
+    }
+
+    pub fn settings(&self) -> Result<PngSettings, String> {
+        Ok(PngSettings {
+            compression_level: self.compression_level,
+            filter: self
+                .filter
+                .as_deref()
+                .map(png_optimize::parse_filter)
+                .transpose()?,
+            optimize: self.optimize.unwrap_or(false),
+            quantize: self.max_colors.map(|max_colors| Quantize {
+                max_colors: max_colors as usize,
+                dithering: self.dithering.unwrap_or(1.0),
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeImageResult {
+    pub data_base64: String,
+    pub mime: String,
+    pub extension: String,
+    pub byte_size: usize,
+    /// Size of a default RGBA encode, when PNG size options were given
+    pub unoptimized_byte_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Jpeg,
+    WebP,
+    Avif,
+    Tiff,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.to_ascii_lowercase().as_str() {
+            FORMAT_PNG => Ok(Self::Png),
+            FORMAT_JPEG | "jpg" => Ok(Self::Jpeg),
+            FORMAT_WEBP => Ok(Self::WebP),
+            FORMAT_AVIF => Ok(Self::Avif),
+            FORMAT_TIFF | "tif" => Ok(Self::Tiff),
+            other => Err(format!("unsupported_format: {other}")),
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::WebP => "image/webp",
+            Self::Avif => "image/avif",
+            Self::Tiff => "image/tiff",
+        }
+    }
+
+    /// Extensions for the save dialog filter; the first is the default.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Png => &["png"],
+            Self::Jpeg => &["jpg", "jpeg"],
+            Self::WebP => &["webp"],
+            Self::Avif => &["avif"],
+            Self::Tiff => &["tif", "tiff"],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Png => "PNG",
+            Self::Jpeg => "JPEG",
+            Self::WebP => "WebP",
+            Self::Avif => "AVIF",
+            Self::Tiff => "TIFF",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorProfile {
+    Srgb,
+    DisplayP3,
+}
+
+impl ColorProfile {
+    fn parse(profile: Option<&str>) -> Result<Self, String> {
+        match profile.unwrap_or(PROFILE_SRGB) {
+            PROFILE_SRGB => Ok(Self::Srgb),
+            PROFILE_DISPLAY_P3 => Ok(Self::DisplayP3),
+            other => Err(format!("unsupported_color_profile: {other}")),
+        }
+    }
+
+    fn icc(self) -> Result<Vec<u8>, String> {
+        let profile = match self {
+            Self::Srgb => moxcms::ColorProfile::new_srgb(),
+            Self::DisplayP3 => moxcms::ColorProfile::new_display_p3(),
+        };
+        profile
+            .encode()
+            .map_err(|e| format!("Failed to build ICC profile: {e:?}"))
+    }
+}
+
+/// Encodes tightly packed RGBA8 pixels (RGBA16 for 16-bit TIFF). The length
+/// must already match the dimensions.
+pub fn encode_rgba(rgba: &[u8], options: &EncodeOptions) -> Result<Vec<u8>, String> {
+    let format = ExportFormat::parse(&options.settings.format)?;
+    let profile = ColorProfile::parse(options.settings.color_profile.as_deref())?;
+    check_supported(format, &options.settings, profile)?;
+    match format {
+        ExportFormat::Png => encode_png(rgba, options, profile),
+        ExportFormat::Jpeg => encode_jpeg(rgba, options, profile),
+        ExportFormat::WebP => encode_webp(rgba, options, profile),
+        ExportFormat::Avif => encode_avif(rgba, options),
+        ExportFormat::Tiff => encode_tiff(rgba, options, profile),
+    }
+}
+
+/// Refuses settings `format` can't write, rather than dropping them.
+fn check_supported(
+    format: ExportFormat,
+    settings: &EncodeSettings,
+    profile: ColorProfile,
+) -> Result<(), String> {
+    let unsupported = |option: &str| {
+        Err(format!(
+            "unsupported_option: {option} is not supported for {}",
+            format.label()
+        ))
+    };
+    // Neither container has a resolution field.
+    if settings.dpi.is_some() && matches!(format, ExportFormat::WebP | ExportFormat::Avif) {
+        return unsupported("dpi");
+    }
+    // ravif only signals sRGB; P3 pixels would be displayed as sRGB.
+    if profile == ColorProfile::DisplayP3 && format == ExportFormat::Avif {
+        return unsupported(PROFILE_DISPLAY_P3);
+    }
+    match settings.bit_depth {
+        None | Some(8) => Ok(()),
+        Some(16) if format == ExportFormat::Tiff => Ok(()),
+        Some(16) => unsupported("16-bit"),
+        Some(other) => Err(format!("unsupported_bit_depth: {other}")),
+    }
+}
+
+fn encode_png(
+    rgba: &[u8],
+    options: &EncodeOptions,
+    profile: ColorProfile,
+) -> Result<Vec<u8>, String> {
+    png_optimize::encode(
+        rgba,
+        options.width,
+        options.height,
+        &options.settings.png.settings()?,
+        &png_metadata(options, profile)?,
+    )
+}
+
+fn png_metadata(options: &EncodeOptions, profile: ColorProfile) -> Result<PngMetadata, String> {
+    Ok(PngMetadata {
+        icc: Some(profile.icc()?),
+        icc_is_srgb: profile == ColorProfile::Srgb,
+        pixel_dims: options.settings.dpi.map(|dpi| {
+            let per_meter = (dpi as f64 / METERS_PER_INCH).round() as u32;
+            png::PixelDimensions {
+                xppu: per_meter,
+                yppu: per_meter,
+                unit: png::Unit::Meter,
+            }
+        }),
+    })
+}
+
+/// Size of a plain RGBA PNG with the same metadata, for reporting savings.
+/// `None` unless PNG size options are in play.
+fn unoptimized_png_size(rgba: &[u8], options: &EncodeOptions) -> Result<Option<usize>, String> {
+    if ExportFormat::parse(&options.settings.format)? != ExportFormat::Png
+        || !options.settings.png.is_tuned()
+    {
+        return Ok(None);
+    }
+    let profile = ColorProfile::parse(options.settings.color_profile.as_deref())?;
+    let bytes = png_optimize::encode(
+        rgba,
+        options.width,
+        options.height,
+        &PngSettings::default(),
+        &png_metadata(options, profile)?,
+    )?;
+    Ok(Some(bytes.len()))
+}
+
+fn encode_jpeg(
+    rgba: &[u8],
+    options: &EncodeOptions,
+    profile: ColorProfile,
+) -> Result<Vec<u8>, String> {
+    let (width, height) = jpeg_dimensions(options)?;
+    let quality = options
+        .settings
+        .quality
+        .unwrap_or(DEFAULT_JPEG_QUALITY)
+        .clamp(1, 100);
+    let rgb = flatten_onto_white(rgba);
+
+    let mut bytes = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut bytes, quality);
+    encoder.set_progressive(options.settings.progressive.unwrap_or(false));
+    if let Some(dpi) = options.settings.dpi {
+        let dpi = u16::try_from(dpi).map_err(|_| format!("dpi_out_of_range: {dpi}"))?;
+        encoder.set_density(jpeg_encoder::Density::Inch { x: dpi, y: dpi });
+    }
+    encoder
+        .add_icc_profile(&profile.icc()?)
+        .map_err(|e| format!("Failed to encode JPEG: {e}"))?;
+    encoder
+        .encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| format!("Failed to encode JPEG: {e}"))?;
+    Ok(bytes)
+}
+
+fn jpeg_dimensions(options: &EncodeOptions) -> Result<(u16, u16), String> {
+    match (u16::try_from(options.width), u16::try_from(options.height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(format!(
+            "image_too_large: JPEG is limited to 65535px, got {}x{}",
+            options.width, options.height
+        )),
+    }
+}
+
+/// JPEG has no alpha; composite over white so transparent areas don't turn
+/// black.
+fn flatten_onto_white(rgba: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(rgba.len() / 4 * 3);
+    for pixel in rgba.chunks_exact(4) {
+        let alpha = pixel[3] as u32;
+        for &channel in &pixel[..3] {
+            rgb.push(((channel as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8);
+        }
+    }
+    rgb
+}
+
+fn encode_webp(
+    rgba: &[u8],
+    options: &EncodeOptions,
+    profile: ColorProfile,
+) -> Result<Vec<u8>, String> {
+    use image::ImageEncoder;
+
+    let mut bytes = Vec::new();
+    let mut encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut bytes);
+    encoder
+        .set_icc_profile(profile.icc()?)
+        .map_err(|e| format!("Failed to encode WebP: {e}"))?;
+    encoder
+        .write_image(
+            rgba,
+            options.width,
+            options.height,
+            image::ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| format!("Failed to encode WebP: {e}"))?;
+    Ok(bytes)
+}
+
+fn encode_avif(rgba: &[u8], options: &EncodeOptions) -> Result<Vec<u8>, String> {
+    let pixels: Vec<ravif::RGBA8> = rgba
+        .chunks_exact(4)
+        .map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3]))
+        .collect();
+    let quality = options
+        .settings
+        .quality
+        .unwrap_or(DEFAULT_AVIF_QUALITY)
+        .clamp(1, 100) as f32;
+    let encoded = ravif::Encoder::new()
+        .with_quality(quality)
+        .with_alpha_quality(quality)
+        .with_speed(
+            options
+                .settings
+                .speed
+                .unwrap_or(DEFAULT_AVIF_SPEED)
+                .clamp(1, 10),
+        )
+        .encode_rgba(ravif::Img::new(
+            &pixels[..],
+            options.width as usize,
+            options.height as usize,
+        ))
+        .map_err(|e| format!("Failed to encode AVIF: {e}"))?;
+    Ok(encoded.avif_file)
+}
+
+fn encode_tiff(
+    rgba: &[u8],
+    options: &EncodeOptions,
+    profile: ColorProfile,
+) -> Result<Vec<u8>, String> {
+    use tiff::encoder::colortype::{RGBA16, RGBA8};
+
+    let icc = profile.icc()?;
+    let mut cursor = Cursor::new(Vec::new());
+    if options.settings.bit_depth == Some(16) {
+        let expected_len = pixel_data_len(options);
+        if rgba.len() != expected_len {
+            return Err(format!(
+                "invalid_pixels: 16-bit TIFF needs RGBA16 data, {expected_len} bytes, got {}",
+                rgba.len()
+            ));
+        }
+        let samples: Vec<u16> = rgba
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        write_tiff::<RGBA16>(&mut cursor, &samples, options, &icc)?;
+    } else {
+        write_tiff::<RGBA8>(&mut cursor, rgba, options, &icc)?;
+    }
+    Ok(cursor.into_inner())
+}
+
+fn write_tiff<C: tiff::encoder::colortype::ColorType>(
+    cursor: &mut Cursor<Vec<u8>>,
+    samples: &[C::Inner],
+    options: &EncodeOptions,
+    icc: &[u8],
+) -> Result<(), String>
+where
+    [C::Inner]: tiff::encoder::TiffValue,
+{
+    use tiff::encoder::{Rational, TiffEncoder};
+    use tiff::tags::{ResolutionUnit, Tag};
+
+    let tiff_error = |e: tiff::TiffError| format!("Failed to encode TIFF: {e}");
+    let mut encoder = TiffEncoder::new(cursor).map_err(tiff_error)?;
+    let mut image = encoder
+        .new_image::<C>(options.width, options.height)
+        .map_err(tiff_error)?;
+    image
+        .encoder()
+        .write_tag(Tag::IccProfile, icc)
+        .map_err(tiff_error)?;
+    if let Some(dpi) = options.settings.dpi {
+        image.resolution(ResolutionUnit::Inch, Rational { n: dpi, d: 1 });
+    }
+    image.write_data(samples).map_err(tiff_error)
+}
+
+/// Bytes of pixel data `options` expects: RGBA8, or RGBA16 at 16 bits.
+fn pixel_data_len(options: &EncodeOptions) -> usize {
+    let bytes_per_pixel = if options.settings.bit_depth == Some(16) {
+        8
+    } else {
+        4
+    };
+    options.width as usize * options.height as usize * bytes_per_pixel
+}
+
+fn validate_len(rgba: &[u8], options: &EncodeOptions) -> Result<(), String> {
+    let expected_len = pixel_data_len(options);
+    if rgba.len() != expected_len {
+        return Err(format!(
+            "Invalid RGBA data length: expected {}, got {}",
+            expected_len,
+            rgba.len()
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn encode_image(args: EncodeImageArgs) -> Result<EncodeImageResult, String> {
+    let rgba = general_purpose::STANDARD
+        .decode(&args.rgba_base64)
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    validate_len(&rgba, &args.options)?;
+    let format = ExportFormat::parse(&args.options.settings.format)?;
+    let bytes = encode_rgba(&rgba, &args.options)?;
+    let unoptimized_byte_size = unoptimized_png_size(&rgba, &args.options)?;
+
+    Ok(EncodeImageResult {
+        byte_size: bytes.len(),
+        unoptimized_byte_size,
+        data_base64: general_purpose::STANDARD.encode(bytes),
+        mime: format.mime().to_string(),
+        extension: format.extensions()[0].to_string(),
+    })
+}
+
+/// `encode_image` over raw bytes: RGBA body in, encoded file out.
+#[tauri::command(async)]
+pub fn encode_image_raw(request: tauri::ipc::Request<'_>) -> Result<tauri::ipc::Response, String> {
+    let options: EncodeOptions = binary_ipc::header_args(&request)?;
+    let rgba = binary_ipc::raw_body(&request)?;
+    validate_len(rgba, &options)?;
+    Ok(tauri::ipc::Response::new(encode_rgba(rgba, &options)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encode_rgba, flatten_onto_white, EncodeImageArgs, EncodeOptions, EncodeSettings,
+        ExportFormat,
+    };
+
+    fn options(format: &str) -> EncodeOptions {
+        EncodeOptions {
+            width: 2,
+            height: 1,
+            settings: EncodeSettings {
+                format: format.to_string(),
+                dpi: Some(144),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn formats_parse_with_aliases() {
+        assert_eq!(ExportFormat::parse("JPG").unwrap(), ExportFormat::Jpeg);
+        assert_eq!(ExportFormat::parse("tif").unwrap(), ExportFormat::Tiff);
+        assert_eq!(ExportFormat::Tiff.extensions()[0], "tif");
+        assert!(ExportFormat::parse("bmp").is_err());
+    }
+
+    #[test]
+    fn jpeg_alpha_is_flattened_onto_white() {
+        let rgb = flatten_onto_white(&[0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 128]);
+        assert_eq!(rgb, vec![255, 255, 255, 255, 0, 0, 127, 127, 127]);
+    }
+
+    #[test]
+    fn png_embeds_profile_and_density() {
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 128];
+        let mut options = options("png");
+        options.settings.color_profile = Some("display-p3".to_string());
+        let bytes = encode_rgba(&rgba, &options).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert!(info.icc_profile.as_ref().is_some_and(|icc| icc.len() > 128));
+        let dims = info.pixel_dims.unwrap();
+        assert_eq!(dims.xppu, 5669);
+        assert!(matches!(dims.unit, png::Unit::Meter));
+    }
+
+    #[test]
+    fn encode_args_require_a_size() {
+        let args = serde_json::json!({ "rgbaBase64": "", "width": 2, "format": "png" });
+        let err = serde_json::from_value::<EncodeImageArgs>(args).unwrap_err();
+        assert!(err.to_string().contains("height"), "{err}");
+    }
+
+    #[test]
+    fn settings_a_format_cannot_write_are_refused() {
+        let error = encode_rgba(&[0; 8], &options("webp")).unwrap_err();
+        assert_eq!(error, "unsupported_option: dpi is not supported for WebP");
+
+        let mut options = options("tiff");
+        options.settings.bit_depth = Some(16);
+        let error = encode_rgba(&[0; 8], &options).unwrap_err();
+        assert!(error.starts_with("invalid_pixels:"), "{error}");
+        options.settings.format = "png".to_string();
+        let error = encode_rgba(&[0; 16], &options).unwrap_err();
+        assert_eq!(error, "unsupported_option: 16-bit is not supported for PNG");
+    }
+
+    #[test]
+    fn unknown_profile_is_rejected() {
+        let mut options = options("png");
+        options.settings.color_profile = Some("adobe-rgb".to_string());
+        let err = encode_rgba(&[0; 8], &options).unwrap_err();
+        assert!(err.starts_with("unsupported_color_profile"));
+    }
+}
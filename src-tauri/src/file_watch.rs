@@ -0,0 +1,356 @@
+//! Pushes changes to open documents, dev plugin folders and linked assets to
+//! the frontend as `file-changed` events.
+//!
+//! Files are watched through their parent directory so that atomic
+//! replacements (ours and other editors') keep being seen; directories are
+//! watched recursively. Events are coalesced per target until the target has
+//! been quiet for [`DEBOUNCE`], and writes to `*.tmp` files (the temp side of
+//! our own atomic saves) are ignored.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub const FILE_CHANGED_EVENT: &str = "file-changed";
+/// Quiet period before a change is reported
+const DEBOUNCE: Duration = Duration::from_millis(250);
+/// A target that never goes quiet is still reported this often
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatchKind {
+    Document,
+    PluginFolder,
+    Asset,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchPathArgs {
+    pub path: String,
+    pub kind: WatchKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnwatchPathArgs {
+    pub path: String,
+}
+
+/// Payload of [`FILE_CHANGED_EVENT`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    /// The path as passed to `watch_path`
+    pub path: String,
+    pub kind: WatchKind,
+    /// False once the file or folder has been deleted or moved away
+    pub exists: bool,
+}
+
+struct Target {
+    path: String,
+    kind: WatchKind,
+    root: PathBuf,
+}
+
+type Targets = Arc<Mutex<HashMap<PathBuf, Target>>>;
+
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    targets: Targets,
+    /// Watched directories and how many targets share each
+    roots: HashMap<PathBuf, (usize, RecursiveMode)>,
+}
+
+fn registry() -> &'static Mutex<Option<FileWatcher>> {
+    static REGISTRY: OnceLock<Mutex<Option<FileWatcher>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(None))
+}
+
+#[tauri::command]
+pub fn watch_path(app: tauri::AppHandle, args: WatchPathArgs) -> Result<(), String> {
+    let mut registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+    let watcher = match registry.as_mut() {
+        Some(watcher) => watcher,
+        None => registry.insert(FileWatcher::new(move |change| {
+            let _ = app.emit(FILE_CHANGED_EVENT, change);
+        })?),
+    };
+    watcher.watch(&args.path, args.kind)
+}
+
+#[tauri::command]
+pub fn unwatch_path(args: UnwatchPathArgs) -> Result<(), String> {
+    let mut registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+    match registry.as_mut() {
+        Some(watcher) => watcher.unwatch(&args.path),
+        None => Ok(()),
+    }
+}
+
+impl FileWatcher {
+    pub fn new(on_change: impl Fn(FileChange) + Send + 'static) -> Result<Self, String> {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).map_err(|e| e.to_string())?;
+        let targets = Targets::default();
+        let shared = targets.clone();
+        thread::spawn(move || debounce(receiver, shared, on_change));
+        Ok(Self {
+            watcher,
+            targets,
+            roots: HashMap::new(),
+        })
+    }
+
+    pub fn watch(&mut self, path: &str, kind: WatchKind) -> Result<(), String> {
+        let key = resolve(Path::new(path))?;
+        let (root, mode) = if key.is_dir() {
+            (key.clone(), RecursiveMode::Recursive)
+        } else {
+            let parent = key
+                .parent()
+                .ok_or_else(|| format!("invalid_path: {path}"))?;
+            (parent.to_path_buf(), RecursiveMode::NonRecursive)
+        };
+        if self.targets().contains_key(&key) {
+            return Ok(());
+        }
+        match self.roots.get_mut(&root) {
+            Some((count, current)) => {
+                if mode == RecursiveMode::Recursive && *current == RecursiveMode::NonRecursive {
+                    self.watcher
+                        .watch(&root, mode)
+                        .map_err(|e| format!("watch_failed: {e}"))?;
+                    *current = mode;
+                }
+                *count += 1;
+            }
+            None => {
+                self.watcher
+                    .watch(&root, mode)
+                    .map_err(|e| format!("watch_failed: {e}"))?;
+                self.roots.insert(root.clone(), (1, mode));
+            }
+        }
+        self.targets().insert(
+            key,
+            Target {
+                path: path.to_string(),
+                kind,
+                root,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, path: &str) -> Result<(), String> {
+        let key = resolve(Path::new(path))?;
+        let Some(target) = self.targets().remove(&key) else {
+            return Ok(());
+        };
+        if let Some((count, _)) = self.roots.get_mut(&target.root) {
+            *count -= 1;
+            if *count == 0 {
+                self.roots.remove(&target.root);
+                // The directory may already be gone, which unwatches it anyway.
+                let _ = self.watcher.unwatch(&target.root);
+            }
+        }
+        Ok(())
+    }
+
+    fn targets(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Target>> {
+        self.targets.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// Canonical form of `path`, so it matches what the OS reports. Files that
+/// do not exist yet resolve through their parent.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return Ok(resolved);
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("invalid_path: {}", path.display()))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent =
+        fs::canonicalize(parent).map_err(|_| format!("path_not_found: {}", path.display()))?;
+    Ok(parent.join(name))
+}
+
+fn debounce(
+    receiver: Receiver<notify::Result<Event>>,
+    targets: Targets,
+    on_change: impl Fn(FileChange),
+) {
+    // Changed targets, keyed by when each was first and last touched
+    let mut pending: BTreeMap<PathBuf, (Instant, Instant)> = BTreeMap::new();
+    loop {
+        let now = Instant::now();
+        let wait = pending
+            .values()
+            .map(|(first, last)| (*last + DEBOUNCE).min(*first + MAX_DELAY))
+            .min()
+            .map(|due| due.saturating_duration_since(now));
+        let received = match wait {
+            Some(wait) => receiver.recv_timeout(wait),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(event)) => {
+                let now = Instant::now();
+                for key in affected_targets(&targets, &event) {
+                    pending
+                        .entry(key)
+                        .and_modify(|(_, last)| *last = now)
+                        .or_insert((now, now));
+                }
+            }
+            Ok(Err(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        let now = Instant::now();
+        let due: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, (first, last))| now >= *last + DEBOUNCE || now >= *first + MAX_DELAY)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in due {
+            pending.remove(&key);
+            let change = targets
+                .lock()
+                .unwrap_or_else(|p| p.into_inner())
+                .get(&key)
+                .map(|target| FileChange {
+                    path: target.path.clone(),
+                    kind: target.kind,
+                    exists: key.exists(),
+                });
+            if let Some(change) = change {
+                on_change(change);
+            }
+        }
+    }
+}
+
+fn affected_targets(targets: &Targets, event: &Event) -> Vec<PathBuf> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+    let targets = targets.lock().unwrap_or_else(|p| p.into_inner());
+    let mut affected = Vec::new();
+    for path in &event.paths {
+        if is_temp_file(path) {
+            continue;
+        }
+        for (key, target) in targets.iter() {
+            let matches = if target.root == *key {
+                path.starts_with(key)
+            } else {
+                path == key
+            };
+            if matches && !affected.contains(key) {
+                affected.push(key.clone());
+            }
+        }
+    }
+    affected
+}
+
+fn is_temp_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "tmp")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("galileo-watch-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    fn watcher() -> (FileWatcher, Receiver<FileChange>) {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = FileWatcher::new(move |change| {
+            let _ = sender.send(change);
+        })
+        .unwrap();
+        (watcher, receiver)
+    }
+
+    #[test]
+    fn bursts_of_writes_are_reported_once() {
+        let dir = temp_dir("burst");
+        let document = dir.join("design.galileo");
+        fs::write(&document, "v0").unwrap();
+        let (mut watcher, changes) = watcher();
+        let path = document.to_string_lossy().to_string();
+        watcher.watch(&path, WatchKind::Document).unwrap();
+
+        for version in 1..5 {
+            fs::write(&document, format!("v{version}")).unwrap();
+        }
+        let change = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            change,
+            FileChange {
+                path,
+                kind: WatchKind::Document,
+                exists: true,
+            }
+        );
+        assert!(changes.recv_timeout(DEBOUNCE * 3).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn temp_files_and_unrelated_siblings_are_ignored() {
+        let dir = temp_dir("ignored");
+        let document = dir.join("design.galileo");
+        fs::write(&document, "v0").unwrap();
+        let (mut watcher, changes) = watcher();
+        let path = document.to_string_lossy().to_string();
+        watcher.watch(&path, WatchKind::Document).unwrap();
+
+        fs::write(dir.join("design.galileo.tmp"), "partial").unwrap();
+        fs::write(dir.join("other.galileo"), "other").unwrap();
+        assert!(changes.recv_timeout(DEBOUNCE * 4).is_err());
+
+        watcher.unwatch(&path).unwrap();
+        fs::write(&document, "v1").unwrap();
+        assert!(changes.recv_timeout(DEBOUNCE * 4).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn folders_report_changes_anywhere_inside() {
+        let dir = temp_dir("folder");
+        let plugin = dir.join("plugin");
+        fs::create_dir_all(plugin.join("dist")).unwrap();
+        let (mut watcher, changes) = watcher();
+        let path = plugin.to_string_lossy().to_string();
+        watcher.watch(&path, WatchKind::PluginFolder).unwrap();
+
+        fs::write(plugin.join("dist").join("index.js"), "export {}").unwrap();
+        let change = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(change.path, path);
+        assert_eq!(change.kind, WatchKind::PluginFolder);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
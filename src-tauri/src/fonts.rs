@@ -0,0 +1,1098 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use ttf_parser::{name_id, Face, Language, PlatformId, RawFace, Style, Width};
+
+#[cfg(target_os = "macos")]
+use objc::runtime::Object;
+#[cfg(target_os = "macos")]
+use objc::{class, msg_send, sel, sel_impl};
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+const FONT_FILE_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+const MAX_SCAN_DEPTH: usize = 8;
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+/// Layout tables carried into unicode-range subsets; the subsetter keeps
+/// glyph ids, so they stay valid as they are.
+const LAYOUT_TABLES: [&[u8; 4]; 4] = [b"GDEF", b"GSUB", b"GPOS", b"kern"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontAxisInfo {
+    pub tag: String,
+    pub name: Option<String>,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFaceInfo {
+    pub family: String,
+    pub style_name: String,
+    pub postscript_name: Option<String>,
+    /// OS/2 weight class, 100-900
+    pub weight: u16,
+    /// "normal", "italic" or "oblique"
+    pub style: String,
+    /// CSS font-stretch percentage, 50-200
+    pub stretch: f32,
+    pub axes: Vec<FontAxisInfo>,
+    pub path: String,
+    pub face_index: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFamilyInfo {
+    pub family: String,
+    pub faces: Vec<FontFaceInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadFontArgs {
+    pub family: String,
+    /// CSS weight, defaults to 400
+    pub weight: Option<u16>,
+    /// "normal", "italic" or "oblique", defaults to "normal"
+    pub style: Option<String>,
+    /// Directories searched before the system fonts, e.g. a project's `fonts/` folder
+    pub font_dirs: Option<Vec<String>>,
+    /// CSS unicode-range syntax, e.g. "U+0000-00FF, U+20AC". Omit for the whole font.
+    pub unicode_range: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadFontResult {
+    pub data_base64: String,
+    pub family: String,
+    pub style_name: String,
+    pub postscript_name: Option<String>,
+    pub weight: u16,
+    pub style: String,
+    pub stretch: f32,
+    pub axes: Vec<FontAxisInfo>,
+    pub path: String,
+    /// True when the bytes were reduced to `unicode_range`
+    pub subset: bool,
+}
+
+struct CachedFontFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    faces: Vec<FontFaceInfo>,
+}
+
+/// Parsed faces keyed by file path. Entries are reused while the file's
+/// size and mtime are unchanged, so repeated scans only stat the font dirs.
+fn face_cache() -> &'static Mutex<HashMap<PathBuf, CachedFontFile>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, CachedFontFile>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A backend that can enumerate the font families installed on this machine.
+pub trait FontSource {
+    fn family_names(&self) -> Result<Vec<String>, String>;
+}
+
+/// Asks AppKit for the families it knows about, including activated fonts
+/// that don't live in any of the standard font directories.
+#[cfg(target_os = "macos")]
+pub struct FontManagerSource;
+
+#[cfg(target_os = "macos")]
+unsafe fn nsstring_to_string(value: *mut Object) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let utf8: *const c_char = msg_send![value, UTF8String];
+    if utf8.is_null() {
+        return None;
+    }
+    let cstr = CStr::from_ptr(utf8);
+    Some(cstr.to_string_lossy().into_owned())
+}
+
+#[cfg(target_os = "macos")]
+impl FontSource for FontManagerSource {
+    fn family_names(&self) -> Result<Vec<String>, String> {
+        unsafe {
+            let font_manager: *mut Object = msg_send![class!(NSFontManager), sharedFontManager];
+            if font_manager.is_null() {
+                return Err("Failed to access NSFontManager".to_string());
+            }
+
+            let families: *mut Object = msg_send![font_manager, availableFontFamilies];
+            if families.is_null() {
+                return Err("Failed to read available font families".to_string());
+            }
+
+            let count: usize = msg_send![families, count];
+            let mut result = Vec::with_capacity(count);
+
+            for index in 0..count {
+                let item: *mut Object = msg_send![families, objectAtIndex: index];
+                if let Some(family) = nsstring_to_string(item) {
+                    result.push(family);
+                }
+            }
+
+            Ok(normalize_families(result))
+        }
+    }
+}
+
+/// Scans font directories and reads family names straight from the `name`
+/// table of every TTF/OTF/TTC file it finds.
+pub struct DirectoryFontSource {
+    dirs: Vec<PathBuf>,
+}
+
+impl DirectoryFontSource {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self { dirs }
+    }
+
+    pub fn system() -> Self {
+        Self::new(system_font_dirs())
+    }
+
+    pub fn font_files(&self) -> Vec<PathBuf> {
+        let mut visited = HashSet::new();
+        let mut files = Vec::new();
+        for dir in &self.dirs {
+            collect_font_files(dir, 0, &mut visited, &mut files);
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    pub fn faces(&self) -> Vec<FontFaceInfo> {
+        let files = self.font_files();
+        let mut cache = face_cache()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut faces = Vec::new();
+        for path in files {
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified().ok();
+            let len = metadata.len();
+            let fresh = cache
+                .get(&path)
+                .is_some_and(|entry| entry.modified == modified && entry.len == len);
+            if !fresh {
+                let parsed = fs::read(&path)
+                    .map(|data| read_faces(&path, &data))
+                    .unwrap_or_default();
+                cache.insert(
+                    path.clone(),
+                    CachedFontFile {
+                        modified,
+                        len,
+                        faces: parsed,
+                    },
+                );
+            }
+            if let Some(entry) = cache.get(&path) {
+                faces.extend(entry.faces.iter().cloned());
+            }
+        }
+        faces
+    }
+}
+
+impl FontSource for DirectoryFontSource {
+    fn family_names(&self) -> Result<Vec<String>, String> {
+        let families = self.faces().into_iter().map(|face| face.family).collect();
+        Ok(normalize_families(families))
+    }
+}
+
+fn system_sources() -> Vec<Box<dyn FontSource>> {
+    #[cfg(target_os = "macos")]
+    {
+        vec![
+            Box::new(FontManagerSource),
+            Box::new(DirectoryFontSource::system()),
+        ]
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        vec![Box::new(DirectoryFontSource::system())]
+    }
+}
+
+fn normalize_families(names: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = names
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && !name.starts_with('.'))
+        .collect();
+    result.sort();
+    result.dedup();
+    result
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|ext| {
+            FONT_FILE_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+fn collect_font_files(
+    dir: &Path,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    if depth > MAX_SCAN_DEPTH {
+        return;
+    }
+    // Font trees are often symlinked into each other; canonicalize so each
+    // directory is walked once and symlink loops terminate.
+    let Ok(canonical) = fs::canonicalize(dir) else {
+        return;
+    };
+    if !visited.insert(canonical) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_font_files(&path, depth + 1, visited, files);
+        } else if is_font_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    let key = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/System/Library/Fonts"),
+        PathBuf::from("/Library/Fonts"),
+    ];
+    if let Some(home) = home_dir() {
+        dirs.push(home.join("Library/Fonts"));
+    }
+    dirs
+}
+
+#[cfg(windows)]
+fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+    dirs.push(PathBuf::from(windir).join("Fonts"));
+    // Fonts installed without admin rights land in the per-user directory.
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+    }
+    dirs
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn system_font_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    let xdg_data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+
+    let mut dirs = fontconfig_dirs(home.as_deref(), xdg_data_home.as_deref());
+    dirs.push(PathBuf::from("/usr/share/fonts"));
+    dirs.push(PathBuf::from("/usr/local/share/fonts"));
+    if let Some(data_home) = &xdg_data_home {
+        dirs.push(data_home.join("fonts"));
+    }
+    if let Some(home) = &home {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+    }
+    dirs
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn fontconfig_dirs(home: Option<&Path>, xdg_data_home: Option<&Path>) -> Vec<PathBuf> {
+    let mut configs = vec![PathBuf::from("/etc/fonts/fonts.conf")];
+    if let Ok(entries) = fs::read_dir("/etc/fonts/conf.d") {
+        let mut extra: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(OsStr::to_str) == Some("conf"))
+            .collect();
+        extra.sort();
+        configs.extend(extra);
+    }
+
+    let mut dirs = Vec::new();
+    for config in configs {
+        if let Ok(text) = fs::read_to_string(&config) {
+            dirs.extend(parse_fontconfig_dirs(&text, home, xdg_data_home));
+        }
+    }
+    dirs
+}
+
+/// Pulls the `<dir>` entries out of a fontconfig file. This is deliberately
+/// not a full XML parser: fontconfig only ever writes plain paths there.
+#[cfg_attr(any(target_os = "macos", windows), allow(dead_code))]
+fn parse_fontconfig_dirs(
+    config: &str,
+    home: Option<&Path>,
+    xdg_data_home: Option<&Path>,
+) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut rest = config;
+    while let Some(start) = rest.find("<dir") {
+        rest = &rest[start + 4..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let attrs = &rest[..tag_end];
+        // Skip `<dirname>`-style tags and self-closing `<dir/>`.
+        if !(attrs.is_empty() || attrs.starts_with(char::is_whitespace)) || attrs.ends_with('/') {
+            continue;
+        }
+        rest = &rest[tag_end + 1..];
+        let Some(close) = rest.find("</dir>") else {
+            break;
+        };
+        let value = rest[..close].trim();
+        rest = &rest[close + 6..];
+        if value.is_empty() {
+            continue;
+        }
+
+        let path = if attrs.contains("prefix=\"xdg\"") {
+            xdg_data_home.map(|base| base.join(value))
+        } else if let Some(stripped) = value.strip_prefix('~') {
+            home.map(|home| home.join(stripped.trim_start_matches('/')))
+        } else if Path::new(value).is_absolute() {
+            Some(PathBuf::from(value))
+        } else {
+            None
+        };
+        if let Some(path) = path {
+            dirs.push(path);
+        }
+    }
+    dirs
+}
+
+fn decode_name(name: &ttf_parser::name::Name) -> Option<String> {
+    if name.is_unicode() {
+        return name.to_string();
+    }
+    // Legacy Mac Roman records; only trust them when they are plain ASCII.
+    if name.platform_id == PlatformId::Macintosh && name.encoding_id == 0 && name.name.is_ascii() {
+        return String::from_utf8(name.name.to_vec()).ok();
+    }
+    None
+}
+
+pub(crate) fn preferred_name(face: &Face, id: u16) -> Option<String> {
+    let mut fallback = None;
+    for name in face.names() {
+        if name.name_id != id {
+            continue;
+        }
+        let Some(value) = decode_name(&name) else {
+            continue;
+        };
+        if value.trim().is_empty() {
+            continue;
+        }
+        if name.language() == Language::English_UnitedStates {
+            return Some(value);
+        }
+        fallback.get_or_insert(value);
+    }
+    fallback
+}
+
+/// Typographic family (name ID 16) groups weights like "Inter Bold" under
+/// "Inter"; older fonts only carry the legacy family (name ID 1).
+pub(crate) fn face_family_name(face: &Face) -> Option<String> {
+    preferred_name(face, name_id::TYPOGRAPHIC_FAMILY)
+        .or_else(|| preferred_name(face, name_id::FAMILY))
+}
+
+fn style_keyword(style: Style) -> &'static str {
+    match style {
+        Style::Normal => "normal",
+        Style::Italic => "italic",
+        Style::Oblique => "oblique",
+    }
+}
+
+fn css_stretch(width: Width) -> f32 {
+    match width {
+        Width::UltraCondensed => 50.0,
+        Width::ExtraCondensed => 62.5,
+        Width::Condensed => 75.0,
+        Width::SemiCondensed => 87.5,
+        Width::Normal => 100.0,
+        Width::SemiExpanded => 112.5,
+        Width::Expanded => 125.0,
+        Width::ExtraExpanded => 150.0,
+        Width::UltraExpanded => 200.0,
+    }
+}
+
+fn face_info(path: &Path, face_index: u32, face: &Face) -> Option<FontFaceInfo> {
+    let family = face_family_name(face)?;
+    let style_name = preferred_name(face, name_id::TYPOGRAPHIC_SUBFAMILY)
+        .or_else(|| preferred_name(face, name_id::SUBFAMILY))
+        .unwrap_or_else(|| "Regular".to_string());
+    let axes = face
+        .variation_axes()
+        .into_iter()
+        .filter(|axis| !axis.hidden)
+        .map(|axis| FontAxisInfo {
+            tag: axis.tag.to_string(),
+            name: preferred_name(face, axis.name_id),
+            min: axis.min_value,
+            default: axis.def_value,
+            max: axis.max_value,
+        })
+        .collect();
+
+    Some(FontFaceInfo {
+        family: family.trim().to_string(),
+        style_name,
+        postscript_name: preferred_name(face, name_id::POST_SCRIPT_NAME),
+        weight: face.weight().to_number(),
+        style: style_keyword(face.style()).to_string(),
+        stretch: css_stretch(face.width()),
+        axes,
+        path: path.to_string_lossy().to_string(),
+        face_index,
+    })
+}
+
+fn read_faces(path: &Path, data: &[u8]) -> Vec<FontFaceInfo> {
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+    (0..count)
+        .filter_map(|index| {
+            let face = Face::parse(data, index).ok()?;
+            face_info(path, index, &face)
+        })
+        .collect()
+}
+
+fn group_families(faces: Vec<FontFaceInfo>) -> Vec<FontFamilyInfo> {
+    let mut families: BTreeMap<String, Vec<FontFaceInfo>> = BTreeMap::new();
+    for face in faces {
+        if face.family.is_empty() || face.family.starts_with('.') {
+            continue;
+        }
+        families.entry(face.family.clone()).or_default().push(face);
+    }
+
+    families
+        .into_iter()
+        .map(|(family, mut faces)| {
+            faces.sort_by(|a, b| {
+                a.stretch
+                    .total_cmp(&b.stretch)
+                    .then(a.weight.cmp(&b.weight))
+                    .then(a.style.cmp(&b.style))
+                    .then(a.path.cmp(&b.path))
+            });
+            // The same face is frequently installed twice (system and user dir).
+            faces.dedup_by(|a, b| {
+                a.postscript_name.is_some()
+                    && a.postscript_name == b.postscript_name
+                    && a.weight == b.weight
+                    && a.style == b.style
+            });
+            FontFamilyInfo { family, faces }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_system_fonts() -> Result<Vec<String>, String> {
+    let mut last_error = None;
+    for source in system_sources() {
+        match source.family_names() {
+            Ok(families) if !families.is_empty() => return Ok(families),
+            Ok(_) => {}
+            Err(err) => last_error = Some(err),
+        }
+    }
+    match last_error {
+        Some(err) => Err(err),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn style_fallbacks(style: &str) -> [&'static str; 3] {
+    match style {
+        "italic" => ["italic", "oblique", "normal"],
+        "oblique" => ["oblique", "italic", "normal"],
+        _ => ["normal", "oblique", "italic"],
+    }
+}
+
+fn weight_range(face: &FontFaceInfo) -> (u16, u16) {
+    face.axes
+        .iter()
+        .find(|axis| axis.tag == "wght")
+        .map(|axis| (axis.min.round() as u16, axis.max.round() as u16))
+        .unwrap_or((face.weight, face.weight))
+}
+
+/// Orders candidate weights the way CSS font matching does: for 400-500 try
+/// up to 500 first, lighter weights prefer lighter faces, bolder prefer bolder.
+fn weight_match_key(face: &FontFaceInfo, desired: u16) -> (u8, u16) {
+    let (min, max) = weight_range(face);
+    if (min..=max).contains(&desired) {
+        return (0, 0);
+    }
+    let lighter = max < desired;
+    let distance = if lighter {
+        desired - max
+    } else {
+        min - desired
+    };
+    let tier = match desired {
+        400..=500 if !lighter && min <= 500 => 1,
+        400..=500 if lighter => 2,
+        400..=500 => 3,
+        0..=399 if lighter => 1,
+        0..=399 => 2,
+        _ if !lighter => 1,
+        _ => 2,
+    };
+    (tier, distance)
+}
+
+pub(crate) fn select_face<'a>(
+    faces: &'a [FontFaceInfo],
+    family: &str,
+    weight: u16,
+    style: &str,
+) -> Option<&'a FontFaceInfo> {
+    let family = family.trim();
+    let candidates: Vec<&FontFaceInfo> = faces
+        .iter()
+        .filter(|face| face.family.eq_ignore_ascii_case(family))
+        .collect();
+
+    style_fallbacks(style).iter().find_map(|wanted| {
+        candidates
+            .iter()
+            .filter(|face| face.style == *wanted)
+            .min_by_key(|face| {
+                let stretch_distance = (face.stretch - 100.0).abs().round() as u16;
+                (stretch_distance, weight_match_key(face, weight))
+            })
+            .copied()
+    })
+}
+
+fn parse_unicode_range(value: &str) -> Result<Vec<(u32, u32)>, String> {
+    let mut ranges = Vec::new();
+    for part in value.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        let body = part
+            .strip_prefix("U+")
+            .or_else(|| part.strip_prefix("u+"))
+            .ok_or_else(|| format!("Invalid unicode range: {part}"))?;
+        let parse_hex = |hex: &str| {
+            u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid unicode range: {part}"))
+        };
+
+        let (start, end) = if let Some((start, end)) = body.split_once('-') {
+            (parse_hex(start)?, parse_hex(end)?)
+        } else if body.contains('?') {
+            (
+                parse_hex(&body.replace('?', "0"))?,
+                parse_hex(&body.replace('?', "F"))?,
+            )
+        } else {
+            let single = parse_hex(body)?;
+            (single, single)
+        };
+        if start > end || start > MAX_CODE_POINT {
+            return Err(format!("Invalid unicode range: {part}"));
+        }
+        ranges.push((start, end.min(MAX_CODE_POINT)));
+    }
+    if ranges.is_empty() {
+        return Err("Unicode range is empty".to_string());
+    }
+    Ok(ranges)
+}
+
+/// Keeps outlines for the glyphs covering `ranges`, plus every unencoded
+/// glyph (ligatures, alternates) that GSUB may substitute in. The subsetter
+/// only empties the other outlines and drops layout tables, so cmap, OS/2
+/// and name come through as they are and [`LAYOUT_TABLES`] are copied back
+/// from the original, keeping kerning and ligatures for the webview.
+fn subset_face(data: &[u8], face_index: u32, ranges: &[(u32, u32)]) -> Result<Vec<u8>, String> {
+    let face = Face::parse(data, face_index).map_err(|e| format!("Failed to parse font: {e}"))?;
+    let mut encoded = vec![false; face.number_of_glyphs() as usize];
+    let mut glyphs = vec![0];
+    let subtables = face
+        .tables()
+        .cmap
+        .into_iter()
+        .flat_map(|cmap| cmap.subtables);
+    for subtable in subtables.filter(|subtable| subtable.is_unicode()) {
+        subtable.codepoints(|code_point| {
+            let Some(glyph) = subtable.glyph_index(code_point) else {
+                return;
+            };
+            if let Some(seen) = encoded.get_mut(glyph.0 as usize) {
+                *seen = true;
+            }
+            if ranges
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&code_point))
+            {
+                glyphs.push(glyph.0);
+            }
+        });
+    }
+    glyphs.extend((0..face.number_of_glyphs()).filter(|&glyph| !encoded[glyph as usize]));
+    glyphs.sort_unstable();
+    glyphs.dedup();
+
+    let subset = subsetter::subset(data, face_index, subsetter::Profile::pdf(&glyphs))
+        .map_err(|e| format!("Failed to subset font: {e}"))?;
+    let original =
+        RawFace::parse(data, face_index).map_err(|e| format!("Failed to parse font: {e}"))?;
+    let kept = RawFace::parse(&subset, 0).map_err(|e| format!("Failed to subset font: {e}"))?;
+    let mut tables = raw_tables(&kept)?;
+    for tag in LAYOUT_TABLES {
+        if let Some(table) = original.table(ttf_parser::Tag::from_bytes(tag)) {
+            tables.push((*tag, table));
+        }
+    }
+    Ok(write_sfnt(tables))
+}
+
+/// Copies face `face_index` out of a TrueType/OpenType collection into a
+/// standalone font, tables untouched, since FontFace can't load collections.
+fn extract_face(data: &[u8], face_index: u32) -> Result<Vec<u8>, String> {
+    let face =
+        RawFace::parse(data, face_index).map_err(|e| format!("Failed to parse font: {e}"))?;
+    Ok(write_sfnt(raw_tables(&face)?))
+}
+
+/// A table's tag and bytes
+type SfntTable<'a> = ([u8; 4], &'a [u8]);
+
+fn raw_tables<'a>(face: &RawFace<'a>) -> Result<Vec<SfntTable<'a>>, String> {
+    face.table_records
+        .into_iter()
+        .map(|record| {
+            let start = record.offset as usize;
+            let table = face
+                .data
+                .get(start..start + record.length as usize)
+                .ok_or("Failed to parse font: table out of bounds")?;
+            Ok((record.tag.to_bytes(), table))
+        })
+        .collect()
+}
+
+/// Lays `tables` out as a standalone sfnt: a sorted directory, each table
+/// 4-byte aligned with its checksum, and `head`'s whole-file adjustment.
+fn write_sfnt(mut tables: Vec<SfntTable>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let cff = tables
+        .iter()
+        .any(|(tag, _)| tag == b"CFF " || tag == b"CFF2");
+    let count = tables.len() as u16;
+    let entry_selector = count.max(1).ilog2() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut out = if cff {
+        b"OTTO".to_vec()
+    } else {
+        vec![0, 1, 0, 0]
+    };
+    for field in [
+        count,
+        search_range,
+        entry_selector,
+        (count * 16).saturating_sub(search_range),
+    ] {
+        out.extend_from_slice(&field.to_be_bytes());
+    }
+    out.resize(12 + tables.len() * 16, 0);
+
+    let checksum = |bytes: &[u8]| {
+        bytes.chunks_exact(4).fold(0u32, |sum, word| {
+            sum.wrapping_add(u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        })
+    };
+    let mut head = None;
+    for (index, (tag, table)) in tables.iter().enumerate() {
+        let offset = out.len();
+        out.extend_from_slice(table);
+        if tag == b"head" && table.len() >= 12 {
+            out[offset + 8..offset + 12].fill(0);
+            head = Some(offset);
+        }
+        out.resize(out.len().next_multiple_of(4), 0);
+        let fields = [checksum(&out[offset..]), offset as u32, table.len() as u32];
+        let record = 12 + index * 16;
+        out[record..record + 4].copy_from_slice(tag);
+        for (at, field) in (record + 4..).step_by(4).zip(fields) {
+            out[at..at + 4].copy_from_slice(&field.to_be_bytes());
+        }
+    }
+
+    if let Some(head) = head {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+/// Returns every installed family with its faces, variable axes and source
+/// files. Parsed font files are cached for the lifetime of the process.
+#[tauri::command]
+pub fn list_font_families() -> Result<Vec<FontFamilyInfo>, String> {
+    Ok(group_families(DirectoryFontSource::system().faces()))
+}
+
+/// Resolves a family, weight and style to a font file and returns its bytes
+/// for the webview's FontFace API, optionally subset to a unicode range.
+#[tauri::command]
+pub fn load_font(args: LoadFontArgs) -> Result<LoadFontResult, String> {
+    let weight = args.weight.unwrap_or(400).clamp(1, 1000);
+    let style = args
+        .style
+        .as_deref()
+        .unwrap_or("normal")
+        .trim()
+        .to_lowercase();
+    let ranges = match args.unicode_range.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => Some(parse_unicode_range(value)?),
+        _ => None,
+    };
+
+    let project_dirs: Vec<PathBuf> = args
+        .font_dirs
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let project_faces = DirectoryFontSource::new(project_dirs).faces();
+    let face = match select_face(&project_faces, &args.family, weight, &style) {
+        Some(face) => face.clone(),
+        None => {
+            let system_faces = DirectoryFontSource::system().faces();
+            select_face(&system_faces, &args.family, weight, &style)
+                .cloned()
+                .ok_or_else(|| format!("font_not_found: {}", args.family.trim()))?
+        }
+    };
+
+    let data = fs::read(&face.path).map_err(|e| e.to_string())?;
+    // Variable fonts are sent whole: the subsetter drops the variation
+    // tables, which would pin the face to its default instance.
+    let (bytes, subset) = match ranges {
+        Some(ranges) if face.axes.is_empty() => {
+            (subset_face(&data, face.face_index, &ranges)?, true)
+        }
+        _ if ttf_parser::fonts_in_collection(&data).is_some() => {
+            (extract_face(&data, face.face_index)?, false)
+        }
+        _ => (data, false),
+    };
+
+    Ok(LoadFontResult {
+        data_base64: general_purpose::STANDARD.encode(&bytes),
+        family: face.family,
+        style_name: face.style_name,
+        postscript_name: face.postscript_name,
+        weight: face.weight,
+        style: face.style,
+        stretch: face.stretch,
+        axes: face.axes,
+        path: face.path,
+        subset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        extract_face, group_families, is_font_file, normalize_families, parse_fontconfig_dirs,
+        parse_unicode_range, select_face, subset_face, write_sfnt, FontAxisInfo, FontFaceInfo,
+    };
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn fontconfig_dirs_expand_home_and_xdg_prefixes() {
+        let config = r#"
+            <fontconfig>
+                <dir>/usr/share/fonts</dir>
+                <dir prefix="xdg">fonts</dir>
+                <dir>~/.fonts</dir>
+                <dir>relative/fonts</dir>
+                <dirname>/ignored</dirname>
+                <cachedir>/var/cache/fontconfig</cachedir>
+            </fontconfig>
+        "#;
+        let dirs = parse_fontconfig_dirs(
+            config,
+            Some(Path::new("/home/ada")),
+            Some(Path::new("/home/ada/.local/share")),
+        );
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("/usr/share/fonts"),
+                PathBuf::from("/home/ada/.local/share/fonts"),
+                PathBuf::from("/home/ada/.fonts"),
+            ]
+        );
+    }
+
+    #[test]
+    fn families_are_trimmed_sorted_and_deduplicated() {
+        let families = normalize_families(vec![
+            " Inter ".to_string(),
+            "Arial".to_string(),
+            "Inter".to_string(),
+            String::new(),
+            ".SF NS".to_string(),
+        ]);
+        assert_eq!(families, vec!["Arial".to_string(), "Inter".to_string()]);
+    }
+
+    #[test]
+    fn font_file_detection_is_case_insensitive() {
+        assert!(is_font_file(Path::new("/fonts/Inter.TTF")));
+        assert!(is_font_file(Path::new("/fonts/Helvetica.ttc")));
+        assert!(!is_font_file(Path::new("/fonts/fonts.dir")));
+    }
+
+    fn face(family: &str, weight: u16, style: &str, path: &str) -> FontFaceInfo {
+        FontFaceInfo {
+            family: family.to_string(),
+            style_name: "Regular".to_string(),
+            postscript_name: Some(format!("{family}-{weight}-{style}")),
+            weight,
+            style: style.to_string(),
+            stretch: 100.0,
+            axes: Vec::new(),
+            path: path.to_string(),
+            face_index: 0,
+        }
+    }
+
+    #[test]
+    fn faces_are_grouped_by_family_and_deduplicated() {
+        let families = group_families(vec![
+            face("Inter", 700, "normal", "/usr/share/fonts/Inter-Bold.ttf"),
+            face("Inter", 400, "italic", "/usr/share/fonts/Inter-Italic.ttf"),
+            face("Inter", 400, "normal", "/usr/share/fonts/Inter.ttf"),
+            face("Inter", 400, "normal", "/home/ada/.fonts/Inter.ttf"),
+            face("Arial", 400, "normal", "/usr/share/fonts/Arial.ttf"),
+        ]);
+        assert_eq!(families.len(), 2);
+        assert_eq!(families[0].family, "Arial");
+        let inter: Vec<(u16, &str)> = families[1]
+            .faces
+            .iter()
+            .map(|face| (face.weight, face.style.as_str()))
+            .collect();
+        assert_eq!(
+            inter,
+            vec![(400, "italic"), (400, "normal"), (700, "normal")]
+        );
+    }
+
+    #[test]
+    fn weight_matching_follows_css_fallback_order() {
+        let faces = vec![
+            face("Inter", 300, "normal", "a"),
+            face("Inter", 600, "normal", "b"),
+            face("Inter", 900, "normal", "c"),
+            face("Inter", 400, "italic", "d"),
+        ];
+        let pick =
+            |weight, style| select_face(&faces, "inter", weight, style).map(|f| f.path.as_str());
+        // 400 looks lighter first because nothing sits between 400 and 500.
+        assert_eq!(pick(400, "normal"), Some("a"));
+        assert_eq!(pick(700, "normal"), Some("c"));
+        assert_eq!(pick(200, "normal"), Some("a"));
+        assert_eq!(pick(700, "italic"), Some("d"));
+        assert_eq!(pick(400, "oblique"), Some("d"));
+        assert_eq!(
+            select_face(&faces, "Roboto", 400, "normal").map(|f| f.weight),
+            None
+        );
+    }
+
+    #[test]
+    fn variable_weight_axis_counts_as_exact_match() {
+        let mut variable = face("Inter", 400, "normal", "variable");
+        variable.axes.push(FontAxisInfo {
+            tag: "wght".to_string(),
+            name: Some("Weight".to_string()),
+            min: 100.0,
+            default: 400.0,
+            max: 900.0,
+        });
+        let faces = vec![face("Inter", 700, "normal", "static"), variable];
+        let picked = select_face(&faces, "Inter", 700, "normal").map(|f| f.path.as_str());
+        assert_eq!(picked, Some("static"));
+        let picked = select_face(&faces, "Inter", 650, "normal").map(|f| f.path.as_str());
+        assert_eq!(picked, Some("variable"));
+    }
+
+    #[test]
+    fn unicode_range_accepts_css_syntax() {
+        assert_eq!(
+            parse_unicode_range("U+0000-00FF, u+20AC, U+4??").unwrap(),
+            vec![(0x0, 0xFF), (0x20AC, 0x20AC), (0x400, 0x4FF)]
+        );
+        assert!(parse_unicode_range("0041").is_err());
+        assert!(parse_unicode_range("U+00FF-0000").is_err());
+        assert!(parse_unicode_range(" , ").is_err());
+    }
+
+    /// A TrueType font with five triangle glyphs: .notdef, 'A', 'B', 'Ж'
+    /// and an unencoded ligature, plus an empty GSUB.
+    fn test_font() -> Vec<u8> {
+        let glyph: &[u8] = &[
+            0, 1, 0, 0, 0, 0, 0, 100, 0, 100, 0, 2, 0, 0, 1, 1, 1, 0, 0, 0, 100, 0xFF, 0x9C, 0, 0,
+            0, 0, 0, 100, 0,
+        ];
+        let glyf = glyph.repeat(5);
+        let loca: Vec<u8> = (0..=5u16)
+            .flat_map(|index| (index * 15).to_be_bytes())
+            .collect();
+        let mut head = [0u8; 54];
+        head[..4].copy_from_slice(&[0, 1, 0, 0]);
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = [0u8; 36];
+        hhea[..4].copy_from_slice(&[0, 1, 0, 0]);
+        hhea[34..36].copy_from_slice(&5u16.to_be_bytes());
+        let maxp = [0, 0, 0x50, 0, 0, 5];
+        let hmtx: Vec<u8> = (0..5).flat_map(|_| [1, 0xF4, 0, 0]).collect();
+        let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 10, 0, 0, 0, 12];
+        cmap.extend_from_slice(&[0, 12, 0, 0, 0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 0, 2]);
+        for (start, end, glyph) in [(0x41u32, 0x42u32, 1u32), (0x416, 0x416, 3)] {
+            for value in [start, end, glyph] {
+                cmap.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        let name = [0, 0, 0, 0, 0, 6];
+        let os2 = [0u8; 78];
+        let gsub = [0, 1, 0, 0, 0, 10, 0, 12, 0, 14, 0, 0, 0, 0, 0, 0];
+        write_sfnt(vec![
+            (*b"head", &head),
+            (*b"hhea", &hhea),
+            (*b"maxp", &maxp),
+            (*b"hmtx", &hmtx),
+            (*b"cmap", &cmap),
+            (*b"loca", &loca),
+            (*b"glyf", &glyf),
+            (*b"name", &name),
+            (*b"OS/2", &os2),
+            (*b"GSUB", &gsub),
+        ])
+    }
+
+    #[test]
+    fn unicode_range_subsets_keep_layout_and_load_as_fonts() {
+        let font = test_font();
+        let original = ttf_parser::Face::parse(&font, 0).unwrap();
+        assert!((0..5).all(|glyph| original
+            .glyph_bounding_box(ttf_parser::GlyphId(glyph))
+            .is_some()));
+
+        let subset = subset_face(&font, 0, &parse_unicode_range("U+41").unwrap()).unwrap();
+        let face = ttf_parser::Face::parse(&subset, 0).unwrap();
+        assert_eq!(face.glyph_index('A'), Some(ttf_parser::GlyphId(1)));
+        let outlined: Vec<u16> = (0..5)
+            .filter(|&glyph| {
+                face.glyph_bounding_box(ttf_parser::GlyphId(glyph))
+                    .is_some()
+            })
+            .collect();
+        assert_eq!(outlined, vec![0, 1, 4]);
+        let tables = face.tables();
+        assert!(tables.gsub.is_some() && tables.os2.is_some() && tables.name.is_some());
+    }
+
+    #[test]
+    fn collection_faces_are_extracted_with_their_tables() {
+        // Two faces sharing one `head`; the second also has a 3-byte table.
+        let head = [7u8; 54];
+        let mut ttc = b"ttcf\0\x01\0\0\0\0\0\x02".to_vec();
+        ttc.extend_from_slice(&20u32.to_be_bytes());
+        ttc.extend_from_slice(&48u32.to_be_bytes());
+        let directory = |tables: &[(&[u8; 4], u32, u32)]| {
+            let mut out = vec![0, 1, 0, 0, 0, tables.len() as u8, 0, 0, 0, 0, 0, 0];
+            for (tag, offset, length) in tables {
+                out.extend_from_slice(*tag);
+                out.extend_from_slice(&0u32.to_be_bytes());
+                out.extend_from_slice(&offset.to_be_bytes());
+                out.extend_from_slice(&length.to_be_bytes());
+            }
+            out
+        };
+        ttc.extend(directory(&[(b"head", 92, 54)]));
+        ttc.extend(directory(&[(b"head", 92, 54), (b"test", 148, 3)]));
+        assert_eq!(ttc.len(), 92);
+        ttc.extend_from_slice(&head);
+        ttc.extend_from_slice(&[0, 0, 1, 2, 3]);
+
+        let face = extract_face(&ttc, 1).unwrap();
+        assert_eq!(face.len(), 44 + 56 + 4);
+        assert_eq!(&face[12..16], b"head");
+        assert_eq!(&face[20..24], &44u32.to_be_bytes());
+        assert_eq!(&face[36..40], &100u32.to_be_bytes());
+        assert_eq!(&face[100..103], &[1, 2, 3]);
+        assert_eq!(&face[44..52], &head[..8]);
+        let sum = face.chunks_exact(4).fold(0u32, |sum, word| {
+            sum.wrapping_add(u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        });
+        assert_eq!(sum, 0xB1B0_AFBA);
+        assert!(extract_face(&ttc, 2).is_err());
+    }
+}
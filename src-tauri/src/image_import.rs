@@ -0,0 +1,1041 @@
+//! Native decoding for every format the import dialog offers, so imports
+//! don't depend on what the webview happens to display. Sources are
+//! sniffed by content, reduced to one still image (the largest ICO/ICNS
+//! representation, the first GIF frame) and re-encoded as PNG or WebP.
+//!
+//! Photos also go through [`prepare`]: EXIF orientation is applied,
+//! embedded ICC profiles are converted to sRGB, and EXIF/XMP/IPTC blocks
+//! (GPS, serial numbers, owner names) are stripped. The capture details
+//! worth keeping come back separately as [`CaptureMetadata`].
+
+use crate::export::{self, EncodeOptions, EncodeSettings};
+use base64::{engine::general_purpose, Engine as _};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ProfileText, TransformOptions};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Cursor;
+
+const PREPARED_JPEG_QUALITY: u8 = 92;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportImageArgs {
+    pub path: Option<String>,
+    pub data_base64: Option<String>,
+    /// "png" (default) or "webp"
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportImageResult {
+    pub data_base64: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    /// The detected source container, e.g. "icns" or "heic"
+    pub source_format: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareImageArgs {
+    pub path: Option<String>,
+    pub data_base64: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareImageResult {
+    pub data_base64: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub capture: CaptureMetadata,
+}
+
+/// Capture details kept from a photo's metadata; location and identifying
+/// fields are never copied here.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lens_model: Option<String>,
+    /// Local capture time as `YYYY-MM-DDTHH:MM:SS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taken_at: Option<String>,
+    /// Seconds, e.g. "1/250" or "2"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposure_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub f_number: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focal_length_mm: Option<f64>,
+    /// The EXIF orientation (1-8) that was applied to the pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<u8>,
+    /// Description of the embedded ICC profile the pixels were converted from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_profile: Option<String>,
+}
+
+/// A photo ready to store: upright, sRGB and free of embedded metadata.
+pub struct PreparedImage {
+    pub bytes: Vec<u8>,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub capture: CaptureMetadata,
+}
+
+/// Containers recognized by their leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+    Bmp,
+    Ico,
+    Icns,
+    Heic,
+    Heif,
+}
+
+impl SourceFormat {
+    pub fn sniff(bytes: &[u8]) -> Result<Self, String> {
+        let brand = bytes
+            .get(8..12)
+            .filter(|_| bytes.get(4..8) == Some(b"ftyp"));
+        Ok(match bytes {
+            [0x89, b'P', b'N', b'G', ..] => Self::Png,
+            [0xff, 0xd8, 0xff, ..] => Self::Jpeg,
+            [b'G', b'I', b'F', b'8', ..] => Self::Gif,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Self::WebP,
+            [b'B', b'M', ..] => Self::Bmp,
+            [0, 0, 1, 0, ..] => Self::Ico,
+            [b'i', b'c', b'n', b's', ..] => Self::Icns,
+            _ => match brand {
+                Some(b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis") => Self::Heic,
+                Some(b"mif1" | b"msf1") => Self::Heif,
+                Some(brand) => {
+                    return Err(format!(
+                        "unsupported_format: {}",
+                        String::from_utf8_lossy(brand).trim()
+                    ))
+                }
+                None => return Err("unsupported_format: unrecognized image data".to_string()),
+            },
+        })
+    }
+
+    /// The `image` format for containers that carry EXIF and ICC data.
+    fn photo_format(self) -> Option<ImageFormat> {
+        match self {
+            Self::Png => Some(ImageFormat::Png),
+            Self::Jpeg => Some(ImageFormat::Jpeg),
+            Self::WebP => Some(ImageFormat::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Gif => "gif",
+            Self::WebP => "webp",
+            Self::Bmp => "bmp",
+            Self::Ico => "ico",
+            Self::Icns => "icns",
+            Self::Heic => "heic",
+            Self::Heif => "heif",
+        }
+    }
+}
+
+#[tauri::command(async)]
+pub fn import_image(args: ImportImageArgs) -> Result<ImportImageResult, String> {
+    let bytes = match (&args.data_base64, &args.path) {
+        (Some(data), _) => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("Invalid base64: {e}"))?,
+        (None, Some(path)) => fs::read(path).map_err(|e| e.to_string())?,
+        (None, None) => return Err("missing_image: pass path or dataBase64".to_string()),
+    };
+    let format = export::ExportFormat::parse(args.format.as_deref().unwrap_or(export::FORMAT_PNG))?;
+    if !matches!(
+        format,
+        export::ExportFormat::Png | export::ExportFormat::WebP
+    ) {
+        return Err(format!(
+            "unsupported_format: imports normalize to png or webp, not {}",
+            format.extensions()[0]
+        ));
+    }
+
+    let (source, image) = decode(&bytes)?;
+    let (width, height) = image.dimensions();
+    let options = EncodeOptions {
+        width,
+        height,
+        settings: EncodeSettings {
+            format: format.extensions()[0].to_string(),
+            ..EncodeSettings::default()
+        },
+    };
+    let encoded = export::encode_rgba(image.as_raw(), &options)?;
+    Ok(ImportImageResult {
+        data_base64: general_purpose::STANDARD.encode(encoded),
+        mime: format.mime().to_string(),
+        width,
+        height,
+        source_format: source.name().to_string(),
+    })
+}
+
+/// Decodes any supported container to straight-alpha, upright sRGB RGBA.
+pub fn decode(bytes: &[u8]) -> Result<(SourceFormat, RgbaImage), String> {
+    let source = SourceFormat::sniff(bytes)?;
+    let image = match source {
+        SourceFormat::Png | SourceFormat::Jpeg | SourceFormat::WebP => {
+            let format = source.photo_format().expect("photo container");
+            correct(decode_with(bytes, format)?, &read_metadata(bytes, format))
+        }
+        SourceFormat::Bmp => decode_with(bytes, ImageFormat::Bmp)?,
+        SourceFormat::Gif => decode_gif(bytes)?,
+        SourceFormat::Ico => decode_ico(bytes)?,
+        SourceFormat::Icns => decode_icns(bytes)?,
+        SourceFormat::Heic | SourceFormat::Heif => {
+            #[cfg(target_os = "macos")]
+            {
+                macos::decode(bytes)?
+            }
+            #[cfg(not(target_os = "macos"))]
+            {
+                return Err(format!(
+                    "unsupported_platform: {} decoding needs macOS",
+                    source.name()
+                ));
+            }
+        }
+    };
+    if image.width() == 0 || image.height() == 0 {
+        return Err(format!("invalid_image: {} has no pixels", source.name()));
+    }
+    Ok((source, image))
+}
+
+fn decode_with(bytes: &[u8], format: ImageFormat) -> Result<RgbaImage, String> {
+    image::load_from_memory_with_format(bytes, format)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("invalid_image: {e}"))
+}
+
+/// The first frame composited onto the logical screen, as a browser shows
+/// it before animating.
+fn decode_gif(bytes: &[u8]) -> Result<RgbaImage, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options
+        .read_info(bytes)
+        .map_err(|e| format!("invalid_image: {e}"))?;
+    let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+    // The logical screen is declared up front; hold it to the same allocation
+    // limit `decode_with` gets from `image` before trusting it.
+    image::Limits::default()
+        .reserve_buffer(width, height, image::ColorType::Rgba8)
+        .map_err(|e| format!("invalid_image: {e}"))?;
+    let mut canvas = RgbaImage::new(width, height);
+    let frame = decoder
+        .read_next_frame()
+        .map_err(|e| format!("invalid_image: {e}"))?
+        .ok_or("invalid_image: gif has no frames")?;
+    let (left, top) = (frame.left as u32, frame.top as u32);
+    for (index, pixel) in frame.buffer.chunks_exact(4).enumerate() {
+        let x = left + index as u32 % frame.width as u32;
+        let y = top + index as u32 / frame.width as u32;
+        if x < canvas.width() && y < canvas.height() {
+            canvas.put_pixel(x, y, image::Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]));
+        }
+    }
+    Ok(canvas)
+}
+
+/// Picks the largest directory entry (then the deepest) and decodes it
+/// alone; the `image` crate would otherwise prefer depth over size.
+fn decode_ico(bytes: &[u8]) -> Result<RgbaImage, String> {
+    let count = bytes
+        .get(4..6)
+        .map_or(0, |count| u16::from_le_bytes([count[0], count[1]]) as usize);
+    let best = (0..count)
+        .filter_map(|index| bytes.get(6 + index * 16..6 + (index + 1) * 16))
+        .max_by_key(|entry| {
+            let side = |value: u8| if value == 0 { 256 } else { value as u32 };
+            let depth = u16::from_le_bytes([entry[6], entry[7]]);
+            (side(entry[0]) * side(entry[1]), depth)
+        })
+        .ok_or("invalid_image: ico has no images")?;
+    let size = u32::from_le_bytes([best[8], best[9], best[10], best[11]]) as usize;
+    let offset = u32::from_le_bytes([best[12], best[13], best[14], best[15]]) as usize;
+    let data = offset
+        .checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or("invalid_image: ico entry is out of bounds")?;
+
+    let mut single = Vec::with_capacity(22 + data.len());
+    single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    single.extend_from_slice(&best[..12]);
+    single.extend_from_slice(&22u32.to_le_bytes());
+    single.extend_from_slice(data);
+    decode_with(&single, ImageFormat::Ico)
+}
+
+/// Legacy 24-bit ICNS icons, their 8-bit masks, and edge length.
+const ICNS_LEGACY: &[(&[u8; 4], &[u8; 4], u32)] = &[
+    (b"is32", b"s8mk", 16),
+    (b"il32", b"l8mk", 32),
+    (b"ih32", b"h8mk", 48),
+    (b"it32", b"t8mk", 128),
+];
+/// RLE-packed ARGB icons and their edge length.
+const ICNS_ARGB: &[(&[u8; 4], u32)] = &[(b"ic04", 16), (b"ic05", 32)];
+const JPEG_2000_SIGNATURE: &[u8] = &[0, 0, 0, 0x0c, b'j', b'P', b' ', b' '];
+
+/// The largest icon in an `.icns`: PNG representations (most modern
+/// types), RLE ARGB, or legacy RGB plus mask. JPEG 2000 icons are skipped.
+fn decode_icns(bytes: &[u8]) -> Result<RgbaImage, String> {
+    let total = bytes
+        .get(4..8)
+        .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+        .unwrap_or(0)
+        .min(bytes.len());
+    let mut entries: Vec<(&[u8], &[u8])> = Vec::new();
+    let mut offset = 8;
+    while offset + 8 <= total {
+        let kind = &bytes[offset..offset + 4];
+        let len = u32::from_be_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+        if len < 8 || offset + len > total {
+            break;
+        }
+        entries.push((kind, &bytes[offset + 8..offset + len]));
+        offset += len;
+    }
+    let entry = |kind: &[u8]| {
+        entries
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, data)| *data)
+    };
+
+    let mut candidates: Vec<RgbaImage> = Vec::new();
+    let mut saw_jpeg_2000 = false;
+    for (kind, data) in &entries {
+        if data.starts_with(b"\x89PNG") {
+            candidates.extend(decode_with(data, ImageFormat::Png).ok());
+        } else if data.starts_with(JPEG_2000_SIGNATURE) {
+            saw_jpeg_2000 = true;
+        } else if let Some((_, side)) = ICNS_ARGB.iter().find(|(k, _)| k == kind) {
+            let Some(packed) = data.strip_prefix(b"ARGB") else {
+                continue;
+            };
+            let pixels = (side * side) as usize;
+            if let Some(planes) = unpack_icns_rle(packed, 4, pixels) {
+                let rgba = (0..pixels)
+                    .flat_map(|i| [planes[1][i], planes[2][i], planes[3][i], planes[0][i]])
+                    .collect();
+                candidates.extend(RgbaImage::from_raw(*side, *side, rgba));
+            }
+        }
+    }
+    for (kind, mask_kind, side) in ICNS_LEGACY {
+        let Some(data) = entry(&kind[..]) else {
+            continue;
+        };
+        let pixels = (side * side) as usize;
+        let packed = if **kind == *b"it32" {
+            data.get(4..).unwrap_or_default()
+        } else {
+            data
+        };
+        let Some(planes) = unpack_icns_rle(packed, 3, pixels) else {
+            continue;
+        };
+        let mask = entry(&mask_kind[..]).filter(|mask| mask.len() >= pixels);
+        let rgba = (0..pixels)
+            .flat_map(|i| {
+                let alpha = mask.map_or(255, |mask| mask[i]);
+                [planes[0][i], planes[1][i], planes[2][i], alpha]
+            })
+            .collect();
+        candidates.extend(RgbaImage::from_raw(*side, *side, rgba));
+    }
+
+    candidates
+        .into_iter()
+        .max_by_key(|image| image.width() * image.height())
+        .ok_or_else(|| {
+            if saw_jpeg_2000 {
+                "unsupported_format: icns holds only JPEG 2000 icons".to_string()
+            } else {
+                "invalid_image: icns has no readable icons".to_string()
+            }
+        })
+}
+
+/// Apple's icon RLE: per channel, a control byte below 0x80 copies the
+/// next `n + 1` bytes, otherwise repeats the next byte `n - 125` times.
+fn unpack_icns_rle(data: &[u8], channels: usize, pixels: usize) -> Option<Vec<Vec<u8>>> {
+    let mut planes = Vec::with_capacity(channels);
+    let mut pos = 0;
+    for _ in 0..channels {
+        let mut plane = Vec::with_capacity(pixels);
+        while plane.len() < pixels {
+            let control = *data.get(pos)? as usize;
+            if control & 0x80 != 0 {
+                let value = *data.get(pos + 1)?;
+                plane.extend(std::iter::repeat_n(value, control - 125));
+                pos += 2;
+            } else {
+                plane.extend_from_slice(data.get(pos + 1..pos + 2 + control)?);
+                pos += 2 + control;
+            }
+        }
+        plane.truncate(pixels);
+        planes.push(plane);
+    }
+    Some(planes)
+}
+
+#[tauri::command(async)]
+pub fn prepare_image(args: PrepareImageArgs) -> Result<PrepareImageResult, String> {
+    let bytes = match (args.data_base64, &args.path) {
+        (Some(data), _) => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("Invalid base64: {e}"))?,
+        (None, Some(path)) => fs::read(path).map_err(|e| e.to_string())?,
+        (None, None) => return Err("missing_image: pass path or dataBase64".to_string()),
+    };
+    let prepared = prepare(bytes)?;
+    Ok(PrepareImageResult {
+        data_base64: general_purpose::STANDARD.encode(&prepared.bytes),
+        mime: prepared.mime,
+        width: prepared.width,
+        height: prepared.height,
+        capture: prepared.capture,
+    })
+}
+
+/// The import stage for photos. JPEG, PNG and WebP keep their container
+/// and are only re-encoded when orientation or color conversion changes
+/// the pixels; otherwise their metadata blocks are cut out losslessly.
+/// Other formats are decoded and normalized to PNG.
+pub fn prepare(bytes: Vec<u8>) -> Result<PreparedImage, String> {
+    let source = SourceFormat::sniff(&bytes)?;
+    let Some(format) = source.photo_format() else {
+        let (_, image) = decode(&bytes)?;
+        return encode_prepared(image, ImageFormat::Png, CaptureMetadata::default());
+    };
+
+    let metadata = read_metadata(&bytes, format);
+    let mut capture = metadata
+        .exif
+        .as_deref()
+        .map(capture_metadata)
+        .unwrap_or_default();
+    if metadata.orientation != Orientation::NoTransforms {
+        capture.orientation = Some(metadata.orientation.to_exif());
+    }
+    if metadata.conversion.is_some() {
+        capture.color_profile = metadata.profile_name.clone();
+    }
+
+    if metadata.orientation == Orientation::NoTransforms && metadata.conversion.is_none() {
+        if let Some(stripped) = strip_metadata(source, &bytes) {
+            let (width, height) = metadata.dimensions;
+            return Ok(PreparedImage {
+                bytes: stripped,
+                mime: format.to_mime_type().to_string(),
+                width,
+                height,
+                capture,
+            });
+        }
+    }
+    let image = correct(decode_with(&bytes, format)?, &metadata);
+    encode_prepared(image, format, capture)
+}
+
+fn encode_prepared(
+    image: RgbaImage,
+    format: ImageFormat,
+    capture: CaptureMetadata,
+) -> Result<PreparedImage, String> {
+    let (width, height) = image.dimensions();
+    let format = match format {
+        ImageFormat::Jpeg => export::ExportFormat::Jpeg,
+        ImageFormat::WebP => export::ExportFormat::WebP,
+        _ => export::ExportFormat::Png,
+    };
+    let options = EncodeOptions {
+        width,
+        height,
+        settings: EncodeSettings {
+            format: format.extensions()[0].to_string(),
+            quality: (format == export::ExportFormat::Jpeg).then_some(PREPARED_JPEG_QUALITY),
+            ..EncodeSettings::default()
+        },
+    };
+    Ok(PreparedImage {
+        bytes: export::encode_rgba(image.as_raw(), &options)?,
+        mime: format.mime().to_string(),
+        width,
+        height,
+        capture,
+    })
+}
+
+/// What a photo container says about its pixels.
+struct SourceMetadata {
+    dimensions: (u32, u32),
+    orientation: Orientation,
+    exif: Option<Vec<u8>>,
+    profile_name: Option<String>,
+    /// The embedded profile, when it isn't already sRGB
+    conversion: Option<ColorProfile>,
+}
+
+fn read_metadata(bytes: &[u8], format: ImageFormat) -> SourceMetadata {
+    let mut metadata = SourceMetadata {
+        dimensions: (0, 0),
+        orientation: Orientation::NoTransforms,
+        exif: None,
+        profile_name: None,
+        conversion: None,
+    };
+    let Ok(mut decoder) = ImageReader::with_format(Cursor::new(bytes), format).into_decoder()
+    else {
+        return metadata;
+    };
+    metadata.dimensions = decoder.dimensions();
+    metadata.exif = decoder.exif_metadata().ok().flatten();
+    metadata.orientation = metadata
+        .exif
+        .as_deref()
+        .and_then(Orientation::from_exif_chunk)
+        .unwrap_or(Orientation::NoTransforms);
+    let profile = decoder
+        .icc_profile()
+        .ok()
+        .flatten()
+        .and_then(|icc| ColorProfile::new_from_slice(&icc).ok());
+    if let Some(profile) = profile {
+        metadata.profile_name = profile.description.as_ref().and_then(profile_text);
+        let srgb = metadata
+            .profile_name
+            .as_deref()
+            .is_some_and(|name| name.to_ascii_lowercase().contains("srgb"));
+        if !srgb && profile.color_space == DataColorSpace::Rgb {
+            metadata.conversion = Some(profile);
+        }
+    }
+    metadata
+}
+
+/// Applies the EXIF orientation and converts to sRGB. A profile moxcms
+/// can't build a transform for leaves the pixels as decoded.
+fn correct(image: RgbaImage, metadata: &SourceMetadata) -> RgbaImage {
+    let mut image = DynamicImage::ImageRgba8(image);
+    image.apply_orientation(metadata.orientation);
+    let mut image = image.into_rgba8();
+    if let Some(profile) = &metadata.conversion {
+        let transform = profile.create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgba,
+            TransformOptions::default(),
+        );
+        if let Ok(transform) = transform {
+            let mut converted = vec![0u8; image.as_raw().len()];
+            if transform.transform(image.as_raw(), &mut converted).is_ok() {
+                image.copy_from_slice(&converted);
+            }
+        }
+    }
+    image
+}
+
+fn profile_text(text: &ProfileText) -> Option<String> {
+    let text = match text {
+        ProfileText::PlainString(text) => text.clone(),
+        ProfileText::Localizable(strings) => strings.first()?.value.clone(),
+        ProfileText::Description(description) => description.ascii_string.clone(),
+    };
+    let text = text.trim_matches(char::from(0)).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn capture_metadata(exif: &[u8]) -> CaptureMetadata {
+    use exif::{In, Tag, Value};
+
+    let Ok(exif) = exif::Reader::new().read_raw(exif.to_vec()) else {
+        return CaptureMetadata::default();
+    };
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let text = |tag: Tag| match field(tag)? {
+        Value::Ascii(parts) => {
+            let text = String::from_utf8_lossy(parts.first()?);
+            let text = text.trim_matches(char::from(0)).trim();
+            (!text.is_empty()).then(|| text.to_string())
+        }
+        _ => None,
+    };
+    let rational = |tag: Tag| match field(tag)? {
+        Value::Rational(values) => values
+            .first()
+            .filter(|value| value.denom != 0)
+            .map(|value| (value.num, value.denom)),
+        _ => None,
+    };
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    CaptureMetadata {
+        camera_make: text(Tag::Make),
+        camera_model: text(Tag::Model),
+        lens_model: text(Tag::LensModel),
+        taken_at: text(Tag::DateTimeOriginal)
+            .or_else(|| text(Tag::DateTime))
+            .and_then(|value| {
+                let (date, time) = value.split_once(' ')?;
+                Some(format!("{}T{time}", date.replace(':', "-")))
+            }),
+        exposure_time: rational(Tag::ExposureTime).map(|(num, denom)| {
+            if num > 0 && num < denom {
+                format!("1/{}", (denom as f64 / num as f64).round())
+            } else {
+                crate::scene::num(num as f64 / denom as f64)
+            }
+        }),
+        f_number: rational(Tag::FNumber).map(|(num, denom)| round(num as f64 / denom as f64)),
+        iso: field(Tag::PhotographicSensitivity).and_then(|value| value.get_uint(0)),
+        focal_length_mm: rational(Tag::FocalLength)
+            .map(|(num, denom)| round(num as f64 / denom as f64)),
+        orientation: None,
+        color_profile: None,
+    }
+}
+
+/// Drops EXIF, XMP, IPTC and comment blocks without touching the image
+/// data. `None` when the container doesn't parse cleanly.
+fn strip_metadata(source: SourceFormat, bytes: &[u8]) -> Option<Vec<u8>> {
+    match source {
+        SourceFormat::Jpeg => strip_jpeg(bytes),
+        SourceFormat::Png => strip_png(bytes),
+        SourceFormat::WebP => strip_webp(bytes),
+        _ => None,
+    }
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    const APP1: u8 = 0xe1;
+    const APP13: u8 = 0xed;
+    const COMMENT: u8 = 0xfe;
+    const START_OF_SCAN: u8 = 0xda;
+
+    let mut out = bytes.get(..2)?.to_vec();
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        if marker == 0xff {
+            pos += 1;
+            continue;
+        }
+        if marker == START_OF_SCAN {
+            out.extend_from_slice(&bytes[pos..]);
+            return Some(out);
+        }
+        let len = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        let segment = bytes.get(pos..pos + 2 + len)?;
+        if !matches!(marker, APP1 | APP13 | COMMENT) {
+            out.extend_from_slice(segment);
+        }
+        pos += 2 + len;
+    }
+}
+
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    const DROPPED: &[&[u8; 4]] = &[b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+    let mut out = bytes.get(..8)?.to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let header = bytes.get(pos..pos + 8)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk = bytes.get(pos..pos + 12 + len)?;
+        if !DROPPED.iter().any(|kind| header[4..8] == kind[..]) {
+            out.extend_from_slice(chunk);
+        }
+        pos += 12 + len;
+    }
+    Some(out)
+}
+
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    const VP8X_EXIF: u8 = 0x08;
+    const VP8X_XMP: u8 = 0x04;
+
+    let mut out = bytes.get(..12)?.to_vec();
+    let mut pos = 12;
+    while pos < bytes.len() {
+        let header = bytes.get(pos..pos + 8)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let padded = len + len % 2;
+        let chunk = bytes.get(pos..(pos + 8 + padded).min(bytes.len()))?;
+        match &header[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = out.len();
+                out.extend_from_slice(chunk);
+                *out.get_mut(start + 8)? &= !(VP8X_EXIF | VP8X_XMP);
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        pos += 8 + padded;
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use image::RgbaImage;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFDataCreate(allocator: *const c_void, bytes: *const u8, length: isize)
+            -> *const c_void;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    #[link(name = "ImageIO", kind = "framework")]
+    extern "C" {
+        fn CGImageSourceCreateWithData(
+            data: *const c_void,
+            options: *const c_void,
+        ) -> *const c_void;
+        fn CGImageSourceGetPrimaryImageIndex(source: *const c_void) -> usize;
+        fn CGImageSourceCreateImageAtIndex(
+            source: *const c_void,
+            index: usize,
+            options: *const c_void,
+        ) -> *const c_void;
+    }
+
+    /// `CGRect` is a `CGPoint` then a `CGSize`, all `CGFloat` (f64).
+    #[repr(C)]
+    struct CGRect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    }
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        static kCGColorSpaceSRGB: *const c_void;
+        fn CGColorSpaceCreateWithName(name: *const c_void) -> *const c_void;
+        fn CGColorSpaceRelease(space: *const c_void);
+        fn CGImageGetWidth(image: *const c_void) -> usize;
+        fn CGImageGetHeight(image: *const c_void) -> usize;
+        fn CGImageRelease(image: *const c_void);
+        fn CGBitmapContextCreate(
+            data: *mut c_void,
+            width: usize,
+            height: usize,
+            bits_per_component: usize,
+            bytes_per_row: usize,
+            space: *const c_void,
+            bitmap_info: u32,
+        ) -> *const c_void;
+        fn CGContextDrawImage(context: *const c_void, rect: CGRect, image: *const c_void);
+        fn CGContextRelease(context: *const c_void);
+    }
+
+    /// kCGImageAlphaPremultipliedLast in the default byte order: RGBA.
+    const BITMAP_RGBA_PREMULTIPLIED: u32 = 1;
+
+    /// The primary image of anything ImageIO reads, drawn into sRGB.
+    pub fn decode(bytes: &[u8]) -> Result<RgbaImage, String> {
+        unsafe {
+            let data = CFDataCreate(ptr::null(), bytes.as_ptr(), bytes.len() as isize);
+            if data.is_null() {
+                return Err("Failed to create CFData".to_string());
+            }
+            let source = CGImageSourceCreateWithData(data, ptr::null());
+            CFRelease(data);
+            if source.is_null() {
+                return Err("invalid_image: ImageIO can't read this file".to_string());
+            }
+            let index = CGImageSourceGetPrimaryImageIndex(source);
+            let image = CGImageSourceCreateImageAtIndex(source, index, ptr::null());
+            CFRelease(source);
+            if image.is_null() {
+                return Err("invalid_image: ImageIO failed to decode the image".to_string());
+            }
+
+            let (width, height) = (CGImageGetWidth(image), CGImageGetHeight(image));
+            let mut pixels = vec![0u8; width * height * 4];
+            let space = CGColorSpaceCreateWithName(kCGColorSpaceSRGB);
+            let context = CGBitmapContextCreate(
+                pixels.as_mut_ptr().cast(),
+                width,
+                height,
+                8,
+                width * 4,
+                space,
+                BITMAP_RGBA_PREMULTIPLIED,
+            );
+            CGColorSpaceRelease(space);
+            if context.is_null() {
+                CGImageRelease(image);
+                return Err("Failed to create bitmap context".to_string());
+            }
+            let rect = CGRect {
+                x: 0.0,
+                y: 0.0,
+                width: width as f64,
+                height: height as f64,
+            };
+            CGContextDrawImage(context, rect, image);
+            CGContextRelease(context);
+            CGImageRelease(image);
+
+            for pixel in pixels.chunks_exact_mut(4) {
+                let alpha = pixel[3] as u32;
+                if alpha > 0 && alpha < 255 {
+                    for channel in &mut pixel[..3] {
+                        *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                    }
+                }
+            }
+            RgbaImage::from_raw(width as u32, height as u32, pixels)
+                .ok_or_else(|| "Failed to decode image".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbaImage::from_pixel(width, height, image::Rgba(color))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    /// Inserts a chunk right after IHDR.
+    fn with_chunk(png: &[u8], kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut crc = flate2::Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        let mut out = png[..33].to_vec();
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        out.extend_from_slice(&crc.sum().to_be_bytes());
+        out.extend_from_slice(&png[33..]);
+        out
+    }
+
+    /// Big-endian TIFF block holding Model "Cam" and the given orientation.
+    fn exif(orientation: u8) -> Vec<u8> {
+        let mut tiff = b"MM\0*\0\0\0\x08\0\x02".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x10, 0, 2, 0, 0, 0, 4]);
+        tiff.extend_from_slice(b"Cam\0");
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        tiff.extend_from_slice(&[0; 4]);
+        tiff
+    }
+
+    fn has_chunk(png: &[u8], kind: &[u8; 4]) -> bool {
+        png.windows(4).any(|window| window == kind)
+    }
+
+    #[test]
+    fn gif_imports_its_first_frame_on_the_screen() {
+        let mut bytes = Vec::new();
+        {
+            let palette = [255, 0, 0, 0, 0, 255];
+            let mut encoder = gif::Encoder::new(&mut bytes, 4, 3, &palette).unwrap();
+            let mut first = gif::Frame::from_indexed_pixels(2, 1, vec![0, 0], None);
+            first.left = 1;
+            first.top = 1;
+            encoder.write_frame(&first).unwrap();
+            let second = gif::Frame::from_indexed_pixels(4, 3, vec![1; 12], None);
+            encoder.write_frame(&second).unwrap();
+        }
+        let (source, image) = decode(&bytes).unwrap();
+        assert_eq!(source, SourceFormat::Gif);
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+
+        // A one-pixel frame on a 65535² screen must not allocate the screen.
+        let mut huge = Vec::new();
+        {
+            let palette = [255, 0, 0];
+            let mut encoder = gif::Encoder::new(&mut huge, u16::MAX, u16::MAX, &palette).unwrap();
+            let frame = gif::Frame::from_indexed_pixels(1, 1, vec![0], None);
+            encoder.write_frame(&frame).unwrap();
+        }
+        assert!(decode(&huge).unwrap_err().starts_with("invalid_image:"));
+    }
+
+    #[test]
+    fn icons_import_their_largest_representation() {
+        let (small, large) = (png(16, 16, [255, 0, 0, 255]), png(32, 32, [0, 0, 255, 255]));
+        let mut ico = vec![0, 0, 1, 0, 2, 0];
+        let mut offset = 6 + 32;
+        for (side, data) in [(32u8, &large), (16, &small)] {
+            ico.extend_from_slice(&[side, side, 0, 0, 1, 0, 32, 0]);
+            ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += data.len();
+        }
+        ico.extend_from_slice(&large);
+        ico.extend_from_slice(&small);
+        let (source, image) = decode(&ico).unwrap();
+        assert_eq!(source, SourceFormat::Ico);
+        assert_eq!(image.dimensions(), (32, 32));
+
+        // A 16px legacy icon (solid green, half-transparent mask) beside a
+        // 32px PNG icon.
+        let rle: Vec<u8> = [0u8, 255, 0]
+            .iter()
+            .flat_map(|value| [0x80 + 125, *value, 0x80 + 125, *value])
+            .collect();
+        let mut icns = b"icns\0\0\0\0".to_vec();
+        for (kind, data) in [
+            (b"is32", rle),
+            (b"s8mk", vec![128; 256]),
+            (b"ic11", large.clone()),
+        ] {
+            icns.extend_from_slice(kind);
+            icns.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+            icns.extend_from_slice(&data);
+        }
+        let total = icns.len() as u32;
+        icns[4..8].copy_from_slice(&total.to_be_bytes());
+        let (source, image) = decode(&icns).unwrap();
+        assert_eq!(source, SourceFormat::Icns);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+
+        let legacy = unpack_icns_rle(&icns[16..], 3, 256).unwrap();
+        assert_eq!(legacy[1], vec![255; 256]);
+    }
+
+    #[test]
+    fn unsupported_inputs_have_clear_errors() {
+        assert_eq!(
+            SourceFormat::sniff(b"%PDF-1.7").unwrap_err(),
+            "unsupported_format: unrecognized image data"
+        );
+        assert_eq!(
+            SourceFormat::sniff(b"\0\0\0\x1cftypavif\0\0\0\0").unwrap_err(),
+            "unsupported_format: avif"
+        );
+        assert_eq!(
+            SourceFormat::sniff(b"\0\0\0\x18ftypheic\0\0\0\0"),
+            Ok(SourceFormat::Heic)
+        );
+        let error = import_image(ImportImageArgs {
+            path: None,
+            data_base64: Some(general_purpose::STANDARD.encode(png(1, 1, [0; 4]))),
+            format: Some("jpeg".to_string()),
+        })
+        .unwrap_err();
+        assert!(error.starts_with("unsupported_format:"), "{error}");
+    }
+
+    #[test]
+    fn prepare_applies_orientation_and_drops_exif() {
+        let mut source = RgbaImage::from_pixel(2, 1, image::Rgba([0, 0, 255, 255]));
+        source.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        let mut bytes = Vec::new();
+        source
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+
+        let prepared = prepare(with_chunk(&bytes, b"eXIf", &exif(6))).unwrap();
+        assert_eq!((prepared.width, prepared.height), (1, 2));
+        assert_eq!(prepared.capture.orientation, Some(6));
+        assert_eq!(prepared.capture.camera_model.as_deref(), Some("Cam"));
+        assert!(!has_chunk(&prepared.bytes, b"eXIf"));
+        let image = image::load_from_memory(&prepared.bytes)
+            .unwrap()
+            .into_rgba8();
+        // Rotating 90° clockwise puts the left pixel on top.
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn prepare_strips_upright_images_without_reencoding() {
+        let bytes = png(3, 2, [10, 20, 30, 255]);
+        let tagged = with_chunk(&bytes, b"tEXt", b"Author\0Someone");
+        let tagged = with_chunk(&tagged, b"eXIf", &exif(1));
+
+        let prepared = prepare(tagged).unwrap();
+        assert_eq!(prepared.bytes, bytes);
+        assert_eq!(prepared.mime, "image/png");
+        assert_eq!(prepared.capture.orientation, None);
+        assert_eq!(prepared.capture.camera_model.as_deref(), Some("Cam"));
+    }
+
+    #[test]
+    fn prepare_converts_wide_gamut_profiles_to_srgb() {
+        use std::io::Write;
+
+        let mut icc = b"P3\0\0".to_vec();
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(&ColorProfile::new_display_p3().encode().unwrap())
+            .unwrap();
+        icc.extend(encoder.finish().unwrap());
+        let bytes = with_chunk(&png(1, 1, [200, 100, 50, 255]), b"iCCP", &icc);
+
+        let prepared = prepare(bytes).unwrap();
+        assert_eq!(
+            prepared.capture.color_profile.as_deref(),
+            Some("Display P3")
+        );
+        let image = image::load_from_memory(&prepared.bytes)
+            .unwrap()
+            .into_rgba8();
+        let [r, g, b, a] = image.get_pixel(0, 0).0;
+        // P3 orange sits outside sRGB: red saturates further, blue drops.
+        assert!(r > 200 && b < 50 && a == 255, "{r} {g} {b}");
+    }
+}
@@ -0,0 +1,413 @@
+//! Resized copies of imported images: an edit proxy the canvas draws, a
+//! thumbnail for asset lists, and the untouched original, which stays in
+//! the document for export. Images are rendered on worker threads and the
+//! results cached by the SHA-256 of the source bytes, so importing the same
+//! photo twice only resamples it once.
+
+use crate::batch_export::{downsample, premultiply, unpremultiply};
+use crate::binary_ipc::{self, BlobRef, FramedResponse};
+use crate::export::{self, EncodeOptions, EncodeSettings, ExportFormat};
+use crate::image_import;
+use base64::{engine::general_purpose, Engine as _};
+use image::{imageops::FilterType, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const DEFAULT_PROXY_MAX_DIMENSION: u32 = 2048;
+const DEFAULT_THUMBNAIL_MAX_DIMENSION: u32 = 256;
+const VARIANT_JPEG_QUALITY: u8 = 85;
+/// Encoded bytes kept across calls before the oldest entries are dropped
+const CACHE_BUDGET_BYTES: usize = 96 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVariantsArgs {
+    pub images: Vec<VariantSource>,
+    #[serde(flatten)]
+    pub options: VariantOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantSource {
+    pub path: Option<String>,
+    pub data_base64: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantOptions {
+    /// Longest edge of the edit proxy; defaults to 2048
+    pub proxy_max_dimension: Option<u32>,
+    /// Longest edge of the thumbnail; defaults to 256
+    pub thumbnail_max_dimension: Option<u32>,
+    /// "png", "jpeg" or "webp". By default opaque images become JPEG and
+    /// the rest PNG.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVariants {
+    /// Hex SHA-256 of the source bytes
+    pub hash: String,
+    pub width: u32,
+    pub height: u32,
+    /// `None` when the original already fits the proxy size
+    pub proxy: Option<EncodedVariant>,
+    pub thumbnail: EncodedVariant,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodedVariant {
+    pub data_base64: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVariantsRawResult {
+    pub hash: String,
+    pub width: u32,
+    pub height: u32,
+    pub proxy: Option<RawVariant>,
+    pub thumbnail: RawVariant,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawVariant {
+    pub data: BlobRef,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+struct Variant {
+    bytes: Vec<u8>,
+    mime: &'static str,
+    width: u32,
+    height: u32,
+}
+
+struct Rendered {
+    hash: String,
+    width: u32,
+    height: u32,
+    proxy: Option<Variant>,
+    thumbnail: Variant,
+}
+
+impl Rendered {
+    fn byte_size(&self) -> usize {
+        self.thumbnail.bytes.len() + self.proxy.as_ref().map_or(0, |proxy| proxy.bytes.len())
+    }
+}
+
+#[tauri::command(async)]
+pub fn image_variants(args: ImageVariantsArgs) -> Result<Vec<ImageVariants>, String> {
+    let rendered = render_all(&args.images, &args.options)?;
+    let encode = |variant: &Variant| EncodedVariant {
+        data_base64: general_purpose::STANDARD.encode(&variant.bytes),
+        mime: variant.mime.to_string(),
+        width: variant.width,
+        height: variant.height,
+    };
+    Ok(rendered
+        .iter()
+        .map(|rendered| ImageVariants {
+            hash: rendered.hash.clone(),
+            width: rendered.width,
+            height: rendered.height,
+            proxy: rendered.proxy.as_ref().map(encode),
+            thumbnail: encode(&rendered.thumbnail),
+        })
+        .collect())
+}
+
+/// `image_variants` for a single image sent as the raw invoke body, with
+/// [`VariantOptions`] in the args header. Returns a framed response whose
+/// metadata is [`ImageVariantsRawResult`].
+#[tauri::command(async)]
+pub fn image_variants_raw(
+    request: tauri::ipc::Request<'_>,
+) -> Result<tauri::ipc::Response, String> {
+    let options: VariantOptions = binary_ipc::header_args(&request)?;
+    let rendered = render(binary_ipc::raw_body(&request)?, &options)?;
+
+    let mut framed = FramedResponse::default();
+    let mut push = |variant: &Variant| RawVariant {
+        data: framed.push(&variant.bytes),
+        mime: variant.mime.to_string(),
+        width: variant.width,
+        height: variant.height,
+    };
+    let proxy = rendered.proxy.as_ref().map(&mut push);
+    let thumbnail = push(&rendered.thumbnail);
+    framed.finish(&ImageVariantsRawResult {
+        hash: rendered.hash.clone(),
+        width: rendered.width,
+        height: rendered.height,
+        proxy,
+        thumbnail,
+    })
+}
+
+/// Hex SHA-256, the key variants (and stored assets) are addressed by.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+type RenderResult = Result<Arc<Rendered>, String>;
+
+fn render_all(
+    sources: &[VariantSource],
+    options: &VariantOptions,
+) -> Result<Vec<Arc<Rendered>>, String> {
+    if sources.is_empty() {
+        return Ok(Vec::new());
+    }
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<RenderResult>>> =
+        Mutex::new((0..sources.len()).map(|_| None).collect());
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(sources.len());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(source) = sources.get(index) else {
+                    break;
+                };
+                let result = read_source(source).and_then(|bytes| render(&bytes, options));
+                results.lock().unwrap_or_else(|p| p.into_inner())[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|p| p.into_inner())
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("Variant worker failed".to_string())))
+        .collect()
+}
+
+fn read_source(source: &VariantSource) -> Result<Vec<u8>, String> {
+    match (&source.data_base64, &source.path) {
+        (Some(data), _) => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("Invalid base64: {e}")),
+        (None, Some(path)) => fs::read(path).map_err(|e| e.to_string()),
+        (None, None) => Err("missing_image: pass path or dataBase64".to_string()),
+    }
+}
+
+/// Cached variants for `bytes`, rendering them on a miss.
+fn render(bytes: &[u8], options: &VariantOptions) -> Result<Arc<Rendered>, String> {
+    let hash = content_hash(bytes);
+    let key = (hash.clone(), options.clone());
+    if let Some(rendered) = cache().lock().unwrap_or_else(|p| p.into_inner()).get(&key) {
+        return Ok(rendered);
+    }
+
+    let (_, image) = image_import::decode(bytes)?;
+    let rendered = Arc::new(render_image(hash, &image, options)?);
+    cache()
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .insert(key, rendered.clone());
+    Ok(rendered)
+}
+
+fn render_image(
+    hash: String,
+    image: &RgbaImage,
+    options: &VariantOptions,
+) -> Result<Rendered, String> {
+    let proxy_max = positive(options.proxy_max_dimension, DEFAULT_PROXY_MAX_DIMENSION)?;
+    let thumbnail_max = positive(
+        options.thumbnail_max_dimension,
+        DEFAULT_THUMBNAIL_MAX_DIMENSION,
+    )?;
+    let format = match options.format.as_deref() {
+        Some(format) => ExportFormat::parse(format)?,
+        None if image.pixels().all(|pixel| pixel[3] == u8::MAX) => ExportFormat::Jpeg,
+        None => ExportFormat::Png,
+    };
+    let encode = |pixels: &[u8], width: u32, height: u32| -> Result<Variant, String> {
+        let options = EncodeOptions {
+            width,
+            height,
+            settings: EncodeSettings {
+                format: format.extensions()[0].to_string(),
+                quality: (format == ExportFormat::Jpeg).then_some(VARIANT_JPEG_QUALITY),
+                ..EncodeSettings::default()
+            },
+        };
+        Ok(Variant {
+            bytes: export::encode_rgba(pixels, &options)?,
+            mime: format.mime(),
+            width,
+            height,
+        })
+    };
+
+    // The thumbnail is resampled from the proxy, which is much cheaper than
+    // from the original and loses nothing at thumbnail size.
+    let (width, height) = image.dimensions();
+    let mut source = premultiply(image);
+    let proxy = match fit(width, height, proxy_max) {
+        Some((proxy_width, proxy_height)) => {
+            source =
+                image::imageops::resize(&source, proxy_width, proxy_height, FilterType::Lanczos3);
+            Some(encode(&unpremultiply(&source), proxy_width, proxy_height)?)
+        }
+        None => None,
+    };
+    let thumbnail = match fit(source.width(), source.height(), thumbnail_max) {
+        Some((thumb_width, thumb_height)) => encode(
+            &downsample(&source, thumb_width, thumb_height),
+            thumb_width,
+            thumb_height,
+        )?,
+        None => encode(&unpremultiply(&source), source.width(), source.height())?,
+    };
+    Ok(Rendered {
+        hash,
+        width,
+        height,
+        proxy,
+        thumbnail,
+    })
+}
+
+fn positive(value: Option<u32>, default: u32) -> Result<u32, String> {
+    match value {
+        Some(0) => Err("invalid_dimension: 0".to_string()),
+        Some(value) => Ok(value),
+        None => Ok(default),
+    }
+}
+
+/// The size that fits `max` on the longest edge, or `None` if the image
+/// already does.
+fn fit(width: u32, height: u32, max: u32) -> Option<(u32, u32)> {
+    let longest = width.max(height);
+    if longest <= max {
+        return None;
+    }
+    let ratio = max as f64 / longest as f64;
+    let scale = |value: u32| ((value as f64 * ratio).round() as u32).clamp(1, max);
+    Some((scale(width), scale(height)))
+}
+
+type CacheKey = (String, VariantOptions);
+
+/// Drops the oldest entries once the encoded bytes exceed the budget.
+#[derive(Default)]
+struct VariantCache {
+    entries: HashMap<CacheKey, Arc<Rendered>>,
+    order: VecDeque<CacheKey>,
+    bytes: usize,
+}
+
+impl VariantCache {
+    fn get(&self, key: &CacheKey) -> Option<Arc<Rendered>> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: CacheKey, rendered: Arc<Rendered>) {
+        self.bytes += rendered.byte_size();
+        if let Some(previous) = self.entries.insert(key.clone(), rendered) {
+            self.bytes -= previous.byte_size();
+        } else {
+            self.order.push_back(key);
+        }
+        while self.bytes > CACHE_BUDGET_BYTES && self.order.len() > 1 {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.byte_size();
+            }
+        }
+    }
+}
+
+fn cache() -> &'static Mutex<VariantCache> {
+    static CACHE: OnceLock<Mutex<VariantCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbaImage::from_fn(width, height, |x, _| Rgba([(x % 256) as u8, 80, 160, 200]))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn options(proxy: u32, thumbnail: u32) -> VariantOptions {
+        VariantOptions {
+            proxy_max_dimension: Some(proxy),
+            thumbnail_max_dimension: Some(thumbnail),
+            format: Some("png".to_string()),
+        }
+    }
+
+    #[test]
+    fn variants_fit_their_bounds_and_keep_the_aspect_ratio() {
+        let rendered = render(&png(400, 100), &options(200, 40)).unwrap();
+        assert_eq!((rendered.width, rendered.height), (400, 100));
+        let proxy = rendered.proxy.as_ref().unwrap();
+        assert_eq!((proxy.width, proxy.height), (200, 50));
+        assert_eq!(proxy.mime, "image/png");
+        assert_eq!(
+            (rendered.thumbnail.width, rendered.thumbnail.height),
+            (40, 10)
+        );
+        let thumbnail = image::load_from_memory(&rendered.thumbnail.bytes).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (40, 10));
+    }
+
+    #[test]
+    fn small_images_skip_the_proxy() {
+        let rendered = render(&png(30, 60), &options(200, 40)).unwrap();
+        assert!(rendered.proxy.is_none());
+        assert_eq!(
+            (rendered.thumbnail.width, rendered.thumbnail.height),
+            (20, 40)
+        );
+        assert_eq!(fit(30, 60, 60), None);
+        assert!(render(&png(4, 4), &options(0, 40)).is_err());
+    }
+
+    #[test]
+    fn repeated_content_is_served_from_the_cache() {
+        let bytes = png(300, 300);
+        let first = render(&bytes, &options(100, 20)).unwrap();
+        let again = render(&bytes, &options(100, 20)).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(first.hash, content_hash(&bytes));
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
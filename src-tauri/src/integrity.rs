@@ -0,0 +1,810 @@
+//! Integrity checks for `.galileo` files, mirroring the frontend's
+//! `validateDocumentIntegrity` plus the duplicate ids it can't see (JSON
+//! objects silently keep the last of two identical keys), and a repair that
+//! rebuilds a broken document's node tree into a copy beside the original.
+
+use crate::bundle;
+use crate::document::{Document, DocumentSource, Node, NodeType, Page, Position, Size};
+use crate::draft_store::replace_atomic;
+use crate::migration;
+use serde::de::{self, Deserializer, MapAccess};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// Saved by a newer Galileo
+    UnsupportedVersion,
+    /// Not JSON, or not shaped like a document
+    InvalidDocument,
+    MissingRoot,
+    MissingPages,
+    DuplicatePageId,
+    DuplicatePageRoot,
+    DanglingPageRoot,
+    MissingActivePage,
+    DuplicateNodeId,
+    NodeIdMismatch,
+    MissingChild,
+    /// Listed as a child more than once, by one parent or several
+    SharedChild,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub valid: bool,
+    /// Version the document was saved with
+    pub version: Option<u64>,
+    /// What loading the document would migrate or normalize
+    pub migrations: Vec<String>,
+    pub issues: Vec<Issue>,
+}
+
+#[tauri::command]
+pub fn validate_document(args: DocumentSource) -> Result<ValidationReport, String> {
+    Ok(validate(&args.text()?))
+}
+
+pub fn validate(json: &str) -> ValidationReport {
+    let mut report = ValidationReport {
+        valid: false,
+        version: None,
+        migrations: Vec::new(),
+        issues: Vec::new(),
+    };
+    let raw: Value = match serde_json::from_str(json) {
+        Ok(raw) => raw,
+        Err(error) => {
+            report.issues.push(issue(
+                IssueKind::InvalidDocument,
+                format!("invalid JSON: {error}"),
+            ));
+            return report;
+        }
+    };
+    report.version = raw.get("version").and_then(Value::as_u64);
+    for id in duplicate_keys(&node_entries(json)) {
+        report.issues.push(issue(
+            IssueKind::DuplicateNodeId,
+            format!("node id appears more than once in nodes: {id}"),
+        ));
+    }
+
+    let mut migrated = match migration::migrate(raw) {
+        Ok(migrated) => migrated,
+        Err(error) => {
+            let kind = if error.starts_with("unsupported_document_version") {
+                IssueKind::UnsupportedVersion
+            } else {
+                IssueKind::InvalidDocument
+            };
+            report.issues.push(issue(kind, error));
+            return report;
+        }
+    };
+    // Integrity is checked before page normalization, which would quietly
+    // drop the broken pages this is meant to report.
+    let mut normalized = migrated.document.clone();
+    migration::normalize_pages(&mut normalized, &mut migrated.warnings);
+    report.migrations = migrated.warnings;
+    match Document::from_migrated(migrated.document) {
+        Ok(doc) => report.issues.extend(integrity_issues(&doc)),
+        Err(error) => report.issues.push(issue(IssueKind::InvalidDocument, error)),
+    }
+    report.valid = report.issues.is_empty();
+    report
+}
+
+pub fn integrity_issues(doc: &Document) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if !doc.nodes.contains_key(&doc.root_id) {
+        issues.push(issue(
+            IssueKind::MissingRoot,
+            format!("rootId does not exist in nodes: {}", doc.root_id),
+        ));
+    }
+    if doc.pages.is_empty() {
+        issues.push(issue(
+            IssueKind::MissingPages,
+            "document must contain at least one page".to_string(),
+        ));
+        return issues;
+    }
+
+    let mut page_ids = HashSet::new();
+    let mut page_roots = HashSet::new();
+    for page in &doc.pages {
+        if !page_ids.insert(&page.id) {
+            issues.push(issue(
+                IssueKind::DuplicatePageId,
+                format!("duplicate page id: {}", page.id),
+            ));
+        }
+        if !page_roots.insert(&page.root_id) {
+            issues.push(issue(
+                IssueKind::DuplicatePageRoot,
+                format!("duplicate page rootId: {}", page.root_id),
+            ));
+        }
+        if !doc.nodes.contains_key(&page.root_id) {
+            issues.push(issue(
+                IssueKind::DanglingPageRoot,
+                format!(
+                    "page {} rootId does not exist in nodes: {}",
+                    page.id, page.root_id
+                ),
+            ));
+        }
+    }
+    if !page_ids.contains(&doc.active_page_id) {
+        issues.push(issue(
+            IssueKind::MissingActivePage,
+            format!(
+                "activePageId does not exist in pages: {}",
+                doc.active_page_id
+            ),
+        ));
+    }
+
+    let mut parents: HashMap<&str, &str> = HashMap::new();
+    for (key, node) in &doc.nodes {
+        if node.id != *key {
+            let (kind, message) = if doc.nodes.contains_key(&node.id) {
+                (
+                    IssueKind::DuplicateNodeId,
+                    format!("node {key} claims id {}, which another node has", node.id),
+                )
+            } else {
+                (
+                    IssueKind::NodeIdMismatch,
+                    format!("node id mismatch: key={key} node.id={}", node.id),
+                )
+            };
+            issues.push(issue(kind, message));
+        }
+        for child_id in node.children.iter().flatten() {
+            if !doc.nodes.contains_key(child_id) {
+                issues.push(issue(
+                    IssueKind::MissingChild,
+                    format!("missing child node: {child_id} referenced by {key}"),
+                ));
+            } else if let Some(parent) = parents.insert(child_id, key) {
+                issues.push(issue(
+                    IssueKind::SharedChild,
+                    format!("node {child_id} is a child of both {parent} and {key}"),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairDocumentArgs {
+    pub path: String,
+    /// Defaults to `<name>.repaired.galileo` beside the original
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FixKind {
+    RegeneratedId,
+    DroppedMissingChild,
+    /// A second reference to a node that already has a parent, or a cycle
+    DroppedSharedChild,
+    ReattachedOrphan,
+    DroppedPage,
+    CreatedPage,
+    RecoveredActivePage,
+    RecoveredRootId,
+    PrunedAsset,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fix {
+    pub kind: FixKind,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub original_path: String,
+    pub repaired_path: String,
+    pub migrations: Vec<String>,
+    pub fixes: Vec<Fix>,
+    /// Problems the repair couldn't fix; empty when the copy is sound
+    pub remaining_issues: Vec<Issue>,
+}
+
+pub struct Repaired {
+    pub document: Document,
+    pub migrations: Vec<String>,
+    pub fixes: Vec<Fix>,
+}
+
+/// Writes a repaired copy of the document; the original is only read.
+#[tauri::command]
+pub fn repair_document(args: RepairDocumentArgs) -> Result<RepairReport, String> {
+    let original = Path::new(&args.path);
+    let output = args
+        .output_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| repaired_path(original));
+    if output == original {
+        return Err("invalid_output: the repaired copy must not replace the original".to_string());
+    }
+    let Repaired {
+        document,
+        migrations,
+        fixes,
+    } = repair(&bundle::read_text(original)?)?;
+    let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    if bundle::is_bundle(original)? {
+        replace_atomic(&output, |file| bundle::write_to(file, &output, &json, None))?;
+    } else {
+        replace_atomic(&output, |file| {
+            file.write_all(json.as_bytes()).map_err(|e| e.to_string())
+        })?;
+    }
+    Ok(RepairReport {
+        original_path: args.path,
+        repaired_path: output.to_string_lossy().to_string(),
+        migrations,
+        fixes,
+        remaining_issues: integrity_issues(&document),
+    })
+}
+
+fn repaired_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}.repaired.{}", extension.to_string_lossy()),
+        None => format!("{stem}.repaired"),
+    };
+    path.with_file_name(name)
+}
+
+/// Migrates the document and rebuilds its node tree: every node ends up
+/// with its own id, at most one parent and a path to a page root.
+pub fn repair(json: &str) -> Result<Repaired, String> {
+    let mut raw: Value =
+        serde_json::from_str(json).map_err(|e| format!("invalid_document: {e}"))?;
+    let mut fixes = Vec::new();
+    separate_duplicate_keys(&mut raw, node_entries(json), &mut fixes);
+    let mut migrated = migration::migrate(raw)?;
+    for (key, default) in [
+        ("rootId", json!("")),
+        ("pages", json!([])),
+        ("activePageId", json!("")),
+    ] {
+        migrated.document.entry(key).or_insert(default);
+    }
+    let mut doc = Document::from_migrated(migrated.document)?;
+
+    fix_node_ids(&mut doc, &mut fixes);
+    drop_missing_children(&mut doc, &mut fixes);
+    fix_pages(&mut doc, &mut fixes);
+    rebuild_tree(&mut doc, &mut fixes);
+    prune_assets(&mut doc, &mut fixes)?;
+    Ok(Repaired {
+        document: doc,
+        migrations: migrated.warnings,
+        fixes,
+    })
+}
+
+fn fix(kind: FixKind, message: String) -> Fix {
+    Fix { kind, message }
+}
+
+fn fresh_id(base: &str, taken: &HashSet<String>) -> String {
+    let mut suffix = 2;
+    loop {
+        let id = format!("{base}_{suffix}");
+        if !taken.contains(&id) {
+            return id;
+        }
+        suffix += 1;
+    }
+}
+
+/// Gives the later of two nodes stored under the same key an id of its own.
+fn separate_duplicate_keys(raw: &mut Value, entries: Vec<(String, Value)>, fixes: &mut Vec<Fix>) {
+    let Some(nodes) = raw.get_mut("nodes").and_then(Value::as_object_mut) else {
+        return;
+    };
+    if entries.len() == nodes.len() {
+        return;
+    }
+    let mut taken: HashSet<String> = entries.iter().map(|(key, _)| key.clone()).collect();
+    let mut separated = Map::new();
+    for (key, mut node) in entries {
+        if !separated.contains_key(&key) {
+            separated.insert(key, node);
+            continue;
+        }
+        let id = fresh_id(&key, &taken);
+        taken.insert(id.clone());
+        if let Some(node) = node.as_object_mut() {
+            node.insert("id".to_string(), json!(id));
+        }
+        fixes.push(fix(
+            FixKind::RegeneratedId,
+            format!("a second node stored as {key} is now {id}"),
+        ));
+        separated.insert(id, node);
+    }
+    *nodes = separated;
+}
+
+fn fix_node_ids(doc: &mut Document, fixes: &mut Vec<Fix>) {
+    let keys: HashSet<String> = doc.nodes.keys().cloned().collect();
+    for (key, node) in doc.nodes.iter_mut() {
+        if node.id == *key {
+            continue;
+        }
+        let message = if keys.contains(&node.id) {
+            format!("node {key} claimed id {}, which another node has", node.id)
+        } else {
+            format!("node {key} claimed id {}", node.id)
+        };
+        fixes.push(fix(
+            FixKind::RegeneratedId,
+            format!("{message}; it now matches its key"),
+        ));
+        node.id = key.clone();
+    }
+}
+
+fn drop_missing_children(doc: &mut Document, fixes: &mut Vec<Fix>) {
+    let keys: HashSet<String> = doc.nodes.keys().cloned().collect();
+    for (key, node) in doc.nodes.iter_mut() {
+        let Some(children) = node.children.as_mut() else {
+            continue;
+        };
+        children.retain(|child_id| {
+            let exists = keys.contains(child_id);
+            if !exists {
+                fixes.push(fix(
+                    FixKind::DroppedMissingChild,
+                    format!("dropped missing child {child_id} from {key}"),
+                ));
+            }
+            exists
+        });
+    }
+}
+
+fn fix_pages(doc: &mut Document, fixes: &mut Vec<Fix>) {
+    let mut page_ids = HashSet::new();
+    let mut page_roots = HashSet::new();
+    let nodes = &doc.nodes;
+    doc.pages.retain(|page| {
+        let reason = if !nodes.contains_key(&page.root_id) {
+            format!("its root {} is missing", page.root_id)
+        } else if !page_ids.insert(page.id.clone()) {
+            "another page has the same id".to_string()
+        } else if !page_roots.insert(page.root_id.clone()) {
+            format!("another page already uses root {}", page.root_id)
+        } else {
+            return true;
+        };
+        fixes.push(fix(
+            FixKind::DroppedPage,
+            format!("dropped page {} ({}): {reason}", page.name, page.id),
+        ));
+        false
+    });
+
+    if doc.pages.is_empty() {
+        let root_id = if doc.nodes.contains_key(&doc.root_id) {
+            doc.root_id.clone()
+        } else {
+            let taken = doc.nodes.keys().cloned().collect();
+            let id = if doc.nodes.contains_key("root") {
+                fresh_id("root", &taken)
+            } else {
+                "root".to_string()
+            };
+            let mut root = Node::new(
+                id.clone(),
+                NodeType::Frame,
+                Position::default(),
+                Size {
+                    width: 1280.0,
+                    height: 800.0,
+                },
+            );
+            root.name = Some("Canvas".to_string());
+            root.children = Some(Vec::new());
+            root.visible = Some(true);
+            doc.nodes.insert(id.clone(), root);
+            id
+        };
+        doc.pages.push(Page {
+            id: "page_1".to_string(),
+            name: "Page 1".to_string(),
+            root_id: root_id.clone(),
+        });
+        fixes.push(fix(
+            FixKind::CreatedPage,
+            format!("created page page_1 with root {root_id}"),
+        ));
+    }
+
+    if doc.page(&doc.active_page_id).is_none() {
+        let page_id = doc.pages[0].id.clone();
+        fixes.push(fix(
+            FixKind::RecoveredActivePage,
+            format!("activePageId {:?} is now {page_id}", doc.active_page_id),
+        ));
+        doc.active_page_id = page_id;
+    }
+    if !doc.nodes.contains_key(&doc.root_id) {
+        let root_id = doc.pages[0].root_id.clone();
+        fixes.push(fix(
+            FixKind::RecoveredRootId,
+            format!("rootId {:?} is now {root_id}", doc.root_id),
+        ));
+        doc.root_id = root_id;
+    }
+}
+
+/// Walks down from the page roots so each node keeps the first parent that
+/// reaches it, then hangs whatever no page reaches off the active page.
+fn rebuild_tree(doc: &mut Document, fixes: &mut Vec<Fix>) {
+    let mut visited: HashSet<String> = doc.pages.iter().map(|page| page.root_id.clone()).collect();
+    visited.insert(doc.root_id.clone());
+    let mut starts: Vec<String> = doc.pages.iter().map(|page| page.root_id.clone()).collect();
+    if !starts.contains(&doc.root_id) {
+        starts.push(doc.root_id.clone());
+    }
+    for start in &starts {
+        walk(doc, start, &mut visited, fixes);
+    }
+
+    // Orphaned subtrees keep their shape: start from nodes nothing else
+    // unreached points at, then break whatever cycles are left.
+    let referenced: HashSet<String> = doc
+        .nodes
+        .iter()
+        .filter(|(key, _)| !visited.contains(*key))
+        .flat_map(|(_, node)| node.children.iter().flatten().cloned())
+        .collect();
+    let mut orphans: Vec<String> = doc
+        .nodes
+        .keys()
+        .filter(|key| !visited.contains(*key) && !referenced.contains(*key))
+        .cloned()
+        .collect();
+    let remaining: Vec<String> = doc.nodes.keys().cloned().collect();
+    for orphan in orphans.clone() {
+        visited.insert(orphan.clone());
+        walk(doc, &orphan, &mut visited, fixes);
+    }
+    for key in remaining {
+        if visited.insert(key.clone()) {
+            walk(doc, &key, &mut visited, fixes);
+            orphans.push(key);
+        }
+    }
+
+    let Some(page) = doc.page(&doc.active_page_id).cloned() else {
+        return;
+    };
+    for orphan in orphans {
+        if let Some(root) = doc.nodes.get_mut(&page.root_id) {
+            root.children
+                .get_or_insert_with(Vec::new)
+                .push(orphan.clone());
+        }
+        let name = doc.nodes[&orphan].name.clone().unwrap_or_default();
+        fixes.push(fix(
+            FixKind::ReattachedOrphan,
+            format!("reattached {orphan} {name:?} to page {}", page.name),
+        ));
+    }
+}
+
+fn walk(doc: &mut Document, start: &str, visited: &mut HashSet<String>, fixes: &mut Vec<Fix>) {
+    let mut queue = VecDeque::from([start.to_string()]);
+    while let Some(id) = queue.pop_front() {
+        let Some(children) = doc
+            .nodes
+            .get_mut(&id)
+            .and_then(|node| node.children.as_mut())
+        else {
+            continue;
+        };
+        children.retain(|child_id| {
+            if visited.insert(child_id.clone()) {
+                queue.push_back(child_id.clone());
+                return true;
+            }
+            fixes.push(fix(
+                FixKind::DroppedSharedChild,
+                format!("dropped {child_id} from {id}: it already has a parent"),
+            ));
+            false
+        });
+    }
+}
+
+/// Removes assets no node, component template or override points at.
+fn prune_assets(doc: &mut Document, fixes: &mut Vec<Fix>) -> Result<(), String> {
+    fn collect(value: &Value, referenced: &mut HashSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match value {
+                        Value::String(id) if key == "assetId" || key.ends_with("AssetId") => {
+                            referenced.insert(id.clone());
+                        }
+                        _ => collect(value, referenced),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| collect(value, referenced)),
+            _ => {}
+        }
+    }
+
+    let mut value = serde_json::to_value(&*doc).map_err(|e| e.to_string())?;
+    if let Some(map) = value.as_object_mut() {
+        map.remove("assets");
+    }
+    let mut referenced = HashSet::new();
+    collect(&value, &mut referenced);
+    doc.assets.retain(|id, _| {
+        let used = referenced.contains(id);
+        if !used {
+            fixes.push(fix(
+                FixKind::PrunedAsset,
+                format!("removed unused asset {id}"),
+            ));
+        }
+        used
+    });
+    Ok(())
+}
+
+fn issue(kind: IssueKind, message: String) -> Issue {
+    Issue { kind, message }
+}
+
+fn duplicate_keys(entries: &[(String, Value)]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for (key, _) in entries {
+        if !seen.insert(key.as_str()) && !duplicates.contains(&key.as_str()) {
+            duplicates.push(key.as_str());
+        }
+    }
+    duplicates
+}
+
+/// Every entry of the document's `nodes` object in file order, including
+/// repeated keys; empty when the document can't be read that way.
+pub(crate) fn node_entries(json: &str) -> Vec<(String, Value)> {
+    #[derive(Deserialize)]
+    struct RawDocument {
+        #[serde(default)]
+        nodes: NodeEntries,
+    }
+
+    #[derive(Default)]
+    struct NodeEntries(Vec<(String, Value)>);
+
+    impl<'de> Deserialize<'de> for NodeEntries {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Entries;
+
+            impl<'de> de::Visitor<'de> for Entries {
+                type Value = NodeEntries;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a map of nodes")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<NodeEntries, A::Error> {
+                    let mut entries = Vec::new();
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(NodeEntries(entries))
+                }
+            }
+
+            deserializer.deserialize_map(Entries)
+        }
+    }
+
+    serde_json::from_str::<RawDocument>(json)
+        .map(|raw| raw.nodes.0)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(report: &ValidationReport) -> Vec<IssueKind> {
+        report.issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn accepts_a_sound_document() {
+        let report = validate(
+            r#"{
+                "version": 9, "rootId": "root", "activePageId": "p",
+                "pages": [{ "id": "p", "name": "Page", "rootId": "root" }],
+                "nodes": {
+                    "root": { "id": "root", "type": "frame", "children": ["a"],
+                        "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 } },
+                    "a": { "id": "a", "type": "rectangle",
+                        "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } }
+                }
+            }"#,
+        );
+        assert!(report.valid, "{:?}", report.issues);
+        assert_eq!(report.version, Some(9));
+        assert!(report.migrations.is_empty());
+    }
+
+    #[test]
+    fn reports_broken_trees_and_duplicate_ids() {
+        let report = validate(
+            r#"{
+                "version": 8, "rootId": "root", "activePageId": "gone",
+                "pages": [
+                    { "id": "p", "name": "Page", "rootId": "root" },
+                    { "id": "q", "name": "Lost", "rootId": "missing_root" }
+                ],
+                "nodes": {
+                    "root": { "id": "root", "type": "frame", "children": ["a", "b", "ghost"],
+                        "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 } },
+                    "a": { "id": "a", "type": "group", "children": ["b"],
+                        "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+                    "b": { "id": "b", "type": "rectangle",
+                        "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+                    "b": { "id": "b", "type": "ellipse",
+                        "position": { "x": 5, "y": 5 }, "size": { "width": 1, "height": 1 } }
+                }
+            }"#,
+        );
+        assert!(!report.valid);
+        assert_eq!(report.version, Some(8));
+        assert!(report.migrations[0].starts_with("Document version 8"));
+        let kinds = kinds(&report);
+        for expected in [
+            IssueKind::DuplicateNodeId,
+            IssueKind::DanglingPageRoot,
+            IssueKind::MissingActivePage,
+            IssueKind::MissingChild,
+            IssueKind::SharedChild,
+        ] {
+            assert!(kinds.contains(&expected), "{expected:?} in {kinds:?}");
+        }
+    }
+
+    #[test]
+    fn reports_unknown_versions_and_unreadable_files() {
+        let report = validate(r#"{ "version": 12, "nodes": {} }"#);
+        assert_eq!(kinds(&report), [IssueKind::UnsupportedVersion]);
+        assert_eq!(report.version, Some(12));
+        assert_eq!(kinds(&validate("not json")), [IssueKind::InvalidDocument]);
+    }
+
+    const BROKEN: &str = r#"{
+        "version": 9, "rootId": "root", "activePageId": "gone",
+        "pages": [
+            { "id": "p", "name": "Page", "rootId": "root" },
+            { "id": "q", "name": "Lost", "rootId": "missing_root" }
+        ],
+        "nodes": {
+            "root": { "id": "root", "type": "frame", "children": ["a", "ghost", "b"],
+                "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 } },
+            "a": { "id": "a", "type": "group", "children": ["b"],
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+            "b": { "id": "b", "type": "image", "image": { "assetId": "used" },
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+            "b": { "id": "b", "type": "ellipse",
+                "position": { "x": 5, "y": 5 }, "size": { "width": 1, "height": 1 } },
+            "lonely": { "id": "lonely", "type": "group", "children": ["leaf"],
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+            "leaf": { "id": "root", "type": "rectangle",
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+            "x": { "id": "x", "type": "group", "children": ["y"],
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } },
+            "y": { "id": "y", "type": "group", "children": ["x"],
+                "position": { "x": 0, "y": 0 }, "size": { "width": 1, "height": 1 } }
+        },
+        "assets": {
+            "used": { "type": "image", "mime": "image/png", "width": 1, "height": 1 },
+            "unused": { "type": "image", "mime": "image/png", "width": 1, "height": 1 }
+        }
+    }"#;
+
+    #[test]
+    fn repair_rebuilds_the_node_tree() {
+        let Repaired {
+            document: doc,
+            fixes,
+            ..
+        } = repair(BROKEN).unwrap();
+        assert!(
+            integrity_issues(&doc).is_empty(),
+            "{:?}",
+            integrity_issues(&doc)
+        );
+
+        assert_eq!(
+            doc.nodes["root"].children.as_deref().unwrap(),
+            ["a", "b", "b_2", "lonely", "x"]
+        );
+        assert_eq!(doc.nodes["a"].children.as_deref().unwrap(), [] as [&str; 0]);
+        assert_eq!(doc.nodes["b_2"].kind, NodeType::Ellipse);
+        assert_eq!(doc.nodes["leaf"].id, "leaf");
+        assert_eq!(doc.nodes["lonely"].children.as_deref().unwrap(), ["leaf"]);
+        assert_eq!(doc.nodes["y"].children.as_deref().unwrap(), [] as [&str; 0]);
+        assert_eq!(doc.pages.len(), 1);
+        assert_eq!(doc.active_page_id, "p");
+        assert_eq!(doc.assets.keys().collect::<Vec<_>>(), ["used"]);
+
+        let kinds: Vec<FixKind> = fixes.iter().map(|fix| fix.kind).collect();
+        for expected in [
+            FixKind::RegeneratedId,
+            FixKind::DroppedMissingChild,
+            FixKind::DroppedSharedChild,
+            FixKind::DroppedPage,
+            FixKind::RecoveredActivePage,
+            FixKind::ReattachedOrphan,
+            FixKind::PrunedAsset,
+        ] {
+            assert!(kinds.contains(&expected), "{expected:?} in {kinds:?}");
+        }
+    }
+
+    #[test]
+    fn repair_document_writes_a_copy_beside_the_original() {
+        let dir = std::env::temp_dir().join(format!("galileo-repair-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("design.galileo");
+        std::fs::write(&path, BROKEN).unwrap();
+        let args = |output_path: Option<&Path>| RepairDocumentArgs {
+            path: path.to_string_lossy().to_string(),
+            output_path: output_path.map(|path| path.to_string_lossy().to_string()),
+        };
+
+        let report = repair_document(args(None)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), BROKEN);
+        let repaired = dir.join("design.repaired.galileo");
+        assert_eq!(report.repaired_path, repaired.to_string_lossy());
+        assert!(report.remaining_issues.is_empty());
+        assert!(!report.fixes.is_empty());
+        assert!(validate(&std::fs::read_to_string(&repaired).unwrap()).valid);
+
+        assert!(repair_document(args(Some(&path)))
+            .unwrap_err()
+            .starts_with("invalid_output"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
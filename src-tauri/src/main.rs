@@ -2,20 +2,34 @@ use base64::{engine::general_purpose, Engine as _};
 use image::{ImageBuffer, ImageFormat, Rgba};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::Cursor;
+use std::io::{Cursor, Write};
+use std::path::Path;
 use tauri::{path::BaseDirectory, Manager};
 
-#[cfg(target_os = "macos")]
-use objc::runtime::Object;
-#[cfg(target_os = "macos")]
-use objc::{class, msg_send, sel, sel_impl};
-#[cfg(target_os = "macos")]
-use std::ffi::CStr;
-#[cfg(target_os = "macos")]
-use std::os::raw::c_char;
-
+mod animation;
+mod asset_store;
 mod background_remove;
+mod batch_export;
+mod binary_ipc;
+mod bundle;
+mod document;
+mod document_backup;
 mod draft_store;
+mod export;
+mod file_watch;
+mod fonts;
+mod image_import;
+mod image_variants;
+mod integrity;
+mod matting;
+mod migration;
+mod path_data;
+mod pdf_export;
+mod png_optimize;
+mod scene;
+mod svg_export;
+mod svg_import;
+mod text_layout;
 mod unsplash;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +37,21 @@ mod unsplash;
 pub struct SaveDocumentArgs {
     pub path: String,
     pub content: String,
+    /// Move inline asset data into the project's asset store
+    #[serde(default)]
+    pub externalize_assets: bool,
+    /// Write a zip bundle with the assets, fonts and thumbnail packaged in
+    #[serde(default)]
+    pub bundle: bool,
+    /// Document a bundle is packaged from, when saving a copy elsewhere;
+    /// its asset store and `fonts/` folder are read. Defaults to `path`.
+    pub project_path: Option<String>,
+    /// PNG preview stored in the bundle
+    pub thumbnail_base64: Option<String>,
+    /// Refuse to overwrite the file if its mtime is no longer this
+    pub expected_mtime_ms: Option<u64>,
+    /// Refuse to overwrite the file if its hash is no longer this
+    pub expected_hash: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,6 +60,15 @@ pub struct LoadDocumentArgs {
     pub path: String,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedDocument {
+    pub content: String,
+    /// Version of the bytes `content` was read from, for the first save's
+    /// conflict check
+    pub version: document_backup::DocumentVersion,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RenameDocumentArgs {
@@ -58,10 +96,18 @@ pub struct SaveBinaryArgs {
     pub data_base64: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveBinaryRawArgs {
+    pub path: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SaveImageDialogArgs {
     pub suggested_name: Option<String>,
+    /// Export format the filter should match; defaults to PNG
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -71,6 +117,17 @@ pub struct EncodePngArgs {
     pub rgba_base64: String,
     pub width: u32,
     pub height: u32,
+    #[serde(flatten)]
+    pub png: export::PngOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodePngResult {
+    pub data_base64: String,
+    pub byte_size: usize,
+    /// Size of a default encode, when size options were given
+    pub unoptimized_byte_size: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,14 +141,73 @@ pub struct EncodeWebpArgs {
     pub quality: Option<u8>,
 }
 
-#[tauri::command]
-fn save_document(args: SaveDocumentArgs) -> Result<(), String> {
-    fs::write(&args.path, args.content).map_err(|e| e.to_string())
+/// Header arguments for the raw encoders; the pixels are the invoke body.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeRawArgs {
+    pub width: u32,
+    pub height: u32,
+    pub quality: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodePngRawArgs {
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub png: export::PngOptions,
+}
+
+/// Metadata of `encode_png_raw`'s framed response.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodedPng {
+    pub data: binary_ipc::BlobRef,
+    pub byte_size: usize,
+    pub unoptimized_byte_size: Option<usize>,
 }
 
+#[tauri::command(async)]
+fn save_document(args: SaveDocumentArgs) -> Result<document_backup::DocumentVersion, String> {
+    let path = Path::new(&args.path);
+    let expected = document_backup::ExpectedVersion {
+        mtime_ms: args.expected_mtime_ms,
+        hash: args.expected_hash,
+    };
+    if args.bundle {
+        let thumbnail = args
+            .thumbnail_base64
+            .map(|data| general_purpose::STANDARD.decode(data))
+            .transpose()
+            .map_err(|e| format!("Invalid base64: {e}"))?;
+        let project = args.project_path.as_deref().map_or(path, Path::new);
+        return document_backup::save(path, &expected, |file| {
+            bundle::write_to(file, project, &args.content, thumbnail.as_deref())
+        });
+    }
+    let content = if args.externalize_assets {
+        let store = asset_store::AssetStore::beside(path);
+        asset_store::externalize(&store, &args.content)?
+    } else {
+        args.content
+    };
+    document_backup::save(path, &expected, |file| {
+        file.write_all(content.as_bytes())
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Returns the document JSON with stored assets' data inlined again.
 #[tauri::command]
-fn load_document(args: LoadDocumentArgs) -> Result<String, String> {
-    fs::read_to_string(&args.path).map_err(|e| e.to_string())
+fn load_document(args: LoadDocumentArgs) -> Result<LoadedDocument, String> {
+    let path = Path::new(&args.path);
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let version = document_backup::file_version(&file)?;
+    Ok(LoadedDocument {
+        content: bundle::read_text_from(path, file)?,
+        version,
+    })
 }
 
 #[tauri::command]
@@ -166,6 +282,12 @@ fn load_binary(path: String) -> Result<String, String> {
     Ok(general_purpose::STANDARD.encode(bytes))
 }
 
+#[tauri::command]
+fn load_binary_raw(path: String) -> Result<tauri::ipc::Response, String> {
+    let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
 #[tauri::command]
 fn load_resource_binary(app: tauri::AppHandle, path: String) -> Result<String, String> {
     let resolved = app
@@ -183,8 +305,17 @@ fn load_text(path: String) -> Result<String, String> {
 
 #[tauri::command]
 fn show_save_image_dialog(args: SaveImageDialogArgs) -> Result<Option<String>, String> {
+    let format = args.format.as_deref().unwrap_or(export::FORMAT_PNG);
+    let (label, extensions) = match format.to_ascii_lowercase().as_str() {
+        "svg" => ("SVG", &["svg"][..]),
+        "pdf" => ("PDF", &["pdf"][..]),
+        _ => {
+            let format = export::ExportFormat::parse(format)?;
+            (format.label(), format.extensions())
+        }
+    };
     let mut dialog = rfd::FileDialog::new()
-        .add_filter("PNG", &["png"])
+        .add_filter(label, extensions)
         .set_title("Export Image");
     if let Some(name) = args.suggested_name {
         dialog = dialog.set_file_name(&name);
@@ -200,14 +331,22 @@ fn save_binary(args: SaveBinaryArgs) -> Result<(), String> {
     fs::write(&args.path, bytes).map_err(|e| e.to_string())
 }
 
-/// Encode raw RGBA pixels to PNG using native Rust (5-10x faster than canvas.toDataURL)
 #[tauri::command]
-fn encode_png(args: EncodePngArgs) -> Result<String, String> {
-    let rgba_bytes = general_purpose::STANDARD
-        .decode(&args.rgba_base64)
-        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+fn save_binary_raw(request: tauri::ipc::Request<'_>) -> Result<(), String> {
+    let args: SaveBinaryRawArgs = binary_ipc::header_args(&request)?;
+    fs::write(&args.path, binary_ipc::raw_body(&request)?).map_err(|e| e.to_string())
+}
 
-    let expected_len = (args.width * args.height * 4) as usize;
+/// Wraps raw RGBA pixels, checking the length matches the dimensions.
+fn rgba_image(
+    rgba_bytes: Vec<u8>,
+    width: u32,
+    height: u32,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let expected_len = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("Invalid RGBA dimensions: {width}x{height}"))?;
     if rgba_bytes.len() != expected_len {
         return Err(format!(
             "Invalid RGBA data length: expected {}, got {}",
@@ -216,16 +355,55 @@ fn encode_png(args: EncodePngArgs) -> Result<String, String> {
         ));
     }
 
-    let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
-        ImageBuffer::from_raw(args.width, args.height, rgba_bytes)
-            .ok_or("Failed to create image buffer")?;
+    ImageBuffer::from_raw(width, height, rgba_bytes)
+        .ok_or_else(|| "Failed to create image buffer".to_string())
+}
+
+fn encode_rgba(
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    format: ImageFormat,
+) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut cursor = Cursor::new(&mut bytes);
+    img.write_to(&mut cursor, format)
+        .map_err(|e| match format {
+            ImageFormat::WebP => format!("Failed to encode WebP: {}", e),
+            _ => format!("Failed to encode PNG: {}", e),
+        })?;
+    Ok(bytes)
+}
 
-    let mut png_bytes: Vec<u8> = Vec::new();
-    let mut cursor = Cursor::new(&mut png_bytes);
-    img.write_to(&mut cursor, ImageFormat::Png)
-        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+/// PNG-encodes `img` with the size options. The second value is the size
+/// of a default encode when the options change anything.
+fn encode_png_optimized(
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    png: &export::PngOptions,
+) -> Result<(Vec<u8>, Option<usize>), String> {
+    let (width, height) = img.dimensions();
+    let metadata = png_optimize::PngMetadata::default();
+    let bytes = png_optimize::encode(img.as_raw(), width, height, &png.settings()?, &metadata)?;
+    let unoptimized_byte_size = if png.is_tuned() {
+        Some(encode_rgba(img, ImageFormat::Png)?.len())
+    } else {
+        None
+    };
+    Ok((bytes, unoptimized_byte_size))
+}
 
-    Ok(general_purpose::STANDARD.encode(&png_bytes))
+/// Encode raw RGBA pixels to PNG using native Rust (5-10x faster than canvas.toDataURL)
+#[tauri::command]
+fn encode_png(args: EncodePngArgs) -> Result<EncodePngResult, String> {
+    let rgba_bytes = general_purpose::STANDARD
+        .decode(&args.rgba_base64)
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    let img = rgba_image(rgba_bytes, args.width, args.height)?;
+    let (png_bytes, unoptimized_byte_size) = encode_png_optimized(&img, &args.png)?;
+
+    Ok(EncodePngResult {
+        byte_size: png_bytes.len(),
+        unoptimized_byte_size,
+        data_base64: general_purpose::STANDARD.encode(&png_bytes),
+    })
 }
 
 /// Encode raw RGBA pixels to WebP (smaller files, good for web)
@@ -234,77 +412,45 @@ fn encode_webp(args: EncodeWebpArgs) -> Result<String, String> {
     let rgba_bytes = general_purpose::STANDARD
         .decode(&args.rgba_base64)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
-
-    let expected_len = (args.width * args.height * 4) as usize;
-    if rgba_bytes.len() != expected_len {
-        return Err(format!(
-            "Invalid RGBA data length: expected {}, got {}",
-            expected_len,
-            rgba_bytes.len()
-        ));
-    }
-
-    let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
-        ImageBuffer::from_raw(args.width, args.height, rgba_bytes)
-            .ok_or("Failed to create image buffer")?;
-
-    let mut webp_bytes: Vec<u8> = Vec::new();
-    let mut cursor = Cursor::new(&mut webp_bytes);
-    img.write_to(&mut cursor, ImageFormat::WebP)
-        .map_err(|e| format!("Failed to encode WebP: {}", e))?;
+    let img = rgba_image(rgba_bytes, args.width, args.height)?;
+    let webp_bytes = encode_rgba(&img, ImageFormat::WebP)?;
 
     Ok(general_purpose::STANDARD.encode(&webp_bytes))
 }
 
-#[cfg(target_os = "macos")]
-unsafe fn nsstring_to_string(value: *mut Object) -> Option<String> {
-    if value.is_null() {
-        return None;
-    }
-    let utf8: *const c_char = msg_send![value, UTF8String];
-    if utf8.is_null() {
-        return None;
-    }
-    let cstr = CStr::from_ptr(utf8);
-    Some(cstr.to_string_lossy().into_owned())
+/// `encode_png` with the RGBA pixels as the raw invoke body. Returns a
+/// framed response: [`EncodedPng`] metadata, then the PNG.
+#[tauri::command]
+fn encode_png_raw(request: tauri::ipc::Request<'_>) -> Result<tauri::ipc::Response, String> {
+    let args: EncodePngRawArgs = binary_ipc::header_args(&request)?;
+    let img = rgba_image(
+        binary_ipc::raw_body(&request)?.to_vec(),
+        args.width,
+        args.height,
+    )?;
+    let (png_bytes, unoptimized_byte_size) = encode_png_optimized(&img, &args.png)?;
+    let mut framed = binary_ipc::FramedResponse::default();
+    let data = framed.push(&png_bytes);
+    framed.finish(&EncodedPng {
+        data,
+        byte_size: png_bytes.len(),
+        unoptimized_byte_size,
+    })
 }
 
+/// `encode_webp` over raw bytes, like `encode_png_raw`.
 #[tauri::command]
-fn list_system_fonts() -> Result<Vec<String>, String> {
-    #[cfg(target_os = "macos")]
-    unsafe {
-        let font_manager: *mut Object = msg_send![class!(NSFontManager), sharedFontManager];
-        if font_manager.is_null() {
-            return Err("Failed to access NSFontManager".to_string());
-        }
-
-        let families: *mut Object = msg_send![font_manager, availableFontFamilies];
-        if families.is_null() {
-            return Err("Failed to read available font families".to_string());
-        }
-
-        let count: usize = msg_send![families, count];
-        let mut result = Vec::with_capacity(count);
-
-        for index in 0..count {
-            let item: *mut Object = msg_send![families, objectAtIndex: index];
-            if let Some(family) = nsstring_to_string(item) {
-                let trimmed = family.trim();
-                if !trimmed.is_empty() {
-                    result.push(trimmed.to_string());
-                }
-            }
-        }
-
-        result.sort();
-        result.dedup();
-        return Ok(result);
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        Err("Native system font listing is only implemented for macOS".to_string())
-    }
+fn encode_webp_raw(request: tauri::ipc::Request<'_>) -> Result<tauri::ipc::Response, String> {
+    let args: EncodeRawArgs = binary_ipc::header_args(&request)?;
+    let img = rgba_image(
+        binary_ipc::raw_body(&request)?.to_vec(),
+        args.width,
+        args.height,
+    )?;
+    Ok(tauri::ipc::Response::new(encode_rgba(
+        &img,
+        ImageFormat::WebP,
+    )?))
 }
 
 fn mask_env_value(value: &str) -> String {
@@ -373,33 +519,66 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             background_remove::remove_background,
+            background_remove::remove_background_raw,
             draft_store::save_draft,
             draft_store::load_draft,
             draft_store::delete_draft,
             draft_store::list_drafts,
             draft_store::get_file_mtime,
+            file_watch::watch_path,
+            file_watch::unwatch_path,
             save_document,
             load_document,
             rename_document,
             delete_document,
             duplicate_document,
+            document_backup::list_document_backups,
+            document_backup::restore_document_backup,
+            integrity::validate_document,
+            integrity::repair_document,
+            asset_store::asset_put,
+            asset_store::asset_put_raw,
+            asset_store::asset_get,
+            asset_store::asset_get_raw,
+            asset_store::asset_gc,
+            bundle::load_bundle_fonts,
             path_exists,
             show_save_dialog,
             show_open_dialog,
             show_open_folder,
             show_import_dialog,
             load_binary,
+            load_binary_raw,
+            image_import::import_image,
+            image_import::prepare_image,
+            image_variants::image_variants,
+            image_variants::image_variants_raw,
             load_resource_binary,
             load_text,
             show_save_image_dialog,
             save_binary,
+            save_binary_raw,
             encode_png,
             encode_webp,
-            list_system_fonts,
+            encode_png_raw,
+            encode_webp_raw,
+            export::encode_image,
+            export::encode_image_raw,
+            batch_export::export_batch,
+            batch_export::export_batch_raw,
+            animation::encode_animation,
+            animation::encode_animation_raw,
+            svg_export::export_svg,
+            pdf_export::export_pdf,
+            svg_import::import_svg,
+            fonts::list_system_fonts,
+            fonts::list_font_families,
+            fonts::load_font,
             unsplash::unsplash_search_photos,
             unsplash::unsplash_get_photo,
             unsplash::unsplash_track_download,
             unsplash::unsplash_fetch_image,
+            unsplash::unsplash_fetch_image_raw,
         ])
         .setup(|_app| {
             log_env_diagnostics();
@@ -0,0 +1,593 @@
+use image::imageops::FilterType;
+use image::{GrayImage, ImageBuffer, Luma, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Side length the guided filter coefficients are computed at. The
+/// coefficients are smooth, so upsampling them loses almost nothing.
+const GUIDED_WORKING_SIDE: u32 = 1024;
+const GUIDED_EPSILON: f32 = 1e-4;
+const FOREGROUND_EPSILON: f32 = 1e-5;
+/// Confidence at which a pixel counts as part of a subject's core.
+const INSTANCE_THRESHOLD: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskBounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Mean over a (2r+1)² window, clipped at the image borders.
+fn box_mean(src: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let mut rows = vec![0f32; src.len()];
+    let mut prefix = vec![0f64; width.max(height) + 1];
+
+    for y in 0..height {
+        let row = &src[y * width..(y + 1) * width];
+        for (x, value) in row.iter().enumerate() {
+            prefix[x + 1] = prefix[x] + *value as f64;
+        }
+        for x in 0..width {
+            let x0 = x.saturating_sub(radius);
+            let x1 = (x + radius + 1).min(width);
+            rows[y * width + x] = ((prefix[x1] - prefix[x0]) / (x1 - x0) as f64) as f32;
+        }
+    }
+
+    let mut out = vec![0f32; src.len()];
+    for x in 0..width {
+        for y in 0..height {
+            prefix[y + 1] = prefix[y] + rows[y * width + x] as f64;
+        }
+        for y in 0..height {
+            let y0 = y.saturating_sub(radius);
+            let y1 = (y + radius + 1).min(height);
+            out[y * width + x] = ((prefix[y1] - prefix[y0]) / (y1 - y0) as f64) as f32;
+        }
+    }
+    out
+}
+
+fn to_unit(values: &[u8]) -> Vec<f32> {
+    values.iter().map(|&value| value as f32 / 255.0).collect()
+}
+
+fn from_unit(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .map(|&value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect()
+}
+
+/// Guided filter (He et al.) with the luminance of `guide` steering `mask`.
+/// Soft edges snap to real image edges, which recovers hair and fur detail
+/// that a low-resolution segmentation mask smears out.
+pub fn guided_refine(guide: &RgbaImage, mask: &[u8], radius: u32) -> Vec<u8> {
+    let (width, height) = guide.dimensions();
+    let luma: GrayImage = image::DynamicImage::ImageRgba8(guide.clone()).to_luma8();
+    let mask_image: GrayImage = ImageBuffer::from_raw(width, height, mask.to_vec())
+        .unwrap_or_else(|| GrayImage::new(width, height));
+
+    let scale = (width.max(height) as f32 / GUIDED_WORKING_SIDE as f32).max(1.0);
+    let low_width = ((width as f32 / scale).round() as u32).max(1);
+    let low_height = ((height as f32 / scale).round() as u32).max(1);
+    let low_radius = ((radius as f32 / scale).round() as usize).max(1);
+
+    let (low_luma, low_mask) = if scale > 1.0 {
+        (
+            image::imageops::resize(&luma, low_width, low_height, FilterType::Triangle),
+            image::imageops::resize(&mask_image, low_width, low_height, FilterType::Triangle),
+        )
+    } else {
+        (luma.clone(), mask_image)
+    };
+
+    let w = low_width as usize;
+    let h = low_height as usize;
+    let guide_values = to_unit(low_luma.as_raw());
+    let input_values = to_unit(low_mask.as_raw());
+    let guide_sq: Vec<f32> = guide_values.iter().map(|v| v * v).collect();
+    let guide_input: Vec<f32> = guide_values
+        .iter()
+        .zip(&input_values)
+        .map(|(g, p)| g * p)
+        .collect();
+
+    let mean_guide = box_mean(&guide_values, w, h, low_radius);
+    let mean_input = box_mean(&input_values, w, h, low_radius);
+    let corr_guide = box_mean(&guide_sq, w, h, low_radius);
+    let corr_guide_input = box_mean(&guide_input, w, h, low_radius);
+
+    let mut a = vec![0f32; w * h];
+    let mut b = vec![0f32; w * h];
+    for i in 0..w * h {
+        let variance = corr_guide[i] - mean_guide[i] * mean_guide[i];
+        let covariance = corr_guide_input[i] - mean_guide[i] * mean_input[i];
+        a[i] = covariance / (variance + GUIDED_EPSILON);
+        b[i] = mean_input[i] - a[i] * mean_guide[i];
+    }
+    let mean_a = box_mean(&a, w, h, low_radius);
+    let mean_b = box_mean(&b, w, h, low_radius);
+
+    let upsample = |values: Vec<f32>| -> Vec<f32> {
+        let plane: ImageBuffer<Luma<f32>, Vec<f32>> =
+            ImageBuffer::from_raw(low_width, low_height, values)
+                .expect("coefficient plane matches its dimensions");
+        if scale > 1.0 {
+            image::imageops::resize(&plane, width, height, FilterType::Triangle).into_raw()
+        } else {
+            plane.into_raw()
+        }
+    };
+    let full_a = upsample(mean_a);
+    let full_b = upsample(mean_b);
+
+    luma.as_raw()
+        .iter()
+        .zip(full_a.iter().zip(&full_b))
+        .map(|(&intensity, (&a, &b))| {
+            let value = a * (intensity as f32 / 255.0) + b;
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Grows (positive) or shrinks (negative) the mask by `pixels` using a
+/// separable max/min filter.
+pub fn shift_edge(mask: &mut [u8], width: usize, height: usize, pixels: f32) {
+    let radius = pixels.abs().round() as usize;
+    if radius == 0 {
+        return;
+    }
+    let grow = pixels > 0.0;
+    // Out-of-image pixels are whatever leaves the window's result alone.
+    let identity = if grow { 0 } else { 255 };
+    let pick = |a: u8, b: u8| if grow { a.max(b) } else { a.min(b) };
+
+    for row in mask.chunks_exact_mut(width) {
+        let filtered = running_extreme(row, radius, identity, pick);
+        row.copy_from_slice(&filtered);
+    }
+    let mut column = vec![0u8; height];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = mask[y * width + x];
+        }
+        for (y, value) in running_extreme(&column, radius, identity, pick)
+            .into_iter()
+            .enumerate()
+        {
+            mask[y * width + x] = value;
+        }
+    }
+}
+
+/// `pick` over the `radius` neighbors on either side of every value, in
+/// constant time per value whatever the radius (van Herk/Gil-Werman).
+fn running_extreme(
+    line: &[u8],
+    radius: usize,
+    identity: u8,
+    pick: impl Fn(u8, u8) -> u8,
+) -> Vec<u8> {
+    let window = 2 * radius + 1;
+    let mut padded = vec![identity; line.len() + 2 * radius];
+    padded[radius..radius + line.len()].copy_from_slice(line);
+    // Within blocks of `window` values: running results from each block's
+    // start and from each block's end. Any window spans at most two blocks.
+    let mut from_start = padded.clone();
+    let mut from_end = padded.clone();
+    for i in 1..padded.len() {
+        if i % window != 0 {
+            from_start[i] = pick(from_start[i - 1], padded[i]);
+        }
+    }
+    for i in (0..padded.len() - 1).rev() {
+        if (i + 1) % window != 0 {
+            from_end[i] = pick(from_end[i + 1], padded[i]);
+        }
+    }
+    (0..line.len())
+        .map(|i| pick(from_end[i], from_start[i + window - 1]))
+        .collect()
+}
+
+/// Softens the mask edge with an approximate Gaussian of roughly `radius`
+/// pixels (three stacked box blurs).
+pub fn feather(mask: &mut [u8], width: usize, height: usize, radius: f32) {
+    if radius <= 0.0 {
+        return;
+    }
+    let sigma = radius / 2.0;
+    let passes = 3.0;
+    let box_width = (12.0 * sigma * sigma / passes + 1.0).sqrt();
+    let box_radius = ((box_width - 1.0) / 2.0).round().max(1.0) as usize;
+
+    let mut values = to_unit(mask);
+    for _ in 0..passes as usize {
+        values = box_mean(&values, width, height, box_radius);
+    }
+    mask.copy_from_slice(&from_unit(&values));
+}
+
+fn blur_fusion(
+    image: &[Vec<f32>; 3],
+    alpha: &[f32],
+    foreground: &mut [Vec<f32>; 3],
+    background: &mut [Vec<f32>; 3],
+    width: usize,
+    height: usize,
+    radius: usize,
+) {
+    let inverse_alpha: Vec<f32> = alpha.iter().map(|a| 1.0 - a).collect();
+    let blurred_alpha = box_mean(alpha, width, height, radius);
+    let blurred_inverse = box_mean(&inverse_alpha, width, height, radius);
+
+    for channel in 0..3 {
+        let weighted_fg: Vec<f32> = foreground[channel]
+            .iter()
+            .zip(alpha)
+            .map(|(f, a)| f * a)
+            .collect();
+        let weighted_bg: Vec<f32> = background[channel]
+            .iter()
+            .zip(&inverse_alpha)
+            .map(|(b, a)| b * a)
+            .collect();
+        let blurred_fg = box_mean(&weighted_fg, width, height, radius);
+        let blurred_bg = box_mean(&weighted_bg, width, height, radius);
+
+        for i in 0..alpha.len() {
+            let f = blurred_fg[i] / (blurred_alpha[i] + FOREGROUND_EPSILON);
+            let b = blurred_bg[i] / (blurred_inverse[i] + FOREGROUND_EPSILON);
+            let a = alpha[i];
+            let corrected = f + a * (image[channel][i] - a * f - (1.0 - a) * b);
+            foreground[channel][i] = corrected.clamp(0.0, 1.0);
+            background[channel][i] = b.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// Re-estimates the foreground colour of partially transparent pixels so the
+/// old background doesn't bleed through the cut-out edge. Uses the two-pass
+/// blur-fusion estimator from Germer et al., "Fast Multi-Level Foreground
+/// Estimation". The returned image keeps the source alpha channel.
+pub fn estimate_foreground(image: &RgbaImage, mask: &[u8]) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let w = width as usize;
+    let h = height as usize;
+    let alpha = to_unit(mask);
+
+    let mut channels: [Vec<f32>; 3] = [
+        Vec::with_capacity(w * h),
+        Vec::with_capacity(w * h),
+        Vec::with_capacity(w * h),
+    ];
+    for pixel in image.pixels() {
+        for (channel, values) in channels.iter_mut().enumerate() {
+            values.push(pixel[channel] as f32 / 255.0);
+        }
+    }
+
+    let mut foreground = channels.clone();
+    let mut background = channels.clone();
+    for radius in [90, 6] {
+        blur_fusion(
+            &channels,
+            &alpha,
+            &mut foreground,
+            &mut background,
+            w,
+            h,
+            radius,
+        );
+    }
+
+    let mut out = image.clone();
+    for (i, pixel) in out.pixels_mut().enumerate() {
+        for channel in 0..3 {
+            pixel[channel] = (foreground[channel][i] * 255.0).round() as u8;
+        }
+    }
+    out
+}
+
+/// Splits a single soft mask into one mask per connected subject. Cores
+/// smaller than `min_area` pixels are treated as noise. Soft edge pixels go
+/// to the nearest core, so each instance keeps its own partial alpha.
+pub fn split_instances(mask: &[u8], width: usize, height: usize, min_area: usize) -> Vec<Vec<u8>> {
+    const UNASSIGNED: usize = usize::MAX;
+    let mut labels = vec![UNASSIGNED; mask.len()];
+    let mut cores: Vec<Vec<usize>> = Vec::new();
+
+    for start in 0..mask.len() {
+        if mask[start] < INSTANCE_THRESHOLD || labels[start] != UNASSIGNED {
+            continue;
+        }
+        let label = cores.len();
+        let mut pixels = vec![start];
+        labels[start] = label;
+        let mut cursor = 0;
+        while cursor < pixels.len() {
+            let index = pixels[cursor];
+            cursor += 1;
+            for next in neighbors(index, width, height) {
+                if mask[next] >= INSTANCE_THRESHOLD && labels[next] == UNASSIGNED {
+                    labels[next] = label;
+                    pixels.push(next);
+                }
+            }
+        }
+        cores.push(pixels);
+    }
+
+    // Drop noise, then renumber the surviving cores.
+    let mut kept = Vec::new();
+    let mut remap = vec![UNASSIGNED; cores.len()];
+    for (label, pixels) in cores.iter().enumerate() {
+        if pixels.len() >= min_area {
+            remap[label] = kept.len();
+            kept.push(label);
+        }
+    }
+    let mut queue = VecDeque::new();
+    for (index, label) in labels.iter_mut().enumerate() {
+        if *label == UNASSIGNED {
+            continue;
+        }
+        *label = remap[*label];
+        if *label != UNASSIGNED {
+            queue.push_back(index);
+        }
+    }
+
+    // Multi-source BFS assigns every remaining soft pixel to its nearest core.
+    while let Some(index) = queue.pop_front() {
+        let label = labels[index];
+        for next in neighbors(index, width, height) {
+            if mask[next] > 0 && labels[next] == UNASSIGNED {
+                labels[next] = label;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    (0..kept.len())
+        .map(|instance| {
+            mask.iter()
+                .zip(&labels)
+                .map(|(&value, &label)| if label == instance { value } else { 0 })
+                .collect()
+        })
+        .collect()
+}
+
+fn neighbors(index: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let x = index % width;
+    let y = index / width;
+    [
+        (x > 0).then(|| index - 1),
+        (x + 1 < width).then(|| index + 1),
+        (y > 0).then(|| index - width),
+        (y + 1 < height).then(|| index + width),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Tight bounds of the pixels whose alpha exceeds `threshold`.
+pub fn mask_bounds(mask: &[u8], width: usize, height: usize, threshold: u8) -> Option<MaskBounds> {
+    let mut min_x = usize::MAX;
+    let mut min_y = usize::MAX;
+    let mut max_x = 0;
+    let mut max_y = 0;
+    for y in 0..height {
+        for x in 0..width {
+            if mask[y * width + x] > threshold {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    (min_x != usize::MAX).then(|| MaskBounds {
+        x: min_x as u32,
+        y: min_y as u32,
+        width: (max_x - min_x + 1) as u32,
+        height: (max_y - min_y + 1) as u32,
+    })
+}
+
+/// Applies `mask` as alpha and crops to `bounds` grown by `padding` on each
+/// side. Padding past the image edge is transparent, so the subject keeps the
+/// same margin wherever it sits; it is capped at the image's larger side.
+/// Returns the cut-out and its origin in image coordinates (negative when the
+/// padding reaches past the top-left edge).
+///
+/// Colors are premultiplied by the new alpha, so the cut-out composites with
+/// a plain `over` and nothing of the masked-out pixels survives in it.
+pub fn cut_out(
+    image: &RgbaImage,
+    mask: &[u8],
+    bounds: &MaskBounds,
+    padding: u32,
+) -> (RgbaImage, i64, i64) {
+    let (width, height) = image.dimensions();
+    let padding = padding.min(width.max(height));
+    let origin_x = bounds.x as i64 - padding as i64;
+    let origin_y = bounds.y as i64 - padding as i64;
+    let mut out = RgbaImage::new(bounds.width + padding * 2, bounds.height + padding * 2);
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let source_x = origin_x + x as i64;
+        let source_y = origin_y + y as i64;
+        if source_x < 0 || source_y < 0 || source_x >= width as i64 || source_y >= height as i64 {
+            continue;
+        }
+        let source = image.get_pixel(source_x as u32, source_y as u32);
+        let coverage = mask[source_y as usize * width as usize + source_x as usize] as u32;
+        let alpha = ((source[3] as u32 * coverage + 127) / 255) as u8;
+        if alpha > 0 {
+            let premultiply = |channel: u8| ((channel as u32 * alpha as u32 + 127) / 255) as u8;
+            *pixel = Rgba([
+                premultiply(source[0]),
+                premultiply(source[1]),
+                premultiply(source[2]),
+                alpha,
+            ]);
+        }
+    }
+    (out, origin_x, origin_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        box_mean, cut_out, estimate_foreground, feather, guided_refine, mask_bounds, shift_edge,
+        split_instances, MaskBounds,
+    };
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn box_mean_clips_window_at_borders() {
+        let values = vec![0.0, 3.0, 6.0];
+        let blurred = box_mean(&values, 3, 1, 1);
+        assert_eq!(blurred, vec![1.5, 3.0, 4.5]);
+    }
+
+    #[test]
+    fn shift_edge_grows_and_shrinks_mask() {
+        let mut mask = vec![0, 0, 255, 0, 0];
+        shift_edge(&mut mask, 5, 1, 1.0);
+        assert_eq!(mask, vec![0, 255, 255, 255, 0]);
+        shift_edge(&mut mask, 5, 1, -1.0);
+        assert_eq!(mask, vec![0, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn shift_edge_matches_a_direct_window_scan() {
+        let (width, height): (usize, usize) = (23, 7);
+        let mask: Vec<u8> = (0..width * height)
+            .map(|i| ((i * 37 + i / 5 * 11) % 256) as u8)
+            .collect();
+        for pixels in [2.0, -3.0, 40.0] {
+            let radius = f32::abs(pixels) as usize;
+            let pick = |a: u8, b: u8| if pixels > 0.0 { a.max(b) } else { a.min(b) };
+            let expected: Vec<u8> = (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    let xs = x.saturating_sub(radius)..(x + radius + 1).min(width);
+                    let ys = y.saturating_sub(radius)..(y + radius + 1).min(height);
+                    let mask = &mask;
+                    ys.flat_map(|y| xs.clone().map(move |x| mask[y * width + x]))
+                        .reduce(pick)
+                        .unwrap()
+                })
+                .collect();
+            let mut shifted = mask.clone();
+            shift_edge(&mut shifted, width, height, pixels);
+            assert_eq!(shifted, expected, "shift {pixels}");
+        }
+    }
+
+    #[test]
+    fn feather_produces_fractional_alpha() {
+        let mut mask = vec![0, 0, 0, 0, 255, 255, 255, 255];
+        feather(&mut mask, 8, 1, 2.0);
+        assert!(mask.iter().any(|&value| value > 0 && value < 255));
+        assert!(mask[0] < mask[7]);
+    }
+
+    #[test]
+    fn guided_refine_keeps_uniform_regions_and_follows_guide_edges() {
+        // Left half dark, right half bright; the mask edge is two pixels off.
+        let guide = RgbaImage::from_fn(16, 4, |x, _| {
+            if x < 8 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let mask: Vec<u8> = (0..16 * 4)
+            .map(|i| if i % 16 < 6 { 0 } else { 255 })
+            .collect();
+        let refined = guided_refine(&guide, &mask, 2);
+        assert_eq!(refined[0], 0);
+        assert_eq!(refined[15], 255);
+        assert!(refined[6] < mask[6]);
+    }
+
+    #[test]
+    fn estimate_foreground_leaves_opaque_pixels_untouched() {
+        let image = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8 * 60, 10, 200, 255]));
+        let mask = vec![255u8; 16];
+        let foreground = estimate_foreground(&image, &mask);
+        assert_eq!(foreground.as_raw(), image.as_raw());
+    }
+
+    #[test]
+    fn split_instances_separates_subjects_and_keeps_soft_edges() {
+        #[rustfmt::skip]
+        let mask = vec![
+            255, 255, 40,  0,   0,   0,
+            255, 255, 0,   0,   200, 255,
+            0,   0,   0,   0,   255, 255,
+            0,   0,   0,   0,   0,   0,
+            0,   0,   0,   0,   0,   130,
+        ];
+        let instances = split_instances(&mask, 6, 5, 2);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0][0], 255);
+        assert_eq!(instances[0][2], 40);
+        assert_eq!(instances[0][10], 0);
+        assert_eq!(instances[1][10], 200);
+        // The lone pixel at the bottom is below the minimum area.
+        assert!(instances.iter().all(|instance| instance[29] == 0));
+    }
+
+    #[test]
+    fn mask_bounds_covers_pixels_above_threshold() {
+        let mask = vec![0, 0, 0, 0, 10, 200, 0, 90, 0];
+        assert_eq!(
+            mask_bounds(&mask, 3, 3, 50),
+            Some(MaskBounds {
+                x: 1,
+                y: 1,
+                width: 2,
+                height: 2
+            })
+        );
+        assert_eq!(mask_bounds(&[0, 0], 2, 1, 0), None);
+    }
+
+    #[test]
+    fn cut_out_pads_with_transparency_past_the_edge() {
+        let mut image = RgbaImage::from_pixel(3, 2, Rgba([10, 20, 30, 255]));
+        image.put_pixel(2, 1, Rgba([40, 50, 60, 255]));
+        let mask = vec![255, 0, 0, 0, 128, 255];
+        let bounds = MaskBounds {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 1,
+        };
+
+        let (cutout, x, y) = cut_out(&image, &mask, &bounds, 1);
+
+        assert_eq!((x, y), (0, 0));
+        assert_eq!(cutout.dimensions(), (4, 3));
+        assert_eq!(cutout.get_pixel(0, 0), &Rgba([10, 20, 30, 255]));
+        assert_eq!(cutout.get_pixel(1, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(cutout.get_pixel(1, 1), &Rgba([5, 10, 15, 128]));
+        assert_eq!(cutout.get_pixel(2, 1), &Rgba([40, 50, 60, 255]));
+        assert_eq!(cutout.get_pixel(3, 1), &Rgba([0, 0, 0, 0]));
+        assert_eq!(cutout.get_pixel(3, 2), &Rgba([0, 0, 0, 0]));
+
+        let (cutout, x, y) = cut_out(&image, &mask, &bounds, u32::MAX);
+        assert_eq!((x, y), (-2, -2));
+        assert_eq!(cutout.dimensions(), (8, 7));
+    }
+}
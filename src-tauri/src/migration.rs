@@ -0,0 +1,829 @@
+//! Native port of the frontend's document migrations (`migrateDocument` in
+//! src/core/doc/serialization.ts), so older files can be upgraded to
+//! [`CURRENT_DOCUMENT_VERSION`] without the webview.
+//!
+//! Migrations work on the raw JSON, since older documents don't match the
+//! typed model in `document.rs` until they have been migrated.
+
+use crate::document::{EffectStyle, PaintStyle, TextStyle, CURRENT_DOCUMENT_VERSION};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+pub struct Migrated {
+    pub document: Map<String, Value>,
+    pub warnings: Vec<String>,
+}
+
+/// Brings `raw` up to the current version and drops malformed style and
+/// variable entries. Pages are left alone; see [`normalize_pages`].
+pub fn migrate(raw: Value) -> Result<Migrated, String> {
+    let Value::Object(mut doc) = raw else {
+        return Err("invalid_document: document must be an object".to_string());
+    };
+    let version = doc
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or("invalid_document: missing version")?;
+    let current = u64::from(CURRENT_DOCUMENT_VERSION);
+    if version > current {
+        return Err(format!("unsupported_document_version: {version}"));
+    }
+
+    let mut warnings = Vec::new();
+    let styles = normalize_styles(doc.get("styles"));
+    let mut variables = normalize_variables(doc.get("variables"));
+    if version < current {
+        warnings.push(format!(
+            "Document version {version} < {current}; migrated to {current}"
+        ));
+        let mut nodes = match doc.remove("nodes") {
+            Some(Value::Object(nodes)) => nodes,
+            _ => Map::new(),
+        };
+        for node in nodes.values_mut() {
+            migrate_node(node, version);
+        }
+        migrate_legacy_effect_variables(&nodes, &mut variables, &mut warnings);
+        let components = if version < 7 {
+            migrate_legacy_components(&nodes)
+        } else {
+            normalize_components(doc.get("components"))
+        };
+        if doc.get("assets").is_none_or(Value::is_null) {
+            doc.insert("assets".to_string(), json!({}));
+        }
+        doc.insert("version".to_string(), json!(current));
+        doc.insert("nodes".to_string(), Value::Object(nodes));
+        doc.insert("components".to_string(), components);
+    }
+    doc.insert("styles".to_string(), styles);
+    doc.insert("variables".to_string(), variables.into_value());
+    Ok(Migrated {
+        document: doc,
+        warnings,
+    })
+}
+
+/// Gives the document at least one page, drops duplicate pages and pages
+/// whose root is missing, and points `activePageId` and `rootId` at pages
+/// that survived.
+pub fn normalize_pages(doc: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    let empty = Map::new();
+    let nodes = doc
+        .get("nodes")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let non_blank = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string)
+    };
+
+    let raw_pages = doc
+        .get("pages")
+        .and_then(Value::as_array)
+        .map(|pages| {
+            pages
+                .iter()
+                .filter_map(Value::as_object)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let pages: Vec<(String, String, String)> = raw_pages
+        .iter()
+        .enumerate()
+        .filter_map(|(index, page)| {
+            let id = non_blank(page.get("id")).unwrap_or_else(|| format!("page_{}", index + 1));
+            let name = non_blank(page.get("name")).unwrap_or_else(|| format!("Page {}", index + 1));
+            Some((id, name, non_blank(page.get("rootId"))?))
+        })
+        .collect();
+    let legacy_root_id = doc
+        .get("rootId")
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string);
+
+    let mut seen = HashSet::new();
+    let mut normalized: Vec<_> = pages
+        .iter()
+        .filter(|(id, _, _)| seen.insert(id.clone()))
+        .cloned()
+        .collect();
+    if normalized.len() != pages.len() {
+        warnings
+            .push("Duplicate page IDs detected; removed duplicates during normalization".into());
+    }
+    if normalized.is_empty() {
+        let root_id = legacy_root_id
+            .clone()
+            .filter(|id| nodes.contains_key(id))
+            .or_else(|| nodes.keys().next().cloned())
+            .unwrap_or_else(|| "root".to_string());
+        normalized.push(("page_1".to_string(), "Page 1".to_string(), root_id));
+        warnings.push("Document missing pages; synthesized default page".into());
+    }
+
+    let first_valid = normalized
+        .iter()
+        .find(|(_, _, root_id)| nodes.contains_key(root_id))
+        .unwrap_or(&normalized[0])
+        .clone();
+    let mut safe: Vec<_> = normalized
+        .iter()
+        .filter(|(_, _, root_id)| nodes.contains_key(root_id))
+        .cloned()
+        .collect();
+    if safe.len() != normalized.len() {
+        warnings.push("Some pages referenced missing roots; removed invalid pages".into());
+    }
+    if safe.is_empty() {
+        safe.push(first_valid);
+    }
+
+    let candidate = doc
+        .get("activePageId")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| safe[0].0.clone());
+    let active_page_id = if safe.iter().any(|(id, _, _)| *id == candidate) {
+        candidate
+    } else {
+        warnings.push("activePageId was invalid and has been normalized".into());
+        safe[0].0.clone()
+    };
+    let root_id = legacy_root_id
+        .filter(|id| nodes.contains_key(id))
+        .unwrap_or_else(|| safe[0].2.clone());
+
+    let pages: Vec<Value> = safe
+        .into_iter()
+        .map(|(id, name, root_id)| json!({ "id": id, "name": name, "rootId": root_id }))
+        .collect();
+    doc.insert("rootId".to_string(), json!(root_id));
+    doc.insert("pages".to_string(), Value::Array(pages));
+    doc.insert("activePageId".to_string(), json!(active_page_id));
+}
+
+fn migrate_node(node: &mut Value, version: u64) {
+    let Some(node) = node.as_object_mut() else {
+        return;
+    };
+    let kind = node
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    if version < 3 && kind == "frame" && !node.contains_key("shadowOverflow") {
+        let clipped = node.get("clipContent") == Some(&Value::Bool(true));
+        let overflow = if clipped { "clipped" } else { "visible" };
+        node.insert("shadowOverflow".to_string(), json!(overflow));
+    }
+
+    if version < 5 {
+        if let Some(vector) = node.get_mut("vector").filter(|vector| vector.is_object()) {
+            migrate_legacy_vector(vector);
+        }
+    }
+
+    if version < 6 && kind == "text" {
+        for (key, default) in [
+            ("textAlign", json!("left")),
+            ("letterSpacingPx", json!(0)),
+            ("textResizeMode", json!("auto-width")),
+        ] {
+            node.entry(key).or_insert(default);
+        }
+    }
+
+    if version < 7 {
+        match normalize_variant_map(node.get("variant")) {
+            Some(variant) => node.insert("variant".to_string(), Value::Object(variant)),
+            None => node.remove("variant"),
+        };
+    }
+}
+
+/// Variant maps keep only non-blank string keys and values.
+fn normalize_variant_map(value: Option<&Value>) -> Option<Map<String, Value>> {
+    let entries: Map<String, Value> = value?
+        .as_object()?
+        .iter()
+        .map(|(key, raw)| {
+            let text = match raw {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), text)
+        })
+        .filter(|(key, text)| !key.trim().is_empty() && !text.trim().is_empty())
+        .map(|(key, text)| (key, Value::String(text)))
+        .collect();
+    (!entries.is_empty()).then_some(entries)
+}
+
+fn migrate_legacy_vector(vector: &mut Value) {
+    let Some(vector) = vector.as_object_mut() else {
+        return;
+    };
+    let points: Vec<Map<String, Value>> = vector
+        .get("points")
+        .and_then(Value::as_array)
+        .map(|points| {
+            points
+                .iter()
+                .enumerate()
+                .filter_map(|(index, point)| normalize_vector_point(point, index))
+                .collect()
+        })
+        .unwrap_or_default();
+    let closed = vector.get("closed") == Some(&Value::Bool(true));
+    let segments = normalize_vector_segments(vector.get("segments"), &points, closed);
+    vector.insert(
+        "points".to_string(),
+        Value::Array(points.into_iter().map(Value::Object).collect()),
+    );
+    vector.insert("segments".to_string(), Value::Array(segments));
+    vector.insert("closed".to_string(), Value::Bool(closed));
+}
+
+fn normalize_vector_point(point: &Value, index: usize) -> Option<Map<String, Value>> {
+    let point = point.as_object()?;
+    let x = point.get("x").filter(|x| x.is_number())?;
+    let y = point.get("y").filter(|y| y.is_number())?;
+    let id = point
+        .get("id")
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("pt_{index}"));
+    let corner_mode = point
+        .get("cornerMode")
+        .and_then(Value::as_str)
+        .filter(|mode| ["sharp", "mirrored", "asymmetric", "disconnected"].contains(mode))
+        .unwrap_or("sharp");
+
+    let mut normalized = Map::new();
+    normalized.insert("id".to_string(), json!(id));
+    normalized.insert("x".to_string(), x.clone());
+    normalized.insert("y".to_string(), y.clone());
+    normalized.insert("cornerMode".to_string(), json!(corner_mode));
+    for key in ["inHandle", "outHandle"] {
+        let handle = point
+            .get(key)
+            .and_then(Value::as_object)
+            .and_then(|handle| {
+                let x = handle.get("x").filter(|x| x.is_number())?;
+                let y = handle.get("y").filter(|y| y.is_number())?;
+                Some(json!({ "x": x, "y": y }))
+            });
+        if let Some(handle) = handle {
+            normalized.insert(key.to_string(), handle);
+        }
+    }
+    Some(normalized)
+}
+
+/// Keeps segments between known points, or chains the points in order when
+/// none survive.
+fn normalize_vector_segments(
+    segments: Option<&Value>,
+    points: &[Map<String, Value>],
+    closed: bool,
+) -> Vec<Value> {
+    let point_id =
+        |point: &Map<String, Value>| point["id"].as_str().unwrap_or_default().to_string();
+    let ids: HashSet<String> = points.iter().map(point_id).collect();
+    let kept: Vec<Value> = segments
+        .and_then(Value::as_array)
+        .map(|segments| {
+            segments
+                .iter()
+                .enumerate()
+                .filter_map(|(index, segment)| {
+                    let segment = segment.as_object()?;
+                    let from_id = segment.get("fromId").and_then(Value::as_str)?;
+                    let to_id = segment.get("toId").and_then(Value::as_str)?;
+                    if !ids.contains(from_id) || !ids.contains(to_id) {
+                        return None;
+                    }
+                    let id = segment
+                        .get("id")
+                        .and_then(Value::as_str)
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("seg_{index}"));
+                    Some(json!({ "id": id, "fromId": from_id, "toId": to_id }))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if !kept.is_empty() || points.len() < 2 {
+        return kept;
+    }
+
+    let mut chained: Vec<Value> = points
+        .windows(2)
+        .enumerate()
+        .map(|(index, pair)| {
+            json!({ "id": format!("seg_{index}"), "fromId": point_id(&pair[0]), "toId": point_id(&pair[1]) })
+        })
+        .collect();
+    if closed {
+        let last = &points[points.len() - 1];
+        chained.push(json!({
+            "id": format!("seg_{}", chained.len()),
+            "fromId": point_id(last),
+            "toId": point_id(&points[0]),
+        }));
+    }
+    chained
+}
+
+/// Drops style entries the typed model can't read.
+fn normalize_styles(raw: Option<&Value>) -> Value {
+    let Some(raw) = raw.and_then(Value::as_object) else {
+        return json!({ "paint": {}, "text": {}, "effect": {}, "grid": {} });
+    };
+    fn parseable<T: DeserializeOwned>(entry: &Value) -> bool {
+        serde_json::from_value::<T>(entry.clone()).is_ok()
+    }
+    let keep = |key: &str, parses: fn(&Value) -> bool| -> Value {
+        let entries: Map<String, Value> = raw
+            .get(key)
+            .and_then(Value::as_object)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|(_, entry)| parses(entry))
+                    .map(|(id, entry)| (id.clone(), entry.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Value::Object(entries)
+    };
+    json!({
+        "paint": keep("paint", parseable::<PaintStyle>),
+        "text": keep("text", parseable::<TextStyle>),
+        "effect": keep("effect", parseable::<EffectStyle>),
+        "grid": keep("grid", Value::is_object),
+    })
+}
+
+#[derive(Default)]
+struct Variables {
+    collections: Map<String, Value>,
+    tokens: Map<String, Value>,
+    active_mode_by_collection: Map<String, Value>,
+}
+
+impl Variables {
+    fn into_value(self) -> Value {
+        json!({
+            "collections": self.collections,
+            "tokens": self.tokens,
+            "activeModeByCollection": self.active_mode_by_collection,
+        })
+    }
+}
+
+/// Fills in mode and collection defaults and drops tokens without a
+/// collection, as `normalizeVariableLibrary` does.
+fn normalize_variables(raw: Option<&Value>) -> Variables {
+    let mut variables = Variables::default();
+    let Some(raw) = raw.and_then(Value::as_object) else {
+        return variables;
+    };
+    let empty = Map::new();
+    let section = |key: &str| raw.get(key).and_then(Value::as_object).unwrap_or(&empty);
+    let non_blank = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string)
+    };
+
+    for (id, collection) in section("collections") {
+        let Some(collection) = collection.as_object() else {
+            continue;
+        };
+        let mut modes: Vec<(String, String)> = collection
+            .get("modes")
+            .and_then(Value::as_array)
+            .map(|modes| {
+                modes
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, mode)| {
+                        let mode = mode.as_object()?;
+                        let mode_id = non_blank(mode.get("id"))
+                            .unwrap_or_else(|| format!("mode_{}", index + 1));
+                        let name = non_blank(mode.get("name"))
+                            .unwrap_or_else(|| format!("Mode {}", index + 1));
+                        Some((mode_id, name))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if modes.is_empty() {
+            modes.push(("mode_default".to_string(), "Default".to_string()));
+        }
+        let default_mode_id = collection
+            .get("defaultModeId")
+            .and_then(Value::as_str)
+            .filter(|mode_id| modes.iter().any(|(id, _)| id == mode_id))
+            .map(str::to_string)
+            .unwrap_or_else(|| modes[0].0.clone());
+        let active_mode_id = section("activeModeByCollection")
+            .get(id)
+            .and_then(Value::as_str)
+            .filter(|mode_id| modes.iter().any(|(id, _)| id == mode_id))
+            .map(str::to_string)
+            .unwrap_or_else(|| default_mode_id.clone());
+        let modes: Vec<Value> = modes
+            .into_iter()
+            .map(|(id, name)| json!({ "id": id, "name": name }))
+            .collect();
+        variables.collections.insert(
+            id.clone(),
+            json!({
+                "id": id,
+                "name": non_blank(collection.get("name")).unwrap_or_else(|| id.clone()),
+                "modes": modes,
+                "defaultModeId": default_mode_id,
+            }),
+        );
+        variables
+            .active_mode_by_collection
+            .insert(id.clone(), json!(active_mode_id));
+    }
+
+    for (id, token) in section("tokens") {
+        let Some(token) = token.as_object() else {
+            continue;
+        };
+        let Some(collection_id) = non_blank(token.get("collectionId")) else {
+            continue;
+        };
+        let kind = token
+            .get("type")
+            .and_then(Value::as_str)
+            .filter(|kind| ["color", "number", "string"].contains(kind))
+            .unwrap_or("string");
+        let values_by_mode: Map<String, Value> = token
+            .get("valuesByMode")
+            .and_then(Value::as_object)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter(|(_, value)| value.is_string() || value.is_number())
+                    .map(|(mode_id, value)| (mode_id.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        variables.tokens.insert(
+            id.clone(),
+            json!({
+                "id": id,
+                "name": non_blank(token.get("name")).unwrap_or_else(|| id.clone()),
+                "collectionId": collection_id,
+                "type": kind,
+                "valuesByMode": values_by_mode,
+            }),
+        );
+    }
+    variables
+}
+
+const LEGACY_COLLECTION_ID: &str = "legacy_effect_variables";
+const LEGACY_MODE_ID: &str = "mode_default";
+
+/// Pre-v9 nodes kept effect variables inline; they become tokens in a
+/// shared "Legacy Effect Variables" collection.
+fn migrate_legacy_effect_variables(
+    nodes: &Map<String, Value>,
+    variables: &mut Variables,
+    warnings: &mut Vec<String>,
+) {
+    let mut migrated = 0;
+    for node in nodes.values() {
+        let Some(effect_variables) = node.get("effectVariables").and_then(Value::as_object) else {
+            continue;
+        };
+        for (key, value) in effect_variables {
+            if !value.is_string() && !value.is_number() {
+                continue;
+            }
+            if !variables.collections.contains_key(LEGACY_COLLECTION_ID) {
+                variables.collections.insert(
+                    LEGACY_COLLECTION_ID.to_string(),
+                    json!({
+                        "id": LEGACY_COLLECTION_ID,
+                        "name": "Legacy Effect Variables",
+                        "modes": [{ "id": LEGACY_MODE_ID, "name": "Default" }],
+                        "defaultModeId": LEGACY_MODE_ID,
+                    }),
+                );
+                variables
+                    .active_mode_by_collection
+                    .insert(LEGACY_COLLECTION_ID.to_string(), json!(LEGACY_MODE_ID));
+            }
+            let segment: String = key
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let token_id = if segment.is_empty() {
+                format!("legacy/token_{}", variables.tokens.len() + 1)
+            } else {
+                format!("legacy/{segment}")
+            };
+            if variables.tokens.contains_key(&token_id) {
+                continue;
+            }
+            let kind = infer_variable_type(value);
+            variables.tokens.insert(
+                token_id.clone(),
+                json!({
+                    "id": token_id,
+                    "name": key,
+                    "collectionId": LEGACY_COLLECTION_ID,
+                    "type": kind,
+                    "valuesByMode": { LEGACY_MODE_ID: normalize_token_value(value, kind) },
+                }),
+            );
+            migrated += 1;
+        }
+    }
+    if migrated > 0 {
+        warnings.push(format!(
+            "Migrated {migrated} legacy effect variable(s) into the default variables library"
+        ));
+    }
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0.0);
+    }
+    text.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn infer_variable_type(value: &Value) -> &'static str {
+    let Some(text) = value.as_str() else {
+        return "number";
+    };
+    let hex = text.trim().strip_prefix('#').unwrap_or_default();
+    if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        "color"
+    } else if parse_number(text).is_some() {
+        "number"
+    } else {
+        "string"
+    }
+}
+
+fn normalize_token_value(value: &Value, kind: &str) -> Value {
+    match (kind, value) {
+        ("number", Value::String(text)) => json!(parse_number(text).unwrap_or(0.0)),
+        (_, Value::Number(number)) if kind != "number" => json!(number.to_string()),
+        _ => value.clone(),
+    }
+}
+
+fn normalize_components(raw: Option<&Value>) -> Value {
+    let section = |key: &str| {
+        raw.and_then(|raw| raw.get(key))
+            .filter(|value| value.is_object())
+            .cloned()
+            .unwrap_or_else(|| json!({}))
+    };
+    json!({ "definitions": section("definitions"), "sets": section("sets") })
+}
+
+/// Before v7, component instances carried their own subtree; each distinct
+/// component id becomes a set with one default definition built from the
+/// first instance found.
+fn migrate_legacy_components(nodes: &Map<String, Value>) -> Value {
+    let mut definitions = Map::new();
+    let mut sets = Map::new();
+    for node in nodes.values() {
+        if node.get("type").and_then(Value::as_str) != Some("componentInstance") {
+            continue;
+        }
+        let Some(component_id) = node.get("componentId").and_then(Value::as_str) else {
+            continue;
+        };
+        if sets.contains_key(component_id) {
+            continue;
+        }
+        let children: Vec<String> = node
+            .get("children")
+            .and_then(Value::as_array)
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|child| child.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if children.is_empty() {
+            continue;
+        }
+
+        let mut template_nodes = Map::new();
+        let mut queue = children.clone();
+        while !queue.is_empty() {
+            let id = queue.remove(0);
+            if template_nodes.contains_key(&id) {
+                continue;
+            }
+            let Some(source) = nodes.get(&id).and_then(Value::as_object) else {
+                continue;
+            };
+            let mut template = source.clone();
+            for key in [
+                "componentId",
+                "componentOverrides",
+                "componentSourceNodeId",
+                "isComponentMainPreview",
+                "variant",
+            ] {
+                template.remove(key);
+            }
+            if let Some(variant) = normalize_variant_map(source.get("variant")) {
+                template.insert("variant".to_string(), Value::Object(variant));
+            }
+            if let Some(grandchildren) = template.get("children").and_then(Value::as_array) {
+                queue.extend(
+                    grandchildren
+                        .iter()
+                        .filter_map(|c| c.as_str().map(str::to_string)),
+                );
+            }
+            template_nodes.insert(id, Value::Object(template));
+        }
+
+        let name = node
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("Component");
+        let definition_id = format!("{component_id}__default");
+        let root_id = format!("{component_id}__template_root");
+        let size = node
+            .get("size")
+            .filter(|size| size.is_object())
+            .cloned()
+            .unwrap_or_else(|| json!({ "width": 100, "height": 100 }));
+        template_nodes.insert(
+            root_id.clone(),
+            json!({
+                "id": root_id,
+                "type": "frame",
+                "name": name,
+                "position": { "x": 0, "y": 0 },
+                "size": size,
+                "children": children,
+                "visible": true,
+            }),
+        );
+
+        let variant = normalize_variant_map(node.get("variant"));
+        let mut definition = json!({
+            "id": definition_id,
+            "name": name,
+            "setId": component_id,
+            "templateRootId": root_id,
+            "templateNodes": template_nodes,
+        });
+        let mut properties = Map::new();
+        if let Some(variant) = variant {
+            for (key, value) in &variant {
+                properties.insert(key.clone(), json!([value]));
+            }
+            definition["variant"] = Value::Object(variant);
+        }
+        definitions.insert(definition_id.clone(), definition);
+        sets.insert(
+            component_id.to_string(),
+            json!({
+                "id": component_id,
+                "name": name,
+                "defaultDefinitionId": definition_id,
+                "definitionIds": [definition_id],
+                "properties": properties,
+            }),
+        );
+    }
+    json!({ "definitions": definitions, "sets": sets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_version_one_documents() {
+        let raw = json!({
+            "version": 1,
+            "rootId": "root",
+            "nodes": {
+                "root": { "id": "root", "type": "frame", "clipContent": true,
+                    "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 },
+                    "children": ["label", "icon", "button"] },
+                "label": { "id": "label", "type": "text", "text": "Hi",
+                    "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 },
+                    "effectVariables": { "shadow color": "#112233", "blur": "4" } },
+                "icon": { "id": "icon", "type": "path",
+                    "position": { "x": 0, "y": 0 }, "size": { "width": 10, "height": 10 },
+                    "vector": { "points": [{ "x": 0, "y": 0 }, { "x": 5, "y": 5 }, { "y": 1 }] } },
+                "button": { "id": "button", "type": "componentInstance", "componentId": "btn",
+                    "variant": { "size": "large", "": "x" },
+                    "position": { "x": 0, "y": 0 }, "size": { "width": 40, "height": 20 },
+                    "children": ["button_label"] },
+                "button_label": { "id": "button_label", "type": "text", "componentId": "btn",
+                    "position": { "x": 0, "y": 0 }, "size": { "width": 40, "height": 20 } }
+            }
+        });
+        let Migrated {
+            mut document,
+            mut warnings,
+        } = migrate(raw).unwrap();
+        normalize_pages(&mut document, &mut warnings);
+
+        let nodes = &document["nodes"];
+        assert_eq!(nodes["root"]["shadowOverflow"], "clipped");
+        assert_eq!(nodes["label"]["textResizeMode"], "auto-width");
+        assert_eq!(nodes["button"]["variant"], json!({ "size": "large" }));
+        let segments = &nodes["icon"]["vector"]["segments"];
+        assert_eq!(
+            segments,
+            &json!([{ "id": "seg_0", "fromId": "pt_0", "toId": "pt_1" }])
+        );
+
+        let tokens = &document["variables"]["tokens"];
+        assert_eq!(tokens["legacy/shadow_color"]["type"], "color");
+        assert_eq!(tokens["legacy/blur"]["valuesByMode"]["mode_default"], 4.0);
+
+        let definition = &document["components"]["definitions"]["btn__default"];
+        assert_eq!(definition["templateRootId"], "btn__template_root");
+        assert!(definition["templateNodes"]["button_label"]
+            .get("componentId")
+            .is_none());
+        assert_eq!(
+            document["components"]["sets"]["btn"]["properties"],
+            json!({ "size": ["large"] })
+        );
+
+        assert_eq!(
+            document["pages"],
+            json!([{ "id": "page_1", "name": "Page 1", "rootId": "root" }])
+        );
+        assert_eq!(document["activePageId"], "page_1");
+        assert!(warnings[0].starts_with("Document version 1 < 9"));
+
+        let doc: crate::document::Document =
+            serde_json::from_value(Value::Object(document)).unwrap();
+        assert_eq!(doc.version, CURRENT_DOCUMENT_VERSION);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_drops_broken_pages() {
+        assert_eq!(
+            migrate(json!({ "version": 10 })).err().unwrap(),
+            "unsupported_document_version: 10"
+        );
+        assert!(migrate(json!({ "nodes": {} })).is_err());
+
+        let Migrated { mut document, .. } = migrate(json!({
+            "version": 9,
+            "rootId": "gone",
+            "activePageId": "p2",
+            "pages": [
+                { "id": "p1", "name": "One", "rootId": "a" },
+                { "id": "p1", "name": "Copy", "rootId": "a" },
+                { "id": "p2", "name": "Two", "rootId": "gone" }
+            ],
+            "nodes": { "a": {} }
+        }))
+        .unwrap();
+        let mut warnings = Vec::new();
+        normalize_pages(&mut document, &mut warnings);
+        assert_eq!(
+            document["pages"],
+            json!([{ "id": "p1", "name": "One", "rootId": "a" }])
+        );
+        assert_eq!(document["activePageId"], "p1");
+        assert_eq!(document["rootId"], "a");
+        assert_eq!(warnings.len(), 3);
+    }
+}
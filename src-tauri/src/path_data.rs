@@ -0,0 +1,494 @@
+//! SVG path data (`d` attributes) parsed into absolute move/line/cubic
+//! segments. Quadratics, smooth curves and arcs are converted to cubics, so
+//! consumers only handle the three primitives PDF and the rasterizer know.
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    CubicTo(f64, f64, f64, f64, f64, f64),
+    Close,
+}
+
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Lexer<'_> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len()
+            && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let byte = *self.bytes.get(self.pos)?;
+        if byte.is_ascii_alphabetic() && byte != b'e' && byte != b'E' {
+            self.pos += 1;
+            return Some(byte);
+        }
+        None
+    }
+
+    fn at_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(
+            self.bytes.get(self.pos),
+            Some(b'0'..=b'9' | b'-' | b'+' | b'.')
+        )
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.pos;
+        let mut seen_dot = false;
+        let mut seen_digit = false;
+        if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            match byte {
+                b'0'..=b'9' => seen_digit = true,
+                b'.' if !seen_dot => seen_dot = true,
+                b'e' | b'E' if seen_digit => {
+                    if matches!(self.bytes.get(self.pos + 1), Some(b'-' | b'+')) {
+                        self.pos += 1;
+                    }
+                    seen_dot = true;
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid_path: bad number at byte {start}"))
+    }
+
+    /// Arc flags may be written without separators, as in `a1 1 0 011 1`.
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        match self.bytes.get(self.pos) {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(format!("invalid_path: bad arc flag at byte {}", self.pos)),
+        }
+    }
+}
+
+/// Parses path data into absolute segments.
+pub fn parse(d: &str) -> Result<Vec<PathSegment>, String> {
+    let mut lexer = Lexer {
+        bytes: d.as_bytes(),
+        pos: 0,
+    };
+    let mut segments = Vec::new();
+    let (mut x, mut y) = (0.0, 0.0);
+    let (mut start_x, mut start_y) = (0.0, 0.0);
+    // Reflection points for S/T
+    let mut last_cubic: Option<(f64, f64)> = None;
+    let mut last_quad: Option<(f64, f64)> = None;
+    let mut command = None;
+
+    loop {
+        let explicit = lexer.command();
+        let current = match (explicit, command) {
+            (Some(next), _) => next,
+            (None, Some(previous)) if lexer.at_number() => match previous {
+                // Extra pairs after a move are implicit line-tos.
+                b'M' => b'L',
+                b'm' => b'l',
+                other => other,
+            },
+            _ => break,
+        };
+        command = Some(current);
+        let relative = current.is_ascii_lowercase();
+        let (ox, oy) = if relative { (x, y) } else { (0.0, 0.0) };
+        let mut cubic = None;
+        let mut quad = None;
+
+        match current.to_ascii_uppercase() {
+            b'M' => {
+                x = ox + lexer.number()?;
+                y = oy + lexer.number()?;
+                start_x = x;
+                start_y = y;
+                segments.push(PathSegment::MoveTo(x, y));
+            }
+            b'L' => {
+                x = ox + lexer.number()?;
+                y = oy + lexer.number()?;
+                segments.push(PathSegment::LineTo(x, y));
+            }
+            b'H' => {
+                x = ox + lexer.number()?;
+                segments.push(PathSegment::LineTo(x, y));
+            }
+            b'V' => {
+                y = oy + lexer.number()?;
+                segments.push(PathSegment::LineTo(x, y));
+            }
+            b'C' | b'S' => {
+                let (x1, y1) = if current.eq_ignore_ascii_case(&b'C') {
+                    (ox + lexer.number()?, oy + lexer.number()?)
+                } else {
+                    last_cubic.map_or((x, y), |(cx, cy)| (2.0 * x - cx, 2.0 * y - cy))
+                };
+                let x2 = ox + lexer.number()?;
+                let y2 = oy + lexer.number()?;
+                x = ox + lexer.number()?;
+                y = oy + lexer.number()?;
+                segments.push(PathSegment::CubicTo(x1, y1, x2, y2, x, y));
+                cubic = Some((x2, y2));
+            }
+            b'Q' | b'T' => {
+                let (qx, qy) = if current.eq_ignore_ascii_case(&b'Q') {
+                    (ox + lexer.number()?, oy + lexer.number()?)
+                } else {
+                    last_quad.map_or((x, y), |(cx, cy)| (2.0 * x - cx, 2.0 * y - cy))
+                };
+                let (x0, y0) = (x, y);
+                x = ox + lexer.number()?;
+                y = oy + lexer.number()?;
+                segments.push(PathSegment::CubicTo(
+                    x0 + 2.0 / 3.0 * (qx - x0),
+                    y0 + 2.0 / 3.0 * (qy - y0),
+                    x + 2.0 / 3.0 * (qx - x),
+                    y + 2.0 / 3.0 * (qy - y),
+                    x,
+                    y,
+                ));
+                quad = Some((qx, qy));
+            }
+            b'A' => {
+                let rx = lexer.number()?;
+                let ry = lexer.number()?;
+                let rotation = lexer.number()?;
+                let large_arc = lexer.flag()?;
+                let sweep = lexer.flag()?;
+                let (x0, y0) = (x, y);
+                x = ox + lexer.number()?;
+                y = oy + lexer.number()?;
+                arc_to_cubics(
+                    (x0, y0),
+                    (rx, ry),
+                    rotation,
+                    large_arc,
+                    sweep,
+                    (x, y),
+                    &mut segments,
+                );
+            }
+            b'Z' => {
+                segments.push(PathSegment::Close);
+                x = start_x;
+                y = start_y;
+                // Z takes no arguments, so numbers after it can't repeat it.
+                command = None;
+            }
+            _ => return Err(format!("invalid_path: unknown command {}", current as char)),
+        }
+        last_cubic = cubic;
+        last_quad = quad;
+    }
+
+    lexer.skip_separators();
+    if lexer.pos < lexer.bytes.len() {
+        return Err(format!(
+            "invalid_path: unexpected input at byte {}",
+            lexer.pos
+        ));
+    }
+    Ok(segments)
+}
+
+/// Endpoint-parameterized elliptical arc (SVG 1.1 appendix F.6) as cubics
+/// of at most 90 degrees each.
+fn arc_to_cubics(
+    (x0, y0): (f64, f64),
+    (rx, ry): (f64, f64),
+    rotation_degrees: f64,
+    large_arc: bool,
+    sweep: bool,
+    (x, y): (f64, f64),
+    segments: &mut Vec<PathSegment>,
+) {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 || (x0 == x && y0 == y) {
+        if x0 != x || y0 != y {
+            segments.push(PathSegment::LineTo(x, y));
+        }
+        return;
+    }
+    let phi = rotation_degrees.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let dx = (x0 - x) / 2.0;
+    let dy = (y0 - y) / 2.0;
+    let x1p = cos_phi * dx + sin_phi * dy;
+    let y1p = -sin_phi * dx + cos_phi * dy;
+
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+    let denominator = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let cxp = coefficient * rx * y1p / ry;
+    let cyp = -coefficient * ry * x1p / rx;
+    let cx = cos_phi * cxp - sin_phi * cyp + (x0 + x) / 2.0;
+    let cy = sin_phi * cxp + cos_phi * cyp + (y0 + y) / 2.0;
+
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| {
+        let sign = if ux * vy - uy * vx < 0.0 { -1.0 } else { 1.0 };
+        let dot = (ux * vx + uy * vy) / ((ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt());
+        sign * dot.clamp(-1.0, 1.0).acos()
+    };
+    let theta = angle(1.0, 0.0, (x1p - cxp) / rx, (y1p - cyp) / ry);
+    let mut delta = angle(
+        (x1p - cxp) / rx,
+        (y1p - cyp) / ry,
+        (-x1p - cxp) / rx,
+        (-y1p - cyp) / ry,
+    );
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    let count = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = delta / count as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |t: f64| {
+        let (sin_t, cos_t) = t.sin_cos();
+        (
+            cx + rx * cos_t * cos_phi - ry * sin_t * sin_phi,
+            cy + rx * cos_t * sin_phi + ry * sin_t * cos_phi,
+        )
+    };
+    let derivative = |t: f64| {
+        let (sin_t, cos_t) = t.sin_cos();
+        (
+            -rx * sin_t * cos_phi - ry * cos_t * sin_phi,
+            -rx * sin_t * sin_phi + ry * cos_t * cos_phi,
+        )
+    };
+    for index in 0..count {
+        let t0 = theta + step * index as f64;
+        let t1 = t0 + step;
+        let (p0, d0) = (point(t0), derivative(t0));
+        let (p1, d1) = (point(t1), derivative(t1));
+        let end = if index + 1 == count { (x, y) } else { p1 };
+        segments.push(PathSegment::CubicTo(
+            p0.0 + k * d0.0,
+            p0.1 + k * d0.1,
+            p1.0 - k * d1.0,
+            p1.1 - k * d1.1,
+            end.0,
+            end.1,
+        ));
+    }
+}
+
+/// Polylines approximating the path, one per subpath, for rasterizing.
+pub fn flatten(segments: &[PathSegment], tolerance: f64) -> Vec<Vec<(f64, f64)>> {
+    let mut polylines: Vec<Vec<(f64, f64)>> = Vec::new();
+    let mut current: Vec<(f64, f64)> = Vec::new();
+    let mut start = (0.0, 0.0);
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo(x, y) => {
+                if current.len() > 1 {
+                    polylines.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                current.push((x, y));
+                start = (x, y);
+            }
+            PathSegment::LineTo(x, y) => {
+                if current.is_empty() {
+                    current.push(start);
+                }
+                current.push((x, y));
+            }
+            PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+                if current.is_empty() {
+                    current.push(start);
+                }
+                let (x0, y0) = *current.last().unwrap_or(&start);
+                let length =
+                    (x1 - x0).hypot(y1 - y0) + (x2 - x1).hypot(y2 - y1) + (x - x2).hypot(y - y2);
+                let steps = ((length / tolerance.max(0.01)).sqrt().ceil() as usize).clamp(1, 256);
+                for step in 1..=steps {
+                    let t = step as f64 / steps as f64;
+                    let mt = 1.0 - t;
+                    let a = mt * mt * mt;
+                    let b = 3.0 * mt * mt * t;
+                    let c = 3.0 * mt * t * t;
+                    let d = t * t * t;
+                    current.push((
+                        a * x0 + b * x1 + c * x2 + d * x,
+                        a * y0 + b * y1 + c * y2 + d * y,
+                    ));
+                }
+            }
+            PathSegment::Close => {
+                if current.len() > 1 {
+                    polylines.push(std::mem::take(&mut current));
+                }
+                current.push(start);
+            }
+        }
+    }
+    if current.len() > 1 {
+        polylines.push(current);
+    }
+    polylines
+}
+
+/// Anti-aliased coverage mask (0-255) for closed polylines on a
+/// `width`×`height` pixel grid, using four sub-scanlines per row and exact
+/// horizontal coverage.
+pub fn rasterize(
+    polylines: &[Vec<(f64, f64)>],
+    width: usize,
+    height: usize,
+    even_odd: bool,
+) -> Vec<u8> {
+    const SUBSAMPLES: usize = 4;
+    // (x0, y0, x1, y1, winding) with y0 < y1
+    let edges: Vec<(f64, f64, f64, f64, i32)> = polylines
+        .iter()
+        .flat_map(|points| {
+            let closing = points.last().zip(points.first()).map(|(a, b)| [*a, *b]);
+            points
+                .windows(2)
+                .map(|pair| [pair[0], pair[1]])
+                .chain(closing)
+                .collect::<Vec<_>>()
+        })
+        .filter(|[a, b]| a.1 != b.1)
+        .map(|[a, b]| {
+            if a.1 < b.1 {
+                (a.0, a.1, b.0, b.1, 1)
+            } else {
+                (b.0, b.1, a.0, a.1, -1)
+            }
+        })
+        .collect();
+
+    let mut mask = vec![0u8; width * height];
+    let mut row = vec![0f32; width];
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    let weight = 1.0 / SUBSAMPLES as f32;
+    for y in 0..height {
+        row.iter_mut().for_each(|value| *value = 0.0);
+        for sample in 0..SUBSAMPLES {
+            let sy = y as f64 + (sample as f64 + 0.5) / SUBSAMPLES as f64;
+            crossings.clear();
+            for &(x0, y0, x1, y1, winding) in &edges {
+                if sy >= y0 && sy < y1 {
+                    crossings.push((x0 + (sy - y0) / (y1 - y0) * (x1 - x0), winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = if even_odd {
+                    winding % 2 != 0
+                } else {
+                    winding != 0
+                };
+                if inside {
+                    add_span(&mut row, pair[0].0, pair[1].0, weight);
+                }
+            }
+        }
+        for (target, value) in mask[y * width..(y + 1) * width].iter_mut().zip(&row) {
+            *target = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    mask
+}
+
+fn add_span(row: &mut [f32], x0: f64, x1: f64, weight: f32) {
+    let width = row.len() as f64;
+    let (x0, x1) = (x0.clamp(0.0, width), x1.clamp(0.0, width));
+    if x1 <= x0 {
+        return;
+    }
+    let (first, last) = (x0.floor() as usize, x1.floor() as usize);
+    if first == last {
+        row[first] += (x1 - x0) as f32 * weight;
+        return;
+    }
+    row[first] += (first as f64 + 1.0 - x0) as f32 * weight;
+    for value in &mut row[first + 1..last] {
+        *value += weight;
+    }
+    if last < row.len() {
+        row[last] += (x1 - last as f64) as f32 * weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatten, parse, rasterize, PathSegment};
+
+    #[test]
+    fn parses_relative_and_implicit_commands() {
+        let segments = parse("m10 10 5 0v5h-5z M0 0L1-1.5.5.5").unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                PathSegment::MoveTo(10.0, 10.0),
+                PathSegment::LineTo(15.0, 10.0),
+                PathSegment::LineTo(15.0, 15.0),
+                PathSegment::LineTo(10.0, 15.0),
+                PathSegment::Close,
+                PathSegment::MoveTo(0.0, 0.0),
+                PathSegment::LineTo(1.0, -1.5),
+                PathSegment::LineTo(0.5, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn arcs_end_on_their_endpoint() {
+        let segments = parse("M0 0a10 10 0 0120 20").unwrap();
+        let Some(PathSegment::CubicTo(.., x, y)) = segments.last() else {
+            panic!("expected a cubic");
+        };
+        assert_eq!((*x, *y), (20.0, 20.0));
+        assert!(parse("M0 0 X").is_err());
+    }
+
+    #[test]
+    fn rasterizes_with_partial_edge_coverage() {
+        let segments = parse("M1.5 1 H4 V3 H1.5 Z").unwrap();
+        let mask = rasterize(&flatten(&segments, 0.25), 5, 4, false);
+        assert_eq!(&mask[5..10], &[0, 128, 255, 255, 0]);
+        assert_eq!(&mask[15..20], &[0, 0, 0, 0, 0]);
+    }
+}
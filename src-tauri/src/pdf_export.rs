@@ -0,0 +1,1418 @@
+//! Multi-page PDF export from a `.galileo` document. Every page root, or
+//! every requested frame, becomes a PDF page at one point per pixel. Shapes
+//! and text stay vector with subsetted fonts embedded from the installed
+//! faces, images keep their native resolution and shadows are rasterized,
+//! since PDF has no blur.
+
+use crate::document::{Document, DocumentSource};
+use crate::matting;
+use crate::path_data::{self, PathSegment};
+use crate::scene::{
+    self, num, parse_color, Bounds, DrawCommand, Effect, Gradient, GradientGeometry, ImageCommand,
+    ImageSource, Paint, SceneOptions, Shape, ShapeCommand, TextCommand,
+};
+use crate::svg_export::parse_data_url;
+use crate::text_layout::{layout_text, FontBook, LoadedFont, TextLayoutInput, TextMetrics};
+use base64::{engine::general_purpose, Engine as _};
+use flate2::{write::ZlibEncoder, Compression};
+use image::ColorType;
+use pdf_writer::types::{CidFontType, FontFlags, FunctionShadingType, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+const DEFAULT_SHADOW_SCALE: f64 = 2.0;
+/// Longest side of a rasterized shadow; larger shadows lower their scale.
+const MAX_SHADOW_PIXELS: f64 = 4096.0;
+const IDENTITY: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPdfArgs {
+    #[serde(flatten)]
+    pub source: DocumentSource,
+    /// Pages to export, in this order; defaults to every page
+    pub page_ids: Option<Vec<String>>,
+    /// Frames to export, one per PDF page, instead of whole pages
+    pub node_ids: Option<Vec<String>>,
+    pub output_path: String,
+    pub include_frame_fill: Option<bool>,
+    pub clip_to_bounds: Option<bool>,
+    /// Shadow pixels per point, 1-4; defaults to 2
+    pub shadow_scale: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPdfResult {
+    pub path: String,
+    pub page_count: usize,
+    pub byte_size: usize,
+    /// Font families that weren't installed and were substituted
+    pub missing_fonts: Vec<String>,
+    /// Nodes whose images were remote or couldn't be decoded
+    pub skipped_images: Vec<String>,
+}
+
+#[tauri::command(async)]
+pub fn export_pdf(args: ExportPdfArgs) -> Result<ExportPdfResult, String> {
+    let doc = args.source.load()?;
+    let options = SceneOptions {
+        include_frame_fill: args.include_frame_fill.unwrap_or(true),
+        clip_to_bounds: args.clip_to_bounds.unwrap_or(false),
+    };
+    let pages = export_roots(&doc, args.page_ids.as_deref(), args.node_ids.as_deref())?
+        .iter()
+        .map(|node_id| scene::build_for_node(&doc, node_id, options))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let shadow_scale = args
+        .shadow_scale
+        .unwrap_or(DEFAULT_SHADOW_SCALE)
+        .clamp(1.0, 4.0);
+    let mut fonts = FontBook::system();
+    let output = render_pdf(&pages, &mut fonts, shadow_scale)?;
+
+    let path = Path::new(&args.output_path);
+    fs::write(path, &output.bytes)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(ExportPdfResult {
+        path: args.output_path,
+        page_count: pages.len(),
+        byte_size: output.bytes.len(),
+        missing_fonts: output.missing_fonts,
+        skipped_images: output.skipped_images,
+    })
+}
+
+/// Nodes that become PDF pages: the requested frames, else the roots of the
+/// requested pages, else every page root.
+fn export_roots(
+    doc: &Document,
+    page_ids: Option<&[String]>,
+    node_ids: Option<&[String]>,
+) -> Result<Vec<String>, String> {
+    if let Some(node_ids) = node_ids.filter(|ids| !ids.is_empty()) {
+        return Ok(node_ids.to_vec());
+    }
+    let roots: Vec<String> = match page_ids {
+        Some(page_ids) => page_ids
+            .iter()
+            .map(|id| {
+                doc.page(id)
+                    .map(|page| page.root_id.clone())
+                    .ok_or_else(|| format!("page_not_found: {id}"))
+            })
+            .collect::<Result<_, _>>()?,
+        None => doc.pages.iter().map(|page| page.root_id.clone()).collect(),
+    };
+    if roots.is_empty() {
+        return Ok(vec![doc.root_id.clone()]);
+    }
+    Ok(roots)
+}
+
+pub struct PdfOutput {
+    pub bytes: Vec<u8>,
+    pub missing_fonts: Vec<String>,
+    pub skipped_images: Vec<String>,
+}
+
+/// Writes one PDF page per draw list.
+pub fn render_pdf(
+    pages: &[(Bounds, Vec<DrawCommand>)],
+    fonts: &mut FontBook,
+    shadow_scale: f64,
+) -> Result<PdfOutput, String> {
+    let mut writer = PdfWriter {
+        pdf: Pdf::new(),
+        next_ref: 1,
+        content: Content::new(),
+        open_clips: 0,
+        fonts,
+        shadow_scale,
+        resources: Vec::new(),
+        states: HashMap::new(),
+        embedded_fonts: Vec::new(),
+        font_keys: HashMap::new(),
+        fallback_font: None,
+        images: HashMap::new(),
+        missing_fonts: BTreeSet::new(),
+        skipped_images: Vec::new(),
+        pages: Vec::new(),
+    };
+    let catalog = writer.alloc();
+    let tree = writer.alloc();
+    for (bounds, commands) in pages {
+        writer.page(*bounds, commands)?;
+    }
+    writer.finish(catalog, tree)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResourceKind {
+    Font,
+    XObject,
+    ExtGState,
+    Shading,
+}
+
+struct EmbeddedFont {
+    font: Arc<LoadedFont>,
+    name: String,
+    reference: Ref,
+    /// Glyph ids shown with this font and the character each came from
+    glyphs: BTreeMap<u16, char>,
+}
+
+/// A font selected for a run of text: an embedded face by index, or the
+/// standard Helvetica fallback when nothing is installed.
+enum FontChoice {
+    Embedded(usize),
+    Fallback(String),
+}
+
+struct EmbeddedImage {
+    name: String,
+    width: u32,
+    height: u32,
+    /// Straight alpha, when the image has any transparency
+    alpha: Option<Vec<u8>>,
+}
+
+/// The opaque area a shadow is cast from, in page coordinates.
+enum Silhouette {
+    Path {
+        segments: Vec<PathSegment>,
+        even_odd: bool,
+        clips: Vec<(Vec<PathSegment>, bool)>,
+    },
+    Image {
+        bounds: Bounds,
+        image: Option<Arc<EmbeddedImage>>,
+    },
+}
+
+impl Silhouette {
+    fn bounds(&self) -> Bounds {
+        let segments = match self {
+            Silhouette::Image { bounds, .. } => return *bounds,
+            Silhouette::Path { segments, .. } => segments,
+        };
+        let (mut x0, mut y0) = (f64::INFINITY, f64::INFINITY);
+        let (mut x1, mut y1) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        let mut extend = |x: f64, y: f64| {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        };
+        for segment in segments {
+            match *segment {
+                PathSegment::MoveTo(x, y) | PathSegment::LineTo(x, y) => extend(x, y),
+                PathSegment::CubicTo(ax, ay, bx, by, x, y) => {
+                    extend(ax, ay);
+                    extend(bx, by);
+                    extend(x, y);
+                }
+                PathSegment::Close => {}
+            }
+        }
+        if x0 > x1 || y0 > y1 {
+            return Bounds::default();
+        }
+        Bounds {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        }
+    }
+
+    /// Coverage on a grid whose top-left pixel starts at `origin`.
+    fn rasterize(&self, origin: (f64, f64), scale: f64, width: usize, height: usize) -> Vec<u8> {
+        let fill = |segments: &[PathSegment], even_odd: bool| {
+            let polylines: Vec<Vec<(f64, f64)>> = path_data::flatten(segments, 0.25 / scale)
+                .into_iter()
+                .map(|points| {
+                    points
+                        .into_iter()
+                        .map(|(x, y)| ((x - origin.0) * scale, (y - origin.1) * scale))
+                        .collect()
+                })
+                .collect();
+            path_data::rasterize(&polylines, width, height, even_odd)
+        };
+        match self {
+            Silhouette::Path {
+                segments,
+                even_odd,
+                clips,
+            } => {
+                let mut mask = fill(segments, *even_odd);
+                for (clip, even_odd) in clips {
+                    multiply(&mut mask, &fill(clip, *even_odd));
+                }
+                mask
+            }
+            Silhouette::Image { bounds, image } => {
+                let outline = parse_path(&scene::rect_path(
+                    bounds.x,
+                    bounds.y,
+                    bounds.width,
+                    bounds.height,
+                ));
+                let mut mask = fill(&outline, false);
+                let Some((image, alpha)) = image
+                    .as_ref()
+                    .and_then(|image| Some((image, image.alpha.as_ref()?)))
+                else {
+                    return mask;
+                };
+                // Nearest sample of the image alpha under each grid pixel
+                for y in 0..height {
+                    let py = origin.1 + (y as f64 + 0.5) / scale;
+                    let v = (py - bounds.y) / bounds.height;
+                    for x in 0..width {
+                        let px = origin.0 + (x as f64 + 0.5) / scale;
+                        let u = (px - bounds.x) / bounds.width;
+                        let value = if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) {
+                            let ix = (u * image.width as f64) as usize;
+                            let iy = (v * image.height as f64) as usize;
+                            alpha[iy * image.width as usize + ix]
+                        } else {
+                            0
+                        };
+                        let index = y * width + x;
+                        mask[index] = (mask[index] as u32 * value as u32 / 255) as u8;
+                    }
+                }
+                mask
+            }
+        }
+    }
+}
+
+fn multiply(mask: &mut [u8], other: &[u8]) {
+    for (value, other) in mask.iter_mut().zip(other) {
+        *value = (*value as u32 * *other as u32 / 255) as u8;
+    }
+}
+
+/// Scene paths are generated or validated upstream; anything unparsable is
+/// drawn as nothing rather than failing the export.
+fn parse_path(d: &str) -> Vec<PathSegment> {
+    path_data::parse(d).unwrap_or_default()
+}
+
+fn translate(segments: Vec<PathSegment>, dx: f64, dy: f64) -> Vec<PathSegment> {
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            PathSegment::MoveTo(x, y) => PathSegment::MoveTo(x + dx, y + dy),
+            PathSegment::LineTo(x, y) => PathSegment::LineTo(x + dx, y + dy),
+            PathSegment::CubicTo(ax, ay, bx, by, x, y) => {
+                PathSegment::CubicTo(ax + dx, ay + dy, bx + dx, by + dy, x + dx, y + dy)
+            }
+            PathSegment::Close => PathSegment::Close,
+        })
+        .collect()
+}
+
+fn rounded_rect_path(bounds: Bounds, corner_radius: f64) -> String {
+    let Bounds {
+        x,
+        y,
+        width,
+        height,
+    } = bounds;
+    let r = corner_radius.min(width / 2.0).min(height / 2.0).max(0.0);
+    if r <= 0.0 {
+        return scene::rect_path(x, y, width, height);
+    }
+    let arc = |ex: f64, ey: f64| format!("A {0} {0} 0 0 1 {1} {2}", num(r), num(ex), num(ey));
+    format!(
+        "M {} {} H {} {} V {} {} H {} {} V {} {} Z",
+        num(x + r),
+        num(y),
+        num(x + width - r),
+        arc(x + width, y + r),
+        num(y + height - r),
+        arc(x + width - r, y + height),
+        num(x + r),
+        arc(x, y + height - r),
+        num(y + r),
+        arc(x + r, y)
+    )
+}
+
+/// The shape's outline in page coordinates, with any boolean clips.
+fn shape_outline(command: &ShapeCommand) -> Silhouette {
+    let bounds = command.bounds;
+    let (segments, even_odd, clips) = match &command.shape {
+        Shape::Rect { corner_radius } => (
+            parse_path(&rounded_rect_path(bounds, *corner_radius)),
+            false,
+            Vec::new(),
+        ),
+        Shape::Ellipse => (
+            parse_path(&scene::ellipse_path(
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+            )),
+            false,
+            Vec::new(),
+        ),
+        Shape::Path { d, even_odd, clips } => (
+            translate(parse_path(d), bounds.x, bounds.y),
+            *even_odd,
+            clips
+                .iter()
+                .map(|clip| {
+                    (
+                        translate(parse_path(&clip.d), bounds.x, bounds.y),
+                        clip.even_odd,
+                    )
+                })
+                .collect(),
+        ),
+    };
+    Silhouette::Path {
+        segments,
+        even_odd,
+        clips,
+    }
+}
+
+/// DeviceRGB components and straight alpha; unknown colors are black.
+fn rgba(color: &str) -> ([f32; 3], f64) {
+    parse_color(color).map_or(([0.0; 3], 1.0), |c| {
+        (
+            [c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0],
+            c.alpha,
+        )
+    })
+}
+
+fn first_stop(gradient: &Gradient) -> &str {
+    gradient
+        .stops
+        .first()
+        .map(|stop| stop.color.as_str())
+        .unwrap_or("transparent")
+}
+
+/// Stops sorted and padded to cover 0..=1, as stitching functions need.
+fn normalized_stops(gradient: &Gradient) -> Vec<(f32, [f32; 3])> {
+    let mut stops: Vec<(f32, [f32; 3])> = gradient
+        .stops
+        .iter()
+        .map(|stop| (stop.offset.clamp(0.0, 1.0) as f32, rgba(&stop.color).0))
+        .collect();
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if let Some(&(offset, color)) = stops.first() {
+        if offset > 0.0 {
+            stops.insert(0, (0.0, color));
+        }
+    }
+    if let Some(&(offset, color)) = stops.last() {
+        if offset < 1.0 {
+            stops.push((1.0, color));
+        }
+    }
+    stops
+}
+
+/// PDF blend mode name for a CSS `mix-blend-mode`, e.g. `ColorDodge`.
+fn blend_mode_name(mode: &str) -> String {
+    mode.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// The first family in a CSS list when the face used for it belongs to
+/// another family.
+fn substituted_family(metrics: &TextMetrics, family_list: &str) -> Option<String> {
+    let wanted = family_list
+        .split(',')
+        .map(|family| family.trim().trim_matches(['"', '\'']).trim())
+        .find(|family| !family.is_empty())?;
+    let generic = matches!(wanted, "sans-serif" | "serif" | "monospace" | "system-ui");
+    match &metrics.font {
+        Some(_) if generic => None,
+        Some(font) if font.info.family.eq_ignore_ascii_case(wanted) => None,
+        _ => Some(wanted.to_string()),
+    }
+}
+
+/// Characters Helvetica's WinAnsi encoding shares with Latin-1.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch as u32 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing into a Vec can't fail.
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+fn postscript_name(font: &LoadedFont) -> String {
+    let name = font
+        .info
+        .postscript_name
+        .clone()
+        .unwrap_or_else(|| format!("{}-{}", font.info.family, font.info.style_name));
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect()
+}
+
+/// Six-letter tag that marks a subset font name, derived from its glyphs.
+fn subset_tag(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    for glyph in glyphs.keys() {
+        for byte in glyph.to_be_bytes() {
+            hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+        }
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 5)) % 26) as u8) as char)
+        .collect()
+}
+
+struct PdfWriter<'a> {
+    pdf: Pdf,
+    next_ref: i32,
+    content: Content,
+    open_clips: usize,
+    fonts: &'a mut FontBook,
+    shadow_scale: f64,
+    /// Shared by every page
+    resources: Vec<(ResourceKind, String, Ref)>,
+    states: HashMap<(u16, u16, String), String>,
+    embedded_fonts: Vec<EmbeddedFont>,
+    font_keys: HashMap<String, usize>,
+    fallback_font: Option<(String, Ref)>,
+    images: HashMap<String, Option<Arc<EmbeddedImage>>>,
+    missing_fonts: BTreeSet<String>,
+    skipped_images: Vec<String>,
+    /// Page, content stream and size
+    pages: Vec<(Ref, Ref, Bounds)>,
+}
+
+impl PdfWriter<'_> {
+    fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.next_ref);
+        self.next_ref += 1;
+        id
+    }
+
+    fn resource(&mut self, kind: ResourceKind, prefix: &str) -> (String, Ref) {
+        let count = self.resources.iter().filter(|r| r.0 == kind).count();
+        let name = format!("{prefix}{}", count + 1);
+        let id = self.alloc();
+        self.resources.push((kind, name.clone(), id));
+        (name, id)
+    }
+
+    fn page(&mut self, bounds: Bounds, commands: &[DrawCommand]) -> Result<(), String> {
+        let page = self.alloc();
+        let content = self.alloc();
+        // Flip to the top-left origin the scene uses.
+        self.content = Content::new();
+        self.content
+            .transform([1.0, 0.0, 0.0, -1.0, 0.0, bounds.height as f32]);
+        self.open_clips = 0;
+        for command in commands {
+            self.command(command)?;
+        }
+        for _ in 0..self.open_clips {
+            self.content.restore_state();
+        }
+        let data = deflate(&std::mem::replace(&mut self.content, Content::new()).finish());
+        self.pdf.stream(content, &data).filter(Filter::FlateDecode);
+        self.pages.push((page, content, bounds));
+        Ok(())
+    }
+
+    fn command(&mut self, command: &DrawCommand) -> Result<(), String> {
+        match command {
+            DrawCommand::Clip {
+                bounds,
+                corner_radius,
+            } => {
+                let outline = parse_path(&rounded_rect_path(*bounds, corner_radius.unwrap_or(0.0)));
+                self.content.save_state();
+                self.path(&outline);
+                self.content.clip_nonzero().end_path();
+                self.open_clips += 1;
+            }
+            DrawCommand::Restore => {
+                if self.open_clips > 0 {
+                    self.open_clips -= 1;
+                    self.content.restore_state();
+                }
+            }
+            DrawCommand::Shape(shape) => self.shape(shape),
+            DrawCommand::Text(text) => self.text(text),
+            DrawCommand::Image(image) => self.image(image)?,
+        }
+        Ok(())
+    }
+
+    fn path(&mut self, segments: &[PathSegment]) {
+        for segment in segments {
+            match *segment {
+                PathSegment::MoveTo(x, y) => {
+                    self.content.move_to(x as f32, y as f32);
+                }
+                PathSegment::LineTo(x, y) => {
+                    self.content.line_to(x as f32, y as f32);
+                }
+                PathSegment::CubicTo(ax, ay, bx, by, x, y) => {
+                    self.content.cubic_to(
+                        ax as f32, ay as f32, bx as f32, by as f32, x as f32, y as f32,
+                    );
+                }
+                PathSegment::Close => {
+                    self.content.close_path();
+                }
+            }
+        }
+    }
+
+    /// Selects an ExtGState for the alphas and blend mode; normal opaque
+    /// drawing needs none.
+    fn set_state(&mut self, fill_alpha: f64, stroke_alpha: f64, blend_mode: &str) {
+        let blend_mode = if blend_mode.is_empty() {
+            "normal"
+        } else {
+            blend_mode
+        };
+        let fill_alpha = (fill_alpha.clamp(0.0, 1.0) * 1000.0).round() as u16;
+        let stroke_alpha = (stroke_alpha.clamp(0.0, 1.0) * 1000.0).round() as u16;
+        if fill_alpha == 1000 && stroke_alpha == 1000 && blend_mode == "normal" {
+            return;
+        }
+        let key = (fill_alpha, stroke_alpha, blend_mode.to_string());
+        let name = match self.states.get(&key) {
+            Some(name) => name.clone(),
+            None => {
+                let (name, id) = self.resource(ResourceKind::ExtGState, "G");
+                let mut state = self.pdf.ext_graphics(id);
+                state
+                    .non_stroking_alpha(fill_alpha as f32 / 1000.0)
+                    .stroking_alpha(stroke_alpha as f32 / 1000.0);
+                if blend_mode != "normal" {
+                    state.pair(Name(b"BM"), Name(blend_mode_name(blend_mode).as_bytes()));
+                }
+                state.finish();
+                self.states.insert(key, name.clone());
+                name
+            }
+        };
+        self.content.set_parameters(Name(name.as_bytes()));
+    }
+
+    fn shape(&mut self, command: &ShapeCommand) {
+        let silhouette = shape_outline(command);
+        let opacity = command.opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+        let shape = Some(&command.shape);
+        self.shadows(&silhouette, shape, &command.effects, false, opacity);
+
+        let Silhouette::Path {
+            segments,
+            even_odd,
+            clips,
+        } = &silhouette
+        else {
+            return;
+        };
+        self.content.save_state();
+        for (clip, clip_even_odd) in clips {
+            self.path(clip);
+            if *clip_even_odd {
+                self.content.clip_even_odd();
+            } else {
+                self.content.clip_nonzero();
+            }
+            self.content.end_path();
+        }
+        if let Some(fill) = &command.fill {
+            self.fill(segments, *even_odd, fill, command.bounds, opacity);
+        }
+        if let Some(stroke) = command
+            .stroke
+            .as_ref()
+            .filter(|_| command.stroke_width > 0.0)
+        {
+            let color = match stroke {
+                Paint::Solid(color) => color.as_str(),
+                // PDF can't stroke with a shading, so use the first stop.
+                Paint::Gradient(gradient) => first_stop(gradient),
+            };
+            let (rgb, alpha) = rgba(color);
+            self.content.save_state();
+            self.set_state(1.0, alpha * opacity, "normal");
+            self.content
+                .set_stroke_rgb(rgb[0], rgb[1], rgb[2])
+                .set_line_width(command.stroke_width as f32);
+            self.path(segments);
+            self.content.stroke();
+            self.content.restore_state();
+        }
+        self.content.restore_state();
+        self.shadows(&silhouette, shape, &command.effects, true, opacity);
+    }
+
+    fn fill(
+        &mut self,
+        segments: &[PathSegment],
+        even_odd: bool,
+        paint: &Paint,
+        bounds: Bounds,
+        opacity: f64,
+    ) {
+        let geometry = match paint {
+            Paint::Gradient(gradient) if gradient.stops.len() > 1 => gradient
+                .geometry(bounds)
+                .map(|geometry| (gradient, geometry)),
+            _ => None,
+        };
+        self.content.save_state();
+        match geometry {
+            Some((gradient, geometry)) => {
+                // Stop alpha is dropped; shadings here are opaque RGB.
+                let shading = self.shading(gradient, geometry);
+                self.set_state(opacity, 1.0, "normal");
+                self.path(segments);
+                if even_odd {
+                    self.content.clip_even_odd();
+                } else {
+                    self.content.clip_nonzero();
+                }
+                self.content.end_path().shading(Name(shading.as_bytes()));
+            }
+            None => {
+                let color = match paint {
+                    Paint::Solid(color) => color.as_str(),
+                    Paint::Gradient(gradient) => first_stop(gradient),
+                };
+                let (rgb, alpha) = rgba(color);
+                self.set_state(alpha * opacity, 1.0, "normal");
+                self.content.set_fill_rgb(rgb[0], rgb[1], rgb[2]);
+                self.path(segments);
+                if even_odd {
+                    self.content.fill_even_odd();
+                } else {
+                    self.content.fill_nonzero();
+                }
+            }
+        }
+        self.content.restore_state();
+    }
+
+    fn shading(&mut self, gradient: &Gradient, geometry: GradientGeometry) -> String {
+        let stops = normalized_stops(gradient);
+        let function = self.alloc();
+        if stops.len() == 2 {
+            self.pdf
+                .exponential_function(function)
+                .domain([0.0, 1.0])
+                .c0(stops[0].1)
+                .c1(stops[1].1)
+                .n(1.0);
+        } else {
+            let parts: Vec<Ref> = stops
+                .windows(2)
+                .map(|pair| {
+                    let id = self.alloc();
+                    self.pdf
+                        .exponential_function(id)
+                        .domain([0.0, 1.0])
+                        .c0(pair[0].1)
+                        .c1(pair[1].1)
+                        .n(1.0);
+                    id
+                })
+                .collect();
+            let bounds: Vec<f32> = stops[1..stops.len() - 1].iter().map(|s| s.0).collect();
+            self.pdf
+                .stitching_function(function)
+                .domain([0.0, 1.0])
+                .functions(parts.iter().copied())
+                .bounds(bounds)
+                .encode(parts.iter().flat_map(|_| [0.0, 1.0]));
+        }
+
+        let (name, id) = self.resource(ResourceKind::Shading, "Sh");
+        let mut shading = self.pdf.function_shading(id);
+        match geometry {
+            GradientGeometry::Linear { x1, y1, x2, y2 } => {
+                shading
+                    .shading_type(FunctionShadingType::Axial)
+                    .coords([x1, y1, x2, y2].map(|v| v as f32));
+            }
+            GradientGeometry::Radial {
+                cx,
+                cy,
+                inner,
+                outer,
+            } => {
+                shading
+                    .shading_type(FunctionShadingType::Radial)
+                    .coords([cx, cy, inner, cx, cy, outer].map(|v| v as f32));
+            }
+        }
+        shading.color_space().device_rgb();
+        shading.function(function).extend([true, true]);
+        shading.finish();
+        name
+    }
+
+    fn font(&mut self, metrics: &TextMetrics) -> FontChoice {
+        let Some(font) = &metrics.font else {
+            let name = match &self.fallback_font {
+                Some((name, _)) => name.clone(),
+                None => {
+                    let (name, id) = self.resource(ResourceKind::Font, "F");
+                    self.fallback_font = Some((name.clone(), id));
+                    name
+                }
+            };
+            return FontChoice::Fallback(name);
+        };
+        let key = format!("{}#{}", font.info.path, font.info.face_index);
+        if let Some(&index) = self.font_keys.get(&key) {
+            return FontChoice::Embedded(index);
+        }
+        let (name, reference) = self.resource(ResourceKind::Font, "F");
+        self.embedded_fonts.push(EmbeddedFont {
+            font: font.clone(),
+            name,
+            reference,
+            glyphs: BTreeMap::new(),
+        });
+        let index = self.embedded_fonts.len() - 1;
+        self.font_keys.insert(key, index);
+        FontChoice::Embedded(index)
+    }
+
+    fn text(&mut self, command: &TextCommand) {
+        let metrics = self
+            .fonts
+            .metrics(&command.font_family, command.font_weight);
+        if let Some(family) = substituted_family(&metrics, &command.font_family) {
+            self.missing_fonts.insert(family);
+        }
+        let layout = layout_text(
+            &TextLayoutInput {
+                text: &command.text,
+                width: command.bounds.width,
+                font_size: command.font_size,
+                text_align: &command.text_align,
+                line_height_px: command.line_height_px,
+                letter_spacing_px: command.letter_spacing_px,
+                resize_mode: &command.resize_mode,
+            },
+            |line| metrics.measure(line, command.font_size),
+        );
+        let lines: Vec<_> = layout.iter().filter(|line| !line.text.is_empty()).collect();
+        let Bounds { x, y, .. } = command.bounds;
+        let ascent = metrics.ascent(command.font_size);
+        let opacity = command.opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+
+        let silhouette = if metrics.font.is_some() {
+            let outline: String = lines
+                .iter()
+                .map(|line| {
+                    metrics.outline(
+                        &line.text,
+                        x + line.x,
+                        y + line.y,
+                        command.font_size,
+                        command.letter_spacing_px,
+                    ) + " "
+                })
+                .collect();
+            Silhouette::Path {
+                segments: parse_path(&outline),
+                even_odd: false,
+                clips: Vec::new(),
+            }
+        } else {
+            Silhouette::Image {
+                bounds: command.bounds,
+                image: None,
+            }
+        };
+        self.shadows(&silhouette, None, &command.effects, false, opacity);
+
+        let font = self.font(&metrics);
+        let (rgb, alpha) = rgba(&command.fill);
+        self.content.save_state();
+        self.set_state(alpha * opacity, 1.0, "normal");
+        if command.resize_mode == "fixed" {
+            self.path(&parse_path(&rounded_rect_path(command.bounds, 0.0)));
+            self.content.clip_nonzero().end_path();
+        }
+        let font_name = match &font {
+            FontChoice::Embedded(index) => self.embedded_fonts[*index].name.clone(),
+            FontChoice::Fallback(name) => name.clone(),
+        };
+        self.content
+            .set_fill_rgb(rgb[0], rgb[1], rgb[2])
+            .begin_text()
+            .set_font(Name(font_name.as_bytes()), command.font_size as f32)
+            .set_char_spacing(command.letter_spacing_px as f32);
+        for line in &lines {
+            let bytes = match &font {
+                FontChoice::Embedded(index) => {
+                    let used = &mut self.embedded_fonts[*index].glyphs;
+                    metrics
+                        .glyphs(&line.text, command.font_size, 0.0)
+                        .iter()
+                        .flat_map(|glyph| {
+                            used.entry(glyph.glyph_id).or_insert(glyph.ch);
+                            glyph.glyph_id.to_be_bytes()
+                        })
+                        .collect()
+                }
+                FontChoice::Fallback(_) => win_ansi(&line.text),
+            };
+            // Text space runs upward, so undo the page flip per line.
+            self.content
+                .set_text_matrix([
+                    1.0,
+                    0.0,
+                    0.0,
+                    -1.0,
+                    (x + line.x) as f32,
+                    (y + line.y + ascent) as f32,
+                ])
+                .show(Str(&bytes));
+        }
+        self.content.end_text();
+        self.content.restore_state();
+        self.shadows(&silhouette, None, &command.effects, true, opacity);
+    }
+
+    fn image(&mut self, command: &ImageCommand) -> Result<(), String> {
+        let Some(image) = self.embedded_image(command)? else {
+            self.skipped_images.push(command.node_id.clone());
+            return Ok(());
+        };
+        let opacity = command.opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+        let silhouette = Silhouette::Image {
+            bounds: command.bounds,
+            image: Some(image.clone()),
+        };
+        self.shadows(&silhouette, None, &command.effects, false, opacity);
+        self.content.save_state();
+        self.set_state(opacity, 1.0, "normal");
+        self.place_x_object(&image.name, command.bounds);
+        self.content.restore_state();
+        self.shadows(&silhouette, None, &command.effects, true, opacity);
+        Ok(())
+    }
+
+    /// Draws an image XObject over `bounds`, top row at the top.
+    fn place_x_object(&mut self, name: &str, bounds: Bounds) {
+        self.content
+            .save_state()
+            .transform([
+                bounds.width as f32,
+                0.0,
+                0.0,
+                -bounds.height as f32,
+                bounds.x as f32,
+                (bounds.y + bounds.height) as f32,
+            ])
+            .x_object(Name(name.as_bytes()))
+            .restore_state();
+    }
+
+    fn embedded_image(
+        &mut self,
+        command: &ImageCommand,
+    ) -> Result<Option<Arc<EmbeddedImage>>, String> {
+        let key = match &command.source {
+            ImageSource::Asset { id, .. } => format!("asset:{id}"),
+            ImageSource::Url(url) if url.starts_with("data:") => {
+                format!("node:{}", command.node_id)
+            }
+            ImageSource::Url(url) => url.clone(),
+        };
+        let key = match &command.mask {
+            Some(_) => format!("{key}#mask:{}", command.node_id),
+            None => key,
+        };
+        if let Some(image) = self.images.get(&key) {
+            return Ok(image.clone());
+        }
+        let image = self.write_image(command)?.map(Arc::new);
+        self.images.insert(key, image.clone());
+        Ok(image)
+    }
+
+    fn write_image(&mut self, command: &ImageCommand) -> Result<Option<EmbeddedImage>, String> {
+        let Some(bytes) = image_bytes(&command.source, &command.node_id)? else {
+            return Ok(None);
+        };
+        let Ok(decoded) = image::load_from_memory(&bytes) else {
+            return Ok(None);
+        };
+        let mut rgba = decoded.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let mask = match &command.mask {
+            Some(mask) => image_bytes(mask, &command.node_id)?
+                .and_then(|bytes| image::load_from_memory(&bytes).ok()),
+            None => None,
+        };
+        if let Some(mask) = mask {
+            let mask = image::imageops::resize(
+                &mask.to_rgba8(),
+                width,
+                height,
+                image::imageops::FilterType::Triangle,
+            );
+            for (pixel, mask) in rgba.pixels_mut().zip(mask.pixels()) {
+                pixel[3] = (pixel[3] as u32 * mask[3] as u32 / 255) as u8;
+            }
+        }
+        let alpha: Vec<u8> = rgba.pixels().map(|pixel| pixel[3]).collect();
+        let alpha = alpha.iter().any(|&a| a < 255).then_some(alpha);
+
+        let (name, id) = self.resource(ResourceKind::XObject, "Im");
+        let s_mask = alpha.as_ref().map(|alpha| {
+            let s_mask = self.alloc();
+            let data = deflate(alpha);
+            self.pdf
+                .image_xobject(s_mask, &data)
+                .width(width as i32)
+                .height(height as i32)
+                .color_space_name(Name(b"DeviceGray"))
+                .bits_per_component(8)
+                .filter(Filter::FlateDecode);
+            s_mask
+        });
+        // Opaque baseline JPEGs are embedded as they are.
+        let jpeg = bytes.starts_with(&[0xff, 0xd8]) && decoded.color() == ColorType::Rgb8;
+        let (data, filter) = if jpeg && s_mask.is_none() {
+            (bytes, Filter::DctDecode)
+        } else {
+            let rgb: Vec<u8> = rgba.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+            (deflate(&rgb), Filter::FlateDecode)
+        };
+        let mut xobject = self.pdf.image_xobject(id, &data);
+        xobject
+            .width(width as i32)
+            .height(height as i32)
+            .color_space_name(Name(b"DeviceRGB"))
+            .bits_per_component(8)
+            .filter(filter);
+        if let Some(s_mask) = s_mask {
+            xobject.s_mask(s_mask);
+        }
+        xobject.finish();
+        Ok(Some(EmbeddedImage {
+            name,
+            width,
+            height,
+            alpha,
+        }))
+    }
+
+    fn shadows(
+        &mut self,
+        silhouette: &Silhouette,
+        shape: Option<&Shape>,
+        effects: &[Effect],
+        inner: bool,
+        opacity: f64,
+    ) {
+        for effect in effects.iter().filter(|effect| effect.inner == inner) {
+            self.shadow(silhouette, shape, effect, opacity);
+        }
+    }
+
+    /// Rasterizes one shadow and draws it as a tinted image with a soft
+    /// mask. Drop shadows are offset copies of the blurred silhouette; inner
+    /// shadows are the blurred, offset outside clipped to the silhouette.
+    fn shadow(
+        &mut self,
+        silhouette: &Silhouette,
+        shape: Option<&Shape>,
+        effect: &Effect,
+        opacity: f64,
+    ) {
+        let (rgb, color_alpha) = rgba(&effect.color);
+        let alpha = color_alpha * effect.opacity * opacity;
+        if alpha <= 0.0 {
+            return;
+        }
+        let sigma = scene::shadow_sigma(effect, shape);
+        let area = silhouette.bounds();
+        let pad = effect.spread.abs() + sigma * 3.0 + 1.0;
+        let (area_width, area_height) = (area.width + pad * 2.0, area.height + pad * 2.0);
+        let scale = self
+            .shadow_scale
+            .min(MAX_SHADOW_PIXELS / area_width.max(area_height));
+        let width = ((area_width * scale).ceil() as usize).max(1);
+        let height = ((area_height * scale).ceil() as usize).max(1);
+        let origin = (area.x - pad, area.y - pad);
+
+        let mut mask = silhouette.rasterize(origin, scale, width, height);
+        let blur = (sigma * 2.0 * scale) as f32;
+        let spread = (effect.spread * scale) as f32;
+        let (x, y) = if effect.inner {
+            let mut outside: Vec<u8> = mask.iter().map(|value| 255 - value).collect();
+            matting::shift_edge(&mut outside, width, height, spread);
+            matting::feather(&mut outside, width, height, blur);
+            let dx = (effect.x * scale).round() as isize;
+            let dy = (effect.y * scale).round() as isize;
+            for row in 0..height {
+                for column in 0..width {
+                    let (sx, sy) = (column as isize - dx, row as isize - dy);
+                    let shadow = if (0..width as isize).contains(&sx)
+                        && (0..height as isize).contains(&sy)
+                    {
+                        outside[sy as usize * width + sx as usize]
+                    } else {
+                        255
+                    };
+                    let index = row * width + column;
+                    mask[index] = (mask[index] as u32 * shadow as u32 / 255) as u8;
+                }
+            }
+            origin
+        } else {
+            matting::shift_edge(&mut mask, width, height, spread);
+            matting::feather(&mut mask, width, height, blur);
+            (origin.0 + effect.x, origin.1 + effect.y)
+        };
+        if mask.iter().all(|&value| value == 0) {
+            return;
+        }
+
+        let (name, id) = self.resource(ResourceKind::XObject, "Im");
+        let s_mask = self.alloc();
+        let alpha_data = deflate(&mask);
+        self.pdf
+            .image_xobject(s_mask, &alpha_data)
+            .width(width as i32)
+            .height(height as i32)
+            .color_space_name(Name(b"DeviceGray"))
+            .bits_per_component(8)
+            .filter(Filter::FlateDecode);
+        let tint: Vec<u8> = rgb
+            .map(|c| (c * 255.0).round() as u8)
+            .repeat(width * height);
+        let color_data = deflate(&tint);
+        self.pdf
+            .image_xobject(id, &color_data)
+            .width(width as i32)
+            .height(height as i32)
+            .color_space_name(Name(b"DeviceRGB"))
+            .bits_per_component(8)
+            .s_mask(s_mask)
+            .filter(Filter::FlateDecode);
+
+        self.content.save_state();
+        self.set_state(alpha, 1.0, &effect.blend_mode);
+        self.place_x_object(
+            &name,
+            Bounds {
+                x,
+                y,
+                width: width as f64 / scale,
+                height: height as f64 / scale,
+            },
+        );
+        self.content.restore_state();
+    }
+
+    fn write_font(&mut self, index: usize) -> Result<(), String> {
+        let cid = self.alloc();
+        let descriptor = self.alloc();
+        let file = self.alloc();
+        let cmap_ref = self.alloc();
+        let font = &self.embedded_fonts[index];
+        let face = font
+            .font
+            .face()
+            .ok_or_else(|| format!("Failed to parse font {}", font.font.info.path))?;
+        // The PDF profile drops layout tables, which is fine here: the text
+        // is already shaped into the glyph ids being kept.
+        let glyphs: Vec<u16> = std::iter::once(0)
+            .chain(font.glyphs.keys().copied())
+            .collect();
+        let subset = subsetter::subset(
+            &font.font.data,
+            font.font.info.face_index,
+            subsetter::Profile::pdf(&glyphs),
+        )
+        .map_err(|e| format!("Failed to subset font: {e}"))?;
+        let cff = face.tables().cff.is_some();
+        let base_font = format!(
+            "{}+{}",
+            subset_tag(&font.glyphs),
+            postscript_name(&font.font)
+        );
+        let units = face.units_per_em() as f32;
+        let to_pdf = |value: f32| value * 1000.0 / units;
+
+        self.pdf
+            .type0_font(font.reference)
+            .base_font(Name(base_font.as_bytes()))
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid)
+            .to_unicode(cmap_ref);
+
+        let mut cid_font = self.pdf.cid_font(cid);
+        cid_font
+            .subtype(if cff {
+                CidFontType::Type0
+            } else {
+                CidFontType::Type2
+            })
+            .base_font(Name(base_font.as_bytes()))
+            .system_info(IDENTITY)
+            .font_descriptor(descriptor)
+            .default_width(0.0);
+        if !cff {
+            cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+        }
+        let mut widths = cid_font.widths();
+        for &glyph in font.glyphs.keys() {
+            let advance = face
+                .glyph_hor_advance(ttf_parser::GlyphId(glyph))
+                .unwrap_or(0);
+            widths.consecutive(glyph, [to_pdf(advance as f32)]);
+        }
+        widths.finish();
+        cid_font.finish();
+
+        let mut flags = FontFlags::NON_SYMBOLIC;
+        if face.is_monospaced() {
+            flags |= FontFlags::FIXED_PITCH;
+        }
+        if face.is_italic() {
+            flags |= FontFlags::ITALIC;
+        }
+        let bbox = face.global_bounding_box();
+        let ascender = to_pdf(face.ascender() as f32);
+        let mut font_descriptor = self.pdf.font_descriptor(descriptor);
+        font_descriptor
+            .name(Name(base_font.as_bytes()))
+            .flags(flags)
+            .bbox(Rect::new(
+                to_pdf(bbox.x_min as f32),
+                to_pdf(bbox.y_min as f32),
+                to_pdf(bbox.x_max as f32),
+                to_pdf(bbox.y_max as f32),
+            ))
+            .italic_angle(face.italic_angle())
+            .ascent(ascender)
+            .descent(to_pdf(face.descender() as f32))
+            .cap_height(
+                face.capital_height()
+                    .map_or(ascender, |height| to_pdf(height as f32)),
+            )
+            .stem_v(80.0);
+        if cff {
+            font_descriptor.font_file3(file);
+        } else {
+            font_descriptor.font_file2(file);
+        }
+        font_descriptor.finish();
+
+        let data = deflate(&subset);
+        let mut stream = self.pdf.stream(file, &data);
+        stream.filter(Filter::FlateDecode);
+        if cff {
+            stream.pair(Name(b"Subtype"), Name(b"OpenType"));
+        }
+        stream.finish();
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), IDENTITY);
+        for (&glyph, &ch) in &self.embedded_fonts[index].glyphs {
+            cmap.pair(glyph, ch);
+        }
+        self.pdf.cmap(cmap_ref, &cmap.finish());
+        Ok(())
+    }
+
+    fn finish(mut self, catalog: Ref, tree: Ref) -> Result<PdfOutput, String> {
+        for index in 0..self.embedded_fonts.len() {
+            self.write_font(index)?;
+        }
+        if let Some((_, id)) = &self.fallback_font {
+            self.pdf
+                .type1_font(*id)
+                .base_font(Name(b"Helvetica"))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        self.pdf.catalog(catalog).pages(tree);
+        self.pdf
+            .pages(tree)
+            .kids(self.pages.iter().map(|page| page.0))
+            .count(self.pages.len() as i32);
+        for &(page_ref, content, bounds) in &self.pages {
+            let mut page = self.pdf.page(page_ref);
+            page.media_box(Rect::new(
+                0.0,
+                0.0,
+                bounds.width.max(1.0) as f32,
+                bounds.height.max(1.0) as f32,
+            ))
+            .parent(tree)
+            .contents(content);
+            let mut resources = page.resources();
+            for kind in [
+                ResourceKind::Font,
+                ResourceKind::XObject,
+                ResourceKind::ExtGState,
+                ResourceKind::Shading,
+            ] {
+                let mut entries = self.resources.iter().filter(|r| r.0 == kind).peekable();
+                if entries.peek().is_none() {
+                    continue;
+                }
+                let mut dict = match kind {
+                    ResourceKind::Font => resources.fonts(),
+                    ResourceKind::XObject => resources.x_objects(),
+                    ResourceKind::ExtGState => resources.ext_g_states(),
+                    ResourceKind::Shading => resources.shadings(),
+                };
+                for (_, name, id) in entries {
+                    dict.pair(Name(name.as_bytes()), *id);
+                }
+            }
+        }
+
+        Ok(PdfOutput {
+            bytes: self.pdf.finish(),
+            missing_fonts: self.missing_fonts.into_iter().collect(),
+            skipped_images: self.skipped_images,
+        })
+    }
+}
+
+/// Raw bytes of an image source. `None` for remote URLs, unreadable files
+/// and non-base64 data URLs, which are skipped.
+fn image_bytes(source: &ImageSource, node_id: &str) -> Result<Option<Vec<u8>>, String> {
+    let data_base64 = match source {
+        ImageSource::Asset { data_base64, .. } => data_base64.as_str(),
+        ImageSource::Url(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            return Ok(None)
+        }
+        ImageSource::Url(url) => match parse_data_url(url) {
+            Some((_, data)) => data,
+            None if url.starts_with("data:") => return Ok(None),
+            None => {
+                let path = Path::new(url.strip_prefix("file://").unwrap_or(url));
+                return Ok(fs::read(path).ok());
+            }
+        },
+    };
+    general_purpose::STANDARD
+        .decode(data_base64)
+        .map(Some)
+        .map_err(|e| format!("Failed to decode image {node_id}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_roots, render_pdf};
+    use crate::document::Document;
+    use crate::scene::{self, SceneOptions};
+    use crate::text_layout::FontBook;
+
+    const DOC: &str = r##"{
+        "version": 9, "rootId": "root", "activePageId": "p1",
+        "pages": [{ "id": "p1", "name": "One", "rootId": "root" },
+                  { "id": "p2", "name": "Two", "rootId": "second" }],
+        "nodes": {
+            "root": { "id": "root", "type": "frame", "position": { "x": 0, "y": 0 },
+                "size": { "width": 120, "height": 80 }, "children": ["card", "label"],
+                "fill": { "type": "solid", "value": "#ffffff" } },
+            "card": { "id": "card", "type": "rectangle", "position": { "x": 10, "y": 10 },
+                "size": { "width": 50, "height": 30 }, "cornerRadius": 6,
+                "fill": { "type": "gradient", "kind": "linear", "stops": ["#ff0000", "#00ff00", "#0000ff"] },
+                "effects": [{ "type": "drop", "x": 0, "y": 2, "blur": 8, "spread": 2,
+                    "color": "rgba(0, 0, 0, 0.5)", "opacity": 1 }] },
+            "label": { "id": "label", "type": "text", "position": { "x": 10, "y": 50 },
+                "size": { "width": 100, "height": 20 }, "text": "Hello",
+                "fontSize": 12, "fontFamily": "Inter", "fill": { "type": "solid", "value": "#333" } },
+            "second": { "id": "second", "type": "frame", "position": { "x": 0, "y": 0 },
+                "size": { "width": 200, "height": 100 }, "children": ["photo"] },
+            "photo": { "id": "photo", "type": "image", "position": { "x": 0, "y": 0 },
+                "size": { "width": 40, "height": 40 }, "image": { "src": "https://example.com/a.png" } }
+        }
+    }"##;
+
+    #[test]
+    fn writes_a_page_per_document_page() {
+        let doc = Document::parse(DOC).unwrap();
+        let pages: Vec<_> = export_roots(&doc, None, None)
+            .unwrap()
+            .iter()
+            .map(|id| scene::build_for_node(&doc, id, SceneOptions::default()).unwrap())
+            .collect();
+        let output = render_pdf(&pages, &mut FontBook::with_faces(Vec::new()), 2.0).unwrap();
+
+        assert!(output.bytes.starts_with(b"%PDF-"));
+        let text = String::from_utf8_lossy(&output.bytes);
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("/MediaBox [0 0 200 100]"));
+        assert!(text.contains("/Helvetica"));
+        assert!(text.contains("/ShadingType 2"));
+        assert!(text.contains("/SMask"));
+        assert_eq!(output.missing_fonts, vec!["Inter".to_string()]);
+        assert_eq!(output.skipped_images, vec!["photo".to_string()]);
+    }
+
+    #[test]
+    fn frames_and_page_selections_pick_roots() {
+        let doc = Document::parse(DOC).unwrap();
+        let pages = ["p2".to_string(), "p1".to_string()];
+        assert_eq!(
+            export_roots(&doc, Some(&pages), None).unwrap(),
+            vec!["second", "root"]
+        );
+        let frames = ["card".to_string()];
+        assert_eq!(
+            export_roots(&doc, Some(&pages), Some(&frames)).unwrap(),
+            vec!["card"]
+        );
+        let missing = ["nope".to_string()];
+        assert_eq!(
+            export_roots(&doc, Some(&missing), None).unwrap_err(),
+            "page_not_found: nope"
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Smaller PNGs for icon and UI-asset exports: lossless color type and bit
+//! depth reductions in the spirit of oxipng, trial filtering, and optional
+//! lossy palette quantization with Floyd–Steinberg dithering.
+
+use std::collections::HashMap;
+
+/// Deflate level used when optimizing and no explicit level is given.
+const OPTIMIZE_LEVEL: u8 = 9;
+const MAX_PALETTE: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+pub struct PngSettings {
+    /// 0 (store) to 9 (smallest); the encoder's balanced default when unset
+    pub compression_level: Option<u8>,
+    /// Fixed row filter; when unset, adaptive, or a few candidates when optimizing
+    pub filter: Option<png::Filter>,
+    /// Try lossless reductions and filters, keeping the smallest result
+    pub optimize: bool,
+    pub quantize: Option<Quantize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quantize {
+    pub max_colors: usize,
+    /// 0 (flat nearest colors) to 1 (full error diffusion)
+    pub dithering: f32,
+}
+
+/// Ancillary chunks written to every candidate.
+#[derive(Debug, Clone, Default)]
+pub struct PngMetadata {
+    pub icc: Option<Vec<u8>>,
+    /// The ICC profile is plain sRGB and may be swapped for the 13-byte
+    /// `sRGB` chunk when optimizing.
+    pub icc_is_srgb: bool,
+    pub pixel_dims: Option<png::PixelDimensions>,
+}
+
+pub fn parse_filter(name: &str) -> Result<png::Filter, String> {
+    match name {
+        "none" => Ok(png::Filter::NoFilter),
+        "sub" => Ok(png::Filter::Sub),
+        "up" => Ok(png::Filter::Up),
+        "average" => Ok(png::Filter::Avg),
+        "paeth" => Ok(png::Filter::Paeth),
+        "adaptive" => Ok(png::Filter::Adaptive),
+        "min-entropy" => Ok(png::Filter::MinEntropy),
+        other => Err(format!("unsupported_filter: {other}")),
+    }
+}
+
+/// Pixel data in one PNG color type, ready for the encoder.
+struct Raster {
+    color: png::ColorType,
+    depth: png::BitDepth,
+    data: Vec<u8>,
+    palette: Option<Vec<u8>>,
+    trns: Option<Vec<u8>>,
+}
+
+impl Raster {
+    fn direct(color: png::ColorType, data: Vec<u8>) -> Self {
+        Raster {
+            color,
+            depth: png::BitDepth::Eight,
+            data,
+            palette: None,
+            trns: None,
+        }
+    }
+}
+
+/// Encodes tightly packed RGBA8 pixels with `settings`, returning the
+/// smallest file among the candidates tried.
+pub fn encode(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    settings: &PngSettings,
+    metadata: &PngMetadata,
+) -> Result<Vec<u8>, String> {
+    let mut metadata = metadata.clone();
+    let mut use_srgb_chunk = false;
+    if settings.optimize && metadata.icc_is_srgb {
+        metadata.icc = None;
+        use_srgb_chunk = true;
+    }
+    // Grayscale needs a gray ICC profile; only reduce when none is embedded.
+    let allow_gray = metadata.icc.is_none();
+
+    let rasters = match settings.quantize {
+        Some(quantize) => {
+            let (palette, indices) = quantize_palette(rgba, width as usize, quantize);
+            vec![indexed_raster(palette, indices, width as usize)]
+        }
+        None if settings.optimize => lossless_candidates(rgba, width as usize, allow_gray),
+        None => vec![Raster::direct(png::ColorType::Rgba, rgba.to_vec())],
+    };
+
+    let compression = match settings.compression_level {
+        Some(0) => Some(png::DeflateCompression::NoCompression),
+        Some(level) => Some(png::DeflateCompression::Level(level.min(9))),
+        None if settings.optimize => Some(png::DeflateCompression::Level(OPTIMIZE_LEVEL)),
+        None => None,
+    };
+    let filters = match settings.filter {
+        Some(filter) => vec![filter],
+        None if settings.optimize => vec![
+            png::Filter::NoFilter,
+            png::Filter::Adaptive,
+            png::Filter::MinEntropy,
+        ],
+        None => vec![png::Filter::Adaptive],
+    };
+
+    let mut best: Option<Vec<u8>> = None;
+    for raster in &rasters {
+        for &filter in &filters {
+            let bytes = write_png(
+                raster,
+                width,
+                height,
+                compression,
+                filter,
+                &metadata,
+                use_srgb_chunk,
+            )?;
+            if best.as_ref().is_none_or(|best| bytes.len() < best.len()) {
+                best = Some(bytes);
+            }
+        }
+    }
+    best.ok_or_else(|| "Failed to encode PNG".to_string())
+}
+
+fn write_png(
+    raster: &Raster,
+    width: u32,
+    height: u32,
+    compression: Option<png::DeflateCompression>,
+    filter: png::Filter,
+    metadata: &PngMetadata,
+    use_srgb_chunk: bool,
+) -> Result<Vec<u8>, String> {
+    let mut info = png::Info::with_size(width, height);
+    info.color_type = raster.color;
+    info.bit_depth = raster.depth;
+    info.palette = raster.palette.as_deref().map(Into::into);
+    info.trns = raster.trns.as_deref().map(Into::into);
+    info.icc_profile = metadata.icc.as_deref().map(Into::into);
+    info.pixel_dims = metadata.pixel_dims;
+    if use_srgb_chunk {
+        info.srgb = Some(png::SrgbRenderingIntent::Perceptual);
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::with_info(&mut bytes, info)
+        .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+    if let Some(compression) = compression {
+        encoder.set_deflate_compression(compression);
+    }
+    encoder.set_filter(filter);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+    writer
+        .write_image_data(&raster.data)
+        .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+    Ok(bytes)
+}
+
+/// The narrowest lossless truecolor/gray form, plus a palette form when the
+/// image has few enough colors. Which wins depends on the content, so both
+/// are encoded.
+fn lossless_candidates(rgba: &[u8], width: usize, allow_gray: bool) -> Vec<Raster> {
+    let pixels = || rgba.chunks_exact(4);
+    let opaque = pixels().all(|p| p[3] == 255);
+    let gray = allow_gray && pixels().all(|p| p[0] == p[1] && p[1] == p[2]);
+
+    let direct = match (gray, opaque) {
+        (true, true) => Raster::direct(png::ColorType::Grayscale, pixels().map(|p| p[0]).collect()),
+        (true, false) => Raster::direct(
+            png::ColorType::GrayscaleAlpha,
+            pixels().flat_map(|p| [p[0], p[3]]).collect(),
+        ),
+        (false, true) => Raster::direct(
+            png::ColorType::Rgb,
+            pixels().flat_map(|p| [p[0], p[1], p[2]]).collect(),
+        ),
+        (false, false) => Raster::direct(png::ColorType::Rgba, rgba.to_vec()),
+    };
+
+    let mut candidates = vec![direct];
+    if let Some((palette, indices)) = exact_palette(rgba) {
+        candidates.push(indexed_raster(palette, indices, width));
+    }
+    candidates
+}
+
+/// Palette and per-pixel indices when the image has at most 256 colors.
+fn exact_palette(rgba: &[u8]) -> Option<(Vec<[u8; 4]>, Vec<u8>)> {
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        let index = match lookup.get(&color) {
+            Some(&index) => index,
+            None => {
+                if palette.len() == MAX_PALETTE {
+                    return None;
+                }
+                let index = palette.len() as u8;
+                lookup.insert(color, index);
+                palette.push(color);
+                index
+            }
+        };
+        indices.push(index);
+    }
+    Some((palette, indices))
+}
+
+/// Packs a palette image at the smallest bit depth that fits, with
+/// translucent entries first so the `tRNS` chunk can stop early.
+fn indexed_raster(palette: Vec<[u8; 4]>, indices: Vec<u8>, width: usize) -> Raster {
+    let mut order: Vec<usize> = (0..palette.len()).collect();
+    order.sort_by_key(|&index| palette[index][3] == 255);
+    let mut remap = vec![0u8; palette.len()];
+    for (new_index, &old_index) in order.iter().enumerate() {
+        remap[old_index] = new_index as u8;
+    }
+    let palette: Vec<[u8; 4]> = order.iter().map(|&index| palette[index]).collect();
+
+    let bits = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+    let per_byte = 8 / bits;
+    let row_bytes = width.div_ceil(per_byte);
+    let rows = indices.len() / width.max(1);
+    let mut data = vec![0u8; row_bytes * rows];
+    for (i, &index) in indices.iter().enumerate() {
+        let (y, x) = (i / width, i % width);
+        let shift = 8 - bits * (x % per_byte + 1);
+        data[y * row_bytes + x / per_byte] |= remap[index as usize] << shift;
+    }
+
+    let translucent = palette.iter().take_while(|color| color[3] < 255).count();
+    Raster {
+        color: png::ColorType::Indexed,
+        depth: match bits {
+            1 => png::BitDepth::One,
+            2 => png::BitDepth::Two,
+            4 => png::BitDepth::Four,
+            _ => png::BitDepth::Eight,
+        },
+        data,
+        palette: Some(palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect()),
+        trns: (translucent > 0).then(|| palette[..translucent].iter().map(|c| c[3]).collect()),
+    }
+}
+
+/// Reduces to at most `max_colors` with median cut, then maps pixels to the
+/// palette, diffusing the error when dithering is on.
+fn quantize_palette(rgba: &[u8], width: usize, settings: Quantize) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let max_colors = settings.max_colors.clamp(2, MAX_PALETTE);
+    let mut histogram: HashMap<[u8; 4], u32> = HashMap::new();
+    for pixel in rgba.chunks_exact(4) {
+        *histogram
+            .entry([pixel[0], pixel[1], pixel[2], pixel[3]])
+            .or_insert(0) += 1;
+    }
+    if histogram.len() <= max_colors {
+        if let Some(exact) = exact_palette(rgba) {
+            return exact;
+        }
+    }
+
+    let palette = median_cut(histogram.into_iter().collect(), max_colors);
+    let dithering = settings.dithering.clamp(0.0, 1.0);
+    let indices = if dithering > 0.0 {
+        dither(rgba, width, &palette, dithering)
+    } else {
+        let mut cache: HashMap<[u8; 4], u8> = HashMap::new();
+        rgba.chunks_exact(4)
+            .map(|p| {
+                let color = [p[0], p[1], p[2], p[3]];
+                *cache
+                    .entry(color)
+                    .or_insert_with(|| nearest(&palette, [p[0], p[1], p[2], p[3]].map(f32::from)))
+            })
+            .collect()
+    };
+    (palette, indices)
+}
+
+pub(crate) fn median_cut(colors: Vec<([u8; 4], u32)>, max_colors: usize) -> Vec<[u8; 4]> {
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Split the box whose widest channel spans the most pixels.
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                let population: u64 = colors.iter().map(|(_, count)| *count as u64).sum();
+                (index, channel, range as u64 * population)
+            })
+            .max_by_key(|(_, _, score)| *score)
+            .filter(|(_, _, score)| *score > 0)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total: u64 = colors.iter().map(|(_, count)| *count as u64).sum();
+        let mut running = 0;
+        let mut split = 1;
+        for (i, (_, count)) in colors.iter().enumerate() {
+            running += *count as u64;
+            if running * 2 >= total {
+                split = (i + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let total: f64 = colors.iter().map(|(_, count)| *count as f64).sum();
+            let mut sum = [0f64; 4];
+            for (color, count) in colors {
+                for channel in 0..4 {
+                    sum[channel] += color[channel] as f64 * *count as f64;
+                }
+            }
+            sum.map(|value| (value / total).round() as u8)
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, 0), |(min, max), (color, _)| {
+                (min.min(color[channel]), max.max(color[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+pub(crate) fn nearest(palette: &[[u8; 4]], color: [f32; 4]) -> u8 {
+    let mut best = 0;
+    let mut best_distance = f32::MAX;
+    for (index, entry) in palette.iter().enumerate() {
+        let distance: f32 = (0..4)
+            .map(|channel| {
+                let delta = color[channel] - entry[channel] as f32;
+                delta * delta
+            })
+            .sum();
+        if distance < best_distance {
+            best_distance = distance;
+            best = index;
+        }
+    }
+    best as u8
+}
+
+/// Floyd–Steinberg error diffusion, with the error scaled by `strength`.
+pub(crate) fn dither(rgba: &[u8], width: usize, palette: &[[u8; 4]], strength: f32) -> Vec<u8> {
+    let height = rgba.len() / 4 / width.max(1);
+    let mut current = vec![[0f32; 4]; width + 2];
+    let mut next = vec![[0f32; 4]; width + 2];
+    let mut indices = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let offset = (y * width + x) * 4;
+            let mut color = [0f32; 4];
+            for channel in 0..4 {
+                color[channel] = (rgba[offset + channel] as f32
+                    + current[x + 1][channel] * strength)
+                    .clamp(0.0, 255.0);
+            }
+            let index = nearest(palette, color);
+            indices.push(index);
+            let chosen = palette[index as usize];
+            for channel in 0..4 {
+                let error = color[channel] - chosen[channel] as f32;
+                current[x + 2][channel] += error * 7.0 / 16.0;
+                next[x][channel] += error * 3.0 / 16.0;
+                next[x + 1][channel] += error * 5.0 / 16.0;
+                next[x + 2][channel] += error / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.iter_mut().for_each(|error| *error = [0.0; 4]);
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, PngMetadata, PngSettings, Quantize};
+    use std::collections::HashSet;
+
+    fn decode(bytes: &[u8]) -> (png::ColorType, png::BitDepth, Vec<u8>) {
+        let image = image::load_from_memory(bytes).unwrap().to_rgba8();
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        (info.color_type, info.bit_depth, image.into_raw())
+    }
+
+    #[test]
+    fn two_color_icon_becomes_one_bit_palette() {
+        let mut rgba = Vec::new();
+        for i in 0..64 * 64 {
+            rgba.extend_from_slice(if (i * 7919) % 13 < 5 {
+                &[255, 0, 0, 255]
+            } else {
+                &[0, 0, 0, 0]
+            });
+        }
+        let settings = PngSettings {
+            optimize: true,
+            ..Default::default()
+        };
+        let bytes = encode(&rgba, 64, 64, &settings, &PngMetadata::default()).unwrap();
+        let (color, depth, decoded) = decode(&bytes);
+        assert_eq!(color, png::ColorType::Indexed);
+        assert_eq!(depth, png::BitDepth::One);
+        assert_eq!(decoded, rgba);
+    }
+
+    #[test]
+    fn opaque_gray_drops_color_channels() {
+        let rgba: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v, 255]).collect();
+        let settings = PngSettings {
+            optimize: true,
+            ..Default::default()
+        };
+        let bytes = encode(&rgba, 16, 16, &settings, &PngMetadata::default()).unwrap();
+        let (color, _, decoded) = decode(&bytes);
+        assert!(matches!(
+            color,
+            png::ColorType::Grayscale | png::ColorType::Indexed
+        ));
+        assert_eq!(decoded, rgba);
+    }
+
+    #[test]
+    fn quantize_limits_colors() {
+        let rgba: Vec<u8> = (0..32 * 32)
+            .flat_map(|i| [(i % 32 * 8) as u8, (i / 32 * 8) as u8, 128, 255])
+            .collect();
+        for dithering in [0.0, 1.0] {
+            let settings = PngSettings {
+                quantize: Some(Quantize {
+                    max_colors: 16,
+                    dithering,
+                }),
+                ..Default::default()
+            };
+            let bytes = encode(&rgba, 32, 32, &settings, &PngMetadata::default()).unwrap();
+            let (color, depth, decoded) = decode(&bytes);
+            assert_eq!(color, png::ColorType::Indexed);
+            assert_eq!(depth, png::BitDepth::Four);
+            let colors: HashSet<&[u8]> = decoded.chunks_exact(4).collect();
+            assert!(colors.len() <= 16);
+        }
+    }
+}
@@ -0,0 +1,1666 @@
+//! Native port of the frontend draw-list builder (src/render/draw-list) for
+//! exporters that run without the canvas renderer. It resolves world bounds
+//! and auto layout, styles, variables and auto shadows, and flattens a node
+//! subtree into the same drawing commands the canvas renderer consumes.
+
+use crate::document::{
+    BooleanData, Color, Document, Layout, Node, NodeType, Shadow, ShadowEffect, VectorData,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::f64::consts::PI;
+
+const DEFAULT_FALLBACK_COLOR: &str = "#000000";
+const DEFAULT_FONT_SIZE: f64 = 14.0;
+const DEFAULT_FONT_FAMILY: &str = "sans-serif";
+/// Control point distance for a quarter ellipse drawn with one cubic.
+const KAPPA: f64 = 0.552_284_749_830_793_4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Solid(String),
+    Gradient(Box<Gradient>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientKind {
+    Linear,
+    Radial,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientStop {
+    pub offset: f64,
+    pub color: String,
+}
+
+/// Handles and radii are fractions of the shape's box when within 0..=1 and
+/// pixels otherwise, as the canvas renderer resolves them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<GradientStop>,
+    pub from: Option<(f64, f64)>,
+    pub to: Option<(f64, f64)>,
+    pub center: Option<(f64, f64)>,
+    pub radius: Option<f64>,
+    pub inner_radius: Option<f64>,
+    pub angle: Option<f64>,
+}
+
+/// Gradient endpoints in the same space as the bounds they were resolved
+/// against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientGeometry {
+    Linear {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    },
+    Radial {
+        cx: f64,
+        cy: f64,
+        inner: f64,
+        outer: f64,
+    },
+}
+
+fn resolve_coord(value: f64, size: f64) -> f64 {
+    if (0.0..=1.0).contains(&value) {
+        value * size
+    } else {
+        value
+    }
+}
+
+impl Gradient {
+    /// Where the canvas renderer would place this gradient on `bounds`;
+    /// `None` for an empty box, which paints the first stop instead.
+    pub fn geometry(&self, bounds: Bounds) -> Option<GradientGeometry> {
+        let Bounds {
+            x,
+            y,
+            width,
+            height,
+        } = bounds;
+        if width == 0.0 && height == 0.0 {
+            return None;
+        }
+        let point =
+            |(px, py): (f64, f64)| (x + resolve_coord(px, width), y + resolve_coord(py, height));
+        match self.kind {
+            GradientKind::Linear => {
+                let ((x1, y1), (x2, y2)) = match (self.from, self.to) {
+                    (Some(from), Some(to)) => (point(from), point(to)),
+                    _ => {
+                        let (cx, cy) = (x + width / 2.0, y + height / 2.0);
+                        match self.angle {
+                            Some(angle) => {
+                                let angle = if !angle.is_finite() {
+                                    0.0
+                                } else if angle.abs() > PI * 2.0 {
+                                    angle.to_radians()
+                                } else {
+                                    angle
+                                };
+                                let half = width.max(height) * 0.5;
+                                let (dx, dy) = (angle.cos() * half, angle.sin() * half);
+                                ((cx - dx, cy - dy), (cx + dx, cy + dy))
+                            }
+                            None => ((x, cy), (x + width, cy)),
+                        }
+                    }
+                };
+                let (x2, y2) = if x1 == x2 && y1 == y2 {
+                    (x2 + 0.0001, y2 + 0.0001)
+                } else {
+                    (x2, y2)
+                };
+                Some(GradientGeometry::Linear { x1, y1, x2, y2 })
+            }
+            GradientKind::Radial => {
+                let (cx, cy) = point(self.center.unwrap_or((0.5, 0.5)));
+                let base = width.min(height) * 0.5;
+                let length = |value: Option<f64>, size: f64, fallback: f64| match value {
+                    Some(value) if value.is_finite() => resolve_coord(value, size),
+                    _ => fallback,
+                };
+                let outer = length(self.radius, base, base).max(0.0001);
+                let inner = length(self.inner_radius, outer, 0.0).clamp(0.0, outer);
+                Some(GradientGeometry::Radial {
+                    cx,
+                    cy,
+                    inner,
+                    outer,
+                })
+            }
+        }
+    }
+}
+
+/// An sRGB color with straight alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CssColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub alpha: f64,
+}
+
+impl CssColor {
+    pub fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Parses the CSS colors documents use: hex, rgb()/rgba(), hsl()/hsla()
+/// and common keywords.
+pub fn parse_color(value: &str) -> Option<CssColor> {
+    let value = value.trim().to_ascii_lowercase();
+    let rgb = |r, g, b| CssColor {
+        r,
+        g,
+        b,
+        alpha: 1.0,
+    };
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?;
+        let (channels, alpha) = match digits.len() {
+            3 | 4 => (
+                digits.iter().map(|d| d * 17).collect::<Vec<u8>>(),
+                digits.get(3).map(|a| (a * 17) as f64 / 255.0),
+            ),
+            6 | 8 => (
+                digits.chunks(2).map(|p| p[0] * 16 + p[1]).collect(),
+                digits
+                    .get(6)
+                    .map(|_| (digits[6] * 16 + digits[7]) as f64 / 255.0),
+            ),
+            _ => return None,
+        };
+        return Some(CssColor {
+            alpha: alpha.unwrap_or(1.0),
+            ..rgb(channels[0], channels[1], channels[2])
+        });
+    }
+    if let Some((name, args)) = value.strip_suffix(')').and_then(|v| v.split_once('(')) {
+        let parts: Vec<&str> = args
+            .split([',', ' ', '/'])
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let component = |part: &str, scale: f64| -> Option<f64> {
+            match part.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok().map(|v| v / 100.0 * scale),
+                None => part.trim_end_matches("deg").parse().ok(),
+            }
+        };
+        let alpha = match parts.get(3) {
+            Some(part) => component(part, 1.0)?.clamp(0.0, 1.0),
+            None => 1.0,
+        };
+        let byte = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+        let color = match name.trim() {
+            "rgb" | "rgba" => rgb(
+                byte(component(parts[0], 255.0)?),
+                byte(component(parts[1], 255.0)?),
+                byte(component(parts[2], 255.0)?),
+            ),
+            "hsl" | "hsla" => {
+                let h = component(parts[0], 360.0)?.rem_euclid(360.0) / 360.0;
+                let s = component(parts[1], 1.0)?.clamp(0.0, 1.0);
+                let l = component(parts[2], 1.0)?.clamp(0.0, 1.0);
+                let q = if l < 0.5 {
+                    l * (1.0 + s)
+                } else {
+                    l + s - l * s
+                };
+                let p = 2.0 * l - q;
+                let channel = |t: f64| {
+                    let t = t.rem_euclid(1.0);
+                    let v = if t < 1.0 / 6.0 {
+                        p + (q - p) * 6.0 * t
+                    } else if t < 0.5 {
+                        q
+                    } else if t < 2.0 / 3.0 {
+                        p + (q - p) * (2.0 / 3.0 - t) * 6.0
+                    } else {
+                        p
+                    };
+                    byte(v * 255.0)
+                };
+                rgb(channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0))
+            }
+            _ => return None,
+        };
+        return Some(CssColor { alpha, ..color });
+    }
+    let named = match value.as_str() {
+        "transparent" => {
+            return Some(CssColor {
+                alpha: 0.0,
+                ..rgb(0, 0, 0)
+            })
+        }
+        "black" => rgb(0, 0, 0),
+        "white" => rgb(255, 255, 255),
+        "red" => rgb(255, 0, 0),
+        "green" => rgb(0, 128, 0),
+        "lime" => rgb(0, 255, 0),
+        "blue" => rgb(0, 0, 255),
+        "yellow" => rgb(255, 255, 0),
+        "orange" => rgb(255, 165, 0),
+        "purple" => rgb(128, 0, 128),
+        "gray" | "grey" => rgb(128, 128, 128),
+        "silver" => rgb(192, 192, 192),
+        _ => return None,
+    };
+    Some(named)
+}
+
+/// Gaussian standard deviation for a shadow. Canvas `shadowBlur`, used for
+/// plain drop shadows on rects and ellipses, is twice the deviation; the
+/// raster path's CSS `blur()` is the deviation itself.
+pub fn shadow_sigma(effect: &Effect, shape: Option<&Shape>) -> f64 {
+    let native = !effect.inner
+        && effect.spread.abs() <= 0.001
+        && effect.blend_mode == "normal"
+        && matches!(shape, Some(Shape::Rect { .. } | Shape::Ellipse));
+    if native {
+        effect.blur / 2.0
+    } else {
+        effect.blur
+    }
+}
+
+/// A normalized, enabled drop or inner shadow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effect {
+    pub inner: bool,
+    pub x: f64,
+    pub y: f64,
+    pub blur: f64,
+    pub spread: f64,
+    pub color: String,
+    pub opacity: f64,
+    pub blend_mode: String,
+}
+
+/// Extra clipping applied to a path, in the same local coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathClip {
+    pub d: String,
+    pub even_odd: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect {
+        corner_radius: f64,
+    },
+    Ellipse,
+    /// `d` is relative to the command's x/y.
+    Path {
+        d: String,
+        even_odd: bool,
+        clips: Vec<PathClip>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeCommand {
+    pub node_id: String,
+    pub bounds: Bounds,
+    pub shape: Shape,
+    pub fill: Option<Paint>,
+    pub stroke: Option<Paint>,
+    pub stroke_width: f64,
+    pub opacity: Option<f64>,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextCommand {
+    pub node_id: String,
+    pub bounds: Bounds,
+    pub text: String,
+    pub font_family: String,
+    pub font_weight: u16,
+    pub font_size: f64,
+    pub text_align: String,
+    pub line_height_px: Option<f64>,
+    pub letter_spacing_px: f64,
+    pub resize_mode: String,
+    pub fill: String,
+    pub opacity: Option<f64>,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    /// Inline document asset
+    Asset {
+        id: String,
+        mime: String,
+        data_base64: String,
+    },
+    /// `image.src` as stored: a data URL, http(s) URL or file path
+    Url(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageCommand {
+    pub node_id: String,
+    pub bounds: Bounds,
+    pub source: ImageSource,
+    /// Alpha mask from background removal
+    pub mask: Option<ImageSource>,
+    pub opacity: Option<f64>,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Shape(ShapeCommand),
+    Text(TextCommand),
+    Image(ImageCommand),
+    /// Clips everything up to the matching `Restore`.
+    Clip {
+        bounds: Bounds,
+        corner_radius: Option<f64>,
+    },
+    Restore,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SceneOptions {
+    pub include_frame_fill: bool,
+    pub clip_to_bounds: bool,
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        SceneOptions {
+            include_frame_fill: true,
+            clip_to_bounds: false,
+        }
+    }
+}
+
+/// The node an export starts from: `node_id` when given, otherwise the root
+/// of `page_id` or of the active page.
+pub fn export_root(
+    doc: &Document,
+    node_id: Option<&str>,
+    page_id: Option<&str>,
+) -> Result<String, String> {
+    if let Some(node_id) = node_id {
+        return Ok(node_id.to_string());
+    }
+    let page_id = page_id.unwrap_or(&doc.active_page_id);
+    doc.page(page_id)
+        .map(|page| page.root_id.clone())
+        .ok_or_else(|| format!("page_not_found: {page_id}"))
+}
+
+/// Drawing commands for `node_id` and its subtree, with the node's top-left
+/// corner at the origin. Returns the node's size alongside.
+pub fn build_for_node(
+    doc: &Document,
+    node_id: &str,
+    options: SceneOptions,
+) -> Result<(Bounds, Vec<DrawCommand>), String> {
+    let node = doc
+        .nodes
+        .get(node_id)
+        .ok_or_else(|| format!("node_not_found: {node_id}"))?;
+    let bounds_map = world_bounds(doc);
+    let base = *bounds_map
+        .get(node_id)
+        .ok_or_else(|| format!("node_not_on_page: {node_id}"))?;
+
+    let mut builder = Builder {
+        doc,
+        bounds: &bounds_map,
+        base,
+        root_id: node_id,
+        include_root_fill: options.include_frame_fill,
+        commands: Vec::new(),
+        depth: 0,
+    };
+    if options.clip_to_bounds {
+        builder.commands.push(DrawCommand::Clip {
+            bounds: Bounds {
+                x: 0.0,
+                y: 0.0,
+                ..base
+            },
+            corner_radius: (node.kind == NodeType::Frame)
+                .then_some(node.corner_radius)
+                .flatten(),
+        });
+    }
+    builder.node(node);
+    if options.clip_to_bounds {
+        builder.commands.push(DrawCommand::Restore);
+    }
+    Ok((base, builder.commands))
+}
+
+/// World bounds for every node reachable from a page root, following
+/// `buildWorldBoundsMap` including auto layout sizing.
+pub fn world_bounds(doc: &Document) -> HashMap<String, Bounds> {
+    let mut bounds_map = HashMap::new();
+    let mut layout_sizes: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut stack: Vec<(String, f64, f64)> = Vec::new();
+    let mut roots: Vec<&str> = doc.pages.iter().map(|page| page.root_id.as_str()).collect();
+    roots.push(&doc.root_id);
+    for root_id in roots {
+        if let Some(root) = doc.nodes.get(root_id) {
+            stack.push((root_id.to_string(), root.position.x, root.position.y));
+        }
+    }
+
+    while let Some((id, world_x, world_y)) = stack.pop() {
+        if bounds_map.contains_key(&id) {
+            continue;
+        }
+        let Some(node) = doc.nodes.get(&id) else {
+            continue;
+        };
+        let children: Vec<&Node> = node
+            .children
+            .iter()
+            .flatten()
+            .filter_map(|child_id| doc.nodes.get(child_id))
+            .collect();
+        let local = if node.kind == NodeType::Group {
+            group_local_bounds(&children)
+        } else {
+            Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: node.size.width,
+                height: node.size.height,
+            }
+        };
+        let (width, height) = layout_sizes
+            .get(&id)
+            .copied()
+            .unwrap_or((local.width, local.height));
+        bounds_map.insert(
+            id.clone(),
+            Bounds {
+                x: world_x + local.x,
+                y: world_y + local.y,
+                width,
+                height,
+            },
+        );
+
+        let positions = match &node.layout {
+            Some(layout) if layout.kind == "auto" => auto_layout_positions(node, layout, &children),
+            _ => HashMap::new(),
+        };
+        for child in children {
+            let (x, y) = match positions.get(&child.id) {
+                Some(position) => {
+                    layout_sizes.insert(child.id.clone(), (position.width, position.height));
+                    (position.x, position.y)
+                }
+                None => (child.position.x, child.position.y),
+            };
+            stack.push((child.id.clone(), world_x + x, world_y + y));
+        }
+    }
+    bounds_map
+}
+
+fn group_local_bounds(children: &[&Node]) -> Bounds {
+    let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for child in children.iter().filter(|child| child.is_visible()) {
+        min_x = min_x.min(child.position.x);
+        min_y = min_y.min(child.position.y);
+        max_x = max_x.max(child.position.x + child.size.width);
+        max_y = max_y.max(child.position.y + child.size.height);
+    }
+    if !min_x.is_finite() {
+        return Bounds::default();
+    }
+    Bounds {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+    }
+}
+
+/// `computeAutoLayoutPositions` for a parent with auto layout.
+fn auto_layout_positions(
+    parent: &Node,
+    layout: &Layout,
+    children: &[&Node],
+) -> HashMap<String, Bounds> {
+    let padding = layout.padding;
+    let is_row = layout.direction == "row";
+    let cross_alignment = layout.cross_alignment.as_deref().unwrap_or("center");
+    let (main_start, cross_start) = if is_row {
+        (padding.left, padding.top)
+    } else {
+        (padding.top, padding.left)
+    };
+    let available_main = if is_row {
+        parent.size.width - padding.left - padding.right
+    } else {
+        parent.size.height - padding.top - padding.bottom
+    };
+    let available_cross = if is_row {
+        parent.size.height - padding.top - padding.bottom
+    } else {
+        parent.size.width - padding.left - padding.right
+    };
+
+    let sizing = |child: &Node| -> (String, String) {
+        let sizing = child.layout_sizing.as_ref();
+        let horizontal = sizing
+            .map_or("fixed", |s| s.horizontal.as_str())
+            .to_string();
+        let vertical = sizing.map_or("fixed", |s| s.vertical.as_str()).to_string();
+        if is_row {
+            (horizontal, vertical)
+        } else {
+            (vertical, horizontal)
+        }
+    };
+    let main_size = |child: &Node| {
+        if is_row {
+            child.size.width
+        } else {
+            child.size.height
+        }
+    };
+    let cross_size = |child: &Node| {
+        if is_row {
+            child.size.height
+        } else {
+            child.size.width
+        }
+    };
+
+    let visible: Vec<&Node> = children
+        .iter()
+        .copied()
+        .filter(|c| c.is_visible())
+        .collect();
+    let gaps = if visible.len() > 1 {
+        layout.gap * (visible.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let fill_count = visible.iter().filter(|c| sizing(c).0 == "fill").count();
+    let fixed_main: f64 = visible
+        .iter()
+        .filter(|c| sizing(c).0 != "fill")
+        .map(|c| main_size(c))
+        .sum();
+    let remaining = (available_main - fixed_main - gaps).max(0.0);
+    let fill_size = if fill_count > 0 {
+        (remaining / fill_count as f64).max(1.0)
+    } else {
+        0.0
+    };
+    let final_main = |child: &Node| {
+        if sizing(child).0 == "fill" {
+            fill_size
+        } else {
+            main_size(child)
+        }
+    };
+    let total: f64 = visible.iter().map(|c| final_main(c)).sum::<f64>() + gaps;
+
+    let mut main_offset = match layout.alignment.as_str() {
+        "center" => main_start + (available_main - total) / 2.0,
+        "end" => main_start + (available_main - total),
+        _ => main_start,
+    }
+    .max(main_start);
+
+    let mut positions = HashMap::new();
+    for child in children {
+        if !child.is_visible() {
+            continue;
+        }
+        let main = final_main(child);
+        let cross_sizing = sizing(child).1;
+        let stretch = cross_alignment == "stretch" && cross_sizing != "fixed";
+        let cross = if cross_sizing == "fill" || stretch {
+            available_cross.max(1.0)
+        } else {
+            cross_size(child)
+        };
+        let cross_offset = match cross_alignment {
+            "start" | "stretch" => cross_start,
+            "end" => cross_start + (available_cross - cross),
+            _ => cross_start + (available_cross - cross) / 2.0,
+        }
+        .max(cross_start);
+
+        let bounds = if is_row {
+            Bounds {
+                x: main_offset,
+                y: cross_offset,
+                width: main,
+                height: cross,
+            }
+        } else {
+            Bounds {
+                x: cross_offset,
+                y: main_offset,
+                width: cross,
+                height: main,
+            }
+        };
+        positions.insert(child.id.clone(), bounds);
+        main_offset += main + layout.gap;
+    }
+    positions
+}
+
+/// Nesting limit, in case a malformed document has a cycle.
+const MAX_DEPTH: usize = 256;
+
+struct Builder<'a> {
+    doc: &'a Document,
+    bounds: &'a HashMap<String, Bounds>,
+    base: Bounds,
+    root_id: &'a str,
+    include_root_fill: bool,
+    commands: Vec<DrawCommand>,
+    depth: usize,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, node: &Node) {
+        let Some(world) = self.bounds.get(&node.id) else {
+            return;
+        };
+        if !node.is_visible() || self.depth > MAX_DEPTH {
+            return;
+        }
+        let bounds = Bounds {
+            x: world.x - self.base.x,
+            y: world.y - self.base.y,
+            ..*world
+        };
+        let style = resolve_style(self.doc, node);
+        let effects = compile_effects(self.doc, node, style.effects.as_deref());
+        let fill = style.fill.as_ref().and_then(color_to_paint);
+        let stroke = node.stroke.as_ref().and_then(|s| color_to_paint(&s.color));
+        let stroke_width = node.stroke.as_ref().map_or(0.0, |s| s.width);
+
+        match node.kind {
+            NodeType::Frame => {
+                let overflow = shadow_overflow(node);
+                let clip_frame = overflow == "clipped";
+                let clip_children = overflow == "clip-content-only";
+                let clip = DrawCommand::Clip {
+                    bounds,
+                    corner_radius: node.corner_radius,
+                };
+                if clip_frame {
+                    self.commands.push(clip.clone());
+                }
+                if fill.is_some() && (node.id != self.root_id || self.include_root_fill) {
+                    self.commands.push(DrawCommand::Shape(ShapeCommand {
+                        node_id: node.id.clone(),
+                        bounds,
+                        shape: Shape::Rect {
+                            corner_radius: node.corner_radius.unwrap_or(0.0),
+                        },
+                        fill,
+                        stroke: None,
+                        stroke_width: 0.0,
+                        opacity: node.opacity,
+                        effects,
+                    }));
+                }
+                let has_children = node.children.as_ref().is_some_and(|c| !c.is_empty());
+                if has_children {
+                    if !clip_frame && clip_children {
+                        self.commands.push(clip);
+                    }
+                    self.children(node);
+                    if !clip_frame && clip_children {
+                        self.commands.push(DrawCommand::Restore);
+                    }
+                }
+                if clip_frame {
+                    self.commands.push(DrawCommand::Restore);
+                }
+            }
+            NodeType::Group | NodeType::ComponentInstance => self.children(node),
+            NodeType::Rectangle | NodeType::Ellipse => {
+                if fill.is_some() || stroke.is_some() {
+                    let shape = if node.kind == NodeType::Ellipse {
+                        Shape::Ellipse
+                    } else {
+                        Shape::Rect {
+                            corner_radius: node.corner_radius.unwrap_or(0.0),
+                        }
+                    };
+                    self.commands.push(DrawCommand::Shape(ShapeCommand {
+                        node_id: node.id.clone(),
+                        bounds,
+                        shape,
+                        fill,
+                        stroke,
+                        stroke_width,
+                        opacity: node.opacity,
+                        effects,
+                    }));
+                }
+            }
+            NodeType::Text => {
+                self.commands.push(DrawCommand::Text(TextCommand {
+                    node_id: node.id.clone(),
+                    bounds,
+                    text: node.text.clone().unwrap_or_default(),
+                    font_family: style
+                        .font_family
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_FONT_FAMILY.to_string()),
+                    font_weight: font_weight(style.font_weight.as_deref()),
+                    font_size: style.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+                    text_align: style.text_align.clone().unwrap_or_else(|| "left".into()),
+                    line_height_px: style.line_height_px,
+                    letter_spacing_px: style.letter_spacing_px.unwrap_or(0.0),
+                    resize_mode: style
+                        .text_resize_mode
+                        .clone()
+                        .unwrap_or_else(|| "auto-width".into()),
+                    fill: text_color(fill.as_ref()),
+                    opacity: node.opacity,
+                    effects,
+                }));
+            }
+            NodeType::Image => {
+                if let Some(source) = image_source(self.doc, node) {
+                    let mask = node
+                        .image
+                        .as_ref()
+                        .and_then(|image| image.mask_asset_id.as_deref())
+                        .and_then(|id| asset_source(self.doc, id));
+                    self.commands.push(DrawCommand::Image(ImageCommand {
+                        node_id: node.id.clone(),
+                        bounds,
+                        source,
+                        mask,
+                        opacity: node.opacity,
+                        effects,
+                    }));
+                }
+            }
+            NodeType::Boolean => {
+                let data = node.boolean_data.as_ref();
+                if let Some(isolated) = data
+                    .and_then(|data| data.isolation_operand_id.as_deref())
+                    .and_then(|id| self.doc.nodes.get(id))
+                {
+                    self.depth += 1;
+                    self.node(isolated);
+                    self.depth -= 1;
+                    return;
+                }
+                let fallback = node
+                    .children
+                    .as_ref()
+                    .and_then(|children| children.first())
+                    .and_then(|id| self.doc.nodes.get(id));
+                let fill = fill.or_else(|| {
+                    fallback
+                        .and_then(|operand| operand.fill.as_ref())
+                        .and_then(color_to_paint)
+                });
+                let stroke_source = node
+                    .stroke
+                    .as_ref()
+                    .or_else(|| fallback.and_then(|operand| operand.stroke.as_ref()));
+                let stroke = stroke_source.and_then(|s| color_to_paint(&s.color));
+                let stroke_width = stroke_source.map_or(0.0, |s| s.width);
+                if let (Some(shape), true) = (
+                    data.and_then(|data| boolean_shape(self.doc, node, data)),
+                    fill.is_some() || stroke.is_some(),
+                ) {
+                    self.commands.push(DrawCommand::Shape(ShapeCommand {
+                        node_id: node.id.clone(),
+                        bounds,
+                        shape,
+                        fill,
+                        stroke,
+                        stroke_width,
+                        opacity: node.opacity,
+                        effects,
+                    }));
+                }
+            }
+            NodeType::Path => {
+                let allow_fill = node.vector.as_ref().is_none_or(|vector| vector.closed);
+                let fill = if allow_fill { fill } else { None };
+                match node_path_data(node) {
+                    Some((d, even_odd)) if fill.is_some() || stroke.is_some() => {
+                        self.commands.push(DrawCommand::Shape(ShapeCommand {
+                            node_id: node.id.clone(),
+                            bounds,
+                            shape: Shape::Path {
+                                d,
+                                even_odd,
+                                clips: Vec::new(),
+                            },
+                            fill,
+                            stroke,
+                            stroke_width,
+                            opacity: node.opacity,
+                            effects,
+                        }));
+                    }
+                    _ if fill.is_some() => {
+                        self.commands.push(DrawCommand::Shape(ShapeCommand {
+                            node_id: node.id.clone(),
+                            bounds,
+                            shape: Shape::Rect { corner_radius: 0.0 },
+                            fill,
+                            stroke: None,
+                            stroke_width: 0.0,
+                            opacity: node.opacity,
+                            effects,
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn children(&mut self, node: &Node) {
+        self.depth += 1;
+        for child_id in node.children.iter().flatten() {
+            if let Some(child) = self.doc.nodes.get(child_id) {
+                self.node(child);
+            }
+        }
+        self.depth -= 1;
+    }
+}
+
+fn shadow_overflow(node: &Node) -> &str {
+    match node.shadow_overflow.as_deref() {
+        Some(mode @ ("visible" | "clipped" | "clip-content-only")) => mode,
+        _ if node.kind == NodeType::Frame && node.clip_content == Some(true) => "clipped",
+        _ => "visible",
+    }
+}
+
+pub fn font_weight(weight: Option<&str>) -> u16 {
+    match weight {
+        Some("bold") => 700,
+        Some(value) => value.parse().unwrap_or(400),
+        None => 400,
+    }
+}
+
+/// The font-family lists and weights the document's text renders with.
+pub fn used_fonts(doc: &Document) -> BTreeSet<(String, u16)> {
+    doc.nodes
+        .values()
+        .filter(|node| node.kind == NodeType::Text)
+        .map(|node| {
+            let style = resolve_style(doc, node);
+            let family = style
+                .font_family
+                .unwrap_or_else(|| DEFAULT_FONT_FAMILY.to_string());
+            (family, font_weight(style.font_weight.as_deref()))
+        })
+        .collect()
+}
+
+/// Node props after paint, text and effect styles (and their variable
+/// bindings) are applied, as `resolveNodeStyleProps` does.
+struct ResolvedStyle {
+    fill: Option<Color>,
+    font_size: Option<f64>,
+    font_family: Option<String>,
+    font_weight: Option<String>,
+    text_align: Option<String>,
+    line_height_px: Option<f64>,
+    letter_spacing_px: Option<f64>,
+    text_resize_mode: Option<String>,
+    effects: Option<Vec<ShadowEffect>>,
+}
+
+fn resolve_style(doc: &Document, node: &Node) -> ResolvedStyle {
+    let paint_fill = node
+        .fill_style_id
+        .as_ref()
+        .and_then(|id| doc.styles.paint.get(id))
+        .map(|style| {
+            let token = style
+                .bindings
+                .as_ref()
+                .and_then(|b| b.get("solidValueTokenId"))
+                .and_then(|id| doc.token_string(id));
+            match (&style.paint, token) {
+                (Color::Solid { .. }, Some(value)) if !value.is_empty() => Color::Solid { value },
+                (paint, _) => paint.clone(),
+            }
+        });
+
+    let text_style = node
+        .text_style_id
+        .as_ref()
+        .and_then(|id| doc.styles.text.get(id));
+    let binding = |key: &str| {
+        text_style
+            .and_then(|style| style.bindings.as_ref())
+            .and_then(|b| b.get(key))
+    };
+    let style_fill = text_style.map(|style| {
+        binding("fillTokenId")
+            .and_then(|id| doc.token_string(id))
+            .filter(|value| !value.is_empty())
+            .map(|value| Color::Solid { value })
+            .or_else(|| style.fill.clone())
+    });
+    let number =
+        |key: &str, value: Option<f64>| binding(key).and_then(|id| doc.token_number(id)).or(value);
+    let font_family = text_style.and_then(|style| {
+        binding("fontFamilyTokenId")
+            .and_then(|id| doc.token_string(id))
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| style.font_family.clone())
+    });
+    let font_weight = text_style.and_then(|style| {
+        binding("fontWeightTokenId")
+            .and_then(|id| doc.token_string(id))
+            .filter(|value| matches!(value.as_str(), "normal" | "bold" | "500" | "600"))
+            .or_else(|| style.font_weight.clone())
+    });
+
+    // A text style replaces the node's fill even when it has none of its own.
+    let fill = paint_fill.or(match style_fill {
+        Some(fill) => fill,
+        None => node.fill.clone(),
+    });
+    let effects = node
+        .effect_style_id
+        .as_ref()
+        .and_then(|id| doc.styles.effect.get(id))
+        .map(|style| style.effects.clone())
+        .or_else(|| node.effects.clone());
+
+    ResolvedStyle {
+        fill,
+        font_size: text_style
+            .and_then(|s| number("fontSizeTokenId", s.font_size))
+            .or(node.font_size),
+        font_family: font_family.or_else(|| node.font_family.clone()),
+        font_weight: font_weight.or_else(|| node.font_weight.clone()),
+        text_align: text_style
+            .and_then(|s| s.text_align.clone())
+            .or_else(|| node.text_align.clone()),
+        line_height_px: text_style
+            .and_then(|s| number("lineHeightTokenId", s.line_height_px))
+            .or(node.line_height_px),
+        letter_spacing_px: text_style
+            .and_then(|s| number("letterSpacingTokenId", s.letter_spacing_px))
+            .or(node.letter_spacing_px),
+        text_resize_mode: text_style
+            .and_then(|s| s.text_resize_mode.clone())
+            .or_else(|| node.text_resize_mode.clone()),
+        effects,
+    }
+}
+
+fn clamp(value: f64, min: f64, max: f64) -> f64 {
+    if !value.is_finite() {
+        return min;
+    }
+    value.clamp(min, max)
+}
+
+fn round1(value: f64) -> f64 {
+    if !value.is_finite() {
+        return 0.0;
+    }
+    (value * 10.0).round() / 10.0
+}
+
+fn is_blend_mode(value: &str) -> bool {
+    matches!(value, "normal" | "multiply" | "screen" | "overlay")
+}
+
+fn normalize_shadow(inner: bool, shadow: &Shadow) -> Option<Effect> {
+    if shadow.enabled == Some(false) {
+        return None;
+    }
+    Some(Effect {
+        inner,
+        x: shadow.x,
+        y: shadow.y,
+        blur: if shadow.blur.is_finite() {
+            shadow.blur.max(0.0)
+        } else {
+            0.0
+        },
+        spread: if shadow.spread.is_finite() {
+            shadow.spread
+        } else {
+            0.0
+        },
+        color: shadow.color.clone(),
+        opacity: if shadow.opacity.is_finite() {
+            shadow.opacity.clamp(0.0, 1.0)
+        } else {
+            1.0
+        },
+        blend_mode: shadow
+            .blend_mode
+            .clone()
+            .unwrap_or_else(|| "normal".to_string()),
+    })
+}
+
+/// `compileShadowEffects`: auto shadows become an ambient and a key drop
+/// shadow; disabled effects are dropped.
+fn compile_effects(doc: &Document, node: &Node, effects: Option<&[ShadowEffect]>) -> Vec<Effect> {
+    let mut compiled = Vec::new();
+    for effect in effects.unwrap_or_default() {
+        match effect {
+            ShadowEffect::Drop(shadow) => compiled.extend(normalize_shadow(false, shadow)),
+            ShadowEffect::Inner(shadow) => compiled.extend(normalize_shadow(true, shadow)),
+            ShadowEffect::Auto(auto) => {
+                let bound = |key: &str| -> Option<Value> {
+                    let name = auto.bindings.as_ref()?.get(key)?;
+                    match doc.token_value(name) {
+                        Some(value @ (Value::String(_) | Value::Number(_))) => Some(value.clone()),
+                        _ => node.effect_variables.as_ref()?.get(name).cloned(),
+                    }
+                };
+                let number = |key: &str, fallback: f64, min: f64, max: f64| {
+                    let raw = match bound(key) {
+                        Some(Value::Number(value)) => value.as_f64(),
+                        Some(Value::String(value)) => value.trim().parse::<f64>().ok(),
+                        _ => None,
+                    };
+                    clamp(raw.filter(|v| v.is_finite()).unwrap_or(fallback), min, max)
+                };
+                let color = match bound("color") {
+                    Some(Value::String(value)) if !value.trim().is_empty() => value,
+                    _ if !auto.color.is_empty() => auto.color.clone(),
+                    _ => DEFAULT_FALLBACK_COLOR.to_string(),
+                };
+                let blend_mode = match bound("blendMode") {
+                    Some(Value::String(value)) if is_blend_mode(&value) => value,
+                    _ => auto
+                        .blend_mode
+                        .clone()
+                        .unwrap_or_else(|| "normal".to_string()),
+                };
+
+                let n = number("elevation", auto.elevation, 0.0, 24.0) / 24.0;
+                let s = number("softness", auto.softness, 0.0, 100.0) / 100.0;
+                let theta = number("angle", auto.angle, -360.0, 360.0) * PI / 180.0;
+                let d = number("distance", auto.distance, 0.0, 80.0);
+                let opacity = number("opacity", auto.opacity, 0.0, 1.0);
+
+                let key_x = round1(theta.cos() * d * (0.45 + 0.55 * n));
+                let key_y = round1(theta.sin() * d * (0.45 + 0.55 * n));
+                let key_blur = round1((2.0 + 22.0 * n) * (0.5 + 1.5 * s));
+                let shadow = |x, y, blur, spread, opacity| Shadow {
+                    x,
+                    y,
+                    blur,
+                    spread,
+                    color: color.clone(),
+                    opacity,
+                    blend_mode: Some(blend_mode.clone()),
+                    enabled: auto.enabled,
+                };
+                let ambient = shadow(
+                    round1(key_x * 0.18),
+                    round1((key_y * 0.35 + 1.5 * n).max(0.0)),
+                    round1(key_blur * 1.45),
+                    round1((0.5 - s) * 2.0),
+                    clamp(opacity * 0.34, 0.0, 1.0),
+                );
+                let key = shadow(
+                    key_x,
+                    key_y,
+                    key_blur,
+                    round1((0.25 - s) * 6.0),
+                    clamp(opacity * 0.68, 0.0, 1.0),
+                );
+                compiled.extend(normalize_shadow(false, &ambient));
+                compiled.extend(normalize_shadow(false, &key));
+            }
+        }
+    }
+    compiled
+}
+
+pub fn color_to_paint(color: &Color) -> Option<Paint> {
+    match color {
+        Color::Solid { value } => Some(Paint::Solid(value.clone())),
+        Color::Gradient(fields) => Some(
+            gradient_paint(fields)
+                .map(|gradient| Paint::Gradient(Box::new(gradient)))
+                .unwrap_or_else(|| Paint::Solid(DEFAULT_FALLBACK_COLOR.to_string())),
+        ),
+    }
+}
+
+fn text_color(paint: Option<&Paint>) -> String {
+    match paint {
+        Some(Paint::Solid(color)) => color.clone(),
+        Some(Paint::Gradient(gradient)) => gradient
+            .stops
+            .first()
+            .map(|stop| stop.color.clone())
+            .unwrap_or_else(|| DEFAULT_FALLBACK_COLOR.to_string()),
+        None => DEFAULT_FALLBACK_COLOR.to_string(),
+    }
+}
+
+fn gradient_paint(fields: &Map<String, Value>) -> Option<Gradient> {
+    let stops = normalize_stops(fields.get("stops")?);
+    if stops.is_empty() {
+        return None;
+    }
+    let first = |keys: &[&str]| keys.iter().find_map(|key| fields.get(*key));
+    let kind = first(&["kind", "gradientType", "mode", "style"])
+        .and_then(Value::as_str)
+        .map(str::to_lowercase);
+    let number = |key: &str| fields.get(key).and_then(Value::as_f64);
+    Some(Gradient {
+        kind: if kind.as_deref() == Some("radial") {
+            GradientKind::Radial
+        } else {
+            GradientKind::Linear
+        },
+        stops,
+        from: first(&["from", "start", "p0", "handleStart"]).and_then(read_point),
+        to: first(&["to", "end", "p1", "handleEnd"]).and_then(read_point),
+        center: first(&["center", "mid"]).and_then(read_point),
+        radius: number("radius"),
+        inner_radius: number("innerRadius"),
+        angle: number("angle"),
+    })
+}
+
+fn read_point(value: &Value) -> Option<(f64, f64)> {
+    match value {
+        Value::Array(items) => Some((items.first()?.as_f64()?, items.get(1)?.as_f64()?)),
+        Value::Object(fields) => Some((fields.get("x")?.as_f64()?, fields.get("y")?.as_f64()?)),
+        _ => None,
+    }
+}
+
+fn normalize_stops(raw: &Value) -> Vec<GradientStop> {
+    let Some(items) = raw.as_array().filter(|items| !items.is_empty()) else {
+        return Vec::new();
+    };
+    let total = items.len();
+    let mut stops: Vec<GradientStop> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, stop)| {
+            let color = stop_color(stop)?;
+            let offset = stop_offset(stop).unwrap_or(if total <= 1 {
+                0.0
+            } else {
+                index as f64 / (total - 1) as f64
+            });
+            Some(GradientStop {
+                offset: if offset.is_finite() {
+                    offset.clamp(0.0, 1.0)
+                } else {
+                    0.0
+                },
+                color,
+            })
+        })
+        .collect();
+    stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    if stops.len() == 1 {
+        let color = stops[0].color.clone();
+        stops.push(GradientStop { offset: 1.0, color });
+    }
+    stops
+}
+
+fn stop_offset(stop: &Value) -> Option<f64> {
+    let raw = match stop {
+        Value::Number(value) => value.as_f64(),
+        Value::Object(fields) => ["position", "offset", "t", "stop", "at"]
+            .iter()
+            .find_map(|key| fields.get(*key).and_then(Value::as_f64)),
+        _ => None,
+    }?;
+    if !raw.is_finite() {
+        return Some(0.0);
+    }
+    Some(if raw > 1.0 && raw <= 100.0 {
+        raw / 100.0
+    } else {
+        raw
+    })
+}
+
+fn stop_color(stop: &Value) -> Option<String> {
+    let fields = match stop {
+        Value::String(color) => return Some(color.clone()),
+        Value::Object(fields) => fields,
+        _ => return None,
+    };
+    for key in ["color", "value", "hex"] {
+        if let Some(color) = fields.get(key).and_then(Value::as_str) {
+            return Some(color.to_string());
+        }
+    }
+    for key in ["color", "fill"] {
+        if let Some(nested) = fields
+            .get(key)
+            .filter(|v| v.is_object())
+            .and_then(stop_color)
+        {
+            return Some(nested);
+        }
+    }
+    let number = |key: &str| fields.get(key).and_then(Value::as_f64);
+    let (r, g, b, a) = match (number("r"), number("g"), number("b")) {
+        (Some(r), Some(g), Some(b)) => (r, g, b, number("a")),
+        _ => (
+            number("red")?,
+            number("green")?,
+            number("blue")?,
+            number("alpha").or(number("opacity")),
+        ),
+    };
+    let byte = |value: f64| {
+        if !value.is_finite() {
+            return 0;
+        }
+        let scaled = if value <= 1.0 { value * 255.0 } else { value };
+        scaled.round().clamp(0.0, 255.0) as u8
+    };
+    let alpha = a
+        .filter(|a| a.is_finite())
+        .map_or(1.0, |a| a.clamp(0.0, 1.0));
+    Some(format!(
+        "rgba({}, {}, {}, {})",
+        byte(r),
+        byte(g),
+        byte(b),
+        alpha
+    ))
+}
+
+fn asset_source(doc: &Document, asset_id: &str) -> Option<ImageSource> {
+    let asset = doc.assets.get(asset_id)?;
+    let data = asset.data_base64.as_ref()?;
+    if asset.kind != "image" || asset.mime.is_empty() {
+        return None;
+    }
+    Some(ImageSource::Asset {
+        id: asset_id.to_string(),
+        mime: asset.mime.clone(),
+        data_base64: data.clone(),
+    })
+}
+
+fn image_source(doc: &Document, node: &Node) -> Option<ImageSource> {
+    let image = node.image.as_ref()?;
+    image
+        .asset_id
+        .as_deref()
+        .and_then(|id| asset_source(doc, id))
+        .or_else(|| {
+            image
+                .src
+                .clone()
+                .filter(|src| !src.is_empty())
+                .map(ImageSource::Url)
+        })
+}
+
+/// `getNodePathData` for non-boolean nodes: the path and whether it fills
+/// even-odd.
+pub fn node_path_data(node: &Node) -> Option<(String, bool)> {
+    if let Some(vector) = node.vector.as_ref().filter(|v| !v.points.is_empty()) {
+        let d = vector_path_data(vector, 0.0, 0.0);
+        return (!d.is_empty()).then_some((d, false));
+    }
+    match &node.path {
+        Some(Value::String(d)) => return Some((d.clone(), false)),
+        Some(Value::Object(fields)) => {
+            let d = ["d", "path", "data"]
+                .iter()
+                .find_map(|key| fields.get(*key).and_then(Value::as_str))
+                .filter(|d| !d.is_empty());
+            if let Some(d) = d {
+                let even_odd = fields.get("fillRule").and_then(Value::as_str) == Some("evenodd");
+                return Some((d.to_string(), even_odd));
+            }
+        }
+        _ => {}
+    }
+    node.path_data
+        .clone()
+        .or_else(|| node.d.clone())
+        .map(|d| (d, false))
+}
+
+/// `buildVectorPathData`, offset by (dx, dy).
+pub fn vector_path_data(vector: &VectorData, dx: f64, dy: f64) -> String {
+    if vector.points.is_empty() {
+        return String::new();
+    }
+    let points: HashMap<&str, &crate::document::VectorPoint> =
+        vector.points.iter().map(|p| (p.id.as_str(), p)).collect();
+    let segments: Vec<(&str, &str, String)> = if vector.segments.is_empty() {
+        let ids: Vec<&str> = vector.points.iter().map(|p| p.id.as_str()).collect();
+        let mut segments: Vec<(&str, &str, String)> = ids
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| (pair[0], pair[1], format!("seg_{i}")))
+            .collect();
+        if vector.closed && ids.len() >= 2 {
+            let id = format!("seg_{}", segments.len());
+            segments.push((ids[ids.len() - 1], ids[0], id));
+        }
+        segments
+    } else {
+        vector
+            .segments
+            .iter()
+            .map(|s| (s.from_id.as_str(), s.to_id.as_str(), s.id.clone()))
+            .collect()
+    };
+    if segments.is_empty() {
+        return String::new();
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    let mut visited: Vec<&str> = Vec::new();
+    let mut current = Some(&segments[0]);
+    let mut guard = 0;
+    while let Some((from_id, to_id, id)) = current {
+        guard += 1;
+        if guard > segments.len() * 2 || visited.contains(&id.as_str()) {
+            break;
+        }
+        visited.push(id);
+        let (Some(from), Some(to)) = (points.get(from_id), points.get(to_id)) else {
+            break;
+        };
+        if parts.is_empty() {
+            parts.push(format!("M {} {}", num(from.x + dx), num(from.y + dy)));
+        }
+        if from.out_handle.is_some() || to.in_handle.is_some() {
+            let c1 = from.out_handle.map_or((from.x, from.y), |h| (h.x, h.y));
+            let c2 = to.in_handle.map_or((to.x, to.y), |h| (h.x, h.y));
+            parts.push(format!(
+                "C {} {} {} {} {} {}",
+                num(c1.0 + dx),
+                num(c1.1 + dy),
+                num(c2.0 + dx),
+                num(c2.1 + dy),
+                num(to.x + dx),
+                num(to.y + dy)
+            ));
+        } else {
+            parts.push(format!("L {} {}", num(to.x + dx), num(to.y + dy)));
+        }
+        current = segments
+            .iter()
+            .find(|(from, _, id)| from == to_id && !visited.contains(&id.as_str()));
+    }
+    if vector.closed {
+        parts.push("Z".to_string());
+    }
+    parts.join(" ")
+}
+
+/// Compact number formatting for path data and markup.
+pub fn num(value: f64) -> String {
+    if !value.is_finite() {
+        return "0".to_string();
+    }
+    let rounded = (value * 1000.0).round() / 1000.0;
+    if rounded == 0.0 {
+        return "0".to_string();
+    }
+    let text = format!("{rounded:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+pub fn rect_path(x: f64, y: f64, width: f64, height: f64) -> String {
+    format!(
+        "M {} {} H {} V {} H {} Z",
+        num(x),
+        num(y),
+        num(x + width),
+        num(y + height),
+        num(x)
+    )
+}
+
+pub fn ellipse_path(x: f64, y: f64, width: f64, height: f64) -> String {
+    let (rx, ry) = (width / 2.0, height / 2.0);
+    let (cx, cy) = (x + rx, y + ry);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    format!(
+        "M {} {} C {} {} {} {} {} {} C {} {} {} {} {} {} C {} {} {} {} {} {} C {} {} {} {} {} {} Z",
+        num(cx + rx),
+        num(cy),
+        num(cx + rx),
+        num(cy + ky),
+        num(cx + kx),
+        num(cy + ry),
+        num(cx),
+        num(cy + ry),
+        num(cx - kx),
+        num(cy + ry),
+        num(cx - rx),
+        num(cy + ky),
+        num(cx - rx),
+        num(cy),
+        num(cx - rx),
+        num(cy - ky),
+        num(cx - kx),
+        num(cy - ry),
+        num(cx),
+        num(cy - ry),
+        num(cx + kx),
+        num(cy - ry),
+        num(cx + rx),
+        num(cy - ky),
+        num(cx + rx),
+        num(cy),
+    )
+}
+
+/// The boolean engine runs in the webview, so booleans are rebuilt from
+/// their operands with fill rules and clips: union fills all operands,
+/// exclude fills them even-odd, intersect clips the first operand by the
+/// rest and subtract clips it by the area outside each of the rest. Operand
+/// geometry matches the engine's (corner radii are ignored).
+fn boolean_shape(doc: &Document, node: &Node, data: &BooleanData) -> Option<Shape> {
+    let operand_ids = if data.operand_ids.is_empty() {
+        node.children.clone().unwrap_or_default()
+    } else {
+        data.operand_ids.clone()
+    };
+    if operand_ids.len() < 2 {
+        return None;
+    }
+    let mut operands = Vec::with_capacity(operand_ids.len());
+    let mut extent = Bounds {
+        x: 0.0,
+        y: 0.0,
+        width: node.size.width,
+        height: node.size.height,
+    };
+    for id in &operand_ids {
+        let operand = doc.nodes.get(id)?;
+        let (x, y) = (operand.position.x, operand.position.y);
+        let (width, height) = (operand.size.width, operand.size.height);
+        let d = match operand.kind {
+            NodeType::Rectangle if width > 0.0 && height > 0.0 => rect_path(x, y, width, height),
+            NodeType::Ellipse if width > 0.0 && height > 0.0 => ellipse_path(x, y, width, height),
+            NodeType::Path => {
+                let vector = operand.vector.as_ref().filter(|v| v.points.len() >= 3)?;
+                vector_path_data(vector, x, y)
+            }
+            _ => return None,
+        };
+        let right = (extent.x + extent.width).max(x + width);
+        let bottom = (extent.y + extent.height).max(y + height);
+        extent.x = extent.x.min(x);
+        extent.y = extent.y.min(y);
+        extent.width = right - extent.x;
+        extent.height = bottom - extent.y;
+        operands.push(d);
+    }
+
+    let shape = match data.op.as_str() {
+        "union" | "exclude" => Shape::Path {
+            d: operands.join(" "),
+            even_odd: data.op == "exclude",
+            clips: Vec::new(),
+        },
+        "intersect" | "subtract" => {
+            let margin = extent.width.max(extent.height) + 100.0;
+            let outside = rect_path(
+                extent.x - margin,
+                extent.y - margin,
+                extent.width + margin * 2.0,
+                extent.height + margin * 2.0,
+            );
+            let subtract = data.op == "subtract";
+            Shape::Path {
+                d: operands[0].clone(),
+                even_odd: false,
+                clips: operands[1..]
+                    .iter()
+                    .map(|d| PathClip {
+                        d: if subtract {
+                            format!("{outside} {d}")
+                        } else {
+                            d.clone()
+                        },
+                        even_odd: subtract,
+                    })
+                    .collect(),
+            }
+        }
+        _ => return None,
+    };
+    Some(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_for_node, num, DrawCommand, Paint, SceneOptions, Shape};
+    use crate::document::Document;
+
+    fn doc(nodes: &str) -> Document {
+        Document::parse(&format!(
+            r#"{{
+                "version": 9, "rootId": "root", "activePageId": "p",
+                "pages": [{{ "id": "p", "name": "Page", "rootId": "root" }}],
+                "nodes": {nodes}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn auto_layout_positions_children() {
+        let doc = doc(r##"{
+                "root": { "id": "root", "type": "frame", "position": { "x": 0, "y": 0 },
+                    "size": { "width": 200, "height": 100 }, "children": ["row"] },
+                "row": { "id": "row", "type": "frame", "position": { "x": 10, "y": 10 },
+                    "size": { "width": 100, "height": 40 }, "children": ["a", "b"],
+                    "layout": { "type": "auto", "direction": "row", "gap": 4, "alignment": "start",
+                        "padding": { "top": 2, "right": 2, "bottom": 2, "left": 2 } } },
+                "a": { "id": "a", "type": "rectangle", "position": { "x": 50, "y": 50 },
+                    "size": { "width": 20, "height": 10 }, "fill": { "type": "solid", "value": "#f00" } },
+                "b": { "id": "b", "type": "ellipse", "position": { "x": 0, "y": 0 },
+                    "size": { "width": 10, "height": 10 }, "fill": { "type": "solid", "value": "#00f" },
+                    "layoutSizing": { "horizontal": "fill", "vertical": "fixed" } }
+            }"##);
+        let (bounds, commands) = build_for_node(&doc, "row", SceneOptions::default()).unwrap();
+        assert_eq!((bounds.width, bounds.height), (100.0, 40.0));
+        let shapes: Vec<_> = commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Shape(shape) => Some(shape),
+                _ => None,
+            })
+            .collect();
+        assert_eq!((shapes[0].bounds.x, shapes[0].bounds.y), (2.0, 15.0));
+        // b fills what's left of the row after a and the gap.
+        assert_eq!((shapes[1].bounds.x, shapes[1].bounds.width), (26.0, 72.0));
+        assert_eq!(shapes[1].fill, Some(Paint::Solid("#00f".into())));
+    }
+
+    #[test]
+    fn subtract_clips_outside_the_cutter() {
+        let doc = doc(r##"{
+                "root": { "id": "root", "type": "frame", "position": { "x": 0, "y": 0 },
+                    "size": { "width": 100, "height": 100 }, "children": ["bool"] },
+                "bool": { "id": "bool", "type": "boolean", "position": { "x": 0, "y": 0 },
+                    "size": { "width": 20, "height": 20 }, "children": ["a", "b"],
+                    "booleanData": { "op": "subtract", "operandIds": ["a", "b"], "status": "ok", "tolerance": 0.1 } },
+                "a": { "id": "a", "type": "rectangle", "position": { "x": 0, "y": 0 },
+                    "size": { "width": 20, "height": 20 }, "fill": { "type": "solid", "value": "#000" } },
+                "b": { "id": "b", "type": "ellipse", "position": { "x": 5, "y": 5 },
+                    "size": { "width": 10, "height": 10 } }
+            }"##);
+        let (_, commands) = build_for_node(&doc, "root", SceneOptions::default()).unwrap();
+        let Some(DrawCommand::Shape(shape)) = commands.first() else {
+            panic!("expected a shape");
+        };
+        let Shape::Path { d, clips, .. } = &shape.shape else {
+            panic!("expected a path");
+        };
+        assert!(d.starts_with("M 0 0 H 20"));
+        assert_eq!(clips.len(), 1);
+        assert!(clips[0].even_odd);
+        assert_eq!(shape.fill, Some(Paint::Solid("#000".into())));
+    }
+
+    #[test]
+    fn numbers_are_compact() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(-0.0004), "0");
+        assert_eq!(num(2.50049), "2.5");
+    }
+}
@@ -0,0 +1,788 @@
+//! SVG export straight from a `.galileo` document. Shapes, booleans and
+//! clips stay vector, text stays editable `<text>` or becomes outlines, and
+//! shadows become SVG filters. Images are embedded as data URLs or written
+//! next to the SVG and linked.
+
+use crate::document::DocumentSource;
+use crate::scene::{
+    self, num, parse_color, Bounds, DrawCommand, Effect, GradientGeometry, ImageCommand,
+    ImageSource, Paint, SceneOptions, Shape, ShapeCommand, TextCommand,
+};
+use crate::text_layout::{layout_text, FontBook, TextLayoutInput};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    #[default]
+    Embed,
+    /// Write images to `<name>_assets/` beside the SVG
+    Link,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextMode {
+    #[default]
+    Text,
+    /// Glyph outlines, for viewers without the fonts
+    Outlines,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSvgArgs {
+    #[serde(flatten)]
+    pub source: DocumentSource,
+    /// Node to export; defaults to the page's root frame
+    pub node_id: Option<String>,
+    /// Page whose root is exported; defaults to the active page
+    pub page_id: Option<String>,
+    #[serde(default)]
+    pub images: ImageMode,
+    #[serde(default)]
+    pub text: TextMode,
+    /// Where to write the SVG; the markup is returned when omitted
+    pub output_path: Option<String>,
+    pub include_frame_fill: Option<bool>,
+    pub clip_to_bounds: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSvgResult {
+    pub svg: Option<String>,
+    pub path: Option<String>,
+    pub width: f64,
+    pub height: f64,
+    /// Image files written for `images: "link"`
+    pub linked_assets: Vec<String>,
+}
+
+#[tauri::command(async)]
+pub fn export_svg(args: ExportSvgArgs) -> Result<ExportSvgResult, String> {
+    let doc = args.source.load()?;
+    let node_id = scene::export_root(&doc, args.node_id.as_deref(), args.page_id.as_deref())?;
+    let options = SceneOptions {
+        include_frame_fill: args.include_frame_fill.unwrap_or(true),
+        clip_to_bounds: args.clip_to_bounds.unwrap_or(false),
+    };
+    let (bounds, commands) = scene::build_for_node(&doc, &node_id, options)?;
+
+    let output_path = args.output_path.as_deref().map(PathBuf::from);
+    let mut images = match args.images {
+        ImageMode::Embed => ImageResolver::embed(),
+        ImageMode::Link => {
+            let path = output_path
+                .as_deref()
+                .ok_or("link_requires_output_path: linked images are written beside the SVG")?;
+            ImageResolver::link(path)
+        }
+    };
+    let mut fonts = FontBook::system();
+    let svg = render_svg(bounds, &commands, args.text, &mut images, &mut fonts)?;
+
+    let linked_assets = images
+        .linked
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    let (svg, path) = match output_path {
+        Some(path) => {
+            fs::write(&path, svg)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+            (None, Some(path.to_string_lossy().to_string()))
+        }
+        None => (Some(svg), None),
+    };
+    Ok(ExportSvgResult {
+        svg,
+        path,
+        width: bounds.width,
+        height: bounds.height,
+        linked_assets,
+    })
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn mime_extension(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/tiff" => "tif",
+        _ => "bin",
+    }
+}
+
+fn extension_mime(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Splits a base64 `data:` URL into its mime and payload.
+pub(crate) fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    Some((mime, data))
+}
+
+/// Turns image sources into hrefs, writing linked files once per source.
+pub struct ImageResolver {
+    assets_dir: Option<PathBuf>,
+    linked: Vec<PathBuf>,
+    hrefs: HashMap<String, Option<String>>,
+}
+
+impl ImageResolver {
+    pub fn embed() -> Self {
+        ImageResolver {
+            assets_dir: None,
+            linked: Vec::new(),
+            hrefs: HashMap::new(),
+        }
+    }
+
+    pub fn link(svg_path: &Path) -> Self {
+        let stem = svg_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("export");
+        ImageResolver {
+            assets_dir: Some(svg_path.with_file_name(format!("{stem}_assets"))),
+            ..Self::embed()
+        }
+    }
+
+    /// `None` when a local image can't be read; the image is left out
+    /// rather than failing the export.
+    fn href(&mut self, source: &ImageSource, node_id: &str) -> Result<Option<String>, String> {
+        let key = match source {
+            ImageSource::Asset { id, .. } => format!("asset:{id}"),
+            ImageSource::Url(url) if url.starts_with("data:") => format!("node:{node_id}"),
+            ImageSource::Url(url) => url.clone(),
+        };
+        if let Some(href) = self.hrefs.get(&key) {
+            return Ok(href.clone());
+        }
+        let href = self.resolve(source, node_id)?;
+        self.hrefs.insert(key, href.clone());
+        Ok(href)
+    }
+
+    fn resolve(&mut self, source: &ImageSource, node_id: &str) -> Result<Option<String>, String> {
+        let (name, mime, data_base64) = match source {
+            ImageSource::Asset {
+                id,
+                mime,
+                data_base64,
+            } => (id.as_str(), mime.as_str(), data_base64.as_str()),
+            ImageSource::Url(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                return Ok(Some(url.clone()))
+            }
+            ImageSource::Url(url) => match parse_data_url(url) {
+                Some((mime, data)) => (node_id, mime, data),
+                None if url.starts_with("data:") => return Ok(Some(url.clone())),
+                None => {
+                    let path = Path::new(url.strip_prefix("file://").unwrap_or(url));
+                    let Ok(bytes) = fs::read(path) else {
+                        return Ok(None);
+                    };
+                    let mime = extension_mime(path);
+                    return self.store(node_id, mime, bytes).map(Some);
+                }
+            },
+        };
+        if self.assets_dir.is_none() {
+            return Ok(Some(format!("data:{mime};base64,{data_base64}")));
+        }
+        let bytes = general_purpose::STANDARD
+            .decode(data_base64)
+            .map_err(|e| format!("Failed to decode image {name}: {e}"))?;
+        self.store(name, mime, bytes).map(Some)
+    }
+
+    fn store(&mut self, name: &str, mime: &str, bytes: Vec<u8>) -> Result<String, String> {
+        let Some(dir) = &self.assets_dir else {
+            let data = general_purpose::STANDARD.encode(&bytes);
+            return Ok(format!("data:{mime};base64,{data}"));
+        };
+        let safe: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let file_name = format!("{safe}.{}", mime_extension(mime));
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let path = dir.join(&file_name);
+        fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        self.linked.push(path);
+        let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        Ok(format!("{dir_name}/{file_name}"))
+    }
+}
+
+/// Serializes a draw list as a standalone SVG document.
+pub fn render_svg(
+    bounds: Bounds,
+    commands: &[DrawCommand],
+    text_mode: TextMode,
+    images: &mut ImageResolver,
+    fonts: &mut FontBook,
+) -> Result<String, String> {
+    let mut writer = SvgWriter {
+        defs: String::new(),
+        body: String::new(),
+        next_id: 0,
+        open_clips: 0,
+        text_mode,
+        images,
+        fonts,
+    };
+    for command in commands {
+        writer.command(command)?;
+    }
+    for _ in 0..writer.open_clips {
+        writer.body.push_str("</g>");
+    }
+
+    let (width, height) = (num(bounds.width), num(bounds.height));
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" \
+         xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    );
+    if !writer.defs.is_empty() {
+        let _ = writeln!(svg, "<defs>\n{}</defs>", writer.defs);
+    }
+    svg.push_str(&writer.body);
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+struct SvgWriter<'a> {
+    defs: String,
+    body: String,
+    next_id: usize,
+    open_clips: usize,
+    text_mode: TextMode,
+    images: &'a mut ImageResolver,
+    fonts: &'a mut FontBook,
+}
+
+impl SvgWriter<'_> {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    fn command(&mut self, command: &DrawCommand) -> Result<(), String> {
+        match command {
+            DrawCommand::Clip {
+                bounds,
+                corner_radius,
+            } => {
+                let id = self.id("clip");
+                let rect = rect_element(*bounds, corner_radius.unwrap_or(0.0), "");
+                let _ = writeln!(self.defs, "<clipPath id=\"{id}\">{rect}</clipPath>");
+                let _ = writeln!(self.body, "<g clip-path=\"url(#{id})\">");
+                self.open_clips += 1;
+            }
+            DrawCommand::Restore => {
+                if self.open_clips > 0 {
+                    self.open_clips -= 1;
+                    self.body.push_str("</g>\n");
+                }
+            }
+            DrawCommand::Shape(shape) => self.shape(shape),
+            DrawCommand::Text(text) => self.text(text),
+            DrawCommand::Image(image) => self.image(image)?,
+        }
+        Ok(())
+    }
+
+    /// Emits `element` with drop shadows behind it and inner shadows over
+    /// it. `silhouette` is the opaque shape the shadows are cast from.
+    fn with_effects(
+        &mut self,
+        bounds: Bounds,
+        shape: Option<&Shape>,
+        silhouette: &str,
+        element: &str,
+        opacity: Option<f64>,
+        effects: &[Effect],
+    ) {
+        let grouped = opacity.is_some_and(|o| o < 1.0);
+        if grouped {
+            let _ = writeln!(
+                self.body,
+                "<g opacity=\"{}\">",
+                num(opacity.unwrap_or(1.0).clamp(0.0, 1.0))
+            );
+        }
+        let silhouette_id = (!effects.is_empty()).then(|| {
+            let id = self.id("shape");
+            let _ = writeln!(self.defs, "<g id=\"{id}\">{silhouette}</g>");
+            id
+        });
+        let shadows = |writer: &mut Self, inner: bool| {
+            let Some(silhouette_id) = &silhouette_id else {
+                return;
+            };
+            for effect in effects.iter().filter(|e| e.inner == inner) {
+                let filter = writer.shadow_filter(bounds, shape, effect);
+                let blend = match effect.blend_mode.as_str() {
+                    "normal" => String::new(),
+                    mode => format!(" style=\"mix-blend-mode:{mode}\""),
+                };
+                let _ = writeln!(
+                    writer.body,
+                    "<use xlink:href=\"#{silhouette_id}\" filter=\"url(#{filter})\"{blend}/>"
+                );
+            }
+        };
+        shadows(self, false);
+        self.body.push_str(element);
+        self.body.push('\n');
+        shadows(self, true);
+        if grouped {
+            self.body.push_str("</g>\n");
+        }
+    }
+
+    fn shadow_filter(&mut self, bounds: Bounds, shape: Option<&Shape>, effect: &Effect) -> String {
+        let id = self.id("shadow");
+        let sigma = scene::shadow_sigma(effect, shape);
+        let pad = effect.spread.abs() + sigma * 3.0 + effect.x.abs().max(effect.y.abs()) + 1.0;
+        let color = parse_color(&effect.color);
+        let flood = color.map_or_else(|| escape(&effect.color), |c| c.hex());
+        let flood_opacity = effect.opacity * color.map_or(1.0, |c| c.alpha);
+
+        let mut filter = format!(
+            "<filter id=\"{id}\" filterUnits=\"userSpaceOnUse\" x=\"{}\" y=\"{}\" width=\"{}\" \
+             height=\"{}\" color-interpolation-filters=\"sRGB\">",
+            num(bounds.x - pad),
+            num(bounds.y - pad),
+            num(bounds.width + pad * 2.0),
+            num(bounds.height + pad * 2.0)
+        );
+        let mut input = "SourceAlpha";
+        if effect.spread.abs() > 0.001 {
+            let operator = if effect.spread > 0.0 {
+                "dilate"
+            } else {
+                "erode"
+            };
+            let _ = write!(
+                filter,
+                "<feMorphology in=\"SourceAlpha\" operator=\"{operator}\" radius=\"{}\" result=\"spread\"/>",
+                num(effect.spread.abs())
+            );
+            input = "spread";
+        }
+        if effect.inner {
+            let _ = write!(
+                filter,
+                "<feComponentTransfer in=\"{input}\"><feFuncA type=\"table\" tableValues=\"1 0\"/></feComponentTransfer>"
+            );
+            input = "";
+        }
+        let input_attr = if input.is_empty() {
+            String::new()
+        } else {
+            format!(" in=\"{input}\"")
+        };
+        let _ = write!(
+            filter,
+            "<feGaussianBlur{input_attr} stdDeviation=\"{}\"/><feOffset dx=\"{}\" dy=\"{}\" result=\"offset\"/>",
+            num(sigma),
+            num(effect.x),
+            num(effect.y)
+        );
+        let mask = if effect.inner {
+            filter.push_str(
+                "<feComposite in=\"offset\" in2=\"SourceAlpha\" operator=\"in\" result=\"inner\"/>",
+            );
+            "inner"
+        } else {
+            "offset"
+        };
+        let _ = writeln!(
+            filter,
+            "<feFlood flood-color=\"{flood}\" flood-opacity=\"{}\"/><feComposite in2=\"{mask}\" operator=\"in\"/></filter>",
+            num(flood_opacity.clamp(0.0, 1.0))
+        );
+        self.defs.push_str(&filter);
+        id
+    }
+
+    fn paint(&mut self, attr: &str, paint: Option<&Paint>, bounds: Bounds) -> String {
+        let color = match paint {
+            None => return format!(" {attr}=\"none\""),
+            Some(Paint::Solid(color)) => color.clone(),
+            Some(Paint::Gradient(gradient)) => match gradient.geometry(bounds) {
+                None => gradient
+                    .stops
+                    .first()
+                    .map(|stop| stop.color.clone())
+                    .unwrap_or_default(),
+                Some(geometry) => {
+                    let id = self.id("paint");
+                    let mut def = match geometry {
+                        GradientGeometry::Linear { x1, y1, x2, y2 } => format!(
+                            "<linearGradient id=\"{id}\" gradientUnits=\"userSpaceOnUse\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">",
+                            num(x1), num(y1), num(x2), num(y2)
+                        ),
+                        GradientGeometry::Radial { cx, cy, inner, outer } => format!(
+                            "<radialGradient id=\"{id}\" gradientUnits=\"userSpaceOnUse\" cx=\"{0}\" cy=\"{1}\" r=\"{2}\" fx=\"{0}\" fy=\"{1}\" fr=\"{3}\">",
+                            num(cx), num(cy), num(outer), num(inner)
+                        ),
+                    };
+                    for stop in &gradient.stops {
+                        let _ = write!(
+                            def,
+                            "<stop offset=\"{}\"{}/>",
+                            num(stop.offset),
+                            color_attrs("stop-color", "stop-opacity", &stop.color)
+                        );
+                    }
+                    def.push_str(match geometry {
+                        GradientGeometry::Linear { .. } => "</linearGradient>\n",
+                        GradientGeometry::Radial { .. } => "</radialGradient>\n",
+                    });
+                    self.defs.push_str(&def);
+                    return format!(" {attr}=\"url(#{id})\"");
+                }
+            },
+        };
+        color_attrs(attr, &format!("{attr}-opacity"), &color)
+    }
+
+    fn shape(&mut self, command: &ShapeCommand) {
+        let fill = self.paint("fill", command.fill.as_ref(), command.bounds);
+        let mut paint = fill;
+        if command.stroke.is_some() && command.stroke_width > 0.0 {
+            paint.push_str(&self.paint("stroke", command.stroke.as_ref(), command.bounds));
+            let _ = write!(paint, " stroke-width=\"{}\"", num(command.stroke_width));
+        }
+        let element = self.geometry(command, &paint);
+        let silhouette = self.geometry(command, " fill=\"#000\"");
+        self.with_effects(
+            command.bounds,
+            Some(&command.shape),
+            &silhouette,
+            &element,
+            command.opacity,
+            &command.effects,
+        );
+    }
+
+    fn geometry(&mut self, command: &ShapeCommand, paint: &str) -> String {
+        let bounds = command.bounds;
+        match &command.shape {
+            Shape::Rect { corner_radius } => rect_element(bounds, *corner_radius, paint),
+            Shape::Ellipse => format!(
+                "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\"{paint}/>",
+                num(bounds.x + bounds.width / 2.0),
+                num(bounds.y + bounds.height / 2.0),
+                num(bounds.width / 2.0),
+                num(bounds.height / 2.0)
+            ),
+            Shape::Path { d, even_odd, clips } => {
+                let transform = format!(
+                    " transform=\"translate({} {})\"",
+                    num(bounds.x),
+                    num(bounds.y)
+                );
+                let rule = if *even_odd {
+                    " fill-rule=\"evenodd\""
+                } else {
+                    ""
+                };
+                let mut element = format!("<path d=\"{}\"{rule}{paint}/>", escape(d));
+                for clip in clips {
+                    let id = self.id("clip");
+                    let rule = if clip.even_odd {
+                        " clip-rule=\"evenodd\""
+                    } else {
+                        ""
+                    };
+                    let _ = writeln!(
+                        self.defs,
+                        "<clipPath id=\"{id}\"><path d=\"{}\"{rule}/></clipPath>",
+                        escape(&clip.d)
+                    );
+                    element = format!("<g clip-path=\"url(#{id})\">{element}</g>");
+                }
+                format!("<g{transform}>{element}</g>")
+            }
+        }
+    }
+
+    fn text(&mut self, command: &TextCommand) {
+        let metrics = self
+            .fonts
+            .metrics(&command.font_family, command.font_weight);
+        let layout = layout_text(
+            &TextLayoutInput {
+                text: &command.text,
+                width: command.bounds.width,
+                font_size: command.font_size,
+                text_align: &command.text_align,
+                line_height_px: command.line_height_px,
+                letter_spacing_px: command.letter_spacing_px,
+                resize_mode: &command.resize_mode,
+            },
+            |line| metrics.measure(line, command.font_size),
+        );
+        let Bounds { x, y, .. } = command.bounds;
+        let ascent = metrics.ascent(command.font_size);
+        let outlines = self.text_mode == TextMode::Outlines && metrics.font.is_some();
+
+        let mut lines = String::new();
+        for line in layout.iter().filter(|line| !line.text.is_empty()) {
+            if outlines {
+                let d = metrics.outline(
+                    &line.text,
+                    x + line.x,
+                    y + line.y,
+                    command.font_size,
+                    command.letter_spacing_px,
+                );
+                let _ = write!(lines, "<path d=\"{d}\"/>");
+            } else {
+                let _ = write!(
+                    lines,
+                    "<text x=\"{}\" y=\"{}\">{}</text>",
+                    num(x + line.x),
+                    num(y + line.y + ascent),
+                    escape(&line.text)
+                );
+            }
+        }
+        let font = if outlines {
+            String::new()
+        } else {
+            let spacing = if command.letter_spacing_px.abs() >= 0.001 {
+                format!(" letter-spacing=\"{}\"", num(command.letter_spacing_px))
+            } else {
+                String::new()
+            };
+            format!(
+                " font-family=\"{}\" font-size=\"{}\" font-weight=\"{}\" xml:space=\"preserve\"{spacing}",
+                escape(&command.font_family),
+                num(command.font_size),
+                command.font_weight
+            )
+        };
+        let fill = color_attrs("fill", "fill-opacity", &command.fill);
+        let mut element = format!("<g{font}{fill}>{lines}</g>");
+        let silhouette = format!("<g{font} fill=\"#000\">{lines}</g>");
+        if command.resize_mode == "fixed" {
+            let id = self.id("clip");
+            let rect = rect_element(command.bounds, 0.0, "");
+            let _ = writeln!(self.defs, "<clipPath id=\"{id}\">{rect}</clipPath>");
+            element = format!("<g clip-path=\"url(#{id})\">{element}</g>");
+        }
+        self.with_effects(
+            command.bounds,
+            None,
+            &silhouette,
+            &element,
+            command.opacity,
+            &command.effects,
+        );
+    }
+
+    fn image(&mut self, command: &ImageCommand) -> Result<(), String> {
+        let Some(href) = self.images.href(&command.source, &command.node_id)? else {
+            return Ok(());
+        };
+        let Bounds {
+            x,
+            y,
+            width,
+            height,
+        } = command.bounds;
+        let image = |href: &str, extra: &str| {
+            format!(
+                "<image xlink:href=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\"{extra}/>",
+                escape(href),
+                num(x),
+                num(y),
+                num(width),
+                num(height)
+            )
+        };
+        let mask_href = match &command.mask {
+            Some(mask) => self
+                .images
+                .href(mask, &format!("{}_mask", command.node_id))?,
+            None => None,
+        };
+        let mask = match mask_href {
+            Some(mask_href) => {
+                let id = self.id("mask");
+                let _ = writeln!(
+                    self.defs,
+                    "<mask id=\"{id}\" mask-type=\"alpha\" maskUnits=\"userSpaceOnUse\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">{}</mask>",
+                    num(x),
+                    num(y),
+                    num(width),
+                    num(height),
+                    image(&mask_href, "")
+                );
+                format!(" mask=\"url(#{id})\"")
+            }
+            None => String::new(),
+        };
+        let element = image(&href, &mask);
+        self.with_effects(
+            command.bounds,
+            None,
+            &element,
+            &element,
+            command.opacity,
+            &command.effects,
+        );
+        Ok(())
+    }
+}
+
+fn rect_element(bounds: Bounds, corner_radius: f64, paint: &str) -> String {
+    let radius = corner_radius
+        .min(bounds.width / 2.0)
+        .min(bounds.height / 2.0)
+        .max(0.0);
+    let rounded = if radius > 0.0 {
+        format!(" rx=\"{}\"", num(radius))
+    } else {
+        String::new()
+    };
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{rounded}{paint}/>",
+        num(bounds.x),
+        num(bounds.y),
+        num(bounds.width.max(0.0)),
+        num(bounds.height.max(0.0))
+    )
+}
+
+/// Splits a CSS color into an opaque color and an opacity attribute, which
+/// SVG 1.1 viewers need for translucent colors.
+fn color_attrs(attr: &str, opacity_attr: &str, color: &str) -> String {
+    match parse_color(color) {
+        Some(parsed) if parsed.alpha >= 1.0 => format!(" {attr}=\"{}\"", parsed.hex()),
+        Some(parsed) => format!(
+            " {attr}=\"{}\" {opacity_attr}=\"{}\"",
+            parsed.hex(),
+            num(parsed.alpha)
+        ),
+        None => format!(" {attr}=\"{}\"", escape(color)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_data_url, render_svg, ImageResolver, TextMode};
+    use crate::document::Document;
+    use crate::scene::{self, SceneOptions};
+    use crate::text_layout::FontBook;
+
+    const DOC: &str = r##"{
+        "version": 9, "rootId": "root", "activePageId": "p",
+        "pages": [{ "id": "p", "name": "Page", "rootId": "root" }],
+        "nodes": {
+            "root": { "id": "root", "type": "frame", "position": { "x": 0, "y": 0 },
+                "size": { "width": 120, "height": 80 }, "children": ["card", "label", "photo"],
+                "fill": { "type": "solid", "value": "#ffffff" } },
+            "card": { "id": "card", "type": "rectangle", "position": { "x": 10, "y": 10 },
+                "size": { "width": 50, "height": 30 }, "cornerRadius": 6, "opacity": 0.5,
+                "fill": { "type": "gradient", "kind": "linear", "stops": ["#ff0000", "#0000ff"] },
+                "effects": [{ "type": "drop", "x": 0, "y": 2, "blur": 8, "spread": 0,
+                    "color": "rgba(0, 0, 0, 0.5)", "opacity": 1 }] },
+            "label": { "id": "label", "type": "text", "position": { "x": 10, "y": 50 },
+                "size": { "width": 100, "height": 20 }, "text": "A & <B>",
+                "fontSize": 12, "fontFamily": "Inter", "fill": { "type": "solid", "value": "#333" } },
+            "photo": { "id": "photo", "type": "image", "position": { "x": 70, "y": 10 },
+                "size": { "width": 40, "height": 40 }, "image": { "assetId": "img" } }
+        },
+        "assets": { "img": { "type": "image", "mime": "image/png", "dataBase64": "iVBORw0K",
+            "width": 1, "height": 1 } }
+    }"##;
+
+    #[test]
+    fn renders_shapes_text_and_images() {
+        let doc = Document::parse(DOC).unwrap();
+        let (bounds, commands) =
+            scene::build_for_node(&doc, "root", SceneOptions::default()).unwrap();
+        let mut fonts = FontBook::with_faces(Vec::new());
+        let svg = render_svg(
+            bounds,
+            &commands,
+            TextMode::Text,
+            &mut ImageResolver::embed(),
+            &mut fonts,
+        )
+        .unwrap();
+
+        assert!(svg.contains("viewBox=\"0 0 120 80\""));
+        assert!(svg.contains("<linearGradient"));
+        assert!(svg.contains("rx=\"6\""));
+        assert!(svg.contains("<g opacity=\"0.5\">"));
+        // Canvas shadowBlur 8 is a deviation of 4.
+        assert!(svg.contains("stdDeviation=\"4\""));
+        assert!(svg.contains("flood-opacity=\"0.5\""));
+        assert!(svg.contains(">A &amp; &lt;B&gt;</text>"));
+        assert!(svg.contains("fill=\"#333333\""));
+        assert!(svg.contains("xlink:href=\"data:image/png;base64,iVBORw0K\""));
+    }
+
+    #[test]
+    fn data_urls_split_into_mime_and_payload() {
+        assert_eq!(
+            parse_data_url("data:image/webp;base64,AAAA"),
+            Some(("image/webp", "AAAA"))
+        );
+        assert_eq!(parse_data_url("data:image/svg+xml,<svg/>"), None);
+    }
+}
@@ -0,0 +1,1658 @@
+//! SVG import: converts an SVG file into editable Galileo nodes (frames,
+//! groups, shapes, vector paths and text) returned as a fragment shaped
+//! like the frontend's `ClipboardPayload`, so it pastes like copied nodes.
+//!
+//! Transforms are baked into path geometry; only axis-aligned rectangles,
+//! ellipses, images and nested viewports stay native shapes. Clip paths,
+//! masks, filters and patterns aren't converted and come back as warnings.
+
+use crate::document::{
+    Color, Handle, Node, NodeImage, NodeType, Position, Size, Stroke, VectorData, VectorPoint,
+    VectorSegment,
+};
+use crate::path_data::{self, PathSegment};
+use crate::scene::{self, num, Bounds, CssColor};
+use crate::text_layout::FontBook;
+use roxmltree::{Document as Xml, Node as XmlNode, ParsingOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const MAX_REFERENCE_DEPTH: usize = 16;
+/// Import budget, so `<use>` fan-out can't expand a small file into
+/// millions of nodes
+const MAX_NODES: usize = 20_000;
+const MAX_REFERENCES: usize = 5_000;
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+/// `layoutText`'s inset around text, so imported text keeps its baseline.
+const TEXT_PADDING: f64 = 4.0;
+const FLATTEN_TOLERANCE: f64 = 0.1;
+
+/// Presentation attributes the importer reads; each may also come from CSS.
+const PROPERTIES: &[&str] = &[
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-dasharray",
+    "opacity",
+    "display",
+    "visibility",
+    "color",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "text-anchor",
+    "stop-color",
+    "stop-opacity",
+    "clip-path",
+    "mask",
+    "filter",
+];
+const NOT_INHERITED: &[&str] = &[
+    "opacity",
+    "display",
+    "clip-path",
+    "mask",
+    "filter",
+    "stop-color",
+    "stop-opacity",
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSvgArgs {
+    pub path: Option<String>,
+    pub content: Option<String>,
+}
+
+/// Mirrors the frontend's `ClipboardPayload`; positions are relative to
+/// each node's parent except in `root_world_positions`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentFragment {
+    pub version: u32,
+    pub root_ids: Vec<String>,
+    pub nodes: BTreeMap<String, Node>,
+    pub bounds: Bounds,
+    pub root_world_positions: BTreeMap<String, Position>,
+    pub parent_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSvgResult {
+    pub fragment: DocumentFragment,
+    pub warnings: Vec<String>,
+}
+
+#[tauri::command]
+pub fn import_svg(args: ImportSvgArgs) -> Result<ImportSvgResult, String> {
+    let name = args.path.as_deref().and_then(|path| {
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    });
+    let content = match (args.content, &args.path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => fs::read_to_string(path).map_err(|e| e.to_string())?,
+        (None, None) => return Err("missing_svg: pass path or content".to_string()),
+    };
+    convert(&content, name)
+}
+
+fn convert(content: &str, name: Option<String>) -> Result<ImportSvgResult, String> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let xml = Xml::parse_with_options(content, options).map_err(|e| format!("invalid_svg: {e}"))?;
+    let root = xml.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(format!(
+            "invalid_svg: root element is <{}>, not <svg>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut importer = Importer::new(&xml);
+    let root_id = importer
+        .viewport(root, Matrix::IDENTITY, &Props::new(), 0, true)
+        .ok_or("invalid_svg: the root <svg> has no size")?;
+    if let Some(reason) = importer.exhausted {
+        return Err(format!("svg_too_complex: {reason}"));
+    }
+    if let Some(node) = importer.nodes.get_mut(&root_id) {
+        node.name = name
+            .or_else(|| node.name.take())
+            .or(Some("SVG".to_string()));
+    }
+    importer.finish(&root_id);
+
+    let bounds = importer.world[&root_id];
+    Ok(ImportSvgResult {
+        fragment: DocumentFragment {
+            version: 1,
+            root_ids: vec![root_id.clone()],
+            nodes: importer.nodes,
+            bounds,
+            root_world_positions: BTreeMap::from([(
+                root_id,
+                Position {
+                    x: bounds.x,
+                    y: bounds.y,
+                },
+            )]),
+            parent_id: None,
+        },
+        warnings: importer.warnings.into_iter().collect(),
+    })
+}
+
+/// Computed style: inherited properties plus the element's own declarations.
+type Props = HashMap<String, String>;
+
+/// 2D affine transform `[a b c d e f]`, as in SVG's `matrix()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Matrix([f64; 6]);
+
+impl Matrix {
+    const IDENTITY: Matrix = Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn translate(x: f64, y: f64) -> Matrix {
+        Matrix([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    fn scale(x: f64, y: f64) -> Matrix {
+        Matrix([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    /// `self` applied after `inner`, like nesting a transform inside this one.
+    fn then(self, inner: Matrix) -> Matrix {
+        let [a, b, c, d, e, f] = self.0;
+        let [ia, ib, ic, id, ie, iff] = inner.0;
+        Matrix([
+            a * ia + c * ib,
+            b * ia + d * ib,
+            a * ic + c * id,
+            b * ic + d * id,
+            a * ie + c * iff + e,
+            b * ie + d * iff + f,
+        ])
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Uniform scale that preserves area, for stroke widths and radii.
+    fn scale_factor(&self) -> f64 {
+        let [a, b, c, d, ..] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+
+    /// Only positive scale and translation, so boxes stay boxes.
+    fn is_axis_aligned(&self) -> bool {
+        let [a, b, c, d, ..] = self.0;
+        b.abs() < 1e-9 && c.abs() < 1e-9 && a > 0.0 && d > 0.0
+    }
+
+    fn rect(&self, x: f64, y: f64, width: f64, height: f64) -> Bounds {
+        let corners = [
+            self.apply(x, y),
+            self.apply(x + width, y),
+            self.apply(x, y + height),
+            self.apply(x + width, y + height),
+        ];
+        points_bounds(corners.iter().copied()).unwrap_or_default()
+    }
+
+    fn segment(&self, segment: &PathSegment) -> PathSegment {
+        match *segment {
+            PathSegment::MoveTo(x, y) => {
+                let (x, y) = self.apply(x, y);
+                PathSegment::MoveTo(x, y)
+            }
+            PathSegment::LineTo(x, y) => {
+                let (x, y) = self.apply(x, y);
+                PathSegment::LineTo(x, y)
+            }
+            PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+                let (x1, y1) = self.apply(x1, y1);
+                let (x2, y2) = self.apply(x2, y2);
+                let (x, y) = self.apply(x, y);
+                PathSegment::CubicTo(x1, y1, x2, y2, x, y)
+            }
+            PathSegment::Close => PathSegment::Close,
+        }
+    }
+}
+
+/// A compound CSS selector without combinators, e.g. `path.icon#logo`.
+struct Selector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Selector {
+    fn parse(text: &str) -> Option<Selector> {
+        let text = text.trim();
+        if text.is_empty()
+            || text
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, '>' | '+' | '~' | '[' | ':' | '*'))
+        {
+            return None;
+        }
+        let mut selector = Selector {
+            tag: None,
+            id: None,
+            classes: Vec::new(),
+        };
+        let mut kind = ' ';
+        let mut current = String::new();
+        for c in text.chars().chain(std::iter::once('.')) {
+            if c == '.' || c == '#' {
+                match kind {
+                    '.' => selector.classes.push(std::mem::take(&mut current)),
+                    '#' => selector.id = Some(std::mem::take(&mut current)),
+                    _ if !current.is_empty() => selector.tag = Some(std::mem::take(&mut current)),
+                    _ => {}
+                }
+                kind = c;
+            } else {
+                current.push(c);
+            }
+        }
+        Some(selector)
+    }
+
+    fn matches(&self, el: XmlNode) -> bool {
+        let classes: Vec<&str> = el
+            .attribute("class")
+            .map(|value| value.split_whitespace().collect())
+            .unwrap_or_default();
+        self.tag
+            .as_deref()
+            .is_none_or(|tag| tag == el.tag_name().name())
+            && self
+                .id
+                .as_deref()
+                .is_none_or(|id| Some(id) == el.attribute("id"))
+            && self
+                .classes
+                .iter()
+                .all(|class| classes.contains(&class.as_str()))
+    }
+}
+
+struct Rule {
+    selectors: Vec<Selector>,
+    declarations: Vec<(String, String)>,
+}
+
+fn parse_declarations(text: &str) -> Vec<(String, String)> {
+    text.split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let value = value.trim().trim_end_matches("!important").trim();
+            Some((name.trim().to_ascii_lowercase(), value.to_string()))
+        })
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+        .collect()
+}
+
+/// Rules from `<style>` elements. Selectors with combinators, attributes or
+/// pseudo-classes are dropped, and rules apply in source order.
+fn parse_stylesheet(css: &str, warnings: &mut BTreeSet<String>) -> Vec<Rule> {
+    let mut css = css.to_string();
+    while let Some(start) = css.find("/*") {
+        let end = css[start..]
+            .find("*/")
+            .map_or(css.len(), |end| start + end + 2);
+        css.replace_range(start..end, "");
+    }
+    css.split('}')
+        .filter_map(|block| {
+            let (selectors, body) = block.split_once('{')?;
+            if selectors.trim_start().starts_with('@') {
+                warnings.insert("CSS at-rules are ignored".to_string());
+                return None;
+            }
+            let parts: Vec<&str> = selectors.split(',').collect();
+            let parsed: Vec<Selector> = parts.iter().filter_map(|s| Selector::parse(s)).collect();
+            if parsed.len() < parts.len() {
+                warnings.insert("complex CSS selectors are ignored".to_string());
+            }
+            Some(Rule {
+                selectors: parsed,
+                declarations: parse_declarations(body),
+            })
+        })
+        .filter(|rule| !rule.selectors.is_empty())
+        .collect()
+}
+
+struct Importer<'a, 'input> {
+    ids: HashMap<&'a str, XmlNode<'a, 'input>>,
+    rules: Vec<Rule>,
+    nodes: BTreeMap<String, Node>,
+    /// World bounds of every node; groups get theirs in `finish`.
+    world: HashMap<String, Bounds>,
+    warnings: BTreeSet<String>,
+    fonts: Option<FontBook>,
+    viewport_size: (f64, f64),
+    next_id: usize,
+    references: usize,
+    /// Set once the import budget is spent; nothing more is converted.
+    exhausted: Option<String>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn new(xml: &'a Xml<'input>) -> Self {
+        let mut warnings = BTreeSet::new();
+        let css: String = xml
+            .descendants()
+            .filter(|node| node.has_tag_name("style"))
+            .flat_map(|style| style.descendants().filter_map(|node| node.text()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Importer {
+            ids: xml
+                .descendants()
+                .filter_map(|node| Some((node.attribute("id")?, node)))
+                .collect(),
+            rules: parse_stylesheet(&css, &mut warnings),
+            nodes: BTreeMap::new(),
+            world: HashMap::new(),
+            warnings,
+            fonts: None,
+            viewport_size: (0.0, 0.0),
+            next_id: 0,
+            references: 0,
+            exhausted: None,
+        }
+    }
+
+    fn warn(&mut self, message: &str) {
+        self.warnings.insert(message.to_string());
+    }
+
+    fn exhaust(&mut self, message: &str) {
+        self.warn(message);
+        self.exhausted.get_or_insert_with(|| message.to_string());
+    }
+
+    fn insert(&mut self, mut node: Node, world: Option<Bounds>) -> String {
+        self.next_id += 1;
+        if self.next_id > MAX_NODES {
+            self.exhaust(&format!("more than {MAX_NODES} nodes"));
+        }
+        let id = format!("svg_{}", self.next_id);
+        node.id = id.clone();
+        if let Some(world) = world {
+            self.world.insert(id.clone(), world);
+        }
+        self.nodes.insert(id.clone(), node);
+        id
+    }
+
+    /// Presentation attributes, then matching stylesheet rules, then the
+    /// inline `style`, each overriding the last.
+    fn declarations(&self, el: XmlNode) -> Vec<(String, String)> {
+        let mut declarations: Vec<(String, String)> = el
+            .attributes()
+            .filter(|attr| attr.namespace().is_none() && PROPERTIES.contains(&attr.name()))
+            .map(|attr| (attr.name().to_string(), attr.value().trim().to_string()))
+            .collect();
+        for rule in &self.rules {
+            if rule.selectors.iter().any(|selector| selector.matches(el)) {
+                declarations.extend(rule.declarations.iter().cloned());
+            }
+        }
+        if let Some(style) = el.attribute("style") {
+            declarations.extend(parse_declarations(style));
+        }
+        declarations
+    }
+
+    fn cascade(&self, el: XmlNode, parent: &Props) -> Props {
+        let mut props: Props = parent
+            .iter()
+            .filter(|(name, _)| !NOT_INHERITED.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for (name, value) in self.declarations(el) {
+            if value != "inherit" {
+                props.insert(name, value);
+            }
+        }
+        props
+    }
+
+    fn children(&mut self, el: XmlNode, ctm: Matrix, props: &Props, depth: usize) -> Vec<String> {
+        el.children()
+            .filter(XmlNode::is_element)
+            .filter_map(|child| self.element(child, ctm, props, depth))
+            .collect()
+    }
+
+    fn element(
+        &mut self,
+        el: XmlNode,
+        ctm: Matrix,
+        parent: &Props,
+        depth: usize,
+    ) -> Option<String> {
+        if self.exhausted.is_some() || el.tag_name().namespace().is_some_and(|ns| ns != SVG_NS) {
+            return None;
+        }
+        let props = self.cascade(el, parent);
+        if props.get("display").map(String::as_str) == Some("none") {
+            return None;
+        }
+        let ctm = match el.attribute("transform") {
+            Some(transform) => ctm.then(parse_transform(transform)),
+            None => ctm,
+        };
+        for (property, message) in [
+            ("clip-path", "clip paths are not imported"),
+            ("mask", "masks are not imported"),
+            ("filter", "filters are not imported"),
+        ] {
+            if props.get(property).is_some_and(|value| value != "none") {
+                self.warn(message);
+            }
+        }
+
+        let number = |name: &str| el.attribute(name).and_then(parse_length).unwrap_or(0.0);
+        let id = match el.tag_name().name() {
+            "g" | "a" | "switch" => self.group(el, ctm, &props, depth)?,
+            "svg" => self.viewport(el, ctm, &props, depth, false)?,
+            "use" => self.reference(el, ctm, &props, depth)?,
+            "path" => {
+                let segments = match path_data::parse(el.attribute("d")?) {
+                    Ok(segments) => segments,
+                    Err(error) => {
+                        self.warn(&format!("skipped a path: {error}"));
+                        return None;
+                    }
+                };
+                self.shape(&segments, ctm, &props, true)?
+            }
+            "rect" => {
+                let (x, y) = (number("x"), number("y"));
+                let (width, height) = (number("width"), number("height"));
+                if width <= 0.0 || height <= 0.0 {
+                    return None;
+                }
+                let rx = el.attribute("rx").and_then(parse_length);
+                let ry = el.attribute("ry").and_then(parse_length);
+                let rx = rx.or(ry).unwrap_or(0.0).clamp(0.0, width / 2.0);
+                let ry = ry.or(Some(rx)).unwrap_or(0.0).clamp(0.0, height / 2.0);
+                let [a, _, _, d, ..] = ctm.0;
+                if ctm.is_axis_aligned() && (rx * a - ry * d).abs() < 1e-6 {
+                    let world = ctm.rect(x, y, width, height);
+                    let mut node = Node::new("", NodeType::Rectangle, origin(world), size(world));
+                    if rx > 0.0 {
+                        node.corner_radius = Some(rx * a);
+                    }
+                    self.paint_node(&mut node, &props, world, world, ctm, true);
+                    self.insert(node, Some(world))
+                } else {
+                    let segments = path_data::parse(&rounded_rect(x, y, width, height, rx, ry))
+                        .unwrap_or_default();
+                    self.shape(&segments, ctm, &props, true)?
+                }
+            }
+            tag @ ("circle" | "ellipse") => {
+                let (rx, ry) = if tag == "circle" {
+                    (number("r"), number("r"))
+                } else {
+                    let rx = el.attribute("rx").and_then(parse_length);
+                    let ry = el.attribute("ry").and_then(parse_length);
+                    (rx.or(ry).unwrap_or(0.0), ry.or(rx).unwrap_or(0.0))
+                };
+                if rx <= 0.0 || ry <= 0.0 {
+                    return None;
+                }
+                let (x, y) = (number("cx") - rx, number("cy") - ry);
+                if ctm.is_axis_aligned() {
+                    let world = ctm.rect(x, y, rx * 2.0, ry * 2.0);
+                    let mut node = Node::new("", NodeType::Ellipse, origin(world), size(world));
+                    self.paint_node(&mut node, &props, world, world, ctm, true);
+                    self.insert(node, Some(world))
+                } else {
+                    let d = scene::ellipse_path(x, y, rx * 2.0, ry * 2.0);
+                    let segments = path_data::parse(&d).unwrap_or_default();
+                    self.shape(&segments, ctm, &props, true)?
+                }
+            }
+            "line" => {
+                let segments = vec![
+                    PathSegment::MoveTo(number("x1"), number("y1")),
+                    PathSegment::LineTo(number("x2"), number("y2")),
+                ];
+                self.shape(&segments, ctm, &props, false)?
+            }
+            tag @ ("polyline" | "polygon") => {
+                let values = parse_numbers(el.attribute("points")?);
+                let mut segments: Vec<PathSegment> = values
+                    .chunks_exact(2)
+                    .enumerate()
+                    .map(|(index, point)| match index {
+                        0 => PathSegment::MoveTo(point[0], point[1]),
+                        _ => PathSegment::LineTo(point[0], point[1]),
+                    })
+                    .collect();
+                if segments.len() < 2 {
+                    return None;
+                }
+                if tag == "polygon" {
+                    segments.push(PathSegment::Close);
+                }
+                self.shape(&segments, ctm, &props, true)?
+            }
+            "text" => self.text(el, ctm, &props)?,
+            "image" => self.image(el, ctm)?,
+            "foreignObject" => {
+                self.warn("foreignObject content is not imported");
+                return None;
+            }
+            _ => return None,
+        };
+
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if let Some(name) = el.attribute("id") {
+                node.name = Some(name.to_string());
+            }
+            let opacity = props.get("opacity").and_then(|value| parse_fraction(value));
+            if let Some(opacity) = opacity.filter(|opacity| *opacity < 1.0) {
+                node.opacity = Some(opacity * node.opacity.unwrap_or(1.0));
+            }
+            if props.get("visibility").map(String::as_str) == Some("hidden") {
+                node.visible = Some(false);
+            }
+        }
+        Some(id)
+    }
+
+    fn group(&mut self, el: XmlNode, ctm: Matrix, props: &Props, depth: usize) -> Option<String> {
+        let children = self.children(el, ctm, props, depth);
+        if children.is_empty() {
+            return None;
+        }
+        Some(self.group_of(children))
+    }
+
+    fn group_of(&mut self, children: Vec<String>) -> String {
+        let mut node = Node::new("", NodeType::Group, Position::default(), Size::default());
+        node.name = Some("Group".to_string());
+        node.children = Some(children);
+        self.insert(node, None)
+    }
+
+    /// An `<svg>` element as a clipping frame, with its viewBox mapped onto
+    /// its width and height.
+    fn viewport(
+        &mut self,
+        el: XmlNode,
+        ctm: Matrix,
+        props: &Props,
+        depth: usize,
+        root: bool,
+    ) -> Option<String> {
+        let view_box = el.attribute("viewBox").and_then(parse_view_box);
+        let length = |name: &str| el.attribute(name).and_then(parse_length);
+        let width = length("width").or(view_box.map(|v| v.2)).unwrap_or(300.0);
+        let height = length("height").or(view_box.map(|v| v.3)).unwrap_or(150.0);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let (x, y) = if root {
+            (0.0, 0.0)
+        } else {
+            (length("x").unwrap_or(0.0), length("y").unwrap_or(0.0))
+        };
+        if root {
+            self.viewport_size = view_box.map_or((width, height), |v| (v.2, v.3));
+        }
+        let inner = ctm.then(Matrix::translate(x, y)).then(view_box_transform(
+            view_box,
+            width,
+            height,
+            el.attribute("preserveAspectRatio"),
+        ));
+        let children = self.children(el, inner, props, depth);
+        let world = ctm.rect(x, y, width, height);
+        let mut node = Node::new("", NodeType::Frame, origin(world), size(world));
+        node.name = Some("SVG".to_string());
+        node.clip_content = Some(true);
+        node.children = Some(children);
+        Some(self.insert(node, Some(world)))
+    }
+
+    /// `<use>`: the referenced element converted in place, offset by x/y.
+    fn reference(
+        &mut self,
+        el: XmlNode,
+        ctm: Matrix,
+        props: &Props,
+        depth: usize,
+    ) -> Option<String> {
+        if depth >= MAX_REFERENCE_DEPTH {
+            self.warn("deeply nested <use> references were skipped");
+            return None;
+        }
+        let href = el
+            .attribute((XLINK_NS, "href"))
+            .or(el.attribute("href"))?
+            .strip_prefix('#')?;
+        let target = *self.ids.get(href)?;
+        if el.ancestors().any(|ancestor| ancestor == target) {
+            self.warn("self-referencing <use> elements were skipped");
+            return None;
+        }
+        self.references += 1;
+        if self.references > MAX_REFERENCES {
+            self.exhaust(&format!("more than {MAX_REFERENCES} <use> expansions"));
+            return None;
+        }
+        let length = |name: &str| el.attribute(name).and_then(parse_length);
+        let ctm = ctm.then(Matrix::translate(
+            length("x").unwrap_or(0.0),
+            length("y").unwrap_or(0.0),
+        ));
+        if target.has_tag_name("symbol") {
+            let props = self.cascade(target, props);
+            let view_box = target.attribute("viewBox").and_then(parse_view_box);
+            let inner = match (view_box, length("width"), length("height")) {
+                (Some(_), Some(width), Some(height)) => ctm.then(view_box_transform(
+                    view_box,
+                    width,
+                    height,
+                    target.attribute("preserveAspectRatio"),
+                )),
+                _ => ctm,
+            };
+            let children = self.children(target, inner, &props, depth + 1);
+            return (!children.is_empty()).then(|| self.group_of(children));
+        }
+        self.element(target, ctm, props, depth + 1)
+    }
+
+    /// Path geometry as vector nodes. Subpaths that don't overlap become
+    /// separate nodes in a group so each stays editable; overlapping ones
+    /// (holes, compound shapes) stay one node with plain path data.
+    fn shape(
+        &mut self,
+        segments: &[PathSegment],
+        ctm: Matrix,
+        props: &Props,
+        fillable: bool,
+    ) -> Option<String> {
+        let segments: Vec<PathSegment> = segments.iter().map(|s| ctm.segment(s)).collect();
+        let subpaths = split_subpaths(&segments);
+        let boxes: Vec<Bounds> = subpaths
+            .iter()
+            .filter_map(|subpath| segments_bounds(subpath))
+            .collect();
+        if boxes.len() != subpaths.len() || subpaths.is_empty() {
+            return None;
+        }
+        let paint_box = boxes.iter().copied().reduce(union)?;
+        let disjoint = boxes.iter().enumerate().all(|(i, a)| {
+            boxes[i + 1..].iter().all(|b| {
+                a.x + a.width < b.x
+                    || b.x + b.width < a.x
+                    || a.y + a.height < b.y
+                    || b.y + b.height < a.y
+            })
+        });
+        if subpaths.len() > 1 && disjoint {
+            let children = subpaths
+                .iter()
+                .zip(&boxes)
+                .map(|(subpath, bounds)| {
+                    let mut node = path_node(subpath, *bounds, props, true);
+                    self.paint_node(&mut node, props, *bounds, paint_box, ctm, fillable);
+                    self.insert(node, Some(*bounds))
+                })
+                .collect();
+            return Some(self.group_of(children));
+        }
+        let mut node = path_node(&subpaths.concat(), paint_box, props, subpaths.len() == 1);
+        self.paint_node(&mut node, props, paint_box, paint_box, ctm, fillable);
+        Some(self.insert(node, Some(paint_box)))
+    }
+
+    fn paint_node(
+        &mut self,
+        node: &mut Node,
+        props: &Props,
+        bounds: Bounds,
+        paint_box: Bounds,
+        ctm: Matrix,
+        fillable: bool,
+    ) {
+        let opacity = |name: &str| {
+            props
+                .get(name)
+                .and_then(|value| parse_fraction(value))
+                .unwrap_or(1.0)
+        };
+        if fillable {
+            let fill = props.get("fill").map_or("black", String::as_str);
+            node.fill = self.paint(fill, opacity("fill-opacity"), props, bounds, paint_box, ctm);
+        }
+        let stroke = props.get("stroke").map_or("none", String::as_str);
+        let width = props
+            .get("stroke-width")
+            .and_then(|value| parse_length(value))
+            .unwrap_or(1.0)
+            * ctm.scale_factor();
+        if width > 0.0 {
+            let color = self.paint(
+                stroke,
+                opacity("stroke-opacity"),
+                props,
+                bounds,
+                paint_box,
+                ctm,
+            );
+            let dashed = props.get("stroke-dasharray").is_some_and(|value| {
+                value != "none" && parse_numbers(value).iter().any(|v| *v > 0.0)
+            });
+            node.stroke = color.map(|color| Stroke {
+                color,
+                width,
+                style: if dashed { "dashed" } else { "solid" }.to_string(),
+            });
+        }
+    }
+
+    fn paint(
+        &mut self,
+        value: &str,
+        opacity: f64,
+        props: &Props,
+        bounds: Bounds,
+        paint_box: Bounds,
+        ctm: Matrix,
+    ) -> Option<Color> {
+        let value = value.trim();
+        if let Some(reference) = value.strip_prefix("url(") {
+            let (target, fallback) = reference.split_once(')')?;
+            let id = target
+                .trim()
+                .trim_matches(['"', '\''])
+                .trim_start_matches('#');
+            if let Some(gradient) = self.gradient(id, opacity, props, bounds, paint_box, ctm) {
+                return Some(gradient);
+            }
+            if self
+                .ids
+                .get(id)
+                .is_some_and(|el| el.has_tag_name("pattern"))
+            {
+                self.warn("pattern fills are not imported");
+            }
+            return match fallback.trim() {
+                "" => None,
+                fallback => self.paint(fallback, opacity, props, bounds, paint_box, ctm),
+            };
+        }
+        let color = self.color(value, props)?;
+        Some(Color::Solid {
+            value: css_color(color, opacity),
+        })
+    }
+
+    fn color(&self, value: &str, props: &Props) -> Option<CssColor> {
+        match value {
+            "none" | "transparent" => None,
+            "currentColor" => {
+                scene::parse_color(props.get("color").map_or("black", String::as_str))
+            }
+            value => scene::parse_color(value),
+        }
+    }
+
+    /// Linear and radial gradients in Galileo's passthrough shape, with
+    /// handles expressed against the node's box the way the renderer reads
+    /// them back.
+    fn gradient(
+        &mut self,
+        id: &str,
+        opacity: f64,
+        props: &Props,
+        bounds: Bounds,
+        paint_box: Bounds,
+        ctm: Matrix,
+    ) -> Option<Color> {
+        let el = *self.ids.get(id)?;
+        let radial = match el.tag_name().name() {
+            "linearGradient" => false,
+            "radialGradient" => true,
+            _ => return None,
+        };
+        let mut chain = vec![el];
+        while let Some(next) = chain
+            .last()
+            .and_then(|el| el.attribute((XLINK_NS, "href")).or(el.attribute("href")))
+            .and_then(|href| self.ids.get(href.strip_prefix('#')?))
+        {
+            if chain.len() >= MAX_REFERENCE_DEPTH || chain.contains(next) {
+                break;
+            }
+            chain.push(*next);
+        }
+        let attr = |name: &str| chain.iter().find_map(|el| el.attribute(name));
+
+        let stop_elements: Vec<XmlNode> = chain
+            .iter()
+            .map(|el| {
+                el.children()
+                    .filter(|c| c.has_tag_name("stop"))
+                    .collect::<Vec<_>>()
+            })
+            .find(|stops| !stops.is_empty())
+            .unwrap_or_default();
+        let mut last_offset: f64 = 0.0;
+        let stops: Vec<Value> = stop_elements
+            .into_iter()
+            .map(|stop| {
+                let stop_props = self.cascade(stop, props);
+                let offset = stop
+                    .attribute("offset")
+                    .and_then(parse_fraction)
+                    .unwrap_or(0.0)
+                    .max(last_offset);
+                last_offset = offset;
+                let color = stop_props
+                    .get("stop-color")
+                    .and_then(|value| self.color(value, &stop_props))
+                    .unwrap_or(CssColor {
+                        r: 0,
+                        g: 0,
+                        b: 0,
+                        alpha: 1.0,
+                    });
+                let stop_opacity = stop_props
+                    .get("stop-opacity")
+                    .and_then(|value| parse_fraction(value))
+                    .unwrap_or(1.0);
+                json!({ "offset": offset, "color": css_color(color, opacity * stop_opacity) })
+            })
+            .collect();
+        match stops.len() {
+            0 => return None,
+            1 => {
+                return Some(Color::Solid {
+                    value: stops[0]["color"].as_str()?.to_string(),
+                })
+            }
+            _ => {}
+        }
+
+        let user_space = attr("gradientUnits") == Some("userSpaceOnUse");
+        let transform = attr("gradientTransform").map_or(Matrix::IDENTITY, parse_transform);
+        let to_world = if user_space {
+            ctm.then(transform)
+        } else {
+            Matrix([
+                paint_box.width,
+                0.0,
+                0.0,
+                paint_box.height,
+                paint_box.x,
+                paint_box.y,
+            ])
+            .then(transform)
+        };
+        let (vw, vh) = self.viewport_size;
+        let coord = |name: &str, fallback: f64, extent: f64| {
+            attr(name).map_or(fallback, |value| match value.trim().strip_suffix('%') {
+                Some(percent) => {
+                    let fraction = percent.trim().parse::<f64>().unwrap_or(0.0) / 100.0;
+                    if user_space {
+                        fraction * extent
+                    } else {
+                        fraction
+                    }
+                }
+                None => parse_length(value).unwrap_or(fallback),
+            })
+        };
+        let full = |extent: f64| if user_space { extent } else { 1.0 };
+        let point = |x: f64, y: f64| {
+            let (x, y) = to_world.apply(x, y);
+            json!({
+                "x": box_coord(x - bounds.x, bounds.width),
+                "y": box_coord(y - bounds.y, bounds.height),
+            })
+        };
+
+        let mut fields = Map::new();
+        fields.insert("type".into(), json!("gradient"));
+        fields.insert("stops".into(), Value::Array(stops));
+        if radial {
+            let cx = coord("cx", full(vw) * 0.5, vw);
+            let cy = coord("cy", full(vh) * 0.5, vh);
+            let diagonal = ((vw * vw + vh * vh) / 2.0).sqrt();
+            let r = coord("r", full(diagonal) * 0.5, diagonal);
+            let radius = r * to_world.scale_factor();
+            fields.insert("kind".into(), json!("radial"));
+            fields.insert("center".into(), point(cx, cy));
+            fields.insert(
+                "radius".into(),
+                json!(box_coord(radius, bounds.width.min(bounds.height) * 0.5)),
+            );
+            if attr("fx").is_some() || attr("fy").is_some() {
+                self.warn("radial gradient focal points are not imported");
+            }
+        } else {
+            let from = point(coord("x1", 0.0, vw), coord("y1", 0.0, vh));
+            let to = point(coord("x2", full(vw), vw), coord("y2", 0.0, vh));
+            fields.insert("kind".into(), json!("linear"));
+            fields.insert("from".into(), from);
+            fields.insert("to".into(), to);
+        }
+        Some(Color::Gradient(fields))
+    }
+
+    /// `<text>` and its `<tspan>`s as one auto-width text node whose
+    /// baseline sits where the SVG's did.
+    fn text(&mut self, el: XmlNode, ctm: Matrix, props: &Props) -> Option<String> {
+        let raw: String = el
+            .descendants()
+            .filter(|node| node.is_text())
+            .filter_map(|node| node.text())
+            .collect();
+        let content = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        if content.is_empty() {
+            return None;
+        }
+        if el.children().any(|c| c.has_tag_name("tspan")) {
+            self.warn("tspans are merged into a single text run");
+        }
+        let first = |name: &str| {
+            el.descendants()
+                .find_map(|node| node.attribute(name))
+                .and_then(|value| parse_numbers(value).first().copied())
+                .unwrap_or(0.0)
+        };
+        let (x, y) = ctm.apply(first("x"), first("y"));
+        let scale = ctm.scale_factor();
+        let font_size = props
+            .get("font-size")
+            .and_then(|value| parse_length(value))
+            .unwrap_or(DEFAULT_FONT_SIZE)
+            * scale;
+        let family = props.get("font-family").cloned();
+        let weight = props.get("font-weight").map(|value| match value.as_str() {
+            "bold" | "bolder" => "bold",
+            value => match value.parse::<u16>().unwrap_or(400) {
+                700.. => "bold",
+                600..=699 => "600",
+                500..=599 => "500",
+                _ => "normal",
+            },
+        });
+        let metrics = self.fonts.get_or_insert_with(FontBook::system).metrics(
+            family.as_deref().unwrap_or("sans-serif"),
+            scene::font_weight(weight),
+        );
+        let width = metrics.measure(&content, font_size);
+        let (left, align) = match props.get("text-anchor").map(String::as_str) {
+            Some("middle") => (x - width / 2.0, "center"),
+            Some("end") => (x - width, "right"),
+            _ => (x, "left"),
+        };
+        let position = Position {
+            x: left - TEXT_PADDING,
+            y: y - metrics.ascent(font_size) - TEXT_PADDING,
+        };
+        let size = Size {
+            width: width + TEXT_PADDING * 2.0,
+            height: font_size * 1.2 + TEXT_PADDING * 2.0,
+        };
+        let mut node = Node::new("", NodeType::Text, position, size);
+        node.name = Some(content.chars().take(40).collect());
+        node.text = Some(content);
+        node.font_size = Some(font_size);
+        node.font_family = family;
+        node.font_weight = weight.map(str::to_string);
+        node.text_align = Some(align.to_string());
+        node.text_resize_mode = Some("auto-width".to_string());
+        let [a, b, ..] = ctm.0;
+        let rotation = b.atan2(a).to_degrees();
+        if rotation.abs() > 0.01 {
+            node.rotation = Some(rotation);
+        }
+        let world = Bounds {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        };
+        self.paint_node(&mut node, props, world, world, ctm, true);
+        node.stroke = None;
+        Some(self.insert(node, Some(world)))
+    }
+
+    /// Embedded (`data:`) images; linked files aren't read.
+    fn image(&mut self, el: XmlNode, ctm: Matrix) -> Option<String> {
+        let href = el.attribute((XLINK_NS, "href")).or(el.attribute("href"))?;
+        let Some(data) = href.strip_prefix("data:") else {
+            self.warn("linked images are not imported");
+            return None;
+        };
+        let mime = data
+            .split([';', ','])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let number = |name: &str| el.attribute(name).and_then(parse_length).unwrap_or(0.0);
+        let (width, height) = (number("width"), number("height"));
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let world = ctm.rect(number("x"), number("y"), width, height);
+        let mut node = Node::new("", NodeType::Image, origin(world), size(world));
+        node.name = Some("Image".to_string());
+        node.image = Some(NodeImage {
+            src: Some(href.to_string()),
+            mime: (!mime.is_empty()).then_some(mime),
+            ..NodeImage::default()
+        });
+        Some(self.insert(node, Some(world)))
+    }
+
+    /// Sizes groups to their children and turns world positions into
+    /// parent-relative ones.
+    fn finish(&mut self, id: &str) -> Bounds {
+        let children = self
+            .nodes
+            .get(id)
+            .and_then(|node| node.children.clone())
+            .unwrap_or_default();
+        let child_bounds: Vec<Bounds> = children.iter().map(|child| self.finish(child)).collect();
+        let bounds = match self.world.get(id) {
+            Some(bounds) => *bounds,
+            None => {
+                let bounds = child_bounds
+                    .iter()
+                    .copied()
+                    .reduce(union)
+                    .unwrap_or_default();
+                self.world.insert(id.to_string(), bounds);
+                if let Some(node) = self.nodes.get_mut(id) {
+                    node.position = origin(bounds);
+                    node.size = size(bounds);
+                }
+                bounds
+            }
+        };
+        for child in &children {
+            if let Some(node) = self.nodes.get_mut(child) {
+                node.position.x -= bounds.x;
+                node.position.y -= bounds.y;
+            }
+        }
+        bounds
+    }
+}
+
+/// A path node at `bounds` holding `segments` in local coordinates, with
+/// vector data when the path is a single editable subpath.
+fn path_node(segments: &[PathSegment], bounds: Bounds, props: &Props, editable: bool) -> Node {
+    let local: Vec<PathSegment> = segments
+        .iter()
+        .map(|s| Matrix::translate(-bounds.x, -bounds.y).segment(s))
+        .collect();
+    let mut node = Node::new("", NodeType::Path, origin(bounds), size(bounds));
+    node.name = Some("Path".to_string());
+    let d = if editable {
+        let vector = vector_data(&local);
+        let d = scene::vector_path_data(&vector, 0.0, 0.0);
+        node.vector = Some(vector);
+        d
+    } else {
+        segments_d(&local)
+    };
+    let even_odd = props.get("fill-rule").map(String::as_str) == Some("evenodd");
+    node.path = Some(json!({
+        "d": d,
+        "fillRule": if even_odd { "evenodd" } else { "nonzero" },
+    }));
+    node
+}
+
+/// Absolute segments split at each move; a segment after `Z` without a
+/// move starts a new subpath at the closed one's start.
+fn split_subpaths(segments: &[PathSegment]) -> Vec<Vec<PathSegment>> {
+    let mut subpaths: Vec<Vec<PathSegment>> = Vec::new();
+    let mut start = (0.0, 0.0);
+    for segment in segments {
+        match segment {
+            PathSegment::MoveTo(x, y) => {
+                start = (*x, *y);
+                subpaths.push(vec![*segment]);
+            }
+            _ => {
+                let closed = subpaths
+                    .last()
+                    .is_none_or(|subpath| subpath.last() == Some(&PathSegment::Close));
+                if closed {
+                    if *segment == PathSegment::Close {
+                        continue;
+                    }
+                    subpaths.push(vec![PathSegment::MoveTo(start.0, start.1)]);
+                }
+                if let Some(subpath) = subpaths.last_mut() {
+                    subpath.push(*segment);
+                }
+            }
+        }
+    }
+    subpaths.retain(|subpath| subpath.len() > 1);
+    subpaths
+}
+
+fn segments_bounds(segments: &[PathSegment]) -> Option<Bounds> {
+    let moves = segments.iter().filter_map(|segment| match segment {
+        PathSegment::MoveTo(x, y) => Some((*x, *y)),
+        _ => None,
+    });
+    let flattened: Vec<(f64, f64)> = path_data::flatten(segments, FLATTEN_TOLERANCE)
+        .into_iter()
+        .flatten()
+        .collect();
+    points_bounds(moves.chain(flattened))
+}
+
+fn points_bounds(points: impl Iterator<Item = (f64, f64)>) -> Option<Bounds> {
+    points
+        .map(|(x, y)| Bounds {
+            x,
+            y,
+            width: 0.0,
+            height: 0.0,
+        })
+        .reduce(union)
+}
+
+fn union(a: Bounds, b: Bounds) -> Bounds {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Bounds {
+        x,
+        y,
+        width: (a.x + a.width).max(b.x + b.width) - x,
+        height: (a.y + a.height).max(b.y + b.height) - y,
+    }
+}
+
+fn origin(bounds: Bounds) -> Position {
+    Position {
+        x: bounds.x,
+        y: bounds.y,
+    }
+}
+
+fn size(bounds: Bounds) -> Size {
+    Size {
+        width: bounds.width,
+        height: bounds.height,
+    }
+}
+
+/// A handle offset as the renderer reads it back: a fraction of `extent`
+/// when that lands within 0..=1, pixels otherwise.
+fn box_coord(offset: f64, extent: f64) -> f64 {
+    let rounded = |value: f64| (value * 10_000.0).round() / 10_000.0;
+    if extent > 0.0 && (0.0..=1.0).contains(&(offset / extent)) {
+        rounded(offset / extent)
+    } else {
+        rounded(offset)
+    }
+}
+
+fn css_color(color: CssColor, opacity: f64) -> String {
+    let alpha = (color.alpha * opacity).clamp(0.0, 1.0);
+    if alpha >= 1.0 {
+        color.hex()
+    } else {
+        format!(
+            "rgba({}, {}, {}, {})",
+            color.r,
+            color.g,
+            color.b,
+            num(alpha)
+        )
+    }
+}
+
+/// Points and handles of one subpath in node-local coordinates. A closing
+/// point that repeats the start is merged into it.
+fn vector_data(segments: &[PathSegment]) -> VectorData {
+    let mut points: Vec<VectorPoint> = Vec::new();
+    let mut closed = false;
+    let point = |x: f64, y: f64, in_handle: Option<Handle>| VectorPoint {
+        id: String::new(),
+        x,
+        y,
+        in_handle,
+        out_handle: None,
+        corner_mode: None,
+    };
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo(x, y) | PathSegment::LineTo(x, y) => points.push(point(x, y, None)),
+            PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+                if let Some(last) = points.last_mut() {
+                    last.out_handle = Some(Handle { x: x1, y: y1 });
+                }
+                points.push(point(x, y, Some(Handle { x: x2, y: y2 })));
+            }
+            PathSegment::Close => closed = true,
+        }
+    }
+    if closed && points.len() > 2 {
+        let (first, last) = (&points[0], &points[points.len() - 1]);
+        if (first.x - last.x).abs() < 1e-6 && (first.y - last.y).abs() < 1e-6 {
+            let last = points.pop().expect("checked length");
+            points[0].in_handle = last.in_handle;
+        }
+    }
+    for (index, point) in points.iter_mut().enumerate() {
+        point.id = format!("pt_{index}");
+        point.corner_mode = Some(corner_mode(point).to_string());
+    }
+    let count = points.len();
+    let pairs = if closed && count > 1 {
+        count
+    } else {
+        count.saturating_sub(1)
+    };
+    let segments = (0..pairs)
+        .map(|index| VectorSegment {
+            id: format!("seg_{index}"),
+            from_id: format!("pt_{index}"),
+            to_id: format!("pt_{}", (index + 1) % count),
+        })
+        .collect();
+    VectorData {
+        points,
+        segments,
+        closed,
+        extra: Map::new(),
+    }
+}
+
+fn corner_mode(point: &VectorPoint) -> &'static str {
+    let (Some(in_handle), Some(out_handle)) = (point.in_handle, point.out_handle) else {
+        return if point.in_handle.is_none() && point.out_handle.is_none() {
+            "sharp"
+        } else {
+            "disconnected"
+        };
+    };
+    let (ix, iy) = (in_handle.x - point.x, in_handle.y - point.y);
+    let (ox, oy) = (out_handle.x - point.x, out_handle.y - point.y);
+    let (in_length, out_length) = (ix.hypot(iy), ox.hypot(oy));
+    if in_length < 1e-9 || out_length < 1e-9 {
+        return "disconnected";
+    }
+    let cross = (ix * oy - iy * ox) / (in_length * out_length);
+    let opposed = ix * ox + iy * oy < 0.0;
+    if cross.abs() > 1e-3 || !opposed {
+        "disconnected"
+    } else if (in_length - out_length).abs() <= 1e-3 * in_length.max(out_length) {
+        "mirrored"
+    } else {
+        "asymmetric"
+    }
+}
+
+fn segments_d(segments: &[PathSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match *segment {
+            PathSegment::MoveTo(x, y) => format!("M {} {}", num(x), num(y)),
+            PathSegment::LineTo(x, y) => format!("L {} {}", num(x), num(y)),
+            PathSegment::CubicTo(x1, y1, x2, y2, x, y) => format!(
+                "C {} {} {} {} {} {}",
+                num(x1),
+                num(y1),
+                num(x2),
+                num(y2),
+                num(x),
+                num(y)
+            ),
+            PathSegment::Close => "Z".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn rounded_rect(x: f64, y: f64, width: f64, height: f64, rx: f64, ry: f64) -> String {
+    if rx <= 0.0 || ry <= 0.0 {
+        return scene::rect_path(x, y, width, height);
+    }
+    let (right, bottom) = (x + width, y + height);
+    let arc = |x: f64, y: f64| format!("A {} {} 0 0 1 {} {}", num(rx), num(ry), num(x), num(y));
+    format!(
+        "M {} {} H {} {} V {} {} H {} {} V {} {} Z",
+        num(x + rx),
+        num(y),
+        num(right - rx),
+        arc(right, y + ry),
+        num(bottom - ry),
+        arc(right - rx, bottom),
+        num(x + rx),
+        arc(x, bottom - ry),
+        num(y + ry),
+        arc(x + rx, y),
+    )
+}
+
+fn parse_numbers(text: &str) -> Vec<f64> {
+    let mut numbers = Vec::new();
+    let mut current = String::new();
+    let flush = |current: &mut String, numbers: &mut Vec<f64>| {
+        if let Ok(value) = current.parse() {
+            numbers.push(value);
+        }
+        current.clear();
+    };
+    for c in text.chars() {
+        let starts_number = (c == '-' || c == '+') && !current.ends_with(['e', 'E']);
+        let second_dot = c == '.' && current.contains('.') && !current.contains(['e', 'E']);
+        if c.is_whitespace() || c == ',' {
+            flush(&mut current, &mut numbers);
+        } else if starts_number || second_dot {
+            flush(&mut current, &mut numbers);
+            current.push(c);
+        } else {
+            current.push(c);
+        }
+    }
+    flush(&mut current, &mut numbers);
+    numbers
+}
+
+/// A length in user units; percentages and font-relative units other than
+/// `em`/`rem` (taken as 16px) aren't resolvable here.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, factor) = [
+        ("px", 1.0),
+        ("pt", 4.0 / 3.0),
+        ("pc", 16.0),
+        ("in", 96.0),
+        ("cm", 96.0 / 2.54),
+        ("mm", 96.0 / 25.4),
+        ("rem", DEFAULT_FONT_SIZE),
+        ("em", DEFAULT_FONT_SIZE),
+    ]
+    .iter()
+    .find_map(|(unit, factor)| Some((value.strip_suffix(unit)?, *factor)))
+    .unwrap_or((value, 1.0));
+    let number: f64 = number.trim().parse().ok()?;
+    Some(number * factor).filter(|value| value.is_finite())
+}
+
+/// Opacities and stop offsets: a number or a percentage, clamped to 0..=1.
+fn parse_fraction(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let fraction = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    fraction.is_finite().then(|| fraction.clamp(0.0, 1.0))
+}
+
+fn parse_view_box(value: &str) -> Option<(f64, f64, f64, f64)> {
+    match parse_numbers(value)[..] {
+        [x, y, width, height] if width > 0.0 && height > 0.0 => Some((x, y, width, height)),
+        _ => None,
+    }
+}
+
+/// Maps a viewBox onto a `width`×`height` viewport per
+/// `preserveAspectRatio` (alignment and `meet`/`slice`).
+fn view_box_transform(
+    view_box: Option<(f64, f64, f64, f64)>,
+    width: f64,
+    height: f64,
+    aspect: Option<&str>,
+) -> Matrix {
+    let Some((vx, vy, vw, vh)) = view_box else {
+        return Matrix::IDENTITY;
+    };
+    let (sx, sy) = (width / vw, height / vh);
+    let mut parts = aspect.unwrap_or("xMidYMid meet").split_whitespace();
+    let align = parts.next().unwrap_or("xMidYMid");
+    if align == "none" {
+        return Matrix::scale(sx, sy).then(Matrix::translate(-vx, -vy));
+    }
+    let scale = if parts.next() == Some("slice") {
+        sx.max(sy)
+    } else {
+        sx.min(sy)
+    };
+    let offset = |key: &str, free: f64| {
+        if align.contains(&format!("{key}Min")) {
+            0.0
+        } else if align.contains(&format!("{key}Max")) {
+            free
+        } else {
+            free / 2.0
+        }
+    };
+    let tx = offset("x", width - vw * scale);
+    let ty = offset("Y", height - vh * scale);
+    Matrix::translate(tx, ty)
+        .then(Matrix::scale(scale, scale))
+        .then(Matrix::translate(-vx, -vy))
+}
+
+/// A `transform` attribute's function list, composed left to right.
+fn parse_transform(text: &str) -> Matrix {
+    let mut matrix = Matrix::IDENTITY;
+    for part in text.split(')') {
+        let Some((name, args)) = part.split_once('(') else {
+            continue;
+        };
+        let args = parse_numbers(args);
+        let arg = |index: usize, fallback: f64| args.get(index).copied().unwrap_or(fallback);
+        let next = match name.trim().trim_start_matches(',').trim() {
+            "matrix" if args.len() == 6 => {
+                Matrix([args[0], args[1], args[2], args[3], args[4], args[5]])
+            }
+            "translate" => Matrix::translate(arg(0, 0.0), arg(1, 0.0)),
+            "scale" => Matrix::scale(arg(0, 1.0), arg(1, arg(0, 1.0))),
+            "rotate" => {
+                let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                Matrix::translate(cx, cy)
+                    .then(Matrix([cos, sin, -sin, cos, 0.0, 0.0]))
+                    .then(Matrix::translate(-cx, -cy))
+            }
+            "skewX" => Matrix([1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0]),
+            "skewY" => Matrix([1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+            _ => continue,
+        };
+        matrix = matrix.then(next);
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(svg: &str) -> ImportSvgResult {
+        convert(svg, Some("icon".to_string())).expect("import")
+    }
+
+    fn find<'a>(result: &'a ImportSvgResult, name: &str) -> &'a Node {
+        result
+            .fragment
+            .nodes
+            .values()
+            .find(|node| node.name.as_deref() == Some(name))
+            .unwrap_or_else(|| panic!("no node named {name}"))
+    }
+
+    #[test]
+    fn converts_structure_styles_and_gradients() {
+        let result = import(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 100 50">
+                <style>.accent { fill: #ff0000 }</style>
+                <defs>
+                    <linearGradient id="fade" x1="0" y1="0" x2="1" y2="0">
+                        <stop offset="0" stop-color="#000"/>
+                        <stop offset="100%" stop-color="#fff" stop-opacity="0.5"/>
+                    </linearGradient>
+                </defs>
+                <g id="layer" transform="translate(10 5)" stroke="#00f" stroke-width="2">
+                    <rect id="box" class="accent" width="20" height="10" rx="2"/>
+                    <path id="bar" d="M30 0 H50 V10 H30 Z" fill="url(#fade)" opacity=".5"/>
+                </g>
+            </svg>"##,
+        );
+        let fragment = &result.fragment;
+        assert_eq!(fragment.root_ids.len(), 1);
+        let root = &fragment.nodes[&fragment.root_ids[0]];
+        assert_eq!(root.kind, NodeType::Frame);
+        assert_eq!(root.name.as_deref(), Some("icon"));
+        assert_eq!(
+            root.size,
+            Size {
+                width: 200.0,
+                height: 100.0
+            }
+        );
+
+        let group = find(&result, "layer");
+        assert_eq!(group.kind, NodeType::Group);
+        assert_eq!(group.position, Position { x: 20.0, y: 10.0 });
+        assert_eq!(
+            group.size,
+            Size {
+                width: 100.0,
+                height: 20.0
+            }
+        );
+
+        let rect = find(&result, "box");
+        assert_eq!(rect.kind, NodeType::Rectangle);
+        assert_eq!(rect.position, Position { x: 0.0, y: 0.0 });
+        assert_eq!(rect.corner_radius, Some(4.0));
+        assert!(matches!(&rect.fill, Some(Color::Solid { value }) if value == "#ff0000"));
+        let stroke = rect.stroke.as_ref().expect("inherited stroke");
+        assert_eq!(stroke.width, 4.0);
+
+        let bar = find(&result, "bar");
+        assert_eq!(bar.kind, NodeType::Path);
+        assert_eq!(bar.position, Position { x: 60.0, y: 0.0 });
+        assert_eq!(bar.opacity, Some(0.5));
+        let Some(Color::Gradient(gradient)) = &bar.fill else {
+            panic!("expected a gradient fill");
+        };
+        assert_eq!(gradient["kind"], "linear");
+        assert_eq!(gradient["from"], json!({ "x": 0.0, "y": 0.0 }));
+        assert_eq!(gradient["to"], json!({ "x": 1.0, "y": 0.0 }));
+        assert_eq!(gradient["stops"][1]["color"], "rgba(255, 255, 255, 0.5)");
+    }
+
+    #[test]
+    fn paths_become_editable_vectors() {
+        let result = import(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40">
+                <path id="drop" d="M10 0 C15 5 20 10 10 20 C0 10 5 5 10 0 Z"/>
+                <path id="dots" d="M0 30 h2 v2 h-2 z M10 30 h2 v2 h-2 z"/>
+                <path id="ring" fill-rule="evenodd" d="M0 0 h10 v10 h-10 z M2 2 h6 v6 h-6 z"/>
+            </svg>"#,
+        );
+        let drop = find(&result, "drop");
+        let vector = drop.vector.as_ref().expect("vector data");
+        assert!(vector.closed);
+        assert_eq!(vector.points.len(), 2);
+        assert_eq!(vector.segments.len(), 2);
+        let in_handle = vector.points[0].in_handle.expect("merged closing handle");
+        assert_eq!(in_handle.y, 5.0);
+        assert!((in_handle.x + drop.position.x - 5.0).abs() < 1e-9);
+        assert_eq!(
+            vector.points[0].corner_mode.as_deref(),
+            Some("disconnected")
+        );
+        assert_eq!(drop.size.height, 20.0);
+
+        let dots = find(&result, "dots");
+        assert_eq!(dots.kind, NodeType::Group);
+        assert_eq!(dots.children.as_ref().map(Vec::len), Some(2));
+
+        let ring = find(&result, "ring");
+        assert_eq!(ring.kind, NodeType::Path);
+        assert!(ring.vector.is_none());
+        assert_eq!(ring.path.as_ref().unwrap()["fillRule"], "evenodd");
+    }
+
+    #[test]
+    fn rejects_non_svg_documents() {
+        let error = convert("<html><body/></html>", None).err().unwrap();
+        assert!(error.starts_with("invalid_svg:"), "{error}");
+        let error = convert("<svg", None).err().unwrap();
+        assert!(error.starts_with("invalid_svg:"), "{error}");
+    }
+
+    #[test]
+    fn self_references_are_skipped_and_fan_out_is_bounded() {
+        let result = import(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <g id="a"><rect width="1" height="1"/><use href="#a"/><use href="#a"/></g>
+            </svg>"##,
+        );
+        assert_eq!(
+            result
+                .fragment
+                .nodes
+                .values()
+                .filter(|node| node.kind == NodeType::Rectangle)
+                .count(),
+            1
+        );
+        assert!(result
+            .warnings
+            .contains(&"self-referencing <use> elements were skipped".to_string()));
+
+        // Each level draws the one below it four times: 4^15 leaves.
+        let mut svg = String::from(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><defs>
+                <rect id="l0" width="1" height="1"/>"##,
+        );
+        for level in 1..16 {
+            let below = level - 1;
+            svg += &format!(r##"<g id="l{level}">"##);
+            for _ in 0..4 {
+                svg += &format!(r##"<use href="#l{below}"/>"##);
+            }
+            svg += "</g>";
+        }
+        svg += r##"</defs><use href="#l15"/></svg>"##;
+        let error = convert(&svg, None).err().unwrap();
+        assert!(error.starts_with("svg_too_complex:"), "{error}");
+    }
+}
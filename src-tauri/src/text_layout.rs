@@ -0,0 +1,454 @@
+//! Native port of `layoutText` (src/core/text/layout.ts) with font metrics
+//! from installed fonts, so exported text wraps where the canvas wraps it.
+
+use crate::fonts::{self, DirectoryFontSource, FontFaceInfo};
+use crate::scene::num;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+const DEFAULT_PADDING: f64 = 4.0;
+const DEFAULT_MIN_WIDTH: f64 = 20.0;
+/// Used when no installed face matches, roughly Helvetica's averages.
+const ESTIMATED_ADVANCE_EM: f64 = 0.55;
+const ESTIMATED_ASCENT_EM: f64 = 0.8;
+
+/// Families tried for CSS generic names, in order.
+fn generic_candidates(family: &str) -> &'static [&'static str] {
+    match family {
+        "serif" => &[
+            "Times New Roman",
+            "Times",
+            "Noto Serif",
+            "DejaVu Serif",
+            "Liberation Serif",
+        ],
+        "monospace" => &[
+            "Menlo",
+            "SF Mono",
+            "Consolas",
+            "Courier New",
+            "Noto Sans Mono",
+            "DejaVu Sans Mono",
+            "Liberation Mono",
+        ],
+        _ => &[
+            "Helvetica Neue",
+            "Helvetica",
+            "Arial",
+            "Segoe UI",
+            "Inter",
+            "Noto Sans",
+            "DejaVu Sans",
+            "Liberation Sans",
+            "Cantarell",
+        ],
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextLayoutInput<'a> {
+    pub text: &'a str,
+    pub width: f64,
+    pub font_size: f64,
+    pub text_align: &'a str,
+    pub line_height_px: Option<f64>,
+    pub letter_spacing_px: f64,
+    pub resize_mode: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub text: String,
+    pub width: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+fn clamp_min(value: f64, min: f64) -> f64 {
+    if value.is_finite() {
+        value.max(min)
+    } else {
+        min
+    }
+}
+
+/// `layoutText`; `measure` returns a line's width without letter spacing.
+pub fn layout_text(input: &TextLayoutInput, measure: impl Fn(&str) -> f64) -> Vec<TextLine> {
+    let mode = input.resize_mode;
+    let letter_spacing = if input.letter_spacing_px.is_finite() {
+        input.letter_spacing_px
+    } else {
+        0.0
+    };
+    let padding = DEFAULT_PADDING;
+    let font_size = clamp_min(input.font_size, 1.0);
+    let line_height = clamp_min(input.line_height_px.unwrap_or(font_size * 1.2), 1.0);
+    let base_width = clamp_min(input.width, DEFAULT_MIN_WIDTH);
+
+    let measure_line = |line: &str| {
+        if line.is_empty() {
+            return 0.0;
+        }
+        let base = clamp_min(measure(line), 0.0);
+        let glyphs = line.chars().count();
+        if letter_spacing == 0.0 || glyphs <= 1 {
+            base
+        } else {
+            base + (glyphs - 1) as f64 * letter_spacing
+        }
+    };
+    let wrap_width = (base_width - padding * 2.0).max(1.0);
+    let lines: Vec<String> = if mode == "auto-width" {
+        input.text.split('\n').map(str::to_string).collect()
+    } else {
+        input
+            .text
+            .split('\n')
+            .flat_map(|line| wrap_line(line, wrap_width, &measure_line))
+            .collect()
+    };
+    let widths: Vec<f64> = lines.iter().map(|line| measure_line(line)).collect();
+    let content_width = widths.iter().copied().fold(0.0, f64::max);
+    // Auto-height boxes round their width up; fixed boxes keep theirs.
+    let alignment_width = match mode {
+        "auto-width" => content_width,
+        "auto-height" => (DEFAULT_MIN_WIDTH.max(base_width.ceil()) - padding * 2.0).max(0.0),
+        _ => (base_width - padding * 2.0).max(0.0),
+    };
+
+    lines
+        .into_iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(index, (text, width))| {
+            let x = match input.text_align {
+                "center" => padding + (alignment_width - width) / 2.0,
+                "right" => padding + (alignment_width - width),
+                _ => padding,
+            };
+            TextLine {
+                text,
+                width,
+                x,
+                y: padding + index as f64 * line_height,
+            }
+        })
+        .collect()
+}
+
+/// Splits like `/\S+\s*|\s+/g`: words with their trailing whitespace, or
+/// runs of leading whitespace.
+fn tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let in_word = !ch.is_whitespace();
+        let mut seen_space = !in_word;
+        let mut end = index + ch.len_utf8();
+        while let Some(&(next_index, next)) = chars.peek() {
+            let space = next.is_whitespace();
+            if !space && (seen_space || !in_word) {
+                break;
+            }
+            seen_space |= space;
+            end = next_index + next.len_utf8();
+            chars.next();
+        }
+        tokens.push(&line[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+fn wrap_line(line: &str, max_width: f64, measure: &impl Fn(&str) -> f64) -> Vec<String> {
+    if line.is_empty() || max_width <= 0.0 {
+        return vec![String::new()];
+    }
+    if measure(line) <= max_width {
+        return vec![line.to_string()];
+    }
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for token in tokens(line) {
+        if current.is_empty() && token.trim().is_empty() {
+            continue;
+        }
+        let candidate = format!("{current}{token}");
+        if current.is_empty() || measure(&candidate) <= max_width {
+            current = candidate;
+            continue;
+        }
+        lines.push(current.trim_end().to_string());
+        current = token.trim_start().to_string();
+        if current.is_empty() || measure(&current) <= max_width {
+            continue;
+        }
+        let mut broken = split_long_token(&current, max_width, measure);
+        current = broken.pop().unwrap_or_default();
+        lines.extend(broken);
+    }
+    lines.push(current.trim_end().to_string());
+    lines
+}
+
+fn split_long_token(token: &str, max_width: f64, measure: &impl Fn(&str) -> f64) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    for glyph in token.chars() {
+        let candidate = format!("{current}{glyph}");
+        if current.is_empty() || measure(&candidate) <= max_width {
+            current = candidate;
+            continue;
+        }
+        result.push(std::mem::take(&mut current));
+        current.push(glyph);
+        if measure(&current) > max_width {
+            result.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() || result.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+/// A resolved face and its file bytes.
+pub struct LoadedFont {
+    pub info: FontFaceInfo,
+    pub data: Arc<Vec<u8>>,
+}
+
+impl LoadedFont {
+    pub fn face(&self) -> Option<Face<'_>> {
+        Face::parse(&self.data, self.info.face_index).ok()
+    }
+}
+
+/// A glyph positioned along a line, `x` from the line start.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedGlyph {
+    pub ch: char,
+    pub glyph_id: u16,
+    pub x: f64,
+    pub advance: f64,
+}
+
+/// Measures and shapes text with an installed face, or with estimated
+/// metrics when the family isn't installed.
+#[derive(Clone)]
+pub struct TextMetrics {
+    pub font: Option<Arc<LoadedFont>>,
+}
+
+impl TextMetrics {
+    /// Advances without kerning or shaping, like per-glyph canvas drawing.
+    pub fn glyphs(&self, text: &str, font_size: f64, letter_spacing: f64) -> Vec<PlacedGlyph> {
+        let face = self.font.as_ref().and_then(|font| font.face());
+        let scale = face
+            .as_ref()
+            .map_or(0.0, |face| font_size / face.units_per_em() as f64);
+        let mut x = 0.0;
+        text.chars()
+            .map(|ch| {
+                let (glyph_id, advance) = match &face {
+                    Some(face) => {
+                        let glyph = face.glyph_index(ch).unwrap_or(GlyphId(0));
+                        let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale;
+                        (glyph.0, advance)
+                    }
+                    None => (0, font_size * ESTIMATED_ADVANCE_EM),
+                };
+                let placed = PlacedGlyph {
+                    ch,
+                    glyph_id,
+                    x,
+                    advance,
+                };
+                x += advance + letter_spacing;
+                placed
+            })
+            .collect()
+    }
+
+    pub fn measure(&self, text: &str, font_size: f64) -> f64 {
+        self.glyphs(text, font_size, 0.0)
+            .iter()
+            .map(|glyph| glyph.advance)
+            .sum()
+    }
+
+    /// Distance from the top of the em box, where canvas's `top` baseline
+    /// places text, down to the alphabetic baseline.
+    pub fn ascent(&self, font_size: f64) -> f64 {
+        let Some(face) = self.font.as_ref().and_then(|font| font.face()) else {
+            return font_size * ESTIMATED_ASCENT_EM;
+        };
+        let ascender = face.ascender() as f64;
+        let descender = face.descender() as f64;
+        if ascender - descender <= 0.0 {
+            return font_size * ESTIMATED_ASCENT_EM;
+        }
+        font_size * ascender / (ascender - descender)
+    }
+
+    /// SVG path data for a line of text whose em box starts at (x, y).
+    pub fn outline(&self, text: &str, x: f64, y: f64, font_size: f64, spacing: f64) -> String {
+        let Some(face) = self.font.as_ref().and_then(|font| font.face()) else {
+            return String::new();
+        };
+        let scale = font_size / face.units_per_em() as f64;
+        let baseline = y + self.ascent(font_size);
+        let mut path = PathSink {
+            d: String::new(),
+            x: 0.0,
+            y: baseline,
+            scale,
+        };
+        for glyph in self.glyphs(text, font_size, spacing) {
+            path.x = x + glyph.x;
+            face.outline_glyph(GlyphId(glyph.glyph_id), &mut path);
+        }
+        path.d.trim_end().to_string()
+    }
+}
+
+struct PathSink {
+    d: String,
+    x: f64,
+    y: f64,
+    scale: f64,
+}
+
+impl PathSink {
+    fn point(&self, x: f32, y: f32) -> String {
+        format!(
+            "{} {}",
+            num(self.x + x as f64 * self.scale),
+            num(self.y - y as f64 * self.scale)
+        )
+    }
+}
+
+impl OutlineBuilder for PathSink {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.d.push_str(&format!("M {point} "));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.d.push_str(&format!("L {point} "));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (control, point) = (self.point(x1, y1), self.point(x, y));
+        self.d.push_str(&format!("Q {control} {point} "));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, point) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.d.push_str(&format!("C {c1} {c2} {point} "));
+    }
+
+    fn close(&mut self) {
+        self.d.push_str("Z ");
+    }
+}
+
+/// Resolves CSS font families to installed faces and keeps their bytes
+/// around for the length of an export.
+pub struct FontBook {
+    faces: Vec<FontFaceInfo>,
+    files: HashMap<String, Arc<Vec<u8>>>,
+    resolved: HashMap<(String, u16), TextMetrics>,
+}
+
+impl FontBook {
+    pub fn system() -> Self {
+        Self::with_faces(DirectoryFontSource::system().faces())
+    }
+
+    pub fn with_faces(faces: Vec<FontFaceInfo>) -> Self {
+        FontBook {
+            faces,
+            files: HashMap::new(),
+            resolved: HashMap::new(),
+        }
+    }
+
+    /// Metrics for a CSS `font-family` list such as `"Inter", sans-serif`.
+    pub fn metrics(&mut self, family_list: &str, weight: u16) -> TextMetrics {
+        let key = (family_list.to_string(), weight);
+        if let Some(metrics) = self.resolved.get(&key) {
+            return metrics.clone();
+        }
+        let mut families: Vec<&str> = family_list
+            .split(',')
+            .map(|family| family.trim().trim_matches(['"', '\'']).trim())
+            .filter(|family| !family.is_empty())
+            .flat_map(|family| match family {
+                "sans-serif" | "serif" | "monospace" | "system-ui" => {
+                    generic_candidates(family).to_vec()
+                }
+                _ => vec![family],
+            })
+            .collect();
+        families.extend(generic_candidates("sans-serif"));
+
+        let font = families.iter().find_map(|family| {
+            let info = fonts::select_face(&self.faces, family, weight, "normal")?.clone();
+            let data = match self.files.get(&info.path) {
+                Some(data) => data.clone(),
+                None => {
+                    let data = Arc::new(fs::read(&info.path).ok()?);
+                    self.files.insert(info.path.clone(), data.clone());
+                    data
+                }
+            };
+            let font = LoadedFont { info, data };
+            font.face().is_some().then(|| Arc::new(font))
+        });
+        let metrics = TextMetrics { font };
+        self.resolved.insert(key, metrics.clone());
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_text, tokens, TextLayoutInput};
+
+    fn input<'a>(text: &'a str, width: f64, mode: &'a str) -> TextLayoutInput<'a> {
+        TextLayoutInput {
+            text,
+            width,
+            font_size: 10.0,
+            text_align: "left",
+            line_height_px: None,
+            letter_spacing_px: 0.0,
+            resize_mode: mode,
+        }
+    }
+
+    #[test]
+    fn tokens_keep_trailing_whitespace() {
+        assert_eq!(tokens("  ab  cd e"), vec!["  ", "ab  ", "cd ", "e"]);
+    }
+
+    #[test]
+    fn wraps_words_and_breaks_long_tokens() {
+        let measure = |text: &str| text.chars().count() as f64 * 10.0;
+        let lines = layout_text(&input("aa bb cccccc", 48.0, "auto-height"), measure);
+        let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(text, vec!["aa", "bb", "cccc", "cc"]);
+        assert_eq!(lines[3].y, 40.0);
+
+        let mut centered = input("aa\nbbbb", 0.0, "auto-width");
+        centered.text_align = "center";
+        let lines = layout_text(&centered, measure);
+        assert_eq!((lines[0].x, lines[1].x), (14.0, 4.0));
+    }
+}
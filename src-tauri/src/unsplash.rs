@@ -1,6 +1,7 @@
+use crate::binary_ipc::{BlobRef, FramedResponse};
+use crate::image_import::{self, CaptureMetadata};
 use base64::{engine::general_purpose, Engine as _};
-use image::GenericImageView;
-use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::env;
@@ -45,6 +46,19 @@ pub struct UnsplashFetchImageResult {
     pub mime: String,
     pub width: u32,
     pub height: u32,
+    /// Capture details from the photo's EXIF, which is stripped from `data`
+    pub capture: CaptureMetadata,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsplashFetchImageRawResult {
+    pub data: BlobRef,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    /// Capture details from the photo's EXIF, which is stripped from `data`
+    pub capture: CaptureMetadata,
 }
 
 fn build_client() -> Result<Client, String> {
@@ -261,10 +275,15 @@ pub async fn unsplash_track_download(args: UnsplashTrackDownloadArgs) -> Result<
     Ok(parsed.unwrap_or_else(|_| json!({ "tracked": true })))
 }
 
-#[tauri::command]
-pub async fn unsplash_fetch_image(
-    args: UnsplashFetchImageArgs,
-) -> Result<UnsplashFetchImageResult, String> {
+struct FetchedImage {
+    bytes: Vec<u8>,
+    mime: String,
+    width: u32,
+    height: u32,
+    capture: CaptureMetadata,
+}
+
+async fn fetch_image(args: UnsplashFetchImageArgs) -> Result<FetchedImage, String> {
     let image_url = parse_and_validate_https_url(&args.url, UNSPLASH_IMAGE_HOST)?;
     let client = build_client()?;
 
@@ -279,27 +298,53 @@ pub async fn unsplash_fetch_image(
         return Err(format_unsplash_http_error(status, &body));
     }
 
-    let mime = response
-        .headers()
-        .get(CONTENT_TYPE)
-        .and_then(|value| value.to_str().ok())
-        .and_then(|value| value.split(';').next())
-        .unwrap_or("image/jpeg")
-        .to_string();
-
     let bytes = response
         .bytes()
         .await
         .map_err(|e| format!("unsplash_response_read_failed: {e}"))?;
-    let decoded =
-        image::load_from_memory(&bytes).map_err(|e| format!("unsplash_decode_failed: {e}"))?;
-    let (width, height) = decoded.dimensions();
+    let prepared = image_import::prepare(bytes.to_vec())
+        .map_err(|e| format!("unsplash_decode_failed: {e}"))?;
+
+    Ok(FetchedImage {
+        bytes: prepared.bytes,
+        mime: prepared.mime,
+        width: prepared.width,
+        height: prepared.height,
+        capture: prepared.capture,
+    })
+}
+
+#[tauri::command]
+pub async fn unsplash_fetch_image(
+    args: UnsplashFetchImageArgs,
+) -> Result<UnsplashFetchImageResult, String> {
+    let image = fetch_image(args).await?;
 
     Ok(UnsplashFetchImageResult {
-        data_base64: general_purpose::STANDARD.encode(&bytes),
-        mime,
-        width,
-        height,
+        data_base64: general_purpose::STANDARD.encode(&image.bytes),
+        mime: image.mime,
+        width: image.width,
+        height: image.height,
+        capture: image.capture,
+    })
+}
+
+/// `unsplash_fetch_image` returning a framed binary response whose metadata
+/// is [`UnsplashFetchImageRawResult`].
+#[tauri::command]
+pub async fn unsplash_fetch_image_raw(
+    args: UnsplashFetchImageArgs,
+) -> Result<tauri::ipc::Response, String> {
+    let image = fetch_image(args).await?;
+
+    let mut framed = FramedResponse::default();
+    let data = framed.push(&image.bytes);
+    framed.finish(&UnsplashFetchImageRawResult {
+        data,
+        mime: image.mime,
+        width: image.width,
+        height: image.height,
+        capture: image.capture,
     })
 }
 
@@ -1,6 +1,7 @@
 import React, { useState, useCallback, useEffect, useMemo, useRef, useLayoutEffect } from 'react';
 import { flushSync } from 'react-dom';
 import { convertFileSrc, invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { Canvas } from './ui/Canvas';
 import { ActionBar, type Tool } from './ui/ActionBar';
 import { PropertiesPanel } from './ui/PropertiesPanel';
@@ -63,7 +64,10 @@ import type {
 	ComponentVariantMap,
 	Constraints,
 	Document,
+	ImageAssetVariant,
+	ImageCaptureMetadata,
 	ImageMeta,
+	ImageMetaPhoto,
 	ImageMetaUnsplash,
 	ImageOutline,
 	Node,
@@ -166,7 +170,15 @@ type RemoveBackgroundResult = {
 	width: number;
 	height: number;
 	revision?: number;
+	provider: string;
+	model: string;
+	decontaminatedPngBase64?: string;
+	instances: Array<{ id: number; bounds: MaskBounds; maskPngBase64: string }>;
+	selectedInstances: number[];
+	bounds?: MaskBounds;
+	cutout?: { dataBase64: string; mime: string; x: number; y: number; width: number; height: number };
 };
+type MaskBounds = { x: number; y: number; width: number; height: number };
 type UnsplashSearchResult = {
 	id: string;
 	width: number;
@@ -190,6 +202,7 @@ type UnsplashFetchImageResult = {
 	mime: string;
 	width: number;
 	height: number;
+	capture: ImageCaptureMetadata;
 };
 type IconifySearchResponse = {
 	icons: string[];
@@ -848,6 +861,70 @@ const getMimeType = (path: string): string => {
 	}
 };
 
+// Formats the webview can't be relied on to draw (or would animate); these
+// are decoded natively and normalized to PNG.
+const NATIVE_IMPORT_EXTENSIONS = new Set(['gif', 'ico', 'icns', 'heic', 'heif']);
+
+// Photo formats that can carry EXIF orientation, ICC profiles and private
+// metadata; these go through the native import stage.
+const PREPARED_IMPORT_EXTENSIONS = new Set(['jpg', 'jpeg', 'png', 'webp']);
+
+const loadImportImage = async (
+	path: string,
+): Promise<{ dataBase64: string; mime: string; width?: number; height?: number; meta?: ImageMetaPhoto }> => {
+	const ext = path.split('.').pop()?.toLowerCase();
+	if (ext && NATIVE_IMPORT_EXTENSIONS.has(ext)) {
+		const { dataBase64, mime, width, height } = await invoke<{
+			dataBase64: string;
+			mime: string;
+			width: number;
+			height: number;
+			sourceFormat: string;
+		}>('import_image', { args: { path } });
+		return { dataBase64, mime, width, height };
+	}
+	if (ext && PREPARED_IMPORT_EXTENSIONS.has(ext)) {
+		const { dataBase64, mime, width, height, capture } = await invoke<{
+			dataBase64: string;
+			mime: string;
+			width: number;
+			height: number;
+			capture: ImageCaptureMetadata;
+		}>('prepare_image', { args: { path } });
+		const meta: ImageMetaPhoto | undefined =
+			Object.keys(capture).length > 0 ? { kind: 'photo', capture, insertedAt: Date.now() } : undefined;
+		return { dataBase64, mime, width, height, meta };
+	}
+	const dataBase64 = await invoke<string>('load_binary', { path });
+	return { dataBase64, mime: getMimeType(path) };
+};
+
+type ImageVariantsResult = {
+	hash: string;
+	width: number;
+	height: number;
+	proxy: ImageAssetVariant | null;
+	thumbnail: ImageAssetVariant;
+};
+
+// The canvas draws the proxy and the Assets panel's Images section the
+// thumbnail; the original stays in the asset for export. Variants are an optimization, so
+// a failure only logs.
+const loadImageVariants = async (
+	dataBase64: string,
+): Promise<{ hash: string; proxy?: ImageAssetVariant; thumbnail: ImageAssetVariant } | null> => {
+	try {
+		const [result] = await invoke<ImageVariantsResult[]>('image_variants', {
+			args: { images: [{ dataBase64 }] },
+		});
+		if (!result) return null;
+		return { hash: result.hash, proxy: result.proxy ?? undefined, thumbnail: result.thumbnail };
+	} catch (error) {
+		console.warn('Image variants failed:', error);
+		return null;
+	}
+};
+
 const buildDataUrl = (mime: string, dataBase64: string): string => {
 	return `data:${mime};base64,${dataBase64}`;
 };
@@ -897,8 +974,8 @@ const resolveImageBytesForNode = async (
 	}
 
 	if (image.originalPath) {
-		const dataBase64 = await invoke<string>('load_binary', { path: image.originalPath });
-		return { dataBase64, mime: image.mime ?? getMimeType(image.originalPath) };
+		const { dataBase64, mime } = await loadImportImage(image.originalPath);
+		return { dataBase64, mime };
 	}
 
 	throw new Error('Image source missing');
@@ -970,6 +1047,82 @@ const base64ToUint8Array = (base64: string): Uint8Array => {
 	return bytes;
 };
 
+type BundleFont = {
+	family: string;
+	styleName: string;
+	weight: number;
+	style: string;
+	dataBase64: string;
+};
+
+// Bundles carry the project fonts their text uses; registering them lets a
+// design render as authored on machines without those fonts installed.
+const registerBundleFonts = async (path: string): Promise<void> => {
+	try {
+		const fonts = await invoke<BundleFont[]>('load_bundle_fonts', { args: { path } });
+		for (const font of fonts) {
+			const face = new FontFace(font.family, base64ToUint8Array(font.dataBase64), {
+				weight: String(font.weight),
+				style: font.style,
+			});
+			window.document.fonts.add(await face.load());
+		}
+	} catch (error) {
+		console.warn('Failed to load bundled fonts:', error);
+	}
+};
+
+type DocumentVersion = {
+	mtimeMs: number | null;
+	hash: string | null;
+};
+
+// save_document rejects with `conflict: {...}` when the file changed on disk
+// since the version we passed; the payload describes what is there now.
+const parseSaveConflict = (error: unknown): DocumentVersion | null => {
+	if (typeof error !== 'string' || !error.startsWith('conflict: ')) return null;
+	try {
+		return JSON.parse(error.slice('conflict: '.length)) as DocumentVersion;
+	} catch {
+		return null;
+	}
+};
+
+type WatchKind = 'document' | 'pluginFolder' | 'asset';
+
+type FileChange = {
+	path: string;
+	kind: WatchKind;
+	exists: boolean;
+};
+
+// Returns the matching unwatch, for use as an effect cleanup.
+const watchPaths = (paths: string[], kind: WatchKind): (() => void) => {
+	for (const path of paths) {
+		void invoke('watch_path', { args: { path, kind } }).catch((error) => {
+			console.warn(`Failed to watch ${path}:`, error);
+		});
+	}
+	return () => {
+		for (const path of paths) {
+			void invoke('unwatch_path', { args: { path } }).catch(() => undefined);
+		}
+	};
+};
+
+// Preview stored in the saved bundle; saving goes ahead without one.
+const renderDocumentThumbnail = async (doc: Document, pageId: string): Promise<string | undefined> => {
+	const page = doc.pages.find((candidate) => candidate.id === pageId) ?? doc.pages[0];
+	if (!page) return undefined;
+	try {
+		const snapshot = await exportNodeSnapshot(doc, page.rootId, { maxDim: 512, format: 'png' });
+		return snapshot.dataBase64;
+	} catch (error) {
+		console.warn('Failed to render document thumbnail:', error);
+		return undefined;
+	}
+};
+
 const arrayBufferToBase64 = (buffer: ArrayBuffer): string => {
 	const bytes = new Uint8Array(buffer);
 	let binary = '';
@@ -1009,7 +1162,7 @@ const resolveStoredPlugin = (stored: StoredPlugin): PluginRegistration | null =>
 	};
 };
 
-const IMAGE_EXTENSIONS = new Set(['png', 'jpg', 'jpeg', 'gif', 'webp', 'svg', 'ico', 'icns']);
+const IMAGE_EXTENSIONS = new Set(['png', 'jpg', 'jpeg', 'gif', 'webp', 'svg', 'ico', 'icns', 'heic', 'heif']);
 
 const isLikelyImageName = (name: string): boolean => {
 	const ext = name.split('.').pop()?.toLowerCase();
@@ -1334,6 +1487,9 @@ export const App: React.FC = () => {
 	} = useDocument();
 	const [activePageId, setActivePageId] = useState<string>(() => document.activePageId);
 	const pageEditorStateRef = useRef<Record<string, PageEditorState>>({});
+	// Last version of the open file we loaded or wrote; saves fail rather than
+	// overwrite changes made on disk since.
+	const savedVersionRef = useRef<{ path: string; version: DocumentVersion } | null>(null);
 
 	const [appView, setAppView] = useState<'projects' | 'editor'>('projects');
 	const [projects, setProjects] = useState<ProjectMeta[]>(() => loadProjects());
@@ -2255,6 +2411,7 @@ export const App: React.FC = () => {
 			const commands: Command[] = [];
 
 			if (assetId && resolvedBase64 && resolvedMime) {
+				const variants = resolvedMime === 'image/svg+xml' ? null : await loadImageVariants(resolvedBase64);
 				commands.push({
 					id: generateId(),
 					timestamp: Date.now(),
@@ -2269,6 +2426,7 @@ export const App: React.FC = () => {
 							dataBase64: resolvedBase64,
 							width: naturalSize.width,
 							height: naturalSize.height,
+							...variants,
 						},
 					},
 				} as Command);
@@ -2426,8 +2584,8 @@ export const App: React.FC = () => {
 									...(node.image || {}),
 									maskAssetId,
 									bgRemoveMeta: {
-										provider: 'apple-vision',
-										model: 'foreground-instance-mask',
+										provider: result.provider,
+										model: result.model,
 										revision: result.revision,
 										createdAt: now,
 									},
@@ -2453,6 +2611,8 @@ export const App: React.FC = () => {
 					showToast('Background removal requires the macOS app (14+).');
 				} else if (message.includes('no_subject_detected')) {
 					showToast('No subject detected in this image.');
+				} else if (message.includes('model_not_found')) {
+					showToast('Background removal model is missing from this build.');
 				} else {
 					showToast('Background removal failed. Try re-importing the image.');
 				}
@@ -3912,6 +4072,7 @@ export const App: React.FC = () => {
 							photographerProfileUrl: appendUnsplashUtm(photo.user.links.html),
 							photoUnsplashUrl: appendUnsplashUtm(photo.links.html),
 							downloadLocation: photo.links.downloadLocation,
+							capture: fetched.capture,
 							insertedAt: Date.now(),
 						};
 
@@ -7375,6 +7536,28 @@ export const App: React.FC = () => {
 		void persistDraftIfNeeded();
 	}, [persistDraftIfNeeded]);
 
+	const saveDocumentAt = useCallback(
+		async (
+			path: string,
+			args: { content: string; externalizeAssets?: boolean },
+			options: { overwrite?: DocumentVersion } = {},
+		): Promise<DocumentVersion> => {
+			const known = savedVersionRef.current?.path === path ? savedVersionRef.current.version : null;
+			const expected = options.overwrite ?? known;
+			const version = await invoke<DocumentVersion>('save_document', {
+				args: {
+					path,
+					...args,
+					expectedMtimeMs: expected?.mtimeMs ?? null,
+					expectedHash: expected?.hash ?? null,
+				},
+			});
+			savedVersionRef.current = { path, version };
+			return version;
+		},
+		[],
+	);
+
 	const persistCurrentWorkBeforeNavigation = useCallback(async () => {
 		if (appView !== 'editor' || !isDirty) {
 			return;
@@ -7384,12 +7567,7 @@ export const App: React.FC = () => {
 
 		if (currentPath) {
 			try {
-				await invoke('save_document', {
-					args: {
-						path: currentPath,
-						content,
-					},
-				});
+				await saveDocumentAt(currentPath, { content, externalizeAssets: true });
 				await deleteDraftByKey(buildDraftKey(currentPath));
 				return;
 			} catch (error) {
@@ -7409,12 +7587,19 @@ export const App: React.FC = () => {
 		activePageId,
 		document,
 		isDirty,
+		saveDocumentAt,
 		saveDraftSnapshot,
 	]);
 
 	const applyLoadedDocument = useCallback(
-		(doc: Document, path: string | null) => {
+		// `version` is what the caller read or saved at `path`; the first save
+		// checks the file against it.
+		(doc: Document, path: string | null, version: DocumentVersion | null) => {
 			replaceDocument(doc);
+			savedVersionRef.current = path && version ? { path, version } : null;
+			if (path) {
+				void registerBundleFonts(path);
+			}
 			pageEditorStateRef.current = {};
 			setActivePageId(doc.activePageId);
 			setCurrentPath(path);
@@ -7440,14 +7625,17 @@ export const App: React.FC = () => {
 	const loadDocumentFromPath = useCallback(
 		async (path: string): Promise<boolean> => {
 			try {
-				const content = await invoke<string>('load_document', { args: { path } });
+				const { content, version } = await invoke<{ content: string; version: DocumentVersion }>(
+					'load_document',
+					{ args: { path } },
+				);
 				const result = parseDocumentText(content);
 				if (!result.ok) {
 					const details = result.details?.join('\n');
 					alert(`Failed to load document: ${result.error}${details ? `\n${details}` : ''}`);
 					return false;
 				}
-				applyLoadedDocument(result.doc, path);
+				applyLoadedDocument(result.doc, path, version);
 				if (result.warnings.length > 0) {
 					console.warn('Document warnings:', result.warnings);
 				}
@@ -7461,6 +7649,139 @@ export const App: React.FC = () => {
 		[applyLoadedDocument],
 	);
 
+	// External edits to the open file, dev plugins and linked images arrive as
+	// debounced `file-changed` events instead of being polled for.
+	const devPluginFolders = useMemo(
+		() =>
+			devPlugins
+				.map((plugin) => plugin.path)
+				.filter((path): path is string => Boolean(path))
+				.join('\n'),
+		[devPlugins],
+	);
+	const linkedImagePaths = useMemo(() => {
+		const paths = new Set<string>();
+		for (const node of Object.values(document.nodes)) {
+			if (node.type === 'image' && node.image?.assetId && node.image.originalPath) {
+				paths.add(node.image.originalPath);
+			}
+		}
+		return Array.from(paths).sort().join('\n');
+	}, [document.nodes]);
+
+	useEffect(() => {
+		if (appView !== 'editor' || !currentPath) return;
+		return watchPaths([currentPath], 'document');
+	}, [appView, currentPath]);
+
+	useEffect(() => watchPaths(devPluginFolders.split('\n').filter(Boolean), 'pluginFolder'), [devPluginFolders]);
+
+	useEffect(() => {
+		if (appView !== 'editor') return;
+		return watchPaths(linkedImagePaths.split('\n').filter(Boolean), 'asset');
+	}, [appView, linkedImagePaths]);
+
+	const handleFileChange = useCallback(
+		async (change: FileChange) => {
+			if (change.kind === 'document') {
+				if (change.path !== currentPath) return;
+				if (!change.exists) {
+					showToast('The open file was moved or deleted outside Galileo.');
+					return;
+				}
+				const mtimeMs = await invoke<number | null>('get_file_mtime', { path: change.path });
+				const saved = savedVersionRef.current;
+				if (saved?.path === change.path && saved.version.mtimeMs === mtimeMs) {
+					// Our own save.
+					return;
+				}
+				if (isDirty) {
+					showToast('This file was changed outside Galileo; saving will ask before overwriting.');
+					return;
+				}
+				if (await loadDocumentFromPath(change.path)) {
+					showToast('Reloaded changes made outside Galileo.');
+				}
+				return;
+			}
+
+			if (change.kind === 'pluginFolder') {
+				const reload = (plugin: PluginRegistration): PluginRegistration =>
+					plugin.source === 'dev' && plugin.path === change.path
+						? { ...plugin, entryUrl: `${plugin.entryUrl.split('?')[0]}?v=${Date.now()}` }
+						: plugin;
+				setPlugins((prev) => prev.map(reload));
+				setActivePlugin((prev) => (prev ? reload(prev) : prev));
+				return;
+			}
+
+			if (!change.exists) return;
+			const assetIds = new Set<string>();
+			for (const node of Object.values(document.nodes)) {
+				if (node.type === 'image' && node.image?.assetId && node.image.originalPath === change.path) {
+					assetIds.add(node.image.assetId);
+				}
+			}
+			if (assetIds.size === 0) return;
+			try {
+				const { dataBase64, mime, width, height } = await loadImportImage(change.path);
+				const size =
+					width && height ? { width, height } : await getImageSize(`data:${mime};base64,${dataBase64}`);
+				const variants = mime === 'image/svg+xml' ? null : await loadImageVariants(dataBase64);
+				const commands: Command[] = Array.from(assetIds).map(
+					(assetId) =>
+						({
+							id: generateId(),
+							timestamp: Date.now(),
+							source: 'user',
+							description: 'Update linked image',
+							type: 'createAsset',
+							payload: {
+								id: assetId,
+								asset: {
+									type: 'image',
+									mime,
+									dataBase64,
+									width: size.width,
+									height: size.height,
+									...variants,
+								},
+							},
+						}) as Command,
+				);
+				executeCommand(
+					commands.length > 1
+						? {
+								id: generateId(),
+								timestamp: Date.now(),
+								source: 'user',
+								description: 'Update linked image',
+								type: 'batch',
+								payload: { commands },
+							}
+						: commands[0],
+				);
+			} catch (error) {
+				console.warn('Failed to reload linked image:', error);
+			}
+		},
+		[currentPath, document.nodes, executeCommand, isDirty, loadDocumentFromPath, showToast],
+	);
+
+	const fileChangeHandlerRef = useRef(handleFileChange);
+	useEffect(() => {
+		fileChangeHandlerRef.current = handleFileChange;
+	}, [handleFileChange]);
+
+	useEffect(() => {
+		const unlisten = listen<FileChange>('file-changed', ({ payload }) => {
+			void fileChangeHandlerRef.current(payload);
+		});
+		return () => {
+			void unlisten.then((stop) => stop());
+		};
+	}, []);
+
 	const tryRestoreDraftForPath = useCallback(
 		async (path: string): Promise<boolean> => {
 			try {
@@ -7486,7 +7807,8 @@ export const App: React.FC = () => {
 					return false;
 				}
 
-				applyLoadedDocument(parsed.doc, path);
+				// The user chose the draft over the file as it was when compared.
+				applyLoadedDocument(parsed.doc, path, { mtimeMs: fileMtime, hash: null });
 				markDirty();
 				return true;
 			} catch (error) {
@@ -7524,17 +7846,23 @@ export const App: React.FC = () => {
 		const path = ensureGalileoExtension(pickedPath);
 		try {
 			const doc = createDocument();
-			await invoke('save_document', {
-				args: { path, content: serializeDocument(doc, { activePageId: doc.activePageId }) },
+			const version = await saveDocumentAt(path, {
+				content: serializeDocument(doc, { activePageId: doc.activePageId }),
 			});
-			applyLoadedDocument(doc, path);
+			applyLoadedDocument(doc, path, version);
 			registerProjectOpened(path);
 			setAppView('editor');
 		} catch (error) {
 			console.error('Create project error:', error);
 			alert('Failed to create project');
 		}
-	}, [applyLoadedDocument, ensureGalileoExtension, persistCurrentWorkBeforeNavigation, registerProjectOpened]);
+	}, [
+		applyLoadedDocument,
+		ensureGalileoExtension,
+		persistCurrentWorkBeforeNavigation,
+		registerProjectOpened,
+		saveDocumentAt,
+	]);
 
 	const handleOpenFile = useCallback(async () => {
 		const path = await invoke<string>('show_open_dialog');
@@ -7603,7 +7931,7 @@ export const App: React.FC = () => {
 				await invoke('delete_document', { args: { path: project.path } });
 				updateProjects((prev) => removeProjectById(prev, project.id));
 				if (currentPath === project.path) {
-					applyLoadedDocument(createDocument(), null);
+					applyLoadedDocument(createDocument(), null, null);
 					setActiveProjectId(null);
 					setAppView('projects');
 				}
@@ -7670,9 +7998,21 @@ export const App: React.FC = () => {
 				path = ensureGalileoExtension(pickedPath);
 			}
 
-			await invoke('save_document', {
-				args: { path, content: serializeDocument(document, { activePageId }) },
-			});
+			const saveArgs = {
+				content: serializeDocument(document, { activePageId }),
+				externalizeAssets: true,
+			};
+			try {
+				await saveDocumentAt(path, saveArgs);
+			} catch (error) {
+				const onDisk = parseSaveConflict(error);
+				if (!onDisk) throw error;
+				const overwrite = window.confirm(
+					'This file was changed outside Galileo since you opened it. Overwrite those changes?',
+				);
+				if (!overwrite) return;
+				await saveDocumentAt(path, saveArgs, { overwrite: onDisk });
+			}
 			if (pickedPath) {
 				setCurrentPath(path);
 				registerProjectOpened(path);
@@ -7685,7 +8025,40 @@ export const App: React.FC = () => {
 			console.error('Save error:', error);
 			alert('Failed to save document');
 		}
-	}, [activePageId, currentPath, deleteDraftByKey, document, ensureGalileoExtension, markSaved, registerProjectOpened]);
+	}, [
+		activePageId,
+		currentPath,
+		deleteDraftByKey,
+		document,
+		ensureGalileoExtension,
+		markSaved,
+		registerProjectOpened,
+		saveDocumentAt,
+	]);
+
+	// Packages every asset, the project fonts and a preview into one zip to
+	// share. The open document keeps saving as JSON beside its asset store.
+	const handleSavePackage = useCallback(async () => {
+		try {
+			const pickedPath = await invoke<string>('show_save_dialog');
+			if (!pickedPath) {
+				return;
+			}
+			await invoke<DocumentVersion>('save_document', {
+				args: {
+					path: ensureGalileoExtension(pickedPath),
+					content: serializeDocument(document, { activePageId }),
+					bundle: true,
+					thumbnailBase64: await renderDocumentThumbnail(document, activePageId),
+					projectPath: currentPath,
+				},
+			});
+			alert('Package saved successfully!');
+		} catch (error) {
+			console.error('Save package error:', error);
+			alert('Failed to save package');
+		}
+	}, [activePageId, currentPath, document, ensureGalileoExtension]);
 
 	const handleImportImage = useCallback(async () => {
 		try {
@@ -7694,12 +8067,25 @@ export const App: React.FC = () => {
 				return;
 			}
 
-			const base64 = await invoke<string>('load_binary', { path });
-			const mime = getMimeType(path);
+			if (path.toLowerCase().endsWith('.svg')) {
+				try {
+					const result = await invoke<{ fragment: ClipboardPayload; warnings: string[] }>('import_svg', {
+						args: { path },
+					});
+					if (result.warnings.length) {
+						console.warn('SVG import:', result.warnings.join('; '));
+					}
+					pasteClipboardPayload(result.fragment);
+					return;
+				} catch (error) {
+					console.warn('SVG import failed, inserting as an image instead:', error);
+				}
+			}
+
+			const image = await loadImportImage(path);
 			const name = path.split(/[/\\\\]/).pop();
 			await insertImageNode({
-				dataBase64: base64,
-				mime,
+				...image,
 				name,
 				originalPath: path,
 			});
@@ -7707,7 +8093,7 @@ export const App: React.FC = () => {
 			console.error('Import error:', error);
 			alert('Failed to import image');
 		}
-	}, [insertImageNode]);
+	}, [insertImageNode, pasteClipboardPayload]);
 
 	const handleCreateDeviceFrame = useCallback(
 		(preset: DevicePreset) => {
@@ -7877,12 +8263,10 @@ export const App: React.FC = () => {
 						for (let i = 0; i < paths.length; i += 1) {
 							const path = paths[i];
 							if (!isLikelyImageName(path)) continue;
-							const base64 = await invoke<string>('load_binary', { path });
-							const mime = getMimeType(path);
+							const image = await loadImportImage(path);
 							const name = path.split(/[/\\\\]/).pop();
 							await insertImageNode({
-								dataBase64: base64,
-								mime,
+								...image,
 								name,
 								originalPath: path,
 								index: i,
@@ -7965,12 +8349,10 @@ export const App: React.FC = () => {
 						for (let i = 0; i < paths.length; i += 1) {
 							const path = paths[i];
 							if (!isLikelyImageName(path)) continue;
-							const base64 = await invoke<string>('load_binary', { path });
-							const mime = getMimeType(path);
+							const image = await loadImportImage(path);
 							const name = path.split(/[/\\\\]/).pop();
 							await insertImageNode({
-								dataBase64: base64,
-								mime,
+								...image,
 								name,
 								originalPath: path,
 								index: i,
@@ -8057,7 +8439,9 @@ export const App: React.FC = () => {
 					return;
 				}
 
-				applyLoadedDocument(result.doc, draft.path ?? null);
+				const path = draft.path ?? null;
+				const mtimeMs = path ? await invoke<number | null>('get_file_mtime', { path }) : null;
+				applyLoadedDocument(result.doc, path, path ? { mtimeMs, hash: null } : null);
 				markDirty();
 				setAppView('editor');
 			} catch (error) {
@@ -8555,6 +8939,15 @@ export const App: React.FC = () => {
 		];
 
 		if (appView === 'editor') {
+			items.push({
+				id: 'command-save-package',
+				label: 'Save as Package…',
+				description: 'One file with images, fonts and a preview, for sharing',
+				section: 'Commands',
+				action: () => {
+					void handleSavePackage();
+				},
+			});
 			items.push({
 				id: 'command-back-projects',
 				label: 'Back to Projects',
@@ -8599,6 +8992,7 @@ export const App: React.FC = () => {
 		handleCreateProject,
 		handleOpenFile,
 		handleOpenProject,
+		handleSavePackage,
 		missingPaths,
 		projects,
 	]);
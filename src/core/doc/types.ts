@@ -120,6 +120,29 @@ export const imageMeta3dIconSchema = z
 	})
 	.passthrough();
 
+export const imageCaptureMetadataSchema = z
+	.object({
+		cameraMake: z.string().optional(),
+		cameraModel: z.string().optional(),
+		lensModel: z.string().optional(),
+		takenAt: z.string().optional(),
+		exposureTime: z.string().optional(),
+		fNumber: z.number().optional(),
+		iso: z.number().optional(),
+		focalLengthMm: z.number().optional(),
+		orientation: z.number().int().optional(),
+		colorProfile: z.string().optional(),
+	})
+	.passthrough();
+
+export const imageMetaPhotoSchema = z
+	.object({
+		kind: z.literal('photo'),
+		capture: imageCaptureMetadataSchema,
+		insertedAt: z.number(),
+	})
+	.passthrough();
+
 export const imageMetaUnsplashSchema = z
 	.object({
 		kind: z.literal('unsplash'),
@@ -129,6 +152,7 @@ export const imageMetaUnsplashSchema = z
 		photographerProfileUrl: z.string(),
 		photoUnsplashUrl: z.string(),
 		downloadLocation: z.string(),
+		capture: imageCaptureMetadataSchema.optional(),
 		insertedAt: z.number(),
 	})
 	.passthrough();
@@ -177,17 +201,20 @@ export const imageMetaSchema = z.discriminatedUnion('kind', [
 	imageMeta3dIconSchema,
 	imageMetaUnsplashSchema,
 	imageMetaIconifyIconSchema,
+	imageMetaPhotoSchema,
 ]);
 
+export type ImageCaptureMetadata = z.infer<typeof imageCaptureMetadataSchema>;
 export type ImageMeta3dIcon = z.infer<typeof imageMeta3dIconSchema>;
+export type ImageMetaPhoto = z.infer<typeof imageMetaPhotoSchema>;
 export type ImageMetaUnsplash = z.infer<typeof imageMetaUnsplashSchema>;
 export type ImageMetaIconifyIcon = z.infer<typeof imageMetaIconifyIconSchema>;
 export type ImageMeta = z.infer<typeof imageMetaSchema>;
 
 export const imageBgRemoveMetaSchema = z
 	.object({
-		provider: z.literal('apple-vision'),
-		model: z.literal('foreground-instance-mask'),
+		provider: z.string(),
+		model: z.string(),
 		revision: z.number().int().optional(),
 		createdAt: z.number(),
 	})
@@ -554,16 +581,28 @@ export type ComponentDefinition = z.infer<typeof componentDefinitionSchema>;
 export type ComponentSet = z.infer<typeof componentSetSchema>;
 export type ComponentsLibrary = z.infer<typeof componentsLibrarySchema>;
 
+export const imageAssetVariantSchema = z.object({
+	mime: z.string(),
+	dataBase64: z.string().optional(),
+	width: z.number(),
+	height: z.number(),
+	hash: z.string().optional(),
+});
+
 export const imageAssetSchema = z.object({
 	type: z.literal('image'),
 	mime: z.string(),
 	dataBase64: z.string().optional(),
 	width: z.number(),
 	height: z.number(),
+	hash: z.string().optional(),
+	proxy: imageAssetVariantSchema.optional(),
+	thumbnail: imageAssetVariantSchema.optional(),
 });
 
 export const assetSchema = z.discriminatedUnion('type', [imageAssetSchema]);
 
+export type ImageAssetVariant = z.infer<typeof imageAssetVariantSchema>;
 export type Asset = z.infer<typeof assetSchema>;
 
 export const pageSchema = z.object({
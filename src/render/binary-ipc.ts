@@ -0,0 +1,49 @@
+/**
+ * Raw byte IPC helpers for the `*_raw` Tauri commands.
+ * Payloads travel as the invoke body; scalar args go in the args header.
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export const ARGS_HEADER = 'x-galileo-args';
+
+export type BlobRef = { offset: number; length: number };
+
+export type FramedResult<T> = {
+	metadata: T;
+	blob: (ref: BlobRef) => Uint8Array;
+};
+
+/**
+ * Invoke a raw command with `body` as the payload and `args` in the header
+ */
+export const invokeRaw = <T = ArrayBuffer>(command: string, body: Uint8Array, args: object = {}): Promise<T> =>
+	invoke<T>(command, body, {
+		headers: { [ARGS_HEADER]: encodeURIComponent(JSON.stringify(args)) },
+	});
+
+/**
+ * Split a framed response: u32 LE metadata length, JSON metadata, blob section
+ */
+export const decodeFramed = <T>(buffer: ArrayBuffer): FramedResult<T> => {
+	const metadataLength = new DataView(buffer).getUint32(0, true);
+	const metadataBytes = new Uint8Array(buffer, 4, metadataLength);
+	const metadata = JSON.parse(new TextDecoder().decode(metadataBytes)) as T;
+	const blobStart = 4 + metadataLength;
+	return {
+		metadata,
+		blob: (ref) => new Uint8Array(buffer, blobStart + ref.offset, ref.length),
+	};
+};
+
+/**
+ * Base64 for callers that still store inline data (documents, data URLs)
+ */
+export const bytesToBase64 = (bytes: Uint8Array | Uint8ClampedArray): string => {
+	let binary = '';
+	const chunkSize = 0x8000;
+	for (let i = 0; i < bytes.length; i += chunkSize) {
+		binary += String.fromCharCode(...bytes.subarray(i, i + chunkSize));
+	}
+	return btoa(binary);
+};
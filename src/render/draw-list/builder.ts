@@ -17,6 +17,8 @@ type BuildDrawListOptions = {
 	clipToBounds?: boolean;
 	textOverflowIndicatorNodeIds?: string[];
 	hiddenNodeIds?: string[];
+	/** Draw image assets from their edit proxy when one exists; exports keep the original */
+	useImageProxies?: boolean;
 };
 
 export const buildDrawList = (doc: Document, boundsMap?: WorldBoundsMap, options: BuildDrawListOptions = {}): DrawCommand[] => {
@@ -33,7 +35,18 @@ export const buildDrawList = (doc: Document, boundsMap?: WorldBoundsMap, options
 	const hiddenNodeIds =
 		options.hiddenNodeIds && options.hiddenNodeIds.length > 0 ? new Set(options.hiddenNodeIds) : null;
 	const commands: DrawCommand[] = [];
-	buildNodeCommandsFromBounds(doc, rootNode, commands, map, { x: 0, y: 0 }, doc.rootId, true, overflowIndicatorIds, hiddenNodeIds);
+	buildNodeCommandsFromBounds(
+		doc,
+		rootNode,
+		commands,
+		map,
+		{ x: 0, y: 0 },
+		doc.rootId,
+		true,
+		overflowIndicatorIds,
+		hiddenNodeIds,
+		options.useImageProxies === true,
+	);
 
 	return commands;
 };
@@ -68,7 +81,18 @@ export const buildDrawListForNode = (
 			cornerRadius: node.type === 'frame' ? node.cornerRadius : undefined,
 		});
 	}
-	buildNodeCommandsFromBounds(doc, node, commands, map, base, nodeId, includeFrameFill, null, null);
+	buildNodeCommandsFromBounds(
+		doc,
+		node,
+		commands,
+		map,
+		base,
+		nodeId,
+		includeFrameFill,
+		null,
+		null,
+		options.useImageProxies === true,
+	);
 	return commands;
 };
 
@@ -82,6 +106,7 @@ const buildNodeCommandsFromBounds = (
 	includeRootFrameFill: boolean,
 	overflowIndicatorIds: Set<string> | null,
 	hiddenNodeIds: Set<string> | null,
+	useImageProxies: boolean,
 ): void => {
 	const bounds = boundsMap[node.id];
 	if (!bounds) {
@@ -162,6 +187,7 @@ const buildNodeCommandsFromBounds = (
 						includeRootFrameFill,
 						overflowIndicatorIds,
 						hiddenNodeIds,
+						useImageProxies,
 					);
 				}
 			}
@@ -191,6 +217,7 @@ const buildNodeCommandsFromBounds = (
 						includeRootFrameFill,
 						overflowIndicatorIds,
 						hiddenNodeIds,
+						useImageProxies,
 					);
 				}
 			}
@@ -259,7 +286,7 @@ const buildNodeCommandsFromBounds = (
 			});
 		}
 	} else if (node.type === 'image') {
-		const src = resolveImageSource(doc, node);
+		const src = resolveImageSource(doc, node, useImageProxies);
 		const maskSrc = resolveImageMaskSource(doc, node);
 		const outline = resolveImageOutlineStyle(doc, node);
 		if (src) {
@@ -292,6 +319,7 @@ const buildNodeCommandsFromBounds = (
 					includeRootFrameFill,
 					overflowIndicatorIds,
 					hiddenNodeIds,
+					useImageProxies,
 				);
 			}
 			return;
@@ -369,6 +397,7 @@ const buildNodeCommandsFromBounds = (
 						includeRootFrameFill,
 						overflowIndicatorIds,
 						hiddenNodeIds,
+						useImageProxies,
 					);
 				}
 			}
@@ -602,10 +631,13 @@ const clamp01 = (value: number): number => {
 	return Math.max(0, Math.min(1, value));
 };
 
-const resolveImageSource = (doc: Document, node: Node): string | null => {
+const resolveImageSource = (doc: Document, node: Node, useProxy: boolean): string | null => {
 	const assetId = node.image?.assetId;
 	if (assetId) {
 		const asset = doc.assets?.[assetId];
+		if (useProxy && asset?.type === 'image' && asset.proxy?.dataBase64) {
+			return `data:${asset.proxy.mime};base64,${asset.proxy.dataBase64}`;
+		}
 		if (asset && asset.type === 'image' && asset.dataBase64 && asset.mime) {
 			return `data:${asset.mime};base64,${asset.dataBase64}`;
 		}
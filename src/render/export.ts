@@ -3,6 +3,26 @@ import { buildDrawListForNode } from './draw-list';
 import type { Document } from '../core/doc/types';
 import type { DrawImageCommand } from './draw-list/types';
 import { invoke } from '@tauri-apps/api/core';
+import { bytesToBase64, decodeFramed, invokeRaw, type BlobRef } from './binary-ipc';
+
+/** PNG size options for the native encoder */
+export type PngEncodeOptions = {
+	/** Deflate level, 0 (store) to 9 (smallest) */
+	compressionLevel?: number;
+	filter?: 'none' | 'sub' | 'up' | 'average' | 'paeth' | 'adaptive' | 'min-entropy';
+	/** Lossless color type, bit depth and filter reductions */
+	optimize?: boolean;
+	/** Quantize to a palette of at most this many colors (lossy) */
+	maxColors?: number;
+	/** Error diffusion for `maxColors`, 0 to 1 */
+	dithering?: number;
+};
+
+type EncodedPng = {
+	dataBase64: string;
+	byteSize: number;
+	unoptimizedByteSize: number | null;
+};
 
 export type SnapshotOptions = {
 	scale?: number;
@@ -18,6 +38,10 @@ export type SnapshotOptions = {
 	useNativeEncoder?: boolean;
 	/** WebP quality 0-100 (only for webp format) */
 	webpQuality?: number;
+	/** PNG size options for the native encoder. Default: lossless optimization */
+	png?: PngEncodeOptions;
+	/** How pixels reach the native encoder: raw bytes (default) or legacy base64 */
+	nativeTransport?: 'raw' | 'base64';
 };
 
 export type SnapshotResult = {
@@ -27,6 +51,9 @@ export type SnapshotResult = {
 	height: number;
 	/** Encoding time in milliseconds (for benchmarking) */
 	encodeTimeMs?: number;
+	/** Native PNG only: encoded size, and the size without the PNG options */
+	byteSize?: number;
+	unoptimizedByteSize?: number;
 	/** Debug info for scale/clamp diagnostics */
 	requestedScale?: number;
 	usedScale?: number;
@@ -50,18 +77,6 @@ const preloadImages = async (sources: string[]): Promise<void> => {
 	);
 };
 
-/**
- * Convert Uint8ClampedArray to base64 string
- */
-const uint8ArrayToBase64 = (bytes: Uint8ClampedArray): string => {
-	let binary = '';
-	const len = bytes.byteLength;
-	for (let i = 0; i < len; i++) {
-		binary += String.fromCharCode(bytes[i]);
-	}
-	return btoa(binary);
-};
-
 /**
  * Encode image using native Rust encoder (PNG or WebP)
  * Falls back to canvas.toDataURL if Tauri invoke fails
@@ -72,32 +87,51 @@ const encodeWithRust = async (
 	height: number,
 	format: 'png' | 'webp',
 	webpQuality?: number,
-): Promise<{ dataBase64: string; encodeTimeMs: number }> => {
+	transport: 'raw' | 'base64' = 'raw',
+	png: PngEncodeOptions = {},
+): Promise<{ dataBase64: string; encodeTimeMs: number; byteSize?: number; unoptimizedByteSize?: number }> => {
 	const imageData = ctx.getImageData(0, 0, width, height);
-	const rgbaBase64 = uint8ArrayToBase64(imageData.data);
 
-	const startTime = performance.now();
+	if (format === 'png') {
+		const startTime = performance.now();
+		let encoded: EncodedPng;
+		if (transport === 'raw') {
+			const rgba = new Uint8Array(imageData.data.buffer, imageData.data.byteOffset, imageData.data.byteLength);
+			const buffer = await invokeRaw('encode_png_raw', rgba, { width, height, ...png });
+			const framed = decodeFramed<Omit<EncodedPng, 'dataBase64'> & { data: BlobRef }>(buffer);
+			encoded = { ...framed.metadata, dataBase64: bytesToBase64(framed.blob(framed.metadata.data)) };
+		} else {
+			const rgbaBase64 = bytesToBase64(imageData.data);
+			encoded = await invoke<EncodedPng>('encode_png', { args: { rgbaBase64, width, height, ...png } });
+		}
+		return {
+			dataBase64: encoded.dataBase64,
+			encodeTimeMs: performance.now() - startTime,
+			byteSize: encoded.byteSize,
+			unoptimizedByteSize: encoded.unoptimizedByteSize ?? undefined,
+		};
+	}
 
-	if (format === 'webp') {
-		const dataBase64 = await invoke<string>('encode_webp', {
-			args: {
-				rgbaBase64,
-				width,
-				height,
-				quality: webpQuality ?? 90,
-			},
-		});
-		return { dataBase64, encodeTimeMs: performance.now() - startTime };
-	} else {
-		const dataBase64 = await invoke<string>('encode_png', {
-			args: {
-				rgbaBase64,
-				width,
-				height,
-			},
-		});
+	const quality = webpQuality ?? 90;
+	if (transport === 'raw') {
+		const startTime = performance.now();
+		const rgba = new Uint8Array(imageData.data.buffer, imageData.data.byteOffset, imageData.data.byteLength);
+		const encoded = await invokeRaw('encode_webp_raw', rgba, { width, height, quality });
+		const dataBase64 = bytesToBase64(new Uint8Array(encoded));
 		return { dataBase64, encodeTimeMs: performance.now() - startTime };
 	}
+
+	const rgbaBase64 = bytesToBase64(imageData.data);
+	const startTime = performance.now();
+	const dataBase64 = await invoke<string>('encode_webp', {
+		args: {
+			rgbaBase64,
+			width,
+			height,
+			quality,
+		},
+	});
+	return { dataBase64, encodeTimeMs: performance.now() - startTime };
 };
 
 /**
@@ -180,12 +214,24 @@ export const exportNodeSnapshot = async (
 	// Encode using Rust (fast) or canvas (fallback)
 	let dataBase64: string;
 	let encodeTimeMs: number;
+	let byteSize: number | undefined;
+	let unoptimizedByteSize: number | undefined;
 
 	if (useNativeEncoder) {
 		try {
-			const result = await encodeWithRust(ctx, width, height, format, options.webpQuality);
+			const result = await encodeWithRust(
+				ctx,
+				width,
+				height,
+				format,
+				options.webpQuality,
+				options.nativeTransport,
+				options.png ?? { optimize: true },
+			);
 			dataBase64 = result.dataBase64;
 			encodeTimeMs = result.encodeTimeMs;
+			byteSize = result.byteSize;
+			unoptimizedByteSize = result.unoptimizedByteSize;
 		} catch (err) {
 			console.warn('Rust encoder failed, falling back to canvas:', err);
 			const result = encodeWithCanvas(canvas, format, options.webpQuality);
@@ -204,6 +250,8 @@ export const exportNodeSnapshot = async (
 		width,
 		height,
 		encodeTimeMs,
+		byteSize,
+		unoptimizedByteSize,
 		requestedScale,
 		usedScale: scale,
 		pixelW: width,
@@ -211,3 +259,166 @@ export const exportNodeSnapshot = async (
 		clampedBy: clampedBy.length > 0 ? clampedBy : undefined,
 	};
 };
+
+export type BatchExportTarget = {
+	scale: number;
+	/** e.g. '@2x' */
+	suffix?: string;
+	format: 'png' | 'jpeg' | 'webp' | 'avif' | 'tiff';
+	quality?: number;
+};
+
+export type BatchExportOptions = {
+	directory: string;
+	name: string;
+	/** Placeholders: {name}, {suffix}, {scale}, {format}, {ext}, {width}, {height} */
+	nameTemplate?: string;
+	targets: BatchExportTarget[];
+};
+
+export type ExportedFile = {
+	path: string;
+	scale: number;
+	format: string;
+	width: number;
+	height: number;
+	byteSize: number;
+};
+
+/**
+ * Render once at the largest target scale and let Rust downsample, encode
+ * and write every target in a single call
+ */
+export const exportNodeBatch = async (
+	doc: Document,
+	nodeId: string,
+	options: BatchExportOptions,
+	snapshot: Pick<SnapshotOptions, 'background' | 'includeFrameFill' | 'clipToBounds'> = {},
+): Promise<ExportedFile[]> => {
+	const node = doc.nodes[nodeId];
+	if (!node) {
+		throw new Error('Node not found');
+	}
+	const renderScale = Math.max(...options.targets.map((target) => target.scale), 0.1);
+	const width = Math.max(1, Math.round(node.size.width * renderScale));
+	const height = Math.max(1, Math.round(node.size.height * renderScale));
+
+	const canvas = window.document.createElement('canvas');
+	canvas.width = width;
+	canvas.height = height;
+	const ctx = canvas.getContext('2d');
+	if (!ctx) {
+		throw new Error('Failed to create snapshot canvas');
+	}
+	if (snapshot.background === 'solid') {
+		ctx.fillStyle = '#ffffff';
+		ctx.fillRect(0, 0, width, height);
+	}
+
+	const commands = buildDrawListForNode(doc, nodeId, {
+		includeFrameFill: snapshot.includeFrameFill,
+		clipToBounds: snapshot.clipToBounds,
+	});
+	await preloadImages(
+		commands
+			.filter((cmd): cmd is DrawImageCommand => cmd.type === 'image')
+			.flatMap((cmd) => [cmd.src, cmd.maskSrc].filter(Boolean) as string[]),
+	);
+	new CanvasRenderer(canvas).render(commands, { pan: { x: 0, y: 0 }, zoom: renderScale });
+
+	const imageData = ctx.getImageData(0, 0, width, height);
+	const rgba = new Uint8Array(imageData.data.buffer, imageData.data.byteOffset, imageData.data.byteLength);
+	return invokeRaw<ExportedFile[]>('export_batch_raw', rgba, { width, height, renderScale, ...options });
+};
+
+export type AnimationFormat = 'gif' | 'apng' | 'webp';
+
+export type AnimationOptions = {
+	format: AnimationFormat;
+	/** Total plays; 0 or omitted loops forever */
+	loopCount?: number;
+	/** GIF palette size including the transparent entry */
+	maxColors?: number;
+	/** GIF error diffusion strength, 0-1 */
+	dithering?: number;
+};
+
+/**
+ * Encode same-sized RGBA frames into an animated GIF, APNG or WebP
+ */
+export const encodeAnimation = async (
+	frames: { rgba: Uint8Array | Uint8ClampedArray; delayMs: number }[],
+	width: number,
+	height: number,
+	options: AnimationOptions,
+): Promise<Uint8Array> => {
+	const frameLength = width * height * 4;
+	const body = new Uint8Array(frameLength * frames.length);
+	frames.forEach((frame, index) => body.set(frame.rgba, index * frameLength));
+	const encoded = await invokeRaw('encode_animation_raw', body, {
+		width,
+		height,
+		delaysMs: frames.map((frame) => frame.delayMs),
+		...options,
+	});
+	return new Uint8Array(encoded);
+};
+
+export type VectorExportOptions = {
+	/** Defaults to the active page's root frame */
+	nodeId?: string;
+	pageId?: string;
+	includeFrameFill?: boolean;
+	clipToBounds?: boolean;
+	/** Write the file here; otherwise the markup is returned */
+	outputPath?: string;
+};
+
+export type SvgExportOptions = VectorExportOptions & {
+	/** 'link' writes images to `<name>_assets/` beside the SVG */
+	images?: 'embed' | 'link';
+	text?: 'text' | 'outlines';
+};
+
+export type SvgExportResult = {
+	svg?: string;
+	path?: string;
+	width: number;
+	height: number;
+	linkedAssets: string[];
+};
+
+/**
+ * Export a node or page as SVG natively from the document JSON
+ */
+export const exportSvg = (doc: Document, options: SvgExportOptions = {}): Promise<SvgExportResult> =>
+	invoke<SvgExportResult>('export_svg', { args: { content: JSON.stringify(doc), ...options } });
+
+export type PdfExportOptions = {
+	/** Pages to export in order; defaults to every page */
+	pageIds?: string[];
+	/** Frames to export one per PDF page instead of whole pages */
+	nodeIds?: string[];
+	includeFrameFill?: boolean;
+	clipToBounds?: boolean;
+	/** Shadow raster pixels per point, 1-4 */
+	shadowScale?: number;
+};
+
+export type PdfExportResult = {
+	path: string;
+	pageCount: number;
+	byteSize: number;
+	missingFonts: string[];
+	skippedImages: string[];
+};
+
+/**
+ * Export pages or frames as a multi-page PDF with embedded font subsets
+ */
+export const exportPdf = (
+	doc: Document,
+	outputPath: string,
+	options: PdfExportOptions = {},
+): Promise<PdfExportResult> =>
+	invoke<PdfExportResult>('export_pdf', { args: { content: JSON.stringify(doc), outputPath, ...options } });
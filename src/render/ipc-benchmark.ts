@@ -0,0 +1,153 @@
+/**
+ * Benchmark utilities for comparing base64 vs raw byte IPC to the Rust encoders
+ * Usage: Call `runIpcBenchmark()` from browser console
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { bytesToBase64, invokeRaw } from './binary-ipc';
+
+export type IpcBenchmarkResult = {
+	label: string;
+	format: 'png' | 'webp';
+	transport: 'raw' | 'base64';
+	width: number;
+	height: number;
+	/** Bytes sent to Rust, including base64 inflation */
+	requestKb: number;
+	/** Bytes received back, including base64 inflation */
+	responseKb: number;
+	/** Median round trip including JS-side base64 conversion, in ms */
+	totalTimeMs: number;
+};
+
+export type IpcBenchmarkSummary = {
+	results: IpcBenchmarkResult[];
+	comparison: {
+		pngSpeedup: number; // Raw vs base64 speedup ratio
+		webpSpeedup: number;
+	};
+};
+
+/**
+ * Deterministic gradient with noise, so encoders can't collapse it to nothing
+ */
+const createTestPixels = (width: number, height: number): Uint8Array => {
+	const pixels = new Uint8Array(width * height * 4);
+	let seed = 1;
+	for (let y = 0; y < height; y++) {
+		for (let x = 0; x < width; x++) {
+			seed = (seed * 1103515245 + 12345) & 0x7fffffff;
+			const noise = seed & 0x1f;
+			const i = (y * width + x) * 4;
+			pixels[i] = ((x / width) * 224 + noise) | 0;
+			pixels[i + 1] = ((y / height) * 224 + noise) | 0;
+			pixels[i + 2] = 128 + noise;
+			pixels[i + 3] = 255;
+		}
+	}
+	return pixels;
+};
+
+const median = (values: number[]): number => {
+	const sorted = [...values].sort((a, b) => a - b);
+	return sorted[Math.floor(sorted.length / 2)];
+};
+
+/**
+ * Run one encode repeatedly and measure timing
+ */
+const runSingleBenchmark = async (
+	pixels: Uint8Array,
+	size: number,
+	format: 'png' | 'webp',
+	transport: 'raw' | 'base64',
+	iterations: number,
+): Promise<IpcBenchmarkResult> => {
+	const command = format === 'webp' ? 'encode_webp' : 'encode_png';
+	const quality = format === 'webp' ? 90 : undefined;
+	const times: number[] = [];
+	let requestBytes = 0;
+	let responseBytes = 0;
+
+	for (let i = 0; i < iterations; i++) {
+		const startTime = performance.now();
+		if (transport === 'raw') {
+			const encoded = await invokeRaw(`${command}_raw`, pixels, { width: size, height: size, quality });
+			requestBytes = pixels.byteLength;
+			responseBytes = encoded.byteLength;
+		} else {
+			const rgbaBase64 = bytesToBase64(pixels);
+			const encoded = await invoke<string | { dataBase64: string }>(command, {
+				args: { rgbaBase64, width: size, height: size, quality },
+			});
+			const dataBase64 = typeof encoded === 'string' ? encoded : encoded.dataBase64;
+			requestBytes = rgbaBase64.length;
+			responseBytes = dataBase64.length;
+		}
+		times.push(performance.now() - startTime);
+	}
+
+	return {
+		label: `${format.toUpperCase()} ${size}² (${transport})`,
+		format,
+		transport,
+		width: size,
+		height: size,
+		requestKb: requestBytes / 1024,
+		responseKb: responseBytes / 1024,
+		totalTimeMs: median(times),
+	};
+};
+
+/**
+ * Run comprehensive benchmark comparing raw vs base64 IPC
+ */
+export const runIpcBenchmark = async (
+	sizes: number[] = [512, 1024, 2048],
+	iterations = 5,
+): Promise<IpcBenchmarkSummary> => {
+	const results: IpcBenchmarkResult[] = [];
+
+	console.log('🚀 Starting IPC benchmark...');
+	console.log(`   Sizes: ${sizes.join(', ')}px`);
+	console.log(`   Iterations: ${iterations}`);
+	console.log('');
+
+	for (const size of sizes) {
+		const pixels = createTestPixels(size, size);
+		for (const format of ['png', 'webp'] as const) {
+			const raw = await runSingleBenchmark(pixels, size, format, 'raw', iterations);
+			const base64 = await runSingleBenchmark(pixels, size, format, 'base64', iterations);
+			results.push(raw, base64);
+
+			console.log(`📊 ${format.toUpperCase()} ${size}×${size}:`);
+			console.log(
+				`   Raw ${raw.totalTimeMs.toFixed(1)}ms vs Base64 ${base64.totalTimeMs.toFixed(1)}ms (${(base64.totalTimeMs / raw.totalTimeMs).toFixed(1)}x faster)`,
+			);
+			console.log(`   Request: Raw ${raw.requestKb.toFixed(0)}KB vs Base64 ${base64.requestKb.toFixed(0)}KB`);
+		}
+		console.log('');
+	}
+
+	const avgTime = (format: 'png' | 'webp', transport: 'raw' | 'base64') => {
+		const matching = results.filter((r) => r.format === format && r.transport === transport);
+		return matching.reduce((sum, r) => sum + r.totalTimeMs, 0) / matching.length;
+	};
+
+	const comparison = {
+		pngSpeedup: avgTime('png', 'base64') / avgTime('png', 'raw'),
+		webpSpeedup: avgTime('webp', 'base64') / avgTime('webp', 'raw'),
+	};
+
+	console.log('📈 Summary:');
+	console.log(`   PNG: raw IPC is ${comparison.pngSpeedup.toFixed(1)}x faster`);
+	console.log(`   WebP: raw IPC is ${comparison.webpSpeedup.toFixed(1)}x faster`);
+	console.log('');
+
+	return { results, comparison };
+};
+
+// Expose to window for easy testing from console
+if (typeof window !== 'undefined') {
+	(window as unknown as Record<string, unknown>).runIpcBenchmark = runIpcBenchmark;
+}
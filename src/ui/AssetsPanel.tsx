@@ -1,5 +1,6 @@
 import React from 'react';
 import type {
+	Asset,
 	ComponentDefinition,
 	ComponentSet,
 	ComponentVariantMap,
@@ -18,8 +19,14 @@ type AssetSetEntry = {
 	defaultDefinition?: ComponentDefinition;
 };
 
+type ImageEntry = {
+	id: string;
+	asset: Extract<Asset, { type: 'image' }>;
+};
+
 interface AssetsPanelProps {
 	components: ComponentsLibrary;
+	assets: Record<string, Asset>;
 	styles: StyleLibrary;
 	variables: StyleVariableLibrary;
 	width?: number;
@@ -63,6 +70,12 @@ const buildAssetEntries = (components: ComponentsLibrary): AssetSetEntry[] => {
 		.sort((a, b) => a.set.name.localeCompare(b.set.name));
 };
 
+const buildImageEntries = (assets: Record<string, Asset>): ImageEntry[] =>
+	Object.entries(assets)
+		.filter((pair): pair is [string, ImageEntry['asset']] => pair[1].type === 'image')
+		.map(([id, asset]) => ({ id, asset }))
+		.sort((a, b) => a.id.localeCompare(b.id));
+
 type StyleEntry = { id: string; name: string };
 
 const STYLE_KIND_LABELS: Record<SharedStyleKind, string> = {
@@ -89,6 +102,7 @@ const buildStyleEntries = (styles: StyleLibrary): Record<SharedStyleKind, StyleE
 
 export const AssetsPanel: React.FC<AssetsPanelProps> = ({
 	components,
+	assets,
 	styles,
 	variables,
 	width = panels.left.width,
@@ -122,6 +136,7 @@ export const AssetsPanel: React.FC<AssetsPanelProps> = ({
 	}, [focusSearchNonce, collapsed]);
 
 	const entries = React.useMemo(() => buildAssetEntries(components), [components]);
+	const imageEntries = React.useMemo(() => buildImageEntries(assets), [assets]);
 	const styleEntries = React.useMemo(() => buildStyleEntries(styles), [styles]);
 	const variableCollections = React.useMemo(
 		() => Object.values(variables.collections).sort((a, b) => a.name.localeCompare(b.name)),
@@ -451,6 +466,58 @@ export const AssetsPanel: React.FC<AssetsPanelProps> = ({
 					);
 				})}
 
+				{imageEntries.length > 0 && (
+					<div
+						style={{
+							padding: spacing.sm,
+							borderRadius: radii.sm,
+							border: `1px solid ${colors.border.default}`,
+							backgroundColor: colors.bg.tertiary,
+							display: 'grid',
+							gap: spacing.sm,
+						}}
+					>
+						<div style={{ fontSize: typography.fontSize.md, color: colors.text.primary }}>Images</div>
+						<div style={{ display: 'grid', gridTemplateColumns: 'repeat(auto-fill, minmax(64px, 1fr))', gap: spacing.xs }}>
+							{imageEntries.map(({ id, asset }) => {
+								// The 256px thumbnail variant; SVGs and older documents have none.
+								const thumbnail = asset.thumbnail?.dataBase64
+									? `data:${asset.thumbnail.mime};base64,${asset.thumbnail.dataBase64}`
+									: null;
+								return (
+									<div
+										key={id}
+										title={`${asset.width} × ${asset.height}`}
+										style={{
+											aspectRatio: '1',
+											borderRadius: radii.sm,
+											border: `1px solid ${colors.border.subtle}`,
+											backgroundColor: colors.bg.secondary,
+											display: 'flex',
+											alignItems: 'center',
+											justifyContent: 'center',
+											overflow: 'hidden',
+										}}
+									>
+										{thumbnail ? (
+											<img
+												src={thumbnail}
+												alt=""
+												draggable={false}
+												style={{ width: '100%', height: '100%', objectFit: 'contain' }}
+											/>
+										) : (
+											<span style={{ fontSize: typography.fontSize.xs, color: colors.text.tertiary }}>
+												{asset.mime.replace(/^image\//, '').toUpperCase()}
+											</span>
+										)}
+									</div>
+								);
+							})}
+						</div>
+					</div>
+				)}
+
 				<div
 					style={{
 						padding: spacing.sm,
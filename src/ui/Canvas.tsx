@@ -66,7 +66,7 @@ export const Canvas: React.FC<CanvasProps> = ({
 	onContextMenu,
 }) => {
 	const drawCommands = useMemo(() => {
-		return buildDrawList(document, boundsMap, { textOverflowIndicatorNodeIds, hiddenNodeIds });
+		return buildDrawList(document, boundsMap, { textOverflowIndicatorNodeIds, hiddenNodeIds, useImageProxies: true });
 	}, [document, boundsMap, textOverflowIndicatorNodeIds, hiddenNodeIds]);
 
 	const { canvasRef, handleMouseDown, handleMouseMove, handleMouseUp, handleWheel } = useCanvas({
@@ -165,6 +165,7 @@ export const LeftSidebar: React.FC<LeftSidebarProps> = ({
 			) : (
 				<AssetsPanel
 					components={components}
+					assets={document.assets}
 					styles={document.styles}
 					variables={document.variables}
 					width={width}